@@ -73,12 +73,14 @@ pub trait LogIndexerBackend<Block: BlockT>: Send + Sync {
 	/// Indicate whether the log indexing feature is supported.
 	fn is_indexed(&self) -> bool;
 
-	/// Filter the logs by the parameters.
+	/// Filter the logs by the parameters, returning at most `limit` logs. A caller accepting
+	/// `n` logs asks for `n + 1` to tell a truncated result from a complete one.
 	async fn filter_logs(
 		&self,
 		from_block: u64,
 		to_block: u64,
 		addresses: Vec<H160>,
 		topics: Vec<Vec<Option<H256>>>,
+		limit: u32,
 	) -> Result<Vec<FilteredLog<Block>>, String>;
 }
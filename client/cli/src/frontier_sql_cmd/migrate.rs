@@ -0,0 +1,98 @@
+// This file is part of Frontier.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use std::sync::Arc;
+
+use ethereum_types::H256;
+// Substrate
+use sc_client_api::{backend::Backend as BackendT, BlockBackend, StorageProvider};
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_runtime::traits::{Block as BlockT, UniqueSaturatedInto};
+// Frontier
+use fc_db::sql::BlockIndexing;
+use fp_rpc::EthereumRuntimeRPCApi;
+
+/// Indexes the canonical blocks `0..=to` synced by the KeyValue `mapping` into the SQL
+/// backend, along with their logs, under the ethereum block and transaction hashes the
+/// mapping recorded for them. Blocks synced without recorded hashes, by an older KeyValue
+/// backend or for lack of an ethereum block, are indexed from their digest. Blocks already
+/// in the index are left untouched, so an interrupted migration resumes where it stopped.
+/// Returns the number of migrated blocks and the number of blocks skipped because the
+/// KeyValue backend had not synced them.
+pub async fn migrate_kv_to_sql<B, C, BE>(
+	client: Arc<C>,
+	mapping: &fc_db::kv::MappingDb<B>,
+	backend: &fc_db::sql::Backend<B>,
+	to: u32,
+) -> Result<(u32, u32), String>
+where
+	B: BlockT<Hash = H256>,
+	C: ProvideRuntimeApi<B>,
+	C::Api: EthereumRuntimeRPCApi<B>,
+	C: HeaderBackend<B> + BlockBackend<B> + StorageProvider<B, BE> + 'static,
+	BE: BackendT<B> + 'static,
+{
+	backend
+		.insert_genesis_block_metadata(client.clone())
+		.await
+		.map_err(|e| format!("Failed indexing the genesis block: {e}"))?;
+
+	let mut migrated = 0;
+	let mut unsynced = 0;
+	for number in 1..=to {
+		let hash = client
+			.hash(number.unique_saturated_into())
+			.map_err(|e| format!("Failed reading the hash of block #{number}: {e}"))?
+			.ok_or_else(|| format!("Block #{number} not found"))?;
+		if !mapping.is_synced(&hash)? {
+			unsynced += 1;
+			continue;
+		}
+		let indexing = match mapping.synced_hashes(&hash)? {
+			Some(synced_hashes) => {
+				backend
+					.insert_block_metadata_with_hashes(
+						client.clone(),
+						hash,
+						synced_hashes.ethereum_block_hash,
+						synced_hashes.ethereum_transaction_hashes,
+					)
+					.await
+			}
+			None => backend.insert_block_metadata(client.clone(), hash).await,
+		}
+		.map_err(|e| format!("Failed indexing block #{number}: {e}"))?;
+		if indexing == BlockIndexing::Indexed {
+			migrated += 1;
+		}
+		backend
+			.index_block_logs(hash)
+			.await
+			.map_err(|e| format!("Failed indexing the logs of block #{number}: {e}"))?;
+	}
+
+	let pending = backend
+		.blocks_pending_logs()
+		.await
+		.map_err(|e| format!("Failed checking the migrated logs: {e}"))?;
+	if !pending.is_empty() {
+		return Err(format!("Failed indexing the logs of blocks {pending:?}"));
+	}
+	Ok((migrated, unsynced))
+}
@@ -0,0 +1,204 @@
+// This file is part of Frontier.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+mod migrate;
+mod reindex;
+mod self_check;
+#[cfg(test)]
+mod tests;
+mod verify;
+
+use std::sync::Arc;
+
+use clap::ValueEnum;
+use ethereum_types::H256;
+// Substrate
+use sc_cli::{PruningParams, SharedParams};
+use sc_client_api::{backend::Backend as BackendT, BlockBackend, StorageProvider};
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_runtime::traits::{Block as BlockT, UniqueSaturatedInto};
+// Frontier
+use fp_rpc::EthereumRuntimeRPCApi;
+
+use self::{
+	migrate::migrate_kv_to_sql,
+	reindex::reindex,
+	self_check::{self_check, SelfCheckFilter},
+	verify::verify,
+};
+
+/// Cli tool to maintain the Frontier SQL backend db
+#[derive(Debug, Clone, clap::Parser)]
+pub struct FrontierSqlCmd {
+	/// Specify the maintenance operation to perform.
+	///
+	/// Can be one of `prune-orphan-logs`, `self-check`, `reindex`, `verify` and
+	/// `migrate-kv-to-sql`.
+	#[arg(value_enum, ignore_case = true, required = true)]
+	pub operation: SqlOperation,
+
+	/// First block of the `self-check`, `reindex` or `verify` range. Defaults to the genesis
+	/// for `self-check` and `verify`, required by `reindex`.
+	#[arg(long)]
+	pub from: Option<u32>,
+
+	/// Last block of the `self-check`, `reindex`, `verify` or `migrate-kv-to-sql` range.
+	/// Defaults to the best block.
+	#[arg(long)]
+	pub to: Option<u32>,
+
+	/// The `self-check` logs filter, as a json object with the optional `addresses` and
+	/// `topics` keys, e.g. `{"topics":[[null,"0x..."]]}`. Defaults to all logs.
+	#[arg(long)]
+	pub filter: Option<String>,
+
+	/// Shared parameters
+	#[command(flatten)]
+	pub shared_params: SharedParams,
+
+	#[allow(missing_docs)]
+	#[command(flatten)]
+	pub pruning_params: PruningParams,
+}
+
+#[derive(ValueEnum, Debug, Clone)]
+pub enum SqlOperation {
+	/// Delete the logs whose block row no longer exists.
+	PruneOrphanLogs,
+	/// Compare the logs returned by the SQL filter with the ones stored in the state.
+	SelfCheck,
+	/// Delete the indexed blocks from `--from` upwards and index the canonical ones again.
+	/// The node must be stopped, which is enforced by the lock of its chain database when
+	/// the index lives in the same base path.
+	Reindex,
+	/// Compare the address and topics of every indexed log with the receipts stored in the
+	/// state, reporting the missing, extra and differing rows.
+	Verify,
+	/// Index the canonical blocks synced by the KeyValue backend up to `--to`, with their
+	/// logs, into the SQL backend under the hashes it mapped. Blocks already indexed are
+	/// skipped, so an interrupted migration can be run again.
+	MigrateKvToSql,
+}
+
+impl FrontierSqlCmd {
+	pub async fn run<B, C, BE>(
+		&self,
+		client: Arc<C>,
+		backend: Arc<fc_db::sql::Backend<B>>,
+	) -> sc_cli::Result<()>
+	where
+		B: BlockT<Hash = H256>,
+		C: ProvideRuntimeApi<B>,
+		C::Api: EthereumRuntimeRPCApi<B>,
+		C: HeaderBackend<B> + BlockBackend<B> + StorageProvider<B, BE> + 'static,
+		BE: BackendT<B> + 'static,
+	{
+		match self.operation {
+			SqlOperation::PruneOrphanLogs => {
+				let removed = backend
+					.prune_orphan_logs()
+					.await
+					.map_err(|e| format!("Failed pruning orphaned logs: {e}"))?;
+				println!("Removed {removed} orphaned logs");
+			}
+			SqlOperation::SelfCheck => {
+				let filter = match &self.filter {
+					Some(filter) => serde_json::from_str(filter)
+						.map_err(|e| format!("Invalid self-check filter: {e}"))?,
+					None => SelfCheckFilter::default(),
+				};
+				let from = self.from.unwrap_or(0);
+				let to = self
+					.to
+					.unwrap_or_else(|| client.info().best_number.unique_saturated_into());
+				let matches = self_check(&*client, &backend, from, to, filter).await?;
+				println!("Self-check passed for blocks #{from}..=#{to}: {matches} matching logs");
+			}
+			SqlOperation::Reindex => {
+				let from = self
+					.from
+					.ok_or_else(|| "The reindex operation requires --from".to_string())?;
+				let to = self
+					.to
+					.unwrap_or_else(|| client.info().best_number.unique_saturated_into());
+				let deleted = reindex(client, &backend, from, to).await?;
+				println!("Reindexed blocks #{from}..=#{to}, {deleted} of them were indexed");
+			}
+			SqlOperation::Verify => {
+				let from = self.from.unwrap_or(0);
+				let to = self
+					.to
+					.unwrap_or_else(|| client.info().best_number.unique_saturated_into());
+				let discrepancies = verify(&*client, &backend, from, to).await?;
+				if !discrepancies.is_empty() {
+					for discrepancy in &discrepancies {
+						eprintln!("{discrepancy}");
+					}
+					return Err(format!(
+						"Found {} log discrepancies in blocks #{from}..=#{to}",
+						discrepancies.len()
+					)
+					.into());
+				}
+				println!("Verified the logs of blocks #{from}..=#{to}");
+			}
+			SqlOperation::MigrateKvToSql => {
+				return Err(
+					"The migrate-kv-to-sql operation requires the KeyValue backend, \
+					see `FrontierSqlCmd::migrate_kv_to_sql`"
+						.into(),
+				);
+			}
+		}
+		Ok(())
+	}
+
+	/// Runs the `migrate-kv-to-sql` operation, reading the synced blocks from `kv_backend`.
+	pub async fn migrate_kv_to_sql<B, C, BE>(
+		&self,
+		client: Arc<C>,
+		kv_backend: Arc<fc_db::kv::Backend<B, C>>,
+		backend: Arc<fc_db::sql::Backend<B>>,
+	) -> sc_cli::Result<()>
+	where
+		B: BlockT<Hash = H256>,
+		C: ProvideRuntimeApi<B>,
+		C::Api: EthereumRuntimeRPCApi<B>,
+		C: HeaderBackend<B> + BlockBackend<B> + StorageProvider<B, BE> + 'static,
+		BE: BackendT<B> + 'static,
+	{
+		let to = self
+			.to
+			.unwrap_or_else(|| client.info().best_number.unique_saturated_into());
+		let (migrated, unsynced) =
+			migrate_kv_to_sql(client, kv_backend.mapping(), &backend, to).await?;
+		println!("Migrated {migrated} blocks up to #{to}, skipped {unsynced} unsynced blocks");
+		Ok(())
+	}
+}
+
+impl sc_cli::CliConfiguration for FrontierSqlCmd {
+	fn shared_params(&self) -> &SharedParams {
+		&self.shared_params
+	}
+
+	fn pruning_params(&self) -> Option<&PruningParams> {
+		Some(&self.pruning_params)
+	}
+}
@@ -0,0 +1,80 @@
+// This file is part of Frontier.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use std::sync::Arc;
+
+use ethereum_types::H256;
+// Substrate
+use sc_client_api::{backend::Backend as BackendT, BlockBackend, StorageProvider};
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_runtime::traits::{Block as BlockT, UniqueSaturatedInto};
+// Frontier
+use fp_rpc::EthereumRuntimeRPCApi;
+
+/// Deletes the indexed blocks `from..=to`, then indexes the canonical ones again with their
+/// logs. Returns the number of deleted blocks.
+pub async fn reindex<B, C, BE>(
+	client: Arc<C>,
+	backend: &fc_db::sql::Backend<B>,
+	from: u32,
+	to: u32,
+) -> Result<u64, String>
+where
+	B: BlockT<Hash = H256>,
+	C: ProvideRuntimeApi<B>,
+	C::Api: EthereumRuntimeRPCApi<B>,
+	C: HeaderBackend<B> + BlockBackend<B> + StorageProvider<B, BE> + 'static,
+	BE: BackendT<B> + 'static,
+{
+	let deleted = backend
+		.delete_blocks_in_range(from, to)
+		.await
+		.map_err(|e| format!("Failed deleting blocks #{from}..=#{to}: {e}"))?;
+
+	for number in from..=to {
+		if number == 0 {
+			backend
+				.insert_genesis_block_metadata(client.clone())
+				.await
+				.map_err(|e| format!("Failed indexing the genesis block: {e}"))?;
+			continue;
+		}
+		let hash = client
+			.hash(number.unique_saturated_into())
+			.map_err(|e| format!("Failed reading the hash of block #{number}: {e}"))?
+			.ok_or_else(|| format!("Block #{number} not found"))?;
+		backend
+			.insert_block_metadata(client.clone(), hash)
+			.await
+			.map_err(|e| format!("Failed indexing block #{number}: {e}"))?;
+		backend
+			.index_block_logs(hash)
+			.await
+			.map_err(|e| format!("Failed indexing the logs of block #{number}: {e}"))?;
+	}
+
+	let pending = backend
+		.blocks_pending_logs()
+		.await
+		.map_err(|e| format!("Failed checking the reindexed logs: {e}"))?;
+	if !pending.is_empty() {
+		return Err(format!("Failed indexing the logs of blocks {pending:?}"));
+	}
+	Ok(deleted)
+}
@@ -0,0 +1,146 @@
+// This file is part of Frontier.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::HashSet;
+
+use ethereum_types::{H160, H256};
+use serde::Deserialize;
+// Substrate
+use sp_blockchain::HeaderBackend;
+use sp_runtime::traits::{Block as BlockT, UniqueSaturatedInto};
+// Frontier
+use fc_api::LogIndexerBackend;
+use fc_storage::StorageOverride;
+
+/// The log position within the canonical chain: block number, transaction index and
+/// log index within the transaction.
+pub type LogPosition = (u32, u32, u32);
+
+/// The logs filter of the self-check, in the `eth_getLogs` shape.
+#[derive(Debug, Default, Deserialize)]
+pub struct SelfCheckFilter {
+	#[serde(default)]
+	pub addresses: Vec<H160>,
+	#[serde(default)]
+	pub topics: Vec<Vec<Option<H256>>>,
+}
+
+/// Runs the filter through the SQL query builder and through the receipts stored in the
+/// state of each canonical block, and compares both results.
+/// Returns the number of matching logs if they agree.
+pub async fn self_check<B, C>(
+	client: &C,
+	backend: &fc_db::sql::Backend<B>,
+	from: u32,
+	to: u32,
+	filter: SelfCheckFilter,
+) -> Result<usize, String>
+where
+	B: BlockT<Hash = H256>,
+	C: HeaderBackend<B>,
+{
+	let mut indexed: Vec<LogPosition> = backend
+		.filter_logs(
+			from.into(),
+			to.into(),
+			filter.addresses.clone(),
+			filter.topics.clone(),
+			// Every indexed log of the range is compared.
+			u32::MAX,
+		)
+		.await?
+		.into_iter()
+		.map(|log| (log.block_number, log.transaction_index, log.log_index))
+		.collect();
+	indexed.sort_unstable();
+
+	let reconstructed = reconstruct_logs(client, &*backend.storage_override(), from, to, &filter)?;
+
+	if indexed != reconstructed {
+		let missing: Vec<_> = reconstructed
+			.iter()
+			.filter(|position| indexed.binary_search(position).is_err())
+			.collect();
+		let unexpected: Vec<_> = indexed
+			.iter()
+			.filter(|position| reconstructed.binary_search(position).is_err())
+			.collect();
+		return Err(format!(
+			"SQL and state logs differ: missing from SQL {missing:?}, unexpected in SQL {unexpected:?}"
+		));
+	}
+	Ok(indexed.len())
+}
+
+/// Collects the positions of the logs matching the filter from the receipts of the
+/// canonical blocks in the range.
+fn reconstruct_logs<B, C>(
+	client: &C,
+	storage_override: &dyn StorageOverride<B>,
+	from: u32,
+	to: u32,
+	filter: &SelfCheckFilter,
+) -> Result<Vec<LogPosition>, String>
+where
+	B: BlockT<Hash = H256>,
+	C: HeaderBackend<B>,
+{
+	// Alternatives of a topic position are merged across the topic combinations, a `None`
+	// being a wildcard.
+	let mut topics: [HashSet<H256>; 4] = Default::default();
+	for combination in &filter.topics {
+		for (index, topic) in combination.iter().enumerate() {
+			if let (Some(alternatives), Some(topic)) = (topics.get_mut(index), topic) {
+				alternatives.insert(*topic);
+			}
+		}
+	}
+
+	let mut out = vec![];
+	for block_number in from..=to {
+		let Some(hash) = client
+			.hash(block_number.unique_saturated_into())
+			.map_err(|e| format!("Failed retrieving hash of block #{block_number}: {e:?}"))?
+		else {
+			break;
+		};
+		let receipts = storage_override.current_receipts(hash).unwrap_or_default();
+		for (transaction_index, receipt) in receipts.iter().enumerate() {
+			let logs = match receipt {
+				ethereum::ReceiptV3::Legacy(d)
+				| ethereum::ReceiptV3::EIP2930(d)
+				| ethereum::ReceiptV3::EIP1559(d) => &d.logs,
+			};
+			for (log_index, log) in logs.iter().enumerate() {
+				let address_matches =
+					filter.addresses.is_empty() || filter.addresses.contains(&log.address);
+				let topics_match = topics.iter().enumerate().all(|(index, alternatives)| {
+					alternatives.is_empty()
+						|| log
+							.topics
+							.get(index)
+							.is_some_and(|topic| alternatives.contains(topic))
+				});
+				if address_matches && topics_match {
+					out.push((block_number, transaction_index as u32, log_index as u32));
+				}
+			}
+		}
+	}
+	Ok(out)
+}
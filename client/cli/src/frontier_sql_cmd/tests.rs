@@ -0,0 +1,798 @@
+// This file is part of Frontier.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{path::Path, sync::Arc};
+
+use ethereum_types::{H160, H256, U256};
+use futures::executor;
+use scale_codec::Encode;
+use sqlx::Row;
+use tempfile::tempdir;
+// Substrate
+use sc_block_builder::BlockBuilderBuilder;
+use sp_consensus::BlockOrigin;
+use sp_io::hashing::twox_128;
+use sp_runtime::{
+	generic::{Block, DigestItem, Header},
+	traits::{BlakeTwo256, Header as HeaderT},
+};
+use substrate_test_runtime_client::{
+	BlockBuilderExt, ClientBlockImportExt, DefaultTestClientBuilderExt, TestClientBuilder,
+};
+// Frontier
+use fc_storage::SchemaV3StorageOverride;
+use fp_storage::{constants::*, EthereumStorageSchema, PALLET_ETHEREUM_SCHEMA};
+use frontier_template_runtime::RuntimeApi;
+
+use crate::frontier_sql_cmd::{
+	migrate_kv_to_sql, reindex, self_check,
+	verify::{verify, LogDiscrepancy},
+	SelfCheckFilter,
+};
+
+type OpaqueBlock =
+	Block<Header<u64, BlakeTwo256>, substrate_test_runtime_client::runtime::Extrinsic>;
+
+fn storage_prefix_build(module: &[u8], storage: &[u8]) -> Vec<u8> {
+	[twox_128(module), twox_128(storage)].concat().to_vec()
+}
+
+fn ethereum_digest() -> DigestItem {
+	let partial_header = ethereum::PartialHeader {
+		parent_hash: H256::random(),
+		beneficiary: H160::default(),
+		state_root: H256::default(),
+		receipts_root: H256::default(),
+		logs_bloom: ethereum_types::Bloom::default(),
+		difficulty: U256::zero(),
+		number: U256::zero(),
+		gas_limit: U256::zero(),
+		gas_used: U256::zero(),
+		timestamp: 0u64,
+		extra_data: Vec::new(),
+		mix_hash: H256::default(),
+		nonce: ethereum_types::H64::default(),
+	};
+	let ethereum_block = ethereum::Block::new(partial_header, vec![], vec![]);
+	DigestItem::Consensus(
+		fp_consensus::FRONTIER_ENGINE_ID,
+		fp_consensus::PostLog::Hashes(fp_consensus::Hashes::from_block(ethereum_block)).encode(),
+	)
+}
+
+fn receipt(address: H160, topics: Vec<H256>) -> ethereum::ReceiptV3 {
+	ethereum::ReceiptV3::EIP1559(ethereum::EIP1559ReceiptData {
+		status_code: 0u8,
+		used_gas: U256::zero(),
+		logs_bloom: ethereum_types::Bloom::zero(),
+		logs: vec![ethereum::Log {
+			address,
+			topics,
+			data: vec![],
+		}],
+	})
+}
+
+#[tokio::test]
+async fn self_check_agrees_on_topic_wildcards() {
+	let tmp = tempdir().expect("create a temporary directory");
+	// Initialize storage with schema V3
+	let builder = TestClientBuilder::new().add_extra_storage(
+		PALLET_ETHEREUM_SCHEMA.to_vec(),
+		Encode::encode(&EthereumStorageSchema::V3),
+	);
+	// Client
+	let (client, _) = builder.build_with_native_executor::<RuntimeApi, _>(None);
+	let mut client = Arc::new(client);
+	// Overrides
+	let storage_override = Arc::new(SchemaV3StorageOverride::new(client.clone()));
+	// Indexer backend
+	let indexer_backend = fc_db::sql::Backend::new(
+		fc_db::sql::BackendConfig::Sqlite(fc_db::sql::SqliteBackendConfig {
+			path: Path::new("sqlite:///")
+				.join(tmp.path())
+				.join("test.db3")
+				.to_str()
+				.unwrap(),
+			create_if_missing: true,
+			cache_size: 204800,
+			thread_count: 4,
+			journal_mode: None,
+			synchronous: None,
+			busy_timeout: None,
+			index_logs: true,
+		}),
+		100,
+		None,
+		storage_override.clone(),
+	)
+	.await
+	.expect("indexer pool to be created");
+
+	let shared_topic = H256::repeat_byte(0x0f);
+	// Create 3 blocks, 2 receipts each, sharing the second topic.
+	for _ in 1..4 {
+		let chain = client.chain_info();
+		let mut builder = BlockBuilderBuilder::new(&*client)
+			.on_parent_block(chain.best_hash)
+			.with_parent_block_number(chain.best_number)
+			.build()
+			.unwrap();
+		builder
+			.push_deposit_log_digest_item(ethereum_digest())
+			.expect("deposit log");
+		let receipts = Encode::encode(&vec![
+			receipt(
+				H160::repeat_byte(0x01),
+				vec![H256::repeat_byte(0x01), shared_topic],
+			),
+			receipt(
+				H160::repeat_byte(0x02),
+				vec![
+					H256::repeat_byte(0x02),
+					shared_topic,
+					H256::repeat_byte(0x03),
+				],
+			),
+		]);
+		builder
+			.push_storage_change(
+				storage_prefix_build(PALLET_ETHEREUM, ETHEREUM_CURRENT_RECEIPTS),
+				Some(receipts),
+			)
+			.unwrap();
+		let block = builder.build().unwrap().block;
+		let block_hash = block.header.hash();
+		executor::block_on(client.import(BlockOrigin::Own, block)).unwrap();
+		indexer_backend
+			.insert_block_metadata::<_, sc_client_db::Backend<OpaqueBlock>>(
+				client.clone(),
+				block_hash,
+			)
+			.await
+			.expect("insert block metadata");
+		indexer_backend
+			.index_block_logs(block_hash)
+			.await
+			.expect("index block logs");
+	}
+
+	// Wildcard on the first position, the second one matches all logs.
+	let filter: SelfCheckFilter =
+		serde_json::from_str(&format!(r#"{{"topics":[[null,"{shared_topic:?}"]]}}"#)).unwrap();
+	assert_eq!(
+		self_check(&*client, &indexer_backend, 0, 3, filter).await,
+		Ok(6)
+	);
+
+	// Alternatives on the first position, a wildcard on the second.
+	let filter = SelfCheckFilter {
+		addresses: vec![],
+		topics: vec![
+			vec![Some(H256::repeat_byte(0x02)), None],
+			vec![Some(H256::repeat_byte(0x04))],
+		],
+	};
+	assert_eq!(
+		self_check(&*client, &indexer_backend, 0, 3, filter).await,
+		Ok(3)
+	);
+
+	// A missing row in the index is reported.
+	sqlx::query("DELETE FROM logs WHERE id = (SELECT MAX(id) FROM logs)")
+		.execute(indexer_backend.pool())
+		.await
+		.expect("delete log");
+	assert!(
+		self_check(&*client, &indexer_backend, 0, 3, SelfCheckFilter::default())
+			.await
+			.is_err()
+	);
+}
+
+#[tokio::test]
+async fn self_check_agrees_on_addresses_and_topic_positions() {
+	let tmp = tempdir().expect("create a temporary directory");
+	// Initialize storage with schema V3
+	let builder = TestClientBuilder::new().add_extra_storage(
+		PALLET_ETHEREUM_SCHEMA.to_vec(),
+		Encode::encode(&EthereumStorageSchema::V3),
+	);
+	// Client
+	let (client, _) = builder.build_with_native_executor::<RuntimeApi, _>(None);
+	let mut client = Arc::new(client);
+	// Overrides
+	let storage_override = Arc::new(SchemaV3StorageOverride::new(client.clone()));
+	// Indexer backend
+	let indexer_backend = fc_db::sql::Backend::new(
+		fc_db::sql::BackendConfig::Sqlite(fc_db::sql::SqliteBackendConfig {
+			path: Path::new("sqlite:///")
+				.join(tmp.path())
+				.join("test.db3")
+				.to_str()
+				.unwrap(),
+			create_if_missing: true,
+			cache_size: 204800,
+			thread_count: 4,
+			journal_mode: None,
+			synchronous: None,
+			busy_timeout: None,
+			index_logs: true,
+		}),
+		100,
+		None,
+		storage_override.clone(),
+	)
+	.await
+	.expect("indexer pool to be created");
+
+	let (topic_a, topic_b, topic_c) = (
+		H256::repeat_byte(0x0a),
+		H256::repeat_byte(0x0b),
+		H256::repeat_byte(0x0c),
+	);
+	// Create 3 blocks with the same topics at different positions.
+	for _ in 1..4 {
+		let chain = client.chain_info();
+		let mut builder = BlockBuilderBuilder::new(&*client)
+			.on_parent_block(chain.best_hash)
+			.with_parent_block_number(chain.best_number)
+			.build()
+			.unwrap();
+		builder
+			.push_deposit_log_digest_item(ethereum_digest())
+			.expect("deposit log");
+		let receipts = Encode::encode(&vec![
+			receipt(H160::repeat_byte(0x01), vec![topic_a, topic_b]),
+			receipt(H160::repeat_byte(0x02), vec![topic_a, topic_c]),
+			receipt(H160::repeat_byte(0x01), vec![topic_b, topic_a]),
+			receipt(H160::repeat_byte(0x03), vec![topic_a]),
+		]);
+		builder
+			.push_storage_change(
+				storage_prefix_build(PALLET_ETHEREUM, ETHEREUM_CURRENT_RECEIPTS),
+				Some(receipts),
+			)
+			.unwrap();
+		let block = builder.build().unwrap().block;
+		let block_hash = block.header.hash();
+		executor::block_on(client.import(BlockOrigin::Own, block)).unwrap();
+		indexer_backend
+			.insert_block_metadata::<_, sc_client_db::Backend<OpaqueBlock>>(
+				client.clone(),
+				block_hash,
+			)
+			.await
+			.expect("insert block metadata");
+		indexer_backend
+			.index_block_logs(block_hash)
+			.await
+			.expect("index block logs");
+	}
+
+	// Alternatives are OR-ed within a position and the positions AND-ed.
+	let filter = SelfCheckFilter {
+		addresses: vec![H160::repeat_byte(0x01), H160::repeat_byte(0x02)],
+		topics: vec![
+			vec![Some(topic_a), Some(topic_b)],
+			vec![Some(topic_a), Some(topic_c)],
+		],
+	};
+	assert_eq!(
+		self_check(&*client, &indexer_backend, 0, 3, filter).await,
+		Ok(6)
+	);
+
+	// A topic only matches at its own position.
+	let filter = SelfCheckFilter {
+		addresses: vec![],
+		topics: vec![vec![None, Some(topic_a)]],
+	};
+	assert_eq!(
+		self_check(&*client, &indexer_backend, 0, 3, filter).await,
+		Ok(3)
+	);
+
+	// A log without a topic at a filtered position never matches.
+	let filter = SelfCheckFilter {
+		addresses: vec![H160::repeat_byte(0x03)],
+		topics: vec![vec![Some(topic_a), Some(topic_b)]],
+	};
+	assert_eq!(
+		self_check(&*client, &indexer_backend, 0, 3, filter).await,
+		Ok(0)
+	);
+
+	// Trailing positions left out of the filter are wildcards.
+	let filter = SelfCheckFilter {
+		addresses: vec![],
+		topics: vec![vec![Some(topic_a)]],
+	};
+	assert_eq!(
+		self_check(&*client, &indexer_backend, 0, 3, filter).await,
+		Ok(9)
+	);
+}
+
+#[tokio::test]
+async fn reindex_rebuilds_the_blocks_from_the_given_height() {
+	let tmp = tempdir().expect("create a temporary directory");
+	let builder = TestClientBuilder::new().add_extra_storage(
+		PALLET_ETHEREUM_SCHEMA.to_vec(),
+		Encode::encode(&EthereumStorageSchema::V3),
+	);
+	let (client, _) = builder.build_with_native_executor::<RuntimeApi, _>(None);
+	let mut client = Arc::new(client);
+	let storage_override = Arc::new(SchemaV3StorageOverride::new(client.clone()));
+	let indexer_backend = fc_db::sql::Backend::new(
+		fc_db::sql::BackendConfig::Sqlite(fc_db::sql::SqliteBackendConfig {
+			path: Path::new("sqlite:///")
+				.join(tmp.path())
+				.join("test.db3")
+				.to_str()
+				.unwrap(),
+			create_if_missing: true,
+			cache_size: 204800,
+			thread_count: 4,
+			journal_mode: None,
+			synchronous: None,
+			busy_timeout: None,
+			index_logs: true,
+		}),
+		100,
+		None,
+		storage_override.clone(),
+	)
+	.await
+	.expect("indexer pool to be created");
+
+	// Index 20 blocks, with a log each.
+	for number in 1..=20u8 {
+		let chain = client.chain_info();
+		let mut builder = BlockBuilderBuilder::new(&*client)
+			.on_parent_block(chain.best_hash)
+			.with_parent_block_number(chain.best_number)
+			.build()
+			.unwrap();
+		builder
+			.push_deposit_log_digest_item(ethereum_digest())
+			.expect("deposit log");
+		let receipts = Encode::encode(&vec![receipt(
+			H160::repeat_byte(number),
+			vec![H256::repeat_byte(number)],
+		)]);
+		builder
+			.push_storage_change(
+				storage_prefix_build(PALLET_ETHEREUM, ETHEREUM_CURRENT_RECEIPTS),
+				Some(receipts),
+			)
+			.unwrap();
+		let block = builder.build().unwrap().block;
+		let block_hash = block.header.hash();
+		executor::block_on(client.import(BlockOrigin::Own, block)).unwrap();
+		indexer_backend
+			.insert_block_metadata::<_, sc_client_db::Backend<OpaqueBlock>>(
+				client.clone(),
+				block_hash,
+			)
+			.await
+			.expect("insert block metadata");
+		indexer_backend
+			.index_block_logs(block_hash)
+			.await
+			.expect("index block logs");
+	}
+
+	let rows_from_10 = || {
+		let indexer_backend = &indexer_backend;
+		async move {
+			let blocks = sqlx::query(
+				"SELECT substrate_block_hash, ethereum_block_hash, block_number, is_canon
+				FROM blocks WHERE block_number >= 10 ORDER BY block_number",
+			)
+			.fetch_all(indexer_backend.pool())
+			.await
+			.expect("test query result")
+			.iter()
+			.map(|row| {
+				(
+					row.get::<Vec<u8>, _>(0),
+					row.get::<Vec<u8>, _>(1),
+					row.get::<i32, _>(2),
+					row.get::<i32, _>(3),
+				)
+			})
+			.collect::<Vec<_>>();
+			let logs = sqlx::query(
+				"SELECT l.substrate_block_hash, address, topic_1, transaction_index, log_index
+				FROM logs AS l INNER JOIN blocks AS b ON l.substrate_block_hash = b.substrate_block_hash
+				WHERE b.block_number >= 10 ORDER BY b.block_number, transaction_index, log_index",
+			)
+			.fetch_all(indexer_backend.pool())
+			.await
+			.expect("test query result")
+			.iter()
+			.map(|row| {
+				(
+					row.get::<Vec<u8>, _>(0),
+					row.get::<Vec<u8>, _>(1),
+					row.get::<Option<Vec<u8>>, _>(2),
+					row.get::<i64, _>(3),
+					row.get::<i64, _>(4),
+				)
+			})
+			.collect::<Vec<_>>();
+			let synced = sqlx::query(
+				"SELECT COUNT(*) FROM sync_status AS s
+				INNER JOIN blocks AS b ON s.substrate_block_hash = b.substrate_block_hash
+				WHERE b.block_number >= 10 AND s.metadata_done = 1 AND s.logs_done = 1",
+			)
+			.fetch_one(indexer_backend.pool())
+			.await
+			.expect("test query result")
+			.get::<i64, _>(0);
+			(blocks, logs, synced)
+		}
+	};
+	let indexed = rows_from_10().await;
+	assert_eq!(indexed.0.len(), 11);
+	assert_eq!(indexed.1.len(), 11);
+	assert_eq!(indexed.2, 11);
+
+	// Corrupt a log, then rebuild the blocks from #10.
+	sqlx::query("UPDATE logs SET topic_1 = NULL WHERE substrate_block_hash = ?")
+		.bind(&indexed.0[5].0)
+		.execute(indexer_backend.pool())
+		.await
+		.expect("corrupt log");
+	assert_eq!(
+		reindex::<_, _, sc_client_db::Backend<OpaqueBlock>>(
+			client.clone(),
+			&indexer_backend,
+			10,
+			20
+		)
+		.await,
+		Ok(11)
+	);
+	assert_eq!(rows_from_10().await, indexed);
+
+	// The blocks above the reindexed range are kept.
+	assert_eq!(
+		reindex::<_, _, sc_client_db::Backend<OpaqueBlock>>(
+			client.clone(),
+			&indexer_backend,
+			10,
+			15
+		)
+		.await,
+		Ok(6)
+	);
+	assert_eq!(rows_from_10().await, indexed);
+}
+
+#[tokio::test]
+async fn verify_reports_the_corrupted_log_row() {
+	let tmp = tempdir().expect("create a temporary directory");
+	// Initialize storage with schema V3
+	let builder = TestClientBuilder::new().add_extra_storage(
+		PALLET_ETHEREUM_SCHEMA.to_vec(),
+		Encode::encode(&EthereumStorageSchema::V3),
+	);
+	// Client
+	let (client, _) = builder.build_with_native_executor::<RuntimeApi, _>(None);
+	let mut client = Arc::new(client);
+	// Overrides
+	let storage_override = Arc::new(SchemaV3StorageOverride::new(client.clone()));
+	// Indexer backend
+	let indexer_backend = fc_db::sql::Backend::new(
+		fc_db::sql::BackendConfig::Sqlite(fc_db::sql::SqliteBackendConfig {
+			path: Path::new("sqlite:///")
+				.join(tmp.path())
+				.join("test.db3")
+				.to_str()
+				.unwrap(),
+			create_if_missing: true,
+			cache_size: 204800,
+			thread_count: 4,
+			journal_mode: None,
+			synchronous: None,
+			busy_timeout: None,
+			index_logs: true,
+		}),
+		100,
+		None,
+		storage_override.clone(),
+	)
+	.await
+	.expect("indexer pool to be created");
+
+	let shared_topic = H256::repeat_byte(0x0f);
+	// Create 3 blocks, 2 receipts each, sharing the second topic.
+	for _ in 1..4 {
+		let chain = client.chain_info();
+		let mut builder = BlockBuilderBuilder::new(&*client)
+			.on_parent_block(chain.best_hash)
+			.with_parent_block_number(chain.best_number)
+			.build()
+			.unwrap();
+		builder
+			.push_deposit_log_digest_item(ethereum_digest())
+			.expect("deposit log");
+		let receipts = Encode::encode(&vec![
+			receipt(
+				H160::repeat_byte(0x01),
+				vec![H256::repeat_byte(0x01), shared_topic],
+			),
+			receipt(
+				H160::repeat_byte(0x02),
+				vec![
+					H256::repeat_byte(0x02),
+					shared_topic,
+					H256::repeat_byte(0x03),
+				],
+			),
+		]);
+		builder
+			.push_storage_change(
+				storage_prefix_build(PALLET_ETHEREUM, ETHEREUM_CURRENT_RECEIPTS),
+				Some(receipts),
+			)
+			.unwrap();
+		let block = builder.build().unwrap().block;
+		let block_hash = block.header.hash();
+		executor::block_on(client.import(BlockOrigin::Own, block)).unwrap();
+		indexer_backend
+			.insert_block_metadata::<_, sc_client_db::Backend<OpaqueBlock>>(
+				client.clone(),
+				block_hash,
+			)
+			.await
+			.expect("insert block metadata");
+		indexer_backend
+			.index_block_logs(block_hash)
+			.await
+			.expect("index block logs");
+	}
+
+	assert_eq!(verify(&*client, &indexer_backend, 0, 3).await, Ok(vec![]));
+
+	// Corrupt the first topic of the second log of block #2.
+	let corrupted_topic = H256::repeat_byte(0xff);
+	sqlx::query("UPDATE logs SET topic_1 = ? WHERE block_number = 2 AND transaction_index = 1")
+		.bind(corrupted_topic.as_bytes())
+		.execute(indexer_backend.pool())
+		.await
+		.expect("corrupt log");
+	assert_eq!(
+		verify(&*client, &indexer_backend, 0, 3).await,
+		Ok(vec![LogDiscrepancy::Mismatch {
+			position: (2, 1, 0),
+			expected: (
+				H160::repeat_byte(0x02),
+				vec![
+					H256::repeat_byte(0x02),
+					shared_topic,
+					H256::repeat_byte(0x03)
+				]
+			),
+			indexed: (
+				H160::repeat_byte(0x02),
+				vec![corrupted_topic, shared_topic, H256::repeat_byte(0x03)]
+			),
+		}])
+	);
+}
+
+#[tokio::test]
+async fn migrate_kv_to_sql_matches_a_direct_sql_sync() {
+	let tmp = tempdir().expect("create a temporary directory");
+	let builder = TestClientBuilder::new().add_extra_storage(
+		PALLET_ETHEREUM_SCHEMA.to_vec(),
+		Encode::encode(&EthereumStorageSchema::V3),
+	);
+	let substrate_backend = builder.backend();
+	let (client, _) = builder.build_with_native_executor::<RuntimeApi, _>(None);
+	let mut client = Arc::new(client);
+	let storage_override = Arc::new(SchemaV3StorageOverride::new(client.clone()));
+	let kv_backend = fc_db::kv::Backend::<OpaqueBlock, _>::new(
+		client.clone(),
+		&fc_db::kv::DatabaseSettings {
+			source: sc_client_db::DatabaseSource::RocksDb {
+				path: tmp.path().join("kv"),
+				cache_size: 0,
+			},
+		},
+	)
+	.expect("key-value backend to be created");
+	let mut sql_backends = vec![];
+	for name in ["migrated.db3", "synced.db3"] {
+		let backend = fc_db::sql::Backend::new(
+			fc_db::sql::BackendConfig::Sqlite(fc_db::sql::SqliteBackendConfig {
+				path: Path::new("sqlite:///")
+					.join(tmp.path())
+					.join(name)
+					.to_str()
+					.unwrap(),
+				create_if_missing: true,
+				cache_size: 204800,
+				thread_count: 4,
+				journal_mode: None,
+				synchronous: None,
+				busy_timeout: None,
+				index_logs: true,
+			}),
+			100,
+			None,
+			storage_override.clone(),
+		)
+		.await
+		.expect("indexer pool to be created");
+		sql_backends.push(backend);
+	}
+	let (migrated_backend, synced_backend) = (&sql_backends[0], &sql_backends[1]);
+
+	// Import 10 blocks with a log each. The key-value backend syncs the first 8 ones, maps
+	// the 9th one to an ethereum block hash of its own and leaves the last one unsynced.
+	let kv_storage_override: Arc<dyn fc_storage::StorageOverride<OpaqueBlock>> =
+		storage_override.clone();
+	let mut block_hashes = vec![];
+	for number in 1..=10u8 {
+		let chain = client.chain_info();
+		let mut builder = BlockBuilderBuilder::new(&*client)
+			.on_parent_block(chain.best_hash)
+			.with_parent_block_number(chain.best_number)
+			.build()
+			.unwrap();
+		builder
+			.push_deposit_log_digest_item(ethereum_digest())
+			.expect("deposit log");
+		let receipts = Encode::encode(&vec![receipt(
+			H160::repeat_byte(number),
+			vec![H256::repeat_byte(number)],
+		)]);
+		builder
+			.push_storage_change(
+				storage_prefix_build(PALLET_ETHEREUM, ETHEREUM_CURRENT_RECEIPTS),
+				Some(receipts),
+			)
+			.unwrap();
+		let block = builder.build().unwrap().block;
+		let block_hash = block.header.hash();
+		let header = block.header.clone();
+		executor::block_on(client.import(BlockOrigin::Own, block)).unwrap();
+		if number < 9 {
+			fc_mapping_sync::kv::sync_block(kv_storage_override.clone(), &kv_backend, &header)
+				.expect("sync the key-value mapping");
+		} else if number == 9 {
+			kv_backend
+				.mapping()
+				.write_hashes(fc_db::kv::MappingCommitment {
+					block_hash,
+					ethereum_block_hash: H256::repeat_byte(number),
+					ethereum_transaction_hashes: vec![],
+				})
+				.expect("write the key-value mapping");
+		}
+		block_hashes.push(block_hash);
+	}
+
+	assert_eq!(
+		migrate_kv_to_sql::<_, _, sc_client_db::Backend<OpaqueBlock>>(
+			client.clone(),
+			kv_backend.mapping(),
+			migrated_backend,
+			10
+		)
+		.await,
+		Ok((9, 1))
+	);
+
+	// Sync the other backend up to the 9th block with the SQL indexing worker.
+	synced_backend
+		.insert_genesis_block_metadata(client.clone())
+		.await
+		.expect("insert genesis block metadata");
+	let pubsub_notification_sinks: fc_mapping_sync::EthereumBlockNotificationSinks<
+		fc_mapping_sync::EthereumBlockNotification<OpaqueBlock>,
+	> = Default::default();
+	let tx = fc_mapping_sync::sql::SyncWorker::spawn_worker(
+		client.clone(),
+		substrate_backend,
+		Arc::new(synced_backend.clone()),
+		Arc::new(pubsub_notification_sinks),
+		None,
+		None,
+		None,
+		fc_mapping_sync::sql::DEFAULT_MAX_REORG_DEPTH,
+	)
+	.await;
+	tx.send(fc_mapping_sync::sql::WorkerCommand::IndexBestBlock(
+		block_hashes[8],
+	))
+	.await
+	.expect("send command");
+	let (done_tx, done_rx) = futures::channel::oneshot::channel();
+	tx.send(fc_mapping_sync::sql::WorkerCommand::Shutdown(done_tx))
+		.await
+		.expect("send command");
+	done_rx.await.expect("worker stopped");
+
+	let rows = |backend: &fc_db::sql::Backend<OpaqueBlock>| {
+		let pool = backend.pool().clone();
+		async move {
+			let blocks = sqlx::query(
+				"SELECT substrate_block_hash, ethereum_block_hash, block_number, is_canon
+				FROM blocks ORDER BY block_number",
+			)
+			.fetch_all(&pool)
+			.await
+			.expect("test query result")
+			.iter()
+			.map(|row| {
+				(
+					row.get::<Vec<u8>, _>(0),
+					row.get::<Vec<u8>, _>(1),
+					row.get::<i32, _>(2),
+					row.get::<i32, _>(3),
+				)
+			})
+			.collect::<Vec<_>>();
+			let logs = sqlx::query(
+				"SELECT substrate_block_hash, address, topic_1, transaction_index, log_index
+				FROM logs ORDER BY block_number, transaction_index, log_index",
+			)
+			.fetch_all(&pool)
+			.await
+			.expect("test query result")
+			.iter()
+			.map(|row| {
+				(
+					row.get::<Vec<u8>, _>(0),
+					row.get::<Vec<u8>, _>(1),
+					row.get::<Option<Vec<u8>>, _>(2),
+					row.get::<i64, _>(3),
+					row.get::<i64, _>(4),
+				)
+			})
+			.collect::<Vec<_>>();
+			(blocks, logs)
+		}
+	};
+	// The genesis and the synced blocks, the 9th one under the hash of the key-value mapping.
+	let migrated = rows(migrated_backend).await;
+	assert_eq!(migrated.0.len(), 10);
+	assert_eq!(migrated.1.len(), 9);
+	let mut synced = rows(synced_backend).await;
+	assert_ne!(synced.0[9].1, H256::repeat_byte(9).as_bytes());
+	synced.0[9].1 = H256::repeat_byte(9).as_bytes().to_vec();
+	assert_eq!(migrated, synced);
+
+	// Running the migration again resumes after the migrated blocks.
+	assert_eq!(
+		migrate_kv_to_sql::<_, _, sc_client_db::Backend<OpaqueBlock>>(
+			client.clone(),
+			kv_backend.mapping(),
+			migrated_backend,
+			10
+		)
+		.await,
+		Ok((0, 1))
+	);
+	assert_eq!(rows(migrated_backend).await, migrated);
+}
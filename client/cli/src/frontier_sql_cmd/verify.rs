@@ -0,0 +1,134 @@
+// This file is part of Frontier.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{collections::BTreeMap, fmt};
+
+use ethereum_types::{H160, H256};
+// Substrate
+use sp_blockchain::HeaderBackend;
+use sp_runtime::traits::{Block as BlockT, UniqueSaturatedInto};
+
+use super::self_check::LogPosition;
+
+/// A difference between the logs table and the receipts stored in the state.
+#[derive(Debug, Eq, PartialEq)]
+pub enum LogDiscrepancy {
+	/// The log is in the state but has no row.
+	Missing(LogPosition),
+	/// The row has no log in the state.
+	Extra(LogPosition),
+	/// The row has another address or other topics than the log in the state.
+	Mismatch {
+		position: LogPosition,
+		expected: (H160, Vec<H256>),
+		indexed: (H160, Vec<H256>),
+	},
+}
+
+impl fmt::Display for LogDiscrepancy {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Missing((block, transaction, log)) => {
+				write!(f, "#{block} transaction {transaction} log {log}: missing")
+			}
+			Self::Extra((block, transaction, log)) => {
+				write!(
+					f,
+					"#{block} transaction {transaction} log {log}: not in the state"
+				)
+			}
+			Self::Mismatch {
+				position: (block, transaction, log),
+				expected,
+				indexed,
+			} => write!(
+				f,
+				"#{block} transaction {transaction} log {log}: indexed as {indexed:?}, \
+				expected {expected:?}"
+			),
+		}
+	}
+}
+
+/// Compares the address and topics of every log row of the canonical blocks in the range
+/// with the receipts stored in the state, returning the differences found.
+pub async fn verify<B, C>(
+	client: &C,
+	backend: &fc_db::sql::Backend<B>,
+	from: u32,
+	to: u32,
+) -> Result<Vec<LogDiscrepancy>, String>
+where
+	B: BlockT<Hash = H256>,
+	C: HeaderBackend<B>,
+{
+	if !backend.logs_indexed() {
+		return Err("The SQL backend does not index logs".to_string());
+	}
+	let storage_override = backend.storage_override();
+	let mut discrepancies = vec![];
+	for block_number in from..=to {
+		let Some(hash) = client
+			.hash(block_number.unique_saturated_into())
+			.map_err(|e| format!("Failed retrieving hash of block #{block_number}: {e:?}"))?
+		else {
+			break;
+		};
+
+		let mut expected = BTreeMap::new();
+		let receipts = storage_override.current_receipts(hash).unwrap_or_default();
+		for (transaction_index, receipt) in receipts.iter().enumerate() {
+			let logs = match receipt {
+				ethereum::ReceiptV3::Legacy(d)
+				| ethereum::ReceiptV3::EIP2930(d)
+				| ethereum::ReceiptV3::EIP1559(d) => &d.logs,
+			};
+			for (log_index, log) in logs.iter().enumerate() {
+				expected.insert(
+					(block_number, transaction_index as u32, log_index as u32),
+					(log.address, log.topics.clone()),
+				);
+			}
+		}
+
+		let indexed = backend
+			.block_logs(hash)
+			.await
+			.map_err(|e| format!("Failed reading the logs of block #{block_number}: {e}"))?;
+		for (transaction_index, log) in indexed {
+			let position = (block_number, transaction_index, log.log_index);
+			let indexed = (log.address, log.topics);
+			match expected.remove(&position) {
+				Some(expected) if expected == indexed => {}
+				Some(expected) => discrepancies.push(LogDiscrepancy::Mismatch {
+					position,
+					expected,
+					indexed,
+				}),
+				None => discrepancies.push(LogDiscrepancy::Extra(position)),
+			}
+		}
+		discrepancies.extend(expected.into_keys().map(LogDiscrepancy::Missing));
+	}
+	discrepancies.sort_by_key(|discrepancy| match discrepancy {
+		LogDiscrepancy::Missing(position)
+		| LogDiscrepancy::Extra(position)
+		| LogDiscrepancy::Mismatch { position, .. } => *position,
+	});
+	Ok(discrepancies)
+}
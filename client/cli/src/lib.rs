@@ -19,5 +19,9 @@
 #![warn(unused_crate_dependencies)]
 
 mod frontier_db_cmd;
+#[cfg(feature = "sql")]
+mod frontier_sql_cmd;
 
 pub use self::frontier_db_cmd::FrontierDbCmd;
+#[cfg(feature = "sql")]
+pub use self::frontier_sql_cmd::{FrontierSqlCmd, SqlOperation};
@@ -110,6 +110,7 @@ impl<Block: BlockT> fc_api::LogIndexerBackend<Block> for LogIndexerBackend<Block
 		_to_block: u64,
 		_addresses: Vec<H160>,
 		_topics: Vec<Vec<Option<H256>>>,
+		_limit: u32,
 	) -> Result<Vec<FilteredLog<Block>>, String> {
 		Err("KeyValue db does not index logs".into())
 	}
@@ -247,6 +248,14 @@ pub struct MappingCommitment<Block: BlockT> {
 	pub ethereum_transaction_hashes: Vec<H256>,
 }
 
+/// The ethereum hashes mapped to a synced block, stored after its synced flag by
+/// [`MappingDb::write_hashes`].
+#[derive(Clone, Debug, Eq, PartialEq, Encode, Decode)]
+pub struct SyncedHashes {
+	pub ethereum_block_hash: H256,
+	pub ethereum_transaction_hashes: Vec<H256>,
+}
+
 pub struct MappingDb<Block> {
 	db: Arc<dyn Database<DbHash>>,
 	write_lock: Arc<Mutex<()>>,
@@ -261,6 +270,21 @@ impl<Block: BlockT> MappingDb<Block> {
 		}
 	}
 
+	/// The ethereum hashes mapped to the given synced block. `None` if the block is not
+	/// synced, has no ethereum block, or was synced before the hashes were recorded.
+	pub fn synced_hashes(&self, block_hash: &Block::Hash) -> Result<Option<SyncedHashes>, String> {
+		let Some(raw) = self.db.get(columns::SYNCED_MAPPING, &block_hash.encode()) else {
+			return Ok(None);
+		};
+		let input = &mut &raw[..];
+		if !bool::decode(input).map_err(|e| format!("{:?}", e))? || input.is_empty() {
+			return Ok(None);
+		}
+		Ok(Some(
+			SyncedHashes::decode(input).map_err(|e| format!("{:?}", e))?,
+		))
+	}
+
 	pub fn block_hash(
 		&self,
 		ethereum_block_hash: &H256,
@@ -311,6 +335,12 @@ impl<Block: BlockT> MappingDb<Block> {
 
 		let mut transaction = sp_database::Transaction::new();
 
+		// Recorded after the synced flag, which is still decoded on its own.
+		let synced_hashes = SyncedHashes {
+			ethereum_block_hash: commitment.ethereum_block_hash,
+			ethereum_transaction_hashes: commitment.ethereum_transaction_hashes.clone(),
+		};
+
 		let substrate_hashes = match self.block_hash(&commitment.ethereum_block_hash) {
 			Ok(Some(mut data)) => {
 				if !data.contains(&commitment.block_hash) {
@@ -354,7 +384,7 @@ impl<Block: BlockT> MappingDb<Block> {
 		transaction.set(
 			columns::SYNCED_MAPPING,
 			&commitment.block_hash.encode(),
-			&true.encode(),
+			&(true, synced_hashes).encode(),
 		);
 
 		self.db.commit(transaction).map_err(|e| e.to_string())?;
@@ -16,22 +16,40 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
-use std::{cmp::Ordering, collections::HashSet, num::NonZeroU32, str::FromStr, sync::Arc};
+use std::{
+	cmp::Ordering,
+	collections::{HashMap, HashSet, VecDeque},
+	num::NonZeroU32,
+	ops::RangeInclusive,
+	str::FromStr,
+	sync::{
+		atomic::{AtomicU64, Ordering as AtomicOrdering},
+		Arc,
+	},
+	time::{Duration, Instant},
+};
 
-use futures::TryStreamExt;
+use futures::{StreamExt, TryStreamExt};
 use scale_codec::{Decode, Encode};
 use sqlx::{
 	query::Query,
 	sqlite::{
 		SqliteArguments, SqliteConnectOptions, SqlitePool, SqlitePoolOptions, SqliteQueryResult,
+		SqliteRow,
 	},
 	ConnectOptions, Error, Execute, QueryBuilder, Row, Sqlite,
 };
 // Substrate
-use sc_client_api::backend::{Backend as BackendT, StorageProvider};
+use sc_client_api::{
+	backend::{Backend as BackendT, StorageProvider},
+	BlockBackend,
+};
 use sp_api::{ApiExt, ProvideRuntimeApi};
 use sp_blockchain::HeaderBackend;
-use sp_core::{H160, H256};
+use sp_core::{
+	hashing::{blake2_128, keccak_256},
+	H160, H256, H64, U256,
+};
 use sp_runtime::{
 	generic::BlockId,
 	traits::{Block as BlockT, Header as HeaderT, UniqueSaturatedInto, Zero},
@@ -43,9 +61,103 @@ use fp_consensus::{FindLogError, Hashes, Log as ConsensusLog, PostLog, PreLog};
 use fp_rpc::EthereumRuntimeRPCApi;
 use fp_storage::EthereumStorageSchema;
 
+mod proof;
+mod recovery;
+
+pub use self::{
+	proof::verify_receipt_proof,
+	recovery::{NativeSignerRecovery, SignerRecovery, SoftwareSignerRecovery},
+};
+pub use sqlx::sqlite::{SqliteJournalMode, SqliteSynchronous};
+
 /// Maximum number to topics allowed to be filtered upon
 const MAX_TOPIC_COUNT: u16 = 4;
 
+/// Default number of rows fetched per chunk when filtering logs.
+const DEFAULT_LOG_FETCH_CHUNK_SIZE: u32 = 1000;
+
+/// Default number of block hashes bound by a single canonicalization statement, below
+/// SQLite's historical limit of 999 parameters.
+const DEFAULT_CANONICALIZE_CHUNK_SIZE: u32 = 500;
+
+/// Default number of attempts at writing the metadata of a block on transient errors.
+const DEFAULT_INSERT_ATTEMPTS: u32 = 4;
+
+/// Default delay before the second attempt at writing the metadata of a block, doubled on
+/// each further attempt.
+const DEFAULT_INSERT_BACKOFF: Duration = Duration::from_millis(50);
+
+/// Number of block hashes bound by a single membership lookup, below SQLite's historical
+/// limit of 999 parameters.
+const HASH_LOOKUP_CHUNK_SIZE: usize = 500;
+
+/// Maximum number of block numbers spanned by a single [`Backend::blocks_in_range`] call.
+const MAX_BLOCKS_IN_RANGE: u32 = 10_000;
+
+/// Default window over which the indexing throughput is averaged.
+const DEFAULT_THROUGHPUT_WINDOW: Duration = Duration::from_secs(60);
+
+/// Default maximum number of distinct addresses a single filter call can match.
+const DEFAULT_MAX_FILTER_ADDRESSES: u32 = 1000;
+
+/// Default maximum number of distinct topic alternatives per position of a filter call.
+const DEFAULT_MAX_TOPIC_ALTERNATIVES: u32 = 1000;
+
+/// Default number of blocks whose logs the sync worker indexes together.
+const DEFAULT_LOGS_BATCH_SIZE: usize = 64;
+
+/// Topic positions with more alternatives are filtered in memory by the
+/// [`TopicFilterStrategy::Auto`] planner, as long as another predicate narrows the query.
+const AUTO_MAX_SQL_TOPIC_ALTERNATIVES: usize = 16;
+
+/// The `indexer_meta` key of the last finalized block observed by the indexer.
+const LAST_FINALIZED_KEY: &str = "last_finalized";
+
+/// The `indexer_meta` key of the highest canonical block the indexer caught up with.
+const CHECKPOINT_KEY: &str = "checkpoint";
+
+/// The `indexer_meta` key of the height below which the blocks were pruned.
+const PRUNED_BELOW_KEY: &str = "pruned_below";
+
+/// Computes the `block_summary` rows of the selected blocks from the detailed tables.
+const SUMMARIZE_BLOCKS: &str = "INSERT OR REPLACE INTO block_summary(
+		substrate_block_hash,
+		block_number,
+		ethereum_block_hash,
+		transaction_count,
+		log_count,
+		gas_used,
+		timestamp,
+		is_canon)
+	SELECT
+		b.substrate_block_hash,
+		b.block_number,
+		b.ethereum_block_hash,
+		(SELECT COUNT(*) FROM transactions AS t
+			WHERE t.substrate_block_hash = b.substrate_block_hash),
+		(SELECT COUNT(*) FROM logs AS l
+			WHERE l.substrate_block_hash = b.substrate_block_hash),
+		h.gas_used,
+		h.timestamp,
+		b.is_canon
+	FROM blocks AS b
+	LEFT JOIN block_headers AS h ON h.substrate_block_hash = b.substrate_block_hash";
+
+/// The tables holding per-block rows, children first so a block is deleted last.
+const BLOCK_TABLES: [&str; 8] = [
+	"logs",
+	"transactions",
+	"raw_receipts",
+	"block_headers",
+	"block_summary",
+	"contracts",
+	"sync_status",
+	"blocks",
+];
+
+/// The error returned when filtering logs on a backend indexing blocks only.
+pub const LOGS_NOT_INDEXED: &str = "logs not indexed";
+
 /// Represents a log item.
 #[derive(Debug, Eq, PartialEq)]
 pub struct Log {
@@ -54,9 +166,347 @@ pub struct Log {
 	pub topic_2: Option<Vec<u8>>,
 	pub topic_3: Option<Vec<u8>>,
 	pub topic_4: Option<Vec<u8>>,
-	pub log_index: i32,
-	pub transaction_index: i32,
+	pub log_index: i64,
+	pub transaction_index: i64,
 	pub substrate_block_hash: Vec<u8>,
+	/// The number of topics of the log, the topic columns past it are `NULL`.
+	pub topic_count: i32,
+}
+
+/// The storage representation of the log address and topic columns.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum LogColumnEncoding {
+	/// Raw bytes, stored as `BLOB`.
+	#[default]
+	Bytes,
+	/// `0x`-prefixed lowercase hex strings, stored as `TEXT`.
+	Hex,
+}
+
+impl LogColumnEncoding {
+	fn column(self, bytes: &[u8]) -> LogColumn {
+		match self {
+			Self::Bytes => LogColumn::Bytes(bytes.to_vec()),
+			Self::Hex => LogColumn::Hex(format!("0x{}", hex_encode(bytes))),
+		}
+	}
+}
+
+/// Where the topic predicates of a filter call are evaluated.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum TopicFilterStrategy {
+	/// Queries the topic positions with few alternatives, and filters the ones with many
+	/// alternatives in memory unless nothing else narrows the query.
+	#[default]
+	Auto,
+	/// Queries every topic predicate.
+	Sql,
+	/// Queries the block range and addresses only, filtering every topic in memory.
+	Memory,
+}
+
+/// How a block is indexed when the sender of one of its transactions cannot be recovered
+/// from the signature.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum MalformedSignaturePolicy {
+	/// Indexes the block, storing the transaction without a sender, and logs a warning.
+	#[default]
+	Skip,
+	/// Leaves the block unindexed and records it in the dead letter table, see
+	/// [`Backend::dead_letter_blocks`].
+	DeadLetter,
+	/// Fails the block and records a fatal error, stopping a worker that exits on them.
+	Halt,
+}
+
+/// The outcome of indexing the metadata of a block.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BlockIndexing {
+	/// The block metadata was written.
+	Indexed,
+	/// The block metadata was already indexed, nothing was written.
+	AlreadyIndexed,
+	/// The block was left unindexed, either non-canonical on a canonical-only backend or
+	/// moved to the dead letters by the [`MalformedSignaturePolicy`].
+	Skipped,
+}
+
+/// The unique key deduplicating the rows of the logs table.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum LogKey {
+	/// The `(log_index, transaction_index, substrate_block_hash)` unique constraint.
+	#[default]
+	Composite,
+	/// A unique 16 bytes `log_key` column, the BLAKE2-128 hash of the composite key.
+	/// Storing a log whose key collides with a different log fails.
+	Hashed,
+}
+
+/// How many blocks have their logs indexed together by the sync worker.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum LogsBatchSize {
+	/// A constant number of blocks.
+	Fixed(usize),
+	/// Starts at `min` blocks, doubled after a full batch committed within half the `target`
+	/// latency and halved after a batch slower than `target`, staying within `[min, max]`.
+	Adaptive {
+		min: usize,
+		max: usize,
+		target: Duration,
+	},
+}
+
+impl Default for LogsBatchSize {
+	fn default() -> Self {
+		Self::Fixed(DEFAULT_LOGS_BATCH_SIZE)
+	}
+}
+
+/// The current logs batch size, adapted to the commit latency of the previous batches.
+struct LogsBatch {
+	config: LogsBatchSize,
+	size: usize,
+}
+
+impl LogsBatch {
+	fn new(config: LogsBatchSize) -> Self {
+		let size = match config {
+			LogsBatchSize::Fixed(size) => size,
+			LogsBatchSize::Adaptive { min, .. } => min,
+		};
+		Self {
+			config,
+			size: size.max(1),
+		}
+	}
+
+	/// Adapts the size to the latency of a committed batch of `blocks` blocks. A batch
+	/// smaller than the current size, as at the tip of the chain, never grows it.
+	fn record(&mut self, blocks: usize, elapsed: Duration) {
+		let LogsBatchSize::Adaptive { min, max, target } = self.config else {
+			return;
+		};
+		if elapsed > target {
+			self.size = (self.size / 2).max(min);
+		} else if elapsed < target / 2 && blocks >= self.size {
+			self.size = self.size.saturating_mul(2).min(max);
+		}
+		self.size = self.size.max(1);
+	}
+}
+
+/// The indexing writes deferred to a single transaction by [`Backend::with_commit_interval`],
+/// committed by the first write past `interval` since the first one, or once `max_blocks`
+/// blocks are grouped. There is no timer, an idle group waiting for a write or a flush.
+struct CommitGroup {
+	interval: Duration,
+	max_blocks: usize,
+	opened: Option<Instant>,
+	/// The metadata of the grouped blocks, in indexing order.
+	blocks: Vec<BlockMetadata>,
+	/// The grouped blocks whose logs are indexed, in indexing order.
+	logs: Vec<H256>,
+	checkpoint: Option<(u32, H256)>,
+	/// The last backfill progress update of each descent, `None` clearing it.
+	backfill_progress: HashMap<H256, Option<u32>>,
+}
+
+impl CommitGroup {
+	fn new(interval: Duration, max_blocks: usize) -> Self {
+		Self {
+			interval,
+			max_blocks: max_blocks.max(1),
+			opened: None,
+			blocks: vec![],
+			logs: vec![],
+			checkpoint: None,
+			backfill_progress: HashMap::new(),
+		}
+	}
+
+	fn is_empty(&self) -> bool {
+		self.opened.is_none()
+	}
+
+	/// Records a deferred write, opening the group if it is the first one.
+	fn touch(&mut self) {
+		self.opened.get_or_insert_with(Instant::now);
+	}
+
+	fn is_due(&self) -> bool {
+		self.blocks.len() >= self.max_blocks
+			|| self
+				.opened
+				.is_some_and(|opened| opened.elapsed() >= self.interval)
+	}
+
+	fn block(&self, hash: H256) -> Option<&BlockMetadata> {
+		self.blocks
+			.iter()
+			.find(|metadata| metadata.substrate_block_hash == hash)
+	}
+}
+
+/// A log address or topic value in its storage representation.
+#[derive(Debug, Eq, Hash, PartialEq)]
+enum LogColumn {
+	Bytes(Vec<u8>),
+	Hex(String),
+}
+
+impl sqlx::Type<Sqlite> for LogColumn {
+	fn type_info() -> sqlx::sqlite::SqliteTypeInfo {
+		<Vec<u8> as sqlx::Type<Sqlite>>::type_info()
+	}
+
+	fn compatible(ty: &sqlx::sqlite::SqliteTypeInfo) -> bool {
+		<Vec<u8> as sqlx::Type<Sqlite>>::compatible(ty)
+			|| <String as sqlx::Type<Sqlite>>::compatible(ty)
+	}
+}
+
+impl<'q> sqlx::Encode<'q, Sqlite> for LogColumn {
+	fn encode_by_ref(
+		&self,
+		buf: &mut Vec<sqlx::sqlite::SqliteArgumentValue<'q>>,
+	) -> sqlx::encode::IsNull {
+		match self {
+			Self::Bytes(bytes) => <Vec<u8> as sqlx::Encode<'q, Sqlite>>::encode_by_ref(bytes, buf),
+			Self::Hex(hex) => <String as sqlx::Encode<'q, Sqlite>>::encode_by_ref(hex, buf),
+		}
+	}
+
+	fn produces(&self) -> Option<sqlx::sqlite::SqliteTypeInfo> {
+		match self {
+			Self::Bytes(_) => Some(<Vec<u8> as sqlx::Type<Sqlite>>::type_info()),
+			Self::Hex(_) => Some(<String as sqlx::Type<Sqlite>>::type_info()),
+		}
+	}
+}
+
+/// Represents an ethereum transaction decoded from the block extrinsics, paired with
+/// its receipt.
+#[derive(Debug, Eq, PartialEq)]
+pub struct IndexedTransaction {
+	pub transaction_hash: H256,
+	pub transaction_index: i64,
+	pub transaction: ethereum::TransactionV2,
+	pub receipt: Option<ethereum::ReceiptV3>,
+	/// The recovered sender, `None` if the signature is invalid.
+	pub sender: Option<H160>,
+}
+
+/// Represents the EIP-1559 fee fields of an indexed transaction.
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct TransactionFees {
+	pub max_fee_per_gas: Option<U256>,
+	pub max_priority_fee_per_gas: Option<U256>,
+}
+
+/// Represents the signature components of an indexed transaction. Legacy transactions
+/// carry `v`, typed transactions carry `y_parity`.
+#[derive(Debug, Eq, PartialEq)]
+pub struct TransactionSignatureComponents {
+	pub v: Option<u64>,
+	pub y_parity: Option<bool>,
+	pub r: H256,
+	pub s: H256,
+}
+
+/// Represents the receipt of an indexed transaction, assembled from the transactions and
+/// logs tables. The log data is not indexed.
+#[derive(Debug, Eq, PartialEq)]
+pub struct Receipt {
+	pub transaction_hash: H256,
+	pub transaction_index: u32,
+	/// The recovered sender, `None` if the transaction was not decoded or its signature
+	/// is invalid.
+	pub sender: Option<H160>,
+	/// The gas used by the block up to and including this transaction, `None` if the
+	/// receipt was not available when the block was indexed.
+	pub cumulative_gas_used: Option<U256>,
+	/// The gas used by this transaction alone, `None` if it cannot be derived from the
+	/// cumulative gas of this and the previous transaction.
+	pub gas_used: Option<U256>,
+	/// `1` if the transaction succeeded, `0` if it failed, `None` if the receipt was not
+	/// available when the block was indexed.
+	pub status_code: Option<u8>,
+	pub logs: Vec<ReceiptLog>,
+}
+
+/// Represents an indexed log of a [`Receipt`].
+#[derive(Debug, Eq, PartialEq)]
+pub struct ReceiptLog {
+	pub address: H160,
+	pub topics: Vec<H256>,
+	pub log_index: u32,
+}
+
+/// Proves that a log was emitted in a block, through the receipts trie of its header.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LogInclusionProof {
+	pub substrate_block_hash: H256,
+	/// The canonical ethereum block hash, the hash of `header`.
+	pub ethereum_block_hash: H256,
+	pub header: ethereum::Header,
+	pub transaction_index: u32,
+	/// The index of the log in its transaction receipt.
+	pub log_index: u32,
+	/// The receipts trie nodes on the path to the transaction receipt, root first.
+	pub receipt_proof: Vec<Vec<u8>>,
+}
+
+impl LogInclusionProof {
+	/// Verifies the proof, returning the proven log if valid. Only the trust in
+	/// `ethereum_block_hash` being canonical is left to the caller.
+	pub fn verify(&self) -> Option<ethereum::Log> {
+		if self.header.hash() != self.ethereum_block_hash {
+			return None;
+		}
+		let receipt = verify_receipt_proof(
+			self.header.receipts_root,
+			self.transaction_index as usize,
+			&self.receipt_proof,
+		)?;
+		let receipt: ethereum::ReceiptV3 = ethereum::EnvelopedDecodable::decode(&receipt).ok()?;
+		receipt_logs(&receipt).get(self.log_index as usize).cloned()
+	}
+}
+
+/// Represents a contract account indexed with the code it had at a block.
+#[derive(Debug, Eq, PartialEq)]
+pub struct IndexedContract {
+	pub address: H160,
+	pub substrate_block_hash: H256,
+	pub block_number: u32,
+	/// The keccak-256 hash of the code.
+	pub code_hash: H256,
+	pub code_size: u32,
+	/// The number of storage slots set.
+	pub storage_slots: u32,
+}
+
+/// Represents the denormalized summary of an indexed block, kept for chain-head queries.
+#[derive(Debug, Eq, PartialEq)]
+pub struct BlockSummary {
+	pub substrate_block_hash: H256,
+	pub block_number: u32,
+	pub ethereum_block_hash: H256,
+	pub transaction_count: u32,
+	pub log_count: u32,
+	/// The gas used by the block, `None` if its ethereum header is not indexed.
+	pub gas_used: Option<U256>,
+	/// The block timestamp, `None` if its ethereum header is not indexed.
+	pub timestamp: Option<u64>,
+	pub is_canon: bool,
+}
+
+/// Represents an indexed block, canonical or not.
+#[derive(Debug, Eq, PartialEq)]
+pub struct IndexedBlock {
+	pub substrate_block_hash: H256,
+	pub block_number: u32,
+	pub is_canon: bool,
 }
 
 /// Represents the block metadata.
@@ -67,6 +517,13 @@ struct BlockMetadata {
 	pub post_hashes: Hashes,
 	pub schema: EthereumStorageSchema,
 	pub is_canon: i32,
+	/// The ethereum transactions decoded from the block extrinsics, if the runtime
+	/// supports filtering them.
+	pub transactions: Option<Vec<IndexedTransaction>>,
+	/// The full ethereum header, if the block is available in the runtime storage.
+	pub header: Option<ethereum::Header>,
+	/// The block beneficiary, read from the digest block or else from the stored header.
+	pub author: Option<H160>,
 }
 
 /// Represents the Sqlite connection options that are
@@ -77,6 +534,15 @@ pub struct SqliteBackendConfig<'a> {
 	pub create_if_missing: bool,
 	pub thread_count: u32,
 	pub cache_size: u64,
+	/// The journal mode of the database, [`SqliteJournalMode::Wal`] if `None`.
+	pub journal_mode: Option<SqliteJournalMode>,
+	/// How often the database syncs to disk, [`SqliteSynchronous::Normal`] if `None`.
+	pub synchronous: Option<SqliteSynchronous>,
+	/// How long a connection waits for a lock held by another one, 8 seconds if `None`.
+	pub busy_timeout: Option<Duration>,
+	/// Whether the logs are indexed. If not, only blocks and transactions are indexed, the
+	/// log indexes are not built and filtering logs fails with [`LOGS_NOT_INDEXED`].
+	pub index_logs: bool,
 }
 
 /// Represents the indexed status of a block and if it's canon or not.
@@ -92,6 +558,172 @@ pub enum BackendConfig<'a> {
 	Sqlite(SqliteBackendConfig<'a>),
 }
 
+/// A token bucket limiting the number of operations performed per second.
+struct RateLimiter {
+	ops_per_sec: f64,
+	bucket: tokio::sync::Mutex<TokenBucket>,
+}
+
+struct TokenBucket {
+	tokens: f64,
+	last_refill: Instant,
+}
+
+impl RateLimiter {
+	fn new(ops_per_sec: NonZeroU32) -> Self {
+		let ops_per_sec = ops_per_sec.get() as f64;
+		Self {
+			ops_per_sec,
+			bucket: tokio::sync::Mutex::new(TokenBucket {
+				tokens: ops_per_sec,
+				last_refill: Instant::now(),
+			}),
+		}
+	}
+
+	/// Waits until an operation is allowed by the configured rate.
+	async fn acquire(&self) {
+		loop {
+			let wait = self.take(&mut *self.bucket.lock().await);
+			match wait {
+				Some(wait) => tokio::time::sleep(wait).await,
+				None => return,
+			}
+		}
+	}
+
+	/// Blocks the thread until an operation is allowed by the configured rate. Only to be
+	/// called from blocking tasks.
+	fn acquire_blocking(&self) {
+		loop {
+			let wait = self.take(&mut *self.bucket.blocking_lock());
+			match wait {
+				Some(wait) => std::thread::sleep(wait),
+				None => return,
+			}
+		}
+	}
+
+	/// Takes a token from the bucket, or returns how long to wait for the next one.
+	fn take(&self, bucket: &mut TokenBucket) -> Option<Duration> {
+		let now = Instant::now();
+		let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+		bucket.tokens = (bucket.tokens + elapsed * self.ops_per_sec).min(self.ops_per_sec);
+		bucket.last_refill = now;
+		if bucket.tokens >= 1.0 {
+			bucket.tokens -= 1.0;
+			return None;
+		}
+		Some(Duration::from_secs_f64(
+			(1.0 - bucket.tokens) / self.ops_per_sec,
+		))
+	}
+}
+
+/// A storage override taking a token from a [`RateLimiter`] before each read.
+struct ThrottledStorageOverride<Block> {
+	inner: Arc<dyn StorageOverride<Block>>,
+	limiter: Arc<RateLimiter>,
+}
+
+impl<Block: BlockT> StorageOverride<Block> for ThrottledStorageOverride<Block> {
+	fn account_code_at(&self, at: Block::Hash, address: H160) -> Option<Vec<u8>> {
+		self.limiter.acquire_blocking();
+		self.inner.account_code_at(at, address)
+	}
+
+	fn account_storage_at(&self, at: Block::Hash, address: H160, index: U256) -> Option<H256> {
+		self.limiter.acquire_blocking();
+		self.inner.account_storage_at(at, address, index)
+	}
+
+	fn current_block(&self, at: Block::Hash) -> Option<ethereum::BlockV2> {
+		self.limiter.acquire_blocking();
+		self.inner.current_block(at)
+	}
+
+	fn current_receipts(&self, at: Block::Hash) -> Option<Vec<ethereum::ReceiptV3>> {
+		self.limiter.acquire_blocking();
+		self.inner.current_receipts(at)
+	}
+
+	fn current_transaction_statuses(
+		&self,
+		at: Block::Hash,
+	) -> Option<Vec<fp_rpc::TransactionStatus>> {
+		self.limiter.acquire_blocking();
+		self.inner.current_transaction_statuses(at)
+	}
+
+	fn elasticity(&self, at: Block::Hash) -> Option<sp_runtime::Permill> {
+		self.limiter.acquire_blocking();
+		self.inner.elasticity(at)
+	}
+
+	fn is_eip1559(&self, at: Block::Hash) -> bool {
+		self.limiter.acquire_blocking();
+		self.inner.is_eip1559(at)
+	}
+}
+
+/// Counts the events recorded over a sliding time window.
+struct RollingRate {
+	window: Duration,
+	samples: VecDeque<(Instant, u64)>,
+}
+
+impl RollingRate {
+	fn new(window: Duration) -> Self {
+		Self {
+			window,
+			samples: VecDeque::new(),
+		}
+	}
+
+	fn record(&mut self, now: Instant, count: u64) {
+		self.samples.push_back((now, count));
+		self.expire(now);
+	}
+
+	/// The number of events per second over the window ending at `now`.
+	fn per_sec(&mut self, now: Instant) -> f64 {
+		self.expire(now);
+		let count: u64 = self.samples.iter().map(|(_, count)| count).sum();
+		count as f64 / self.window.as_secs_f64()
+	}
+
+	fn expire(&mut self, now: Instant) {
+		while let Some((at, _)) = self.samples.front() {
+			if now.saturating_duration_since(*at) < self.window {
+				break;
+			}
+			self.samples.pop_front();
+		}
+	}
+}
+
+/// The rolling rates of the blocks and logs committed by the indexer.
+struct Throughput {
+	blocks: RollingRate,
+	logs: RollingRate,
+}
+
+impl Throughput {
+	fn new(window: Duration) -> Self {
+		Self {
+			blocks: RollingRate::new(window),
+			logs: RollingRate::new(window),
+		}
+	}
+}
+
+/// Represents the indexing throughput averaged over the configured window.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct IndexingThroughput {
+	pub blocks_per_sec: f64,
+	pub logs_per_sec: f64,
+}
+
 #[derive(Clone)]
 pub struct Backend<Block> {
 	/// The Sqlite connection.
@@ -102,6 +734,62 @@ pub struct Backend<Block> {
 	/// The number of allowed operations for the Sqlite filter call.
 	/// A value of `0` disables the timeout.
 	num_ops_timeout: i32,
+	/// The number of rows fetched per chunk for the filter call.
+	log_fetch_chunk_size: u32,
+	/// The number of block hashes bound by a single canonicalization statement.
+	canonicalize_chunk_size: u32,
+	/// The maximum number of distinct addresses of a filter call.
+	max_filter_addresses: u32,
+	/// The maximum number of distinct topic alternatives per position of a filter call.
+	max_topic_alternatives: u32,
+	/// Optional limit on the rate of write transactions, unlimited if `None`.
+	write_limiter: Option<Arc<RateLimiter>>,
+	/// Optional limit on the rate of indexer state reads, unlimited if `None`.
+	state_read_limiter: Option<Arc<RateLimiter>>,
+	/// The number of attempts at writing the metadata of a block on transient errors.
+	insert_attempts: u32,
+	/// The delay before the second attempt at writing the metadata of a block.
+	insert_backoff: Duration,
+	/// The number of blocks whose logs are decoded concurrently, the configured thread count.
+	log_decoding_tasks: usize,
+	/// The storage representation of the log address and topic columns.
+	log_column_encoding: LogColumnEncoding,
+	/// Where the topic predicates of a filter call are evaluated.
+	topic_filter_strategy: TopicFilterStrategy,
+	/// Recovers the senders of the indexed transactions.
+	signer_recovery: Arc<dyn SignerRecovery>,
+	/// How the blocks with an unrecoverable transaction signature are indexed.
+	malformed_signature_policy: MalformedSignaturePolicy,
+	/// Whether the logs are indexed, `false` for block-only indexing.
+	index_logs: bool,
+	/// Whether only canonical blocks are stored, retracted blocks being deleted.
+	canonical_only: bool,
+	/// Whether the contracts seeded in the genesis state are indexed.
+	index_genesis_contracts: bool,
+	/// Whether the encoded receipts are stored to build [`LogInclusionProof`]s.
+	store_receipts: bool,
+	/// The unique key of the logs table.
+	log_key: LogKey,
+	/// The number of finalized blocks a retracted block is kept for, never pruned if `None`.
+	retracted_block_retention: Option<u32>,
+	/// The number of finalized blocks a log is kept for, never pruned if `None`.
+	log_retention: Option<u32>,
+	/// The log retention of specific contracts, overriding `log_retention`. Their logs are
+	/// never pruned if `None`.
+	contract_log_retention: HashMap<H160, Option<u32>>,
+	/// The first unrecoverable error hit while indexing, if any.
+	fatal_error: Arc<parking_lot::Mutex<Option<String>>>,
+	/// The rolling rates of the committed blocks and logs.
+	throughput: Arc<parking_lot::Mutex<Throughput>>,
+	/// The number of blocks whose logs the sync worker indexes together.
+	logs_batch: Arc<parking_lot::Mutex<LogsBatch>>,
+	/// The indexing writes grouped in a single transaction, each one committed on its own
+	/// if `None`.
+	commit_group: Option<Arc<tokio::sync::Mutex<CommitGroup>>>,
+	/// The number of transactions committed by the indexing writes.
+	indexing_commits: Arc<AtomicU64>,
+	/// The most rows a single `filter_logs` chunk has read.
+	log_fetch_peak: Arc<AtomicU64>,
 }
 
 impl<Block> Backend<Block>
@@ -109,6 +797,10 @@ where
 	Block: BlockT<Hash = H256>,
 {
 	/// Creates a new instance of the SQL backend.
+	///
+	/// Blocks are read through `storage_override`, which must support the ethereum storage
+	/// schema of every indexed block: chains migrated from schema V1 or V2 need a
+	/// [`fc_storage::StorageOverrideHandler`], which dispatches on the schema of each block.
 	pub async fn new(
 		config: BackendConfig<'_>,
 		pool_size: u32,
@@ -119,8 +811,25 @@ where
 			.max_connections(pool_size)
 			.connect_lazy_with(Self::connect_options(&config)?.disable_statement_logging());
 		let _ = Self::create_database_if_not_exists(&any_pool).await?;
+		Self::split_sync_status_phases(&any_pool).await?;
+		Self::create_columns_if_not_exist(&any_pool).await?;
+		let index_logs = match &config {
+			BackendConfig::Sqlite(config) => config.index_logs,
+		};
 		let _ = Self::create_indexes_if_not_exist(&any_pool).await?;
-		Ok(Self {
+		Self::create_log_indexes_if_not_exist(&any_pool, index_logs).await?;
+		Self::number_logs(&any_pool).await?;
+		Self::count_log_topics(&any_pool).await?;
+		Self::summarize_blocks(&any_pool).await?;
+		let log_key = if Self::has_hashed_log_key(&any_pool).await? {
+			LogKey::Hashed
+		} else {
+			LogKey::Composite
+		};
+		let log_decoding_tasks = match &config {
+			BackendConfig::Sqlite(config) => config.thread_count.max(1) as usize,
+		};
+		let backend = Self {
 			pool: any_pool,
 			storage_override,
 			num_ops_timeout: num_ops_timeout
@@ -128,100 +837,491 @@ where
 				.unwrap_or(0)
 				.try_into()
 				.unwrap_or(i32::MAX),
-		})
+			log_fetch_chunk_size: DEFAULT_LOG_FETCH_CHUNK_SIZE,
+			canonicalize_chunk_size: DEFAULT_CANONICALIZE_CHUNK_SIZE,
+			max_filter_addresses: DEFAULT_MAX_FILTER_ADDRESSES,
+			max_topic_alternatives: DEFAULT_MAX_TOPIC_ALTERNATIVES,
+			write_limiter: None,
+			state_read_limiter: None,
+			insert_attempts: DEFAULT_INSERT_ATTEMPTS,
+			insert_backoff: DEFAULT_INSERT_BACKOFF,
+			log_decoding_tasks,
+			log_column_encoding: LogColumnEncoding::default(),
+			topic_filter_strategy: TopicFilterStrategy::default(),
+			signer_recovery: Arc::new(SoftwareSignerRecovery),
+			malformed_signature_policy: MalformedSignaturePolicy::default(),
+			index_logs,
+			canonical_only: false,
+			index_genesis_contracts: false,
+			store_receipts: false,
+			log_key,
+			retracted_block_retention: None,
+			log_retention: None,
+			contract_log_retention: HashMap::new(),
+			fatal_error: Arc::new(parking_lot::Mutex::new(None)),
+			throughput: Arc::new(parking_lot::Mutex::new(Throughput::new(
+				DEFAULT_THROUGHPUT_WINDOW,
+			))),
+			logs_batch: Arc::new(parking_lot::Mutex::new(LogsBatch::new(
+				LogsBatchSize::default(),
+			))),
+			commit_group: None,
+			indexing_commits: Arc::new(AtomicU64::new(0)),
+			log_fetch_peak: Arc::new(AtomicU64::new(0)),
+		};
+		backend.create_indexes().await?;
+		Ok(backend)
 	}
 
-	fn connect_options(config: &BackendConfig) -> Result<SqliteConnectOptions, Error> {
-		match config {
-			BackendConfig::Sqlite(config) => {
-				log::info!(target: "frontier-sql", "📑 Connection configuration: {config:?}");
-				let config = sqlx::sqlite::SqliteConnectOptions::from_str(config.path)?
-					.create_if_missing(config.create_if_missing)
-					// https://www.sqlite.org/pragma.html#pragma_busy_timeout
-					.busy_timeout(std::time::Duration::from_secs(8))
-					// 200MB, https://www.sqlite.org/pragma.html#pragma_cache_size
-					.pragma("cache_size", format!("-{}", config.cache_size))
-					// https://www.sqlite.org/pragma.html#pragma_analysis_limit
-					.pragma("analysis_limit", "1000")
-					// https://www.sqlite.org/pragma.html#pragma_threads
-					.pragma("threads", config.thread_count.to_string())
-					// https://www.sqlite.org/pragma.html#pragma_threads
-					.pragma("temp_store", "memory")
-					// https://www.sqlite.org/wal.html
-					.journal_mode(sqlx::sqlite::SqliteJournalMode::Wal)
-					// https://www.sqlite.org/pragma.html#pragma_synchronous
-					.synchronous(sqlx::sqlite::SqliteSynchronous::Normal);
-				Ok(config)
-			}
+	/// Builds the secondary indexes of the logs table serving queries the indexer does not
+	/// run itself, such as [`Self::logs_by_address`]. Existing indexes are kept, and nothing
+	/// is built when log indexing is disabled.
+	pub async fn create_indexes(&self) -> Result<(), Error> {
+		if self.index_logs {
+			sqlx::query(
+				"CREATE INDEX IF NOT EXISTS logs_address_number_idx ON logs (
+					address,
+					block_number
+				)",
+			)
+			.execute(self.pool())
+			.await?;
 		}
+		Ok(())
 	}
 
-	/// Get the underlying Sqlite pool.
-	pub fn pool(&self) -> &SqlitePool {
-		&self.pool
+	/// Sets the number of rows fetched per chunk when filtering logs, bounding the memory
+	/// used by a single database round-trip.
+	pub fn with_log_fetch_chunk_size(mut self, chunk_size: NonZeroU32) -> Self {
+		self.log_fetch_chunk_size = chunk_size.get();
+		self
 	}
 
-	/// Canonicalize the indexed blocks, marking/demarking them as canon based on the
-	/// provided `retracted` and `enacted` values.
-	pub async fn canonicalize(&self, retracted: &[H256], enacted: &[H256]) -> Result<(), Error> {
-		let mut tx = self.pool().begin().await?;
+	/// Sets the number of block hashes updated by a single statement when canonicalizing,
+	/// a deep re-org being split into as many statements as needed.
+	pub fn with_canonicalize_chunk_size(mut self, chunk_size: NonZeroU32) -> Self {
+		self.canonicalize_chunk_size = chunk_size.get();
+		self
+	}
 
-		// Retracted
-		let mut builder: QueryBuilder<Sqlite> =
-			QueryBuilder::new("UPDATE blocks SET is_canon = 0 WHERE substrate_block_hash IN (");
-		let mut retracted_hashes = builder.separated(", ");
-		for hash in retracted.iter() {
-			let hash = hash.as_bytes();
-			retracted_hashes.push_bind(hash);
-		}
-		retracted_hashes.push_unseparated(")");
-		let query = builder.build();
-		query.execute(&mut *tx).await?;
+	/// Sets the maximum number of distinct addresses a filter call can match, as each one
+	/// is bound as a parameter of the `address IN (...)` clause.
+	pub fn with_max_filter_addresses(mut self, max_addresses: NonZeroU32) -> Self {
+		self.max_filter_addresses = max_addresses.get();
+		self
+	}
 
-		// Enacted
-		let mut builder: QueryBuilder<Sqlite> =
-			QueryBuilder::new("UPDATE blocks SET is_canon = 1 WHERE substrate_block_hash IN (");
-		let mut enacted_hashes = builder.separated(", ");
-		for hash in enacted.iter() {
-			let hash = hash.as_bytes();
-			enacted_hashes.push_bind(hash);
-		}
-		enacted_hashes.push_unseparated(")");
-		let query = builder.build();
-		query.execute(&mut *tx).await?;
+	/// Sets the maximum number of distinct topic alternatives a filter call can match at a
+	/// single topic position, as each one is bound in a `topic_N IN (...)` clause.
+	pub fn with_max_topic_alternatives(mut self, max_alternatives: NonZeroU32) -> Self {
+		self.max_topic_alternatives = max_alternatives.get();
+		self
+	}
 
-		tx.commit().await
+	/// Limits the number of write transactions the indexer commits per second, so the
+	/// database disk can be shared with other services.
+	pub fn with_max_write_ops_per_sec(mut self, ops_per_sec: NonZeroU32) -> Self {
+		self.write_limiter = Some(Arc::new(RateLimiter::new(ops_per_sec)));
+		self
 	}
 
-	/// Index the block metadata for the genesis block.
-	pub async fn insert_genesis_block_metadata<Client, BE>(
-		&self,
-		client: Arc<Client>,
-	) -> Result<Option<H256>, Error>
-	where
-		Client: StorageProvider<Block, BE> + HeaderBackend<Block> + 'static,
-		Client: ProvideRuntimeApi<Block>,
-		Client::Api: EthereumRuntimeRPCApi<Block>,
-		BE: BackendT<Block> + 'static,
-	{
-		let id = BlockId::Number(Zero::zero());
-		let substrate_genesis_hash = client
-			.expect_block_hash_from_id(&id)
-			.map_err(|_| Error::Protocol("Cannot resolve genesis hash".to_string()))?;
-		let maybe_substrate_hash: Option<H256> = if let Ok(Some(_)) =
-			client.header(substrate_genesis_hash)
-		{
-			let has_api = client
-				.runtime_api()
-				.has_api_with::<dyn EthereumRuntimeRPCApi<Block>, _>(
-					substrate_genesis_hash,
-					|version| version >= 1,
-				)
-				.expect("runtime api reachable");
+	/// Limits the number of state reads the indexer performs per second through the storage
+	/// override, so a backfill does not starve the RPC state reads.
+	pub fn with_max_state_reads_per_sec(mut self, reads_per_sec: NonZeroU32) -> Self {
+		self.state_read_limiter = Some(Arc::new(RateLimiter::new(reads_per_sec)));
+		self
+	}
 
-			log::debug!(target: "frontier-sql", "Index genesis block, has_api={has_api}, hash={substrate_genesis_hash:?}");
+	/// Sets how many times the metadata of a block is written before giving up on transient
+	/// errors, such as another connection holding the database lock, and the delay before the
+	/// second attempt, doubled on each further one.
+	pub fn with_insert_retries(mut self, max_attempts: NonZeroU32, base_delay: Duration) -> Self {
+		self.insert_attempts = max_attempts.get();
+		self.insert_backoff = base_delay;
+		self
+	}
 
-			if has_api {
+	/// Sets the storage representation of the log address and topic columns. It must match
+	/// the representation the database was populated with.
+	pub fn with_log_column_encoding(mut self, encoding: LogColumnEncoding) -> Self {
+		self.log_column_encoding = encoding;
+		self
+	}
+
+	/// Sets how many blocks have their logs indexed together by the sync worker.
+	pub fn with_logs_batch_size(mut self, size: LogsBatchSize) -> Self {
+		self.logs_batch = Arc::new(parking_lot::Mutex::new(LogsBatch::new(size)));
+		self
+	}
+
+	/// The number of blocks whose logs the sync worker currently indexes together.
+	pub fn logs_batch_size(&self) -> usize {
+		self.logs_batch.lock().size
+	}
+
+	/// Overrides where the topic predicates of a filter call are evaluated, both strategies
+	/// returning the same logs.
+	pub fn with_topic_filter_strategy(mut self, strategy: TopicFilterStrategy) -> Self {
+		self.topic_filter_strategy = strategy;
+		self
+	}
+
+	/// Sets the implementation recovering the senders of the indexed transactions.
+	pub fn with_signer_recovery(mut self, signer_recovery: Arc<dyn SignerRecovery>) -> Self {
+		self.signer_recovery = signer_recovery;
+		self
+	}
+
+	/// Sets how the blocks with an unrecoverable transaction signature are indexed.
+	pub fn with_malformed_signature_policy(mut self, policy: MalformedSignaturePolicy) -> Self {
+		self.malformed_signature_policy = policy;
+		self
+	}
+
+	/// Sets the unique key of the logs table. Switching an existing database to
+	/// [`LogKey::Hashed`] rebuilds its logs table. The key of an existing table is detected
+	/// when opening the database, so it does not need to be set on subsequent starts, and a
+	/// hashed table cannot be switched back to [`LogKey::Composite`].
+	pub async fn with_log_key(mut self, log_key: LogKey) -> Result<Self, Error> {
+		match (self.log_key, log_key) {
+			(LogKey::Composite, LogKey::Hashed) => {
+				log::info!(target: "frontier-sql", "🛠️  Rebuilding the logs table with a hashed key");
+				self.rebuild_logs_with_hashed_key().await?;
+				self.log_key = LogKey::Hashed;
+			}
+			(LogKey::Hashed, LogKey::Composite) => {
+				log::warn!(target: "frontier-sql", "The logs table has a hashed key, keeping it");
+			}
+			_ => {}
+		}
+		Ok(self)
+	}
+
+	/// Whether the backend indexes the logs.
+	pub fn logs_indexed(&self) -> bool {
+		self.index_logs
+	}
+
+	/// Stores canonical blocks only. Non-canonical blocks are not inserted, and the blocks
+	/// retracted by a re-org are deleted along with their transactions and logs.
+	pub fn canonical_only(mut self) -> Self {
+		self.canonical_only = true;
+		self
+	}
+
+	/// Whether the backend stores canonical blocks only.
+	pub fn is_canonical_only(&self) -> bool {
+		self.canonical_only
+	}
+
+	/// Indexes the contracts seeded in the genesis state into the contracts table along with
+	/// the genesis block, see [`Backend::contracts`].
+	pub fn with_genesis_contracts(mut self) -> Self {
+		self.index_genesis_contracts = true;
+		self
+	}
+
+	/// Stores the encoded receipts of the indexed blocks, needed to build log inclusion
+	/// proofs, see [`Backend::log_inclusion_proof`].
+	pub fn with_receipt_storage(mut self) -> Self {
+		self.store_receipts = true;
+		self
+	}
+
+	/// Prunes the retracted blocks once finality is `blocks` past them. Only finalized
+	/// heights are pruned, as no re-org can enact a block below the finalized one.
+	pub fn with_retracted_block_retention(mut self, blocks: u32) -> Self {
+		self.retracted_block_retention = Some(blocks);
+		self
+	}
+
+	/// Prunes the logs once finality is `blocks` past their block, see
+	/// [`Backend::prune_logs`].
+	pub fn with_log_retention(mut self, blocks: u32) -> Self {
+		self.log_retention = Some(blocks);
+		self
+	}
+
+	/// Overrides the log retention for the logs emitted by `address`, which are kept for
+	/// `blocks` past finality, or never pruned if `None`.
+	pub fn with_contract_log_retention(mut self, address: H160, blocks: Option<u32>) -> Self {
+		self.contract_log_retention.insert(address, blocks);
+		self
+	}
+
+	/// Records an error hit while indexing, keeping the first one that retrying cannot
+	/// recover from, such as a constraint violation or a closed pool.
+	pub fn record_error(&self, err: &Error) {
+		if is_fatal_error(err) {
+			self.fatal_error
+				.lock()
+				.get_or_insert_with(|| err.to_string());
+		}
+	}
+
+	/// The first unrecoverable error recorded while indexing, if any.
+	pub fn fatal_error(&self) -> Option<String> {
+		self.fatal_error.lock().clone()
+	}
+
+	/// Sets the window over which the indexing throughput is averaged, 60 seconds by default.
+	pub fn with_throughput_window(self, window: Duration) -> Self {
+		*self.throughput.lock() = Throughput::new(window);
+		self
+	}
+
+	/// The blocks and logs committed per second over the throughput window.
+	pub fn throughput(&self) -> IndexingThroughput {
+		let now = Instant::now();
+		let mut throughput = self.throughput.lock();
+		IndexingThroughput {
+			blocks_per_sec: throughput.blocks.per_sec(now),
+			logs_per_sec: throughput.logs.per_sec(now),
+		}
+	}
+
+	/// Groups the block metadata, logs, checkpoint and backfill progress writes of the
+	/// indexer into a single transaction, committed by the first grouped write past `interval`
+	/// or once `max_blocks` blocks are grouped, or on [`Self::flush_writes`]. The interval is
+	/// only checked on writes, the caller flushing the group once it has nothing left to write.
+	/// The grouped blocks are reported as indexed by the indexer lookups, but are only
+	/// visible to the other queries once committed. Canonicalization and deletions commit
+	/// the group first.
+	pub fn with_commit_interval(mut self, interval: Duration, max_blocks: NonZeroU32) -> Self {
+		self.commit_group = Some(Arc::new(tokio::sync::Mutex::new(CommitGroup::new(
+			interval,
+			max_blocks.get() as usize,
+		))));
+		self
+	}
+
+	/// The number of transactions committed by the block metadata and logs writes and by the
+	/// canonicalizations.
+	pub fn indexing_commits(&self) -> u64 {
+		self.indexing_commits.load(AtomicOrdering::Relaxed)
+	}
+
+	/// The most rows a single `filter_logs` chunk has read, bounded by the log fetch chunk
+	/// size whatever the size of the result set.
+	pub fn log_fetch_peak(&self) -> u64 {
+		self.log_fetch_peak.load(AtomicOrdering::Relaxed)
+	}
+
+	/// The storage override used to read the ethereum state of the indexed blocks.
+	pub fn storage_override(&self) -> Arc<dyn StorageOverride<Block>> {
+		self.storage_override.clone()
+	}
+
+	/// Waits for the write rate limiter, if any, before starting a write transaction.
+	async fn throttle_write(&self) {
+		if let Some(limiter) = &self.write_limiter {
+			limiter.acquire().await;
+		}
+	}
+
+	/// The storage override the indexer reads the blocks through, each read waiting for the
+	/// state read rate limiter, if any. It must only be used from blocking tasks.
+	fn indexer_storage_override(&self) -> Arc<dyn StorageOverride<Block>> {
+		match &self.state_read_limiter {
+			Some(limiter) => Arc::new(ThrottledStorageOverride {
+				inner: self.storage_override.clone(),
+				limiter: limiter.clone(),
+			}),
+			None => self.storage_override.clone(),
+		}
+	}
+
+	fn connect_options(config: &BackendConfig) -> Result<SqliteConnectOptions, Error> {
+		match config {
+			BackendConfig::Sqlite(config) => {
+				log::info!(target: "frontier-sql", "📑 Connection configuration: {config:?}");
+				let config = sqlx::sqlite::SqliteConnectOptions::from_str(config.path)?
+					.create_if_missing(config.create_if_missing)
+					// https://www.sqlite.org/pragma.html#pragma_busy_timeout
+					.busy_timeout(config.busy_timeout.unwrap_or(Duration::from_secs(8)))
+					// 200MB, https://www.sqlite.org/pragma.html#pragma_cache_size
+					.pragma("cache_size", format!("-{}", config.cache_size))
+					// https://www.sqlite.org/pragma.html#pragma_analysis_limit
+					.pragma("analysis_limit", "1000")
+					// https://www.sqlite.org/pragma.html#pragma_threads
+					.pragma("threads", config.thread_count.to_string())
+					// https://www.sqlite.org/pragma.html#pragma_threads
+					.pragma("temp_store", "memory")
+					// https://www.sqlite.org/wal.html
+					.journal_mode(config.journal_mode.unwrap_or(SqliteJournalMode::Wal))
+					// https://www.sqlite.org/pragma.html#pragma_synchronous
+					.synchronous(config.synchronous.unwrap_or(SqliteSynchronous::Normal));
+				Ok(config)
+			}
+		}
+	}
+
+	/// Get the underlying Sqlite pool.
+	pub fn pool(&self) -> &SqlitePool {
+		&self.pool
+	}
+
+	/// Begins a transaction reading a consistent point-in-time view of the index, for
+	/// analytics spanning several queries. In WAL mode, the snapshot is taken by the first
+	/// read of a transaction and is unaffected by the commits of the indexer until the
+	/// transaction ends. The indexer is not blocked meanwhile, but the WAL cannot be
+	/// checkpointed past an open snapshot, so it should not be held for long.
+	///
+	/// Fails if the database is not in WAL mode, where an open snapshot would block the
+	/// indexer writes instead.
+	pub async fn read_snapshot(&self) -> Result<sqlx::Transaction<'static, Sqlite>, Error> {
+		let mut tx = self.pool().begin().await?;
+		let journal_mode = sqlx::query("PRAGMA journal_mode")
+			.fetch_one(&mut *tx)
+			.await?
+			.get::<String, _>(0);
+		if !journal_mode.eq_ignore_ascii_case("wal") {
+			return Err(Error::Protocol(format!(
+				"Read snapshots require the WAL journal mode, the database is in {journal_mode} mode"
+			)));
+		}
+		// Take the snapshot now rather than on the first query of the caller.
+		sqlx::query("SELECT 1 FROM sync_status LIMIT 1")
+			.fetch_optional(&mut *tx)
+			.await?;
+		Ok(tx)
+	}
+
+	/// Canonicalize the indexed blocks, marking/demarking them as canon based on the
+	/// provided `retracted` and `enacted` values. Any other block left canonical at the height
+	/// of an enacted one, as a re-org deeper than its reported route would leave, is retracted
+	/// as well.
+	pub async fn canonicalize(&self, retracted: &[H256], enacted: &[H256]) -> Result<(), Error> {
+		// The grouped blocks are canonicalized along with the committed ones.
+		self.flush_writes().await?;
+		self.canonicalize_in_chunks(retracted, enacted).await?;
+		self.repair_canonical_heights(enacted).await.map(|_| ())
+	}
+
+	/// Retracts the blocks marked canonical at the height of an enacted block, other than the
+	/// enacted block itself. Returns the number of repaired blocks.
+	async fn repair_canonical_heights(&self, enacted: &[H256]) -> Result<usize, Error> {
+		let mut stale = vec![];
+		for chunk in enacted.chunks(self.canonicalize_chunk_size as usize) {
+			// The chunk is bound once, staying within `canonicalize_chunk_size` parameters.
+			let mut builder: QueryBuilder<Sqlite> =
+				QueryBuilder::new("WITH enacted(substrate_block_hash) AS (VALUES ");
+			let mut separated = builder.separated(", ");
+			for hash in chunk {
+				separated.push("(");
+				separated.push_bind_unseparated(hash.as_bytes());
+				separated.push_unseparated(")");
+			}
+			builder.push(
+				") SELECT substrate_block_hash FROM blocks
+				WHERE is_canon = 1 AND block_number IN
+					(SELECT block_number FROM blocks WHERE substrate_block_hash IN enacted)
+				AND substrate_block_hash NOT IN enacted",
+			);
+			for row in builder.build().fetch_all(self.pool()).await? {
+				stale.push(H256::from_slice(&row.try_get::<Vec<u8>, _>(0)?[..]));
+			}
+		}
+		if !stale.is_empty() {
+			log::warn!(
+				target: "frontier-sql",
+				"Retracting {} blocks left canonical at the height of enacted blocks: {stale:?}",
+				stale.len(),
+			);
+			self.canonicalize_in_chunks(&stale, &[]).await?;
+		}
+		Ok(stale.len())
+	}
+
+	/// Canonicalize in a single transaction, binding up to `canonicalize_chunk_size` hashes
+	/// per statement. Returns the number of executed statements.
+	async fn canonicalize_in_chunks(
+		&self,
+		retracted: &[H256],
+		enacted: &[H256],
+	) -> Result<usize, Error> {
+		let chunk_size = self.canonicalize_chunk_size as usize;
+		self.throttle_write().await;
+		let mut tx = self.pool().begin().await?;
+		let mut statements = 0;
+
+		// Retracted
+		let retracted_tables: &[&str] = if self.canonical_only {
+			&BLOCK_TABLES
+		} else {
+			&["blocks", "block_summary"]
+		};
+		for table in retracted_tables {
+			let statement = if self.canonical_only {
+				format!("DELETE FROM {table} WHERE substrate_block_hash IN (")
+			} else {
+				format!("UPDATE {table} SET is_canon = 0 WHERE substrate_block_hash IN (")
+			};
+			for chunk in retracted.chunks(chunk_size) {
+				Self::execute_for_hashes(&mut *tx, &statement, chunk).await?;
+				statements += 1;
+			}
+		}
+
+		// Enacted
+		for table in ["blocks", "block_summary"] {
+			let statement =
+				format!("UPDATE {table} SET is_canon = 1 WHERE substrate_block_hash IN (");
+			for chunk in enacted.chunks(chunk_size) {
+				Self::execute_for_hashes(&mut *tx, &statement, chunk).await?;
+				statements += 1;
+			}
+		}
+
+		tx.commit().await?;
+		self.indexing_commits.fetch_add(1, AtomicOrdering::Relaxed);
+		Ok(statements)
+	}
+
+	/// Execute a statement ending with an open `IN (` clause over the given block hashes.
+	async fn execute_for_hashes(
+		conn: &mut sqlx::SqliteConnection,
+		statement: &str,
+		hashes: &[H256],
+	) -> Result<SqliteQueryResult, Error> {
+		let mut builder: QueryBuilder<Sqlite> = QueryBuilder::new(statement);
+		let mut separated = builder.separated(", ");
+		for hash in hashes {
+			separated.push_bind(hash.as_bytes());
+		}
+		separated.push_unseparated(")");
+		builder.build().execute(conn).await
+	}
+
+	/// Index the block metadata for the genesis block.
+	pub async fn insert_genesis_block_metadata<Client, BE>(
+		&self,
+		client: Arc<Client>,
+	) -> Result<Option<H256>, Error>
+	where
+		Client: StorageProvider<Block, BE> + HeaderBackend<Block> + 'static,
+		Client: ProvideRuntimeApi<Block>,
+		Client::Api: EthereumRuntimeRPCApi<Block>,
+		BE: BackendT<Block> + 'static,
+	{
+		let id = BlockId::Number(Zero::zero());
+		let substrate_genesis_hash = client
+			.expect_block_hash_from_id(&id)
+			.map_err(|_| Error::Protocol("Cannot resolve genesis hash".to_string()))?;
+		let maybe_substrate_hash: Option<H256> = if let Ok(Some(_)) =
+			client.header(substrate_genesis_hash)
+		{
+			let has_api = client
+				.runtime_api()
+				.has_api_with::<dyn EthereumRuntimeRPCApi<Block>, _>(
+					substrate_genesis_hash,
+					|version| version >= 1,
+				)
+				.expect("runtime api reachable");
+
+			log::debug!(target: "frontier-sql", "Index genesis block, has_api={has_api}, hash={substrate_genesis_hash:?}");
+
+			if has_api {
 				// The chain has frontier support from genesis.
 				// Read from the runtime and store the block metadata.
 				let ethereum_block = client
@@ -230,7 +1330,8 @@ where
 					.expect("runtime api reachable")
 					.expect("ethereum genesis block");
 
-				let schema = StorageQuerier::new(client)
+				let querier = StorageQuerier::new(client);
+				let schema = querier
 					.storage_schema(substrate_genesis_hash)
 					.unwrap_or(EthereumStorageSchema::V3)
 					.encode();
@@ -238,6 +1339,7 @@ where
 				let substrate_block_hash = substrate_genesis_hash.as_bytes();
 				let block_number = 0i32;
 				let is_canon = 1i32;
+				let author = ethereum_block.header.beneficiary.as_bytes().to_owned();
 
 				let _ = sqlx::query(
 					"INSERT OR IGNORE INTO blocks(
@@ -245,16 +1347,57 @@ where
 						substrate_block_hash,
 						block_number,
 						ethereum_storage_schema,
-						is_canon)
-					VALUES (?, ?, ?, ?, ?)",
+						is_canon,
+						author)
+					VALUES (?, ?, ?, ?, ?, ?)",
 				)
 				.bind(ethereum_block_hash)
 				.bind(substrate_block_hash)
 				.bind(block_number)
 				.bind(schema)
 				.bind(is_canon)
+				.bind(author)
 				.execute(self.pool())
 				.await?;
+
+				if self.index_genesis_contracts {
+					let contracts = querier
+						.account_codes(substrate_genesis_hash)
+						.into_iter()
+						.map(|(address, code)| {
+							let storage_slots =
+								querier.account_storage_count(substrate_genesis_hash, address);
+							(address, code, storage_slots)
+						})
+						.collect::<Vec<_>>();
+					log::debug!(
+						target: "frontier-sql",
+						"Index {} genesis contracts, hash={substrate_genesis_hash:?}",
+						contracts.len(),
+					);
+					let mut tx = self.pool().begin().await?;
+					for (address, code, storage_slots) in contracts {
+						sqlx::query(
+							"INSERT OR IGNORE INTO contracts(
+								address,
+								substrate_block_hash,
+								block_number,
+								code_hash,
+								code_size,
+								storage_slots)
+							VALUES (?, ?, ?, ?, ?, ?)",
+						)
+						.bind(address.as_bytes())
+						.bind(substrate_block_hash)
+						.bind(block_number)
+						.bind(&keccak_256(&code)[..])
+						.bind(code.len() as i64)
+						.bind(storage_slots as i64)
+						.execute(&mut *tx)
+						.await?;
+					}
+					tx.commit().await?;
+				}
 			}
 			Some(substrate_genesis_hash)
 		} else {
@@ -267,21 +1410,38 @@ where
 		client: Arc<Client>,
 		hash: H256,
 		storage_override: &dyn StorageOverride<Block>,
+		signer_recovery: &dyn SignerRecovery,
 	) -> Result<BlockMetadata, Error>
 	where
-		Client: StorageProvider<Block, BE> + HeaderBackend<Block> + 'static,
+		Client: StorageProvider<Block, BE> + HeaderBackend<Block> + BlockBackend<Block> + 'static,
+		Client: ProvideRuntimeApi<Block>,
+		Client::Api: EthereumRuntimeRPCApi<Block>,
 		BE: BackendT<Block> + 'static,
 	{
 		log::trace!(target: "frontier-sql", "🛠️  [Metadata] Retrieving digest data for block {hash:?}");
 		if let Ok(Some(header)) = client.header(hash) {
 			match fp_consensus::find_log(header.digest()) {
 				Ok(log) => {
-					let schema = StorageQuerier::new(client.clone())
-						.storage_schema(hash)
-						.unwrap_or(EthereumStorageSchema::V3);
+					// Without a schema in the state, as for pruned or pre-frontier states, the
+					// block is still indexed, its data read through the storage override,
+					// whose handler falls back to the runtime API.
+					let schema = match StorageQuerier::new(client.clone()).storage_schema(hash) {
+						Some(schema) => schema,
+						None => {
+							log::warn!(
+								target: "frontier-sql",
+								"⚠️  [Metadata] No ethereum storage schema in the state of block {hash:?}, indexing it as V3",
+							);
+							EthereumStorageSchema::V3
+						}
+					};
+					let mut digest_author = None;
 					let log_hashes = match log {
 						ConsensusLog::Post(PostLog::Hashes(post_hashes)) => post_hashes,
-						ConsensusLog::Post(PostLog::Block(block)) => Hashes::from_block(block),
+						ConsensusLog::Post(PostLog::Block(block)) => {
+							digest_author = Some(block.header.beneficiary);
+							Hashes::from_block(block)
+						}
 						ConsensusLog::Post(PostLog::BlockHash(expect_eth_block_hash)) => {
 							let ethereum_block = storage_override.current_block(hash);
 							match ethereum_block {
@@ -304,7 +1464,10 @@ where
 								}
 							}
 						}
-						ConsensusLog::Pre(PreLog::Block(block)) => Hashes::from_block(block),
+						ConsensusLog::Pre(PreLog::Block(block)) => {
+							digest_author = Some(block.header.beneficiary);
+							Hashes::from_block(block)
+						}
 					};
 
 					let header_number = *header.number();
@@ -325,6 +1488,18 @@ where
 						}
 					};
 
+					let transactions = Self::decode_transactions(
+						&*client,
+						hash,
+						storage_override,
+						signer_recovery,
+					);
+					let ethereum_header = storage_override
+						.current_block(hash)
+						.map(|block| block.header);
+					let author = digest_author
+						.or_else(|| ethereum_header.as_ref().map(|header| header.beneficiary));
+
 					log::trace!(
 						target: "frontier-sql",
 						"[Metadata] Prepared block metadata for #{block_number} ({hash:?}) canon={is_canon}",
@@ -335,6 +1510,9 @@ where
 						post_hashes: log_hashes,
 						schema,
 						is_canon,
+						transactions,
+						header: ethereum_header,
+						author,
 					})
 				}
 				Err(FindLogError::NotFound) => Err(Error::Protocol(format!(
@@ -351,34 +1529,264 @@ where
 		}
 	}
 
+	/// Decodes the ethereum transactions from the block extrinsics (`pallet_ethereum::Call::transact`)
+	/// and pairs them with the block receipts by index, recovering their senders.
+	/// Returns `None` if the runtime does not support filtering the extrinsics.
+	fn decode_transactions<Client>(
+		client: &Client,
+		hash: H256,
+		storage_override: &dyn StorageOverride<Block>,
+		signer_recovery: &dyn SignerRecovery,
+	) -> Option<Vec<IndexedTransaction>>
+	where
+		Client: HeaderBackend<Block> + BlockBackend<Block> + ProvideRuntimeApi<Block>,
+		Client::Api: EthereumRuntimeRPCApi<Block>,
+	{
+		let api = client.runtime_api();
+		let has_api = api
+			.has_api_with::<dyn EthereumRuntimeRPCApi<Block>, _>(hash, |version| version >= 2)
+			.unwrap_or(false);
+		if !has_api {
+			log::trace!(
+				target: "frontier-sql",
+				"[Metadata] Extrinsic filter not supported for {hash:?}",
+			);
+			return None;
+		}
+
+		let body = match client.block_body(hash) {
+			Ok(Some(body)) => body,
+			_ => {
+				log::debug!(
+					target: "frontier-sql",
+					"[Metadata] Missing block body for {hash:?}",
+				);
+				return None;
+			}
+		};
+		let transactions = match api.extrinsic_filter(hash, body) {
+			Ok(transactions) => transactions,
+			Err(err) => {
+				log::debug!(
+					target: "frontier-sql",
+					"[Metadata] Failed filtering extrinsics for {hash:?}: {err:?}",
+				);
+				return None;
+			}
+		};
+		let receipts = storage_override.current_receipts(hash).unwrap_or_default();
+		if receipts.len() != transactions.len() {
+			log::warn!(
+				target: "frontier-sql",
+				"[Metadata] Found {} transactions but {} receipts for {hash:?}",
+				transactions.len(),
+				receipts.len(),
+			);
+		}
+
+		let senders = signer_recovery.recover_batch(&transactions);
+		Some(pair_transactions_with_receipts(
+			transactions,
+			receipts,
+			senders,
+		))
+	}
+
 	/// Insert the block metadata for the provided block hashes.
 	pub async fn insert_block_metadata<Client, BE>(
 		&self,
 		client: Arc<Client>,
 		hash: H256,
-	) -> Result<(), Error>
+	) -> Result<BlockIndexing, Error>
 	where
-		Client: StorageProvider<Block, BE> + HeaderBackend<Block> + 'static,
+		Client: StorageProvider<Block, BE> + HeaderBackend<Block> + BlockBackend<Block> + 'static,
+		Client: ProvideRuntimeApi<Block>,
+		Client::Api: EthereumRuntimeRPCApi<Block>,
+		BE: BackendT<Block> + 'static,
+	{
+		self.index_block_metadata(client, hash, None).await
+	}
+
+	/// Insert the block metadata for the provided block hash, under the ethereum block and
+	/// transaction hashes mapped to it by another backend rather than those of its digest.
+	pub async fn insert_block_metadata_with_hashes<Client, BE>(
+		&self,
+		client: Arc<Client>,
+		hash: H256,
+		ethereum_block_hash: H256,
+		transaction_hashes: Vec<H256>,
+	) -> Result<BlockIndexing, Error>
+	where
+		Client: StorageProvider<Block, BE> + HeaderBackend<Block> + BlockBackend<Block> + 'static,
+		Client: ProvideRuntimeApi<Block>,
+		Client::Api: EthereumRuntimeRPCApi<Block>,
+		BE: BackendT<Block> + 'static,
+	{
+		let mapped_hashes = Hashes {
+			block_hash: ethereum_block_hash,
+			transaction_hashes,
+		};
+		self.index_block_metadata(client, hash, Some(mapped_hashes))
+			.await
+	}
+
+	async fn index_block_metadata<Client, BE>(
+		&self,
+		client: Arc<Client>,
+		hash: H256,
+		mapped_hashes: Option<Hashes>,
+	) -> Result<BlockIndexing, Error>
+	where
+		Client: StorageProvider<Block, BE> + HeaderBackend<Block> + BlockBackend<Block> + 'static,
+		Client: ProvideRuntimeApi<Block>,
+		Client::Api: EthereumRuntimeRPCApi<Block>,
 		BE: BackendT<Block> + 'static,
 	{
+		if self.is_block_indexed(hash).await {
+			log::debug!(
+				target: "frontier-sql",
+				"🛠️  [Metadata] Block {hash:?} already indexed"
+			);
+			return Ok(BlockIndexing::AlreadyIndexed);
+		}
 		// Spawn a blocking task to get block metadata from substrate backend.
-		let storage_override = self.storage_override.clone();
-		let metadata = tokio::task::spawn_blocking(move || {
-			Self::insert_block_metadata_inner(client.clone(), hash, &*storage_override)
+		let storage_override = self.indexer_storage_override();
+		let signer_recovery = self.signer_recovery.clone();
+		let mut metadata = tokio::task::spawn_blocking(move || {
+			Self::insert_block_metadata_inner(
+				client.clone(),
+				hash,
+				&*storage_override,
+				&*signer_recovery,
+			)
 		})
 		.await
 		.map_err(|_| Error::Protocol("tokio blocking metadata task failed".to_string()))??;
+		if let Some(mapped_hashes) = mapped_hashes {
+			apply_mapped_hashes(&mut metadata, mapped_hashes);
+		}
 
-		let mut tx = self.pool().begin().await?;
+		if self.canonical_only && metadata.is_canon == 0 {
+			log::debug!(target: "frontier-sql", "🛠️  [Metadata] Skipping non-canonical block {hash:?}");
+			return Ok(BlockIndexing::Skipped);
+		}
+		if !self.check_signatures(&metadata).await? {
+			return Ok(BlockIndexing::Skipped);
+		}
+		if let Some(commit_group) = &self.commit_group {
+			let mut group = commit_group.lock().await;
+			group.touch();
+			group.blocks.push(metadata);
+			if group.is_due() {
+				self.commit_grouped_writes(&mut group).await?;
+			}
+		} else {
+			self.commit_block_metadata(&metadata).await?;
+		}
+		Ok(BlockIndexing::Indexed)
+	}
 
-		log::debug!(
-			target: "frontier-sql",
-			"🛠️  [Metadata] Starting execution of statements on db transaction"
-		);
-		let post_hashes = metadata.post_hashes;
-		let ethereum_block_hash = post_hashes.block_hash.as_bytes();
-		let substrate_block_hash = metadata.substrate_block_hash.as_bytes();
-		let schema = metadata.schema.encode();
+	/// Commits the grouped indexing writes, if any, in a single transaction.
+	pub async fn flush_writes(&self) -> Result<(), Error> {
+		match &self.commit_group {
+			Some(commit_group) => {
+				self.commit_grouped_writes(&mut *commit_group.lock().await)
+					.await
+			}
+			None => Ok(()),
+		}
+	}
+
+	/// Writes the grouped blocks, then their logs, checkpoint and backfill progress, in a
+	/// single transaction. The group is kept for the next attempt if the commit fails.
+	async fn commit_grouped_writes(&self, group: &mut CommitGroup) -> Result<(), Error> {
+		if group.is_empty() {
+			return Ok(());
+		}
+		let logs = self.decode_blocks_logs(&group.logs).await?;
+		self.throttle_write().await;
+		let mut tx = self.pool().begin().await?;
+		for metadata in &group.blocks {
+			self.insert_block_rows(&mut *tx, metadata).await?;
+		}
+		let log_count = self.insert_blocks_logs(&mut *tx, logs).await?.unwrap_or(0);
+		if let Some((block_number, block_hash)) = group.checkpoint {
+			Self::set_meta(
+				&mut *tx,
+				CHECKPOINT_KEY,
+				(block_number, block_hash).encode(),
+			)
+			.await?;
+		}
+		for (&descent, &progress) in &group.backfill_progress {
+			Self::write_backfill_progress(&mut *tx, descent, progress).await?;
+		}
+		tx.commit().await?;
+		self.indexing_commits.fetch_add(1, AtomicOrdering::Relaxed);
+		log::debug!(
+			target: "frontier-sql",
+			"Committed {} grouped blocks and the logs of {}",
+			group.blocks.len(),
+			group.logs.len(),
+		);
+		let now = Instant::now();
+		let mut throughput = self.throughput.lock();
+		throughput.blocks.record(now, group.blocks.len() as u64);
+		throughput.logs.record(now, log_count);
+		drop(throughput);
+		*group = CommitGroup::new(group.interval, group.max_blocks);
+		Ok(())
+	}
+
+	/// Writes the block metadata, retrying with an exponential backoff on transient errors.
+	/// The last error is returned if every attempt failed.
+	async fn commit_block_metadata(&self, metadata: &BlockMetadata) -> Result<(), Error> {
+		let mut backoff = self.insert_backoff;
+		let mut attempt = 1;
+		loop {
+			match self.write_block_metadata(metadata).await {
+				Err(err) if attempt < self.insert_attempts && is_transient_error(&err) => {
+					log::debug!(
+						target: "frontier-sql",
+						"Failed writing metadata of block {:?}, retrying in {backoff:?}: {err}",
+						metadata.substrate_block_hash,
+					);
+					tokio::time::sleep(backoff).await;
+					backoff *= 2;
+					attempt += 1;
+				}
+				result => return result,
+			}
+		}
+	}
+
+	async fn write_block_metadata(&self, metadata: &BlockMetadata) -> Result<(), Error> {
+		self.throttle_write().await;
+		let mut tx = self.pool().begin().await?;
+		self.insert_block_rows(&mut *tx, metadata).await?;
+		log::debug!(target: "frontier-sql", "[Metadata] Ready to commit");
+		tx.commit().await?;
+		self.indexing_commits.fetch_add(1, AtomicOrdering::Relaxed);
+		self.throughput.lock().blocks.record(Instant::now(), 1);
+		Ok(())
+	}
+
+	/// Inserts the rows of the block, its transactions, header and receipts, and marks its
+	/// metadata as indexed.
+	async fn insert_block_rows(
+		&self,
+		conn: &mut sqlx::SqliteConnection,
+		metadata: &BlockMetadata,
+	) -> Result<(), Error> {
+		let hash = metadata.substrate_block_hash;
+		log::debug!(
+			target: "frontier-sql",
+			"🛠️  [Metadata] Starting execution of statements on db transaction"
+		);
+		let post_hashes = &metadata.post_hashes;
+		let ethereum_block_hash = post_hashes.block_hash.as_bytes();
+		let substrate_block_hash = metadata.substrate_block_hash.as_bytes();
+		let schema = metadata.schema.encode();
 		let block_number = metadata.block_number;
 		let is_canon = metadata.is_canon;
 
@@ -388,53 +1796,176 @@ where
 					substrate_block_hash,
 					block_number,
 					ethereum_storage_schema,
-					is_canon)
-				VALUES (?, ?, ?, ?, ?)",
+					is_canon,
+					author)
+				VALUES (?, ?, ?, ?, ?, ?)",
 		)
 		.bind(ethereum_block_hash)
 		.bind(substrate_block_hash)
 		.bind(block_number)
 		.bind(schema)
 		.bind(is_canon)
-		.execute(&mut *tx)
+		.bind(metadata.author.map(|author| author.as_bytes().to_vec()))
+		.execute(&mut *conn)
 		.await?;
-		for (i, &transaction_hash) in post_hashes.transaction_hashes.iter().enumerate() {
-			let ethereum_transaction_hash = transaction_hash.as_bytes();
-			let ethereum_transaction_index = i as i32;
+
+		// Prefer the transactions decoded from the extrinsics, falling back to the hashes
+		// committed in the consensus digest.
+		let transactions: Vec<(H256, Option<&IndexedTransaction>)> = match &metadata.transactions {
+			Some(transactions) => {
+				if transactions.len() != post_hashes.transaction_hashes.len() {
+					log::warn!(
+						target: "frontier-sql",
+						"[Metadata] Decoded {} transactions but the digest has {} for block #{block_number}",
+						transactions.len(),
+						post_hashes.transaction_hashes.len(),
+					);
+				}
+				transactions
+					.iter()
+					.map(|t| (t.transaction_hash, Some(t)))
+					.collect()
+			}
+			None => post_hashes
+				.transaction_hashes
+				.iter()
+				.map(|transaction_hash| (*transaction_hash, None))
+				.collect(),
+		};
+		for (i, (transaction_hash, transaction)) in transactions.into_iter().enumerate() {
+			let ethereum_transaction_index = i as i64;
 			log::trace!(
 				target: "frontier-sql",
 				"[Metadata] Inserting TX for block #{block_number} - {transaction_hash:?} index {ethereum_transaction_index}",
 			);
-			let _ = sqlx::query(
-				"INSERT OR IGNORE INTO transactions(
-						ethereum_transaction_hash,
-						substrate_block_hash,
-						ethereum_block_hash,
-						ethereum_transaction_index)
-					VALUES (?, ?, ?, ?)",
+			Self::insert_transaction(
+				&mut *conn,
+				hash,
+				post_hashes.block_hash,
+				ethereum_transaction_index,
+				transaction_hash,
+				transaction.map(|t| &t.transaction),
+				transaction.and_then(|t| t.sender),
+				transaction
+					.and_then(|t| t.receipt.as_ref())
+					.map(receipt_cumulative_gas_used),
+				transaction
+					.and_then(|t| t.receipt.as_ref())
+					.map(receipt_status_code),
 			)
-			.bind(ethereum_transaction_hash)
-			.bind(substrate_block_hash)
-			.bind(ethereum_block_hash)
-			.bind(ethereum_transaction_index)
-			.execute(&mut *tx)
 			.await?;
 		}
 
-		sqlx::query("INSERT INTO sync_status(substrate_block_hash) VALUES (?)")
+		if let Some(header) = &metadata.header {
+			Self::insert_ethereum_header(&mut *conn, hash, header).await?;
+		}
+
+		if self.store_receipts {
+			// A receipts trie proof needs every receipt of the block.
+			let receipts = metadata
+				.transactions
+				.iter()
+				.flatten()
+				.map(|t| t.receipt.as_ref())
+				.collect::<Option<Vec<_>>>();
+			for (i, receipt) in receipts.into_iter().flatten().enumerate() {
+				Self::insert_raw_receipt(&mut *conn, hash, i as i64, receipt).await?;
+			}
+		}
+
+		sqlx::query("INSERT INTO sync_status(substrate_block_hash, metadata_done) VALUES (?, 1)")
 			.bind(hash.as_bytes())
-			.execute(&mut *tx)
+			.execute(&mut *conn)
 			.await?;
 
-		log::debug!(target: "frontier-sql", "[Metadata] Ready to commit");
-		tx.commit().await
+		Self::refresh_block_summary(&mut *conn, hash).await?;
+		Ok(())
+	}
+
+	/// Applies the [`MalformedSignaturePolicy`] to the block if the sender of any of its
+	/// transactions could not be recovered. Returns whether the block is to be indexed.
+	async fn check_signatures(&self, metadata: &BlockMetadata) -> Result<bool, Error> {
+		let malformed = metadata
+			.transactions
+			.iter()
+			.flatten()
+			.filter(|transaction| transaction.sender.is_none())
+			.map(|transaction| transaction.transaction_hash)
+			.collect::<Vec<H256>>();
+		if malformed.is_empty() {
+			return Ok(true);
+		}
+
+		let hash = metadata.substrate_block_hash;
+		let reason = format!("Unrecoverable signature of transactions {malformed:?}");
+		match self.malformed_signature_policy {
+			MalformedSignaturePolicy::Skip => {
+				log::warn!(
+					target: "frontier-sql",
+					"⚠️  [Metadata] {reason} in block {hash:?}, indexing them without sender",
+				);
+				Ok(true)
+			}
+			MalformedSignaturePolicy::DeadLetter => {
+				log::warn!(
+					target: "frontier-sql",
+					"⚠️  [Metadata] {reason} in block {hash:?}, moving the block to dead letters",
+				);
+				self.throttle_write().await;
+				sqlx::query(
+					"INSERT OR REPLACE INTO dead_letter_blocks(substrate_block_hash, reason)
+					VALUES (?, ?)",
+				)
+				.bind(hash.as_bytes())
+				.bind(reason)
+				.execute(self.pool())
+				.await?;
+				Ok(false)
+			}
+			MalformedSignaturePolicy::Halt => {
+				let err = Error::Protocol(format!("{reason} in block {hash:?}"));
+				log::error!(target: "frontier-sql", "💀  [Metadata] {err}");
+				self.fatal_error
+					.lock()
+					.get_or_insert_with(|| err.to_string());
+				Err(err)
+			}
+		}
 	}
 
 	/// Index the logs for the newly indexed blocks upto a `max_pending_blocks` value.
-	pub async fn index_block_logs(&self, block_hash: Block::Hash) {
+	/// Without log indexing, the block is only marked as processed.
+	pub async fn index_block_logs(&self, block_hash: Block::Hash) -> Result<(), Error> {
+		self.index_blocks_logs(&[block_hash]).await
+	}
+
+	/// Index the logs of a batch of blocks whose metadata is indexed, in a single transaction.
+	/// The receipts of up to `thread_count` blocks are decoded concurrently. Blocks whose logs
+	/// are already indexed are skipped. On error, the blocks of the batch are left pending
+	/// their logs, to be indexed again.
+	pub async fn index_blocks_logs(&self, block_hashes: &[Block::Hash]) -> Result<(), Error> {
+		if let Some(commit_group) = &self.commit_group {
+			let mut group = commit_group.lock().await;
+			// Blocks committed already are indexed on their own, after the group.
+			if block_hashes.iter().all(|hash| group.block(*hash).is_some()) {
+				group.logs.extend_from_slice(block_hashes);
+				if group.is_due() {
+					if let Err(err) = self.commit_grouped_writes(&mut group).await {
+						self.record_error(&err);
+						log::error!(target: "frontier-sql", "{err}");
+						return Err(err);
+					}
+				}
+				return Ok(());
+			}
+			if let Err(err) = self.commit_grouped_writes(&mut group).await {
+				self.record_error(&err);
+				log::error!(target: "frontier-sql", "{err}");
+				return Err(err);
+			}
+		}
 		let pool = self.pool().clone();
-		let storage_override = self.storage_override.clone();
-		let _ = async {
+		let result = async {
 			// The overarching db transaction for the task.
 			// Due to the async nature of this task, the same work is likely to happen
 			// more than once. For example when a new batch is scheduled when the previous one
@@ -443,64 +1974,225 @@ where
 			// That is expected, we are exchanging extra work for *acid*ity.
 			// There is no case of unique constrain violation or race condition as already
 			// existing entries are ignored.
+			let started = Instant::now();
+			// The receipts are decoded before opening the write transaction, not to hold the
+			// database write lock meanwhile.
+			let pending = self.pending_logs(block_hashes).await?;
+			if pending.is_empty() {
+				return Ok(());
+			}
+			let logs = self.decode_blocks_logs(&pending).await?;
+			self.throttle_write().await;
 			let mut tx = pool.begin().await?;
-			// Update statement returning the substrate block hashes for this batch.
-			match sqlx::query(
+			let Some(log_count) = self.insert_blocks_logs(&mut *tx, logs).await? else {
+				return Ok(());
+			};
+			tx.commit().await?;
+			self.indexing_commits.fetch_add(1, AtomicOrdering::Relaxed);
+			self.throughput
+				.lock()
+				.logs
+				.record(Instant::now(), log_count);
+			self.logs_batch
+				.lock()
+				.record(block_hashes.len(), started.elapsed());
+			Ok::<_, Error>(())
+		}
+		.await;
+		if let Err(e) = &result {
+			self.record_error(e);
+			log::error!(target: "frontier-sql", "{e}");
+		}
+		// https://www.sqlite.org/pragma.html#pragma_optimize
+		let _ = sqlx::query("PRAGMA optimize").execute(&pool).await;
+		log::debug!(target: "frontier-sql", "Batch committed");
+		result
+	}
+
+	/// Retrieves the subset of the provided block hashes whose metadata is indexed but not
+	/// their logs, preserving the input order.
+	async fn pending_logs(&self, block_hashes: &[H256]) -> Result<Vec<H256>, Error> {
+		let mut pending = HashSet::new();
+		for chunk in block_hashes.chunks(HASH_LOOKUP_CHUNK_SIZE) {
+			let mut builder: QueryBuilder<Sqlite> = QueryBuilder::new(
+				"SELECT substrate_block_hash FROM sync_status
+				WHERE metadata_done = 1 AND logs_done = 0 AND substrate_block_hash IN (",
+			);
+			let mut hashes = builder.separated(", ");
+			for hash in chunk {
+				hashes.push_bind(hash.as_bytes());
+			}
+			hashes.push_unseparated(")");
+			pending.extend(
+				builder
+					.build()
+					.fetch_all(self.pool())
+					.await?
+					.iter()
+					.map(|row| H256::from_slice(&row.get::<Vec<u8>, _>(0)[..])),
+			);
+		}
+		Ok(block_hashes
+			.iter()
+			.filter(|hash| pending.contains(hash))
+			.copied()
+			.collect())
+	}
+
+	/// Decodes the logs of the given blocks in blocking tasks, in block order. Nothing is
+	/// decoded without log indexing.
+	async fn decode_blocks_logs(
+		&self,
+		block_hashes: &[H256],
+	) -> Result<Vec<(H256, Vec<Log>)>, Error> {
+		if !self.index_logs {
+			return Ok(block_hashes.iter().map(|hash| (*hash, vec![])).collect());
+		}
+		// `buffered` yields the decoded logs in block order.
+		let logs = futures::stream::iter(block_hashes.iter().copied())
+			.map(|block_hash| {
+				let storage_override = self.indexer_storage_override();
+				tokio::task::spawn_blocking(move || Self::get_logs(storage_override, block_hash))
+			})
+			.buffered(self.log_decoding_tasks)
+			.try_collect::<Vec<_>>()
+			.await
+			.map_err(|_| Error::Protocol("tokio blocking task failed".to_string()))?;
+		Ok(block_hashes.iter().copied().zip(logs).collect())
+	}
+
+	/// Claims the blocks whose metadata is indexed but not their logs, and inserts their
+	/// decoded logs. Returns the number of inserted logs, `None` if no block was claimed.
+	async fn insert_blocks_logs(
+		&self,
+		conn: &mut sqlx::SqliteConnection,
+		blocks_logs: Vec<(H256, Vec<Log>)>,
+	) -> Result<Option<u64>, Error> {
+		// Update statements claiming the substrate block hashes of this batch.
+		let mut pending = Vec::with_capacity(blocks_logs.len());
+		for (block_hash, logs) in blocks_logs {
+			let claimed = sqlx::query(
 				"UPDATE sync_status
-			SET status = 1
+			SET logs_done = 1
 			WHERE substrate_block_hash IN
 				(SELECT substrate_block_hash
 				FROM sync_status
-				WHERE status = 0 AND substrate_block_hash = ?) RETURNING substrate_block_hash",
+				WHERE metadata_done = 1 AND logs_done = 0 AND substrate_block_hash = ?)
+			RETURNING substrate_block_hash",
 			)
 			.bind(block_hash.as_bytes())
-			.fetch_one(&mut *tx)
-			.await
-			{
-				Ok(_) => {
-					// Spawn a blocking task to get log data from substrate backend.
-					let logs = tokio::task::spawn_blocking(move || {
-						Self::get_logs(storage_override, block_hash)
-					})
-					.await
-					.map_err(|_| Error::Protocol("tokio blocking task failed".to_string()))?;
-
-					for log in logs {
-						let _ = sqlx::query(
-							"INSERT OR IGNORE INTO logs(
-						address,
-						topic_1,
-						topic_2,
-						topic_3,
-						topic_4,
-						log_index,
+			.fetch_optional(&mut *conn)
+			.await?;
+			if claimed.is_some() {
+				pending.push((block_hash, logs));
+			}
+		}
+		if pending.is_empty() {
+			return Ok(None);
+		}
+
+		let mut log_count = 0;
+		if self.index_logs {
+			for (block_hash, logs) in pending {
+				log_count += logs.len() as u64;
+				self.insert_logs(&mut *conn, logs).await?;
+				Self::refresh_block_summary(&mut *conn, block_hash).await?;
+			}
+		}
+		Ok(Some(log_count))
+	}
+
+	/// Store the logs, encoding the address and topic columns as configured.
+	async fn insert_logs(
+		&self,
+		conn: &mut sqlx::SqliteConnection,
+		logs: Vec<Log>,
+	) -> Result<(), Error> {
+		let encoding = self.log_column_encoding;
+		for log in logs {
+			match self.log_key {
+				LogKey::Composite => {
+					let _ = sqlx::query(
+						"INSERT OR IGNORE INTO logs(
+							address,
+							topic_1,
+							topic_2,
+							topic_3,
+							topic_4,
+							log_index,
+							transaction_index,
+							substrate_block_hash,
+							block_number,
+							topic_count)
+						VALUES (?, ?, ?, ?, ?, ?, ?, ?,
+							(SELECT block_number FROM blocks WHERE substrate_block_hash = ?), ?)",
+					)
+					.bind(encoding.column(&log.address))
+					.bind(log.topic_1.map(|t| encoding.column(&t)))
+					.bind(log.topic_2.map(|t| encoding.column(&t)))
+					.bind(log.topic_3.map(|t| encoding.column(&t)))
+					.bind(log.topic_4.map(|t| encoding.column(&t)))
+					.bind(non_negative_index("log_index", log.log_index)?)
+					.bind(non_negative_index(
+						"transaction_index",
+						log.transaction_index,
+					)?)
+					.bind(&log.substrate_block_hash)
+					.bind(&log.substrate_block_hash)
+					.bind(log.topic_count)
+					.execute(&mut *conn)
+					.await?;
+				}
+				LogKey::Hashed => {
+					let log_index = non_negative_index("log_index", log.log_index)?;
+					let transaction_index =
+						non_negative_index("transaction_index", log.transaction_index)?;
+					let log_key =
+						hashed_log_key(&log.substrate_block_hash, transaction_index, log_index);
+					if Self::is_log_key_stored(
+						&mut *conn,
+						&log_key,
+						&log.substrate_block_hash,
 						transaction_index,
-						substrate_block_hash)
-					VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
-						)
-						.bind(log.address)
-						.bind(log.topic_1)
-						.bind(log.topic_2)
-						.bind(log.topic_3)
-						.bind(log.topic_4)
-						.bind(log.log_index)
-						.bind(log.transaction_index)
-						.bind(log.substrate_block_hash)
-						.execute(&mut *tx)
-						.await?;
+						log_index,
+					)
+					.await?
+					{
+						continue;
 					}
-					Ok(tx.commit().await?)
+					let _ = sqlx::query(
+						"INSERT INTO logs(
+							address,
+							topic_1,
+							topic_2,
+							topic_3,
+							topic_4,
+							log_index,
+							transaction_index,
+							substrate_block_hash,
+							block_number,
+							topic_count,
+							log_key)
+						VALUES (?, ?, ?, ?, ?, ?, ?, ?,
+							(SELECT block_number FROM blocks WHERE substrate_block_hash = ?), ?, ?)",
+					)
+					.bind(encoding.column(&log.address))
+					.bind(log.topic_1.map(|t| encoding.column(&t)))
+					.bind(log.topic_2.map(|t| encoding.column(&t)))
+					.bind(log.topic_3.map(|t| encoding.column(&t)))
+					.bind(log.topic_4.map(|t| encoding.column(&t)))
+					.bind(log_index)
+					.bind(transaction_index)
+					.bind(&log.substrate_block_hash)
+					.bind(&log.substrate_block_hash)
+					.bind(log.topic_count)
+					.bind(&log_key[..])
+					.execute(&mut *conn)
+					.await?;
 				}
-				Err(e) => Err(e),
 			}
 		}
-		.await
-		.map_err(|e| {
-			log::error!(target: "frontier-sql", "{e}");
-		});
-		// https://www.sqlite.org/pragma.html#pragma_optimize
-		let _ = sqlx::query("PRAGMA optimize").execute(&pool).await;
-		log::debug!(target: "frontier-sql", "Batch committed");
+		Ok(())
 	}
 
 	fn get_logs(
@@ -516,12 +2208,8 @@ where
 
 		transaction_count += receipts.len();
 		for (transaction_index, receipt) in receipts.iter().enumerate() {
-			let receipt_logs = match receipt {
-				ethereum::ReceiptV3::Legacy(d)
-				| ethereum::ReceiptV3::EIP2930(d)
-				| ethereum::ReceiptV3::EIP1559(d) => &d.logs,
-			};
-			let transaction_index = transaction_index as i32;
+			let receipt_logs = receipt_logs(receipt);
+			let transaction_index = transaction_index as i64;
 			log_count += receipt_logs.len();
 			for (log_index, log) in receipt_logs.iter().enumerate() {
 				#[allow(clippy::get_first)]
@@ -531,9 +2219,10 @@ where
 					topic_2: log.topics.get(1).map(|l| l.as_bytes().to_owned()),
 					topic_3: log.topics.get(2).map(|l| l.as_bytes().to_owned()),
 					topic_4: log.topics.get(3).map(|l| l.as_bytes().to_owned()),
-					log_index: log_index as i32,
+					log_index: log_index as i64,
 					transaction_index,
 					substrate_block_hash: substrate_block_hash.as_bytes().to_owned(),
+					topic_count: log.topics.len() as i32,
 				});
 			}
 		}
@@ -546,6 +2235,9 @@ where
 
 	/// Retrieves the status if a block has been already indexed.
 	pub async fn is_block_indexed(&self, block_hash: Block::Hash) -> bool {
+		if self.grouped_block_status(block_hash).await.is_some() {
+			return true;
+		}
 		sqlx::query("SELECT substrate_block_hash FROM sync_status WHERE substrate_block_hash = ?")
 			.bind(block_hash.as_bytes().to_owned())
 			.fetch_optional(self.pool())
@@ -554,11 +2246,58 @@ where
 			.unwrap_or(false)
 	}
 
+	/// Retrieves the subset of the provided block hashes that have not been indexed yet nor
+	/// moved to the dead letters, preserving the input order.
+	pub async fn get_unindexed_blocks(&self, hashes: &[H256]) -> Result<Vec<H256>, Error> {
+		let mut indexed = HashSet::new();
+		if let Some(commit_group) = &self.commit_group {
+			let group = commit_group.lock().await;
+			indexed.extend(
+				hashes
+					.iter()
+					.filter(|hash| group.block(**hash).is_some())
+					.copied(),
+			);
+		}
+		for chunk in hashes.chunks(HASH_LOOKUP_CHUNK_SIZE) {
+			let mut builder: QueryBuilder<Sqlite> =
+				QueryBuilder::new("WITH hashes(substrate_block_hash) AS (VALUES ");
+			let mut block_hashes = builder.separated(", ");
+			for hash in chunk {
+				block_hashes.push("(");
+				block_hashes.push_bind_unseparated(hash.as_bytes());
+				block_hashes.push_unseparated(")");
+			}
+			builder.push(
+				") SELECT substrate_block_hash FROM hashes
+				WHERE substrate_block_hash IN (SELECT substrate_block_hash FROM sync_status)
+				OR substrate_block_hash IN (SELECT substrate_block_hash FROM dead_letter_blocks)",
+			);
+			indexed.extend(
+				builder
+					.build()
+					.fetch_all(self.pool())
+					.await?
+					.iter()
+					.map(|row| H256::from_slice(&row.get::<Vec<u8>, _>(0)[..])),
+			);
+		}
+
+		Ok(hashes
+			.iter()
+			.filter(|hash| !indexed.contains(hash))
+			.copied()
+			.collect())
+	}
+
 	/// Retrieves the status if a block is indexed and if also marked as canon.
 	pub async fn block_indexed_and_canon_status(
 		&self,
 		block_hash: Block::Hash,
 	) -> BlockIndexedStatus {
+		if let Some(status) = self.grouped_block_status(block_hash).await {
+			return status;
+		}
 		sqlx::query(
 			"SELECT b.is_canon FROM sync_status AS s
 			INNER JOIN blocks AS b
@@ -582,17 +2321,183 @@ where
 		.unwrap_or_default()
 	}
 
+	/// The status of a block grouped by [`Self::with_commit_interval`] and not committed yet.
+	async fn grouped_block_status(&self, block_hash: H256) -> Option<BlockIndexedStatus> {
+		let group = self.commit_group.as_ref()?.lock().await;
+		group.block(block_hash).map(|metadata| BlockIndexedStatus {
+			indexed: true,
+			canon: metadata.is_canon != 0,
+		})
+	}
+
+	/// Retrieve the block number of the given indexed substrate block. A block grouped by
+	/// [`Self::with_commit_interval`] is only found once committed.
+	pub async fn block_number(&self, block_hash: H256) -> Result<Option<u32>, Error> {
+		let row = sqlx::query("SELECT block_number FROM blocks WHERE substrate_block_hash = ?")
+			.bind(block_hash.as_bytes())
+			.fetch_optional(self.pool())
+			.await?;
+		Ok(row.map(|row| row.get::<i32, _>(0) as u32))
+	}
+
+	/// Retrieve whether the given substrate block is indexed as canonical, `None` if it is
+	/// not indexed. Reads the committed index only, ignoring the grouped blocks.
+	pub async fn is_canon(&self, block_hash: H256) -> Result<Option<bool>, Error> {
+		Ok(self
+			.block_by_hash(block_hash)
+			.await?
+			.map(|block| block.is_canon))
+	}
+
+	/// Retrieve the substrate hash of the canonical block indexed at the given number, among
+	/// the committed blocks. See [`Self::flush_writes`] to commit the grouped ones first.
+	pub async fn canonical_block_by_number(
+		&self,
+		block_number: u32,
+	) -> Result<Option<H256>, Error> {
+		let row = sqlx::query(
+			"SELECT substrate_block_hash FROM blocks WHERE block_number = ? AND is_canon = 1",
+		)
+		.bind(block_number as i64)
+		.fetch_optional(self.pool())
+		.await?;
+		Ok(row.map(|row| H256::from_slice(&row.get::<Vec<u8>, _>(0)[..])))
+	}
+
+	/// Retrieve the canonical blocks authored by the given beneficiary within the block
+	/// number range, in ascending order.
+	pub async fn blocks_by_author(
+		&self,
+		author: H160,
+		range: RangeInclusive<u32>,
+	) -> Result<Vec<H256>, Error> {
+		Ok(sqlx::query(
+			"SELECT substrate_block_hash FROM blocks
+			WHERE author = ? AND block_number BETWEEN ? AND ? AND is_canon = 1
+			ORDER BY block_number ASC",
+		)
+		.bind(author.as_bytes())
+		.bind(*range.start() as i64)
+		.bind(*range.end() as i64)
+		.fetch_all(self.pool())
+		.await?
+		.iter()
+		.map(|row| H256::from_slice(&row.get::<Vec<u8>, _>(0)[..]))
+		.collect())
+	}
+
+	/// Retrieve the indexed blocks within the block number range, in ascending order. Blocks
+	/// retracted by a re-org are left out if `only_canon` is set. The range spans at most
+	/// [`MAX_BLOCKS_IN_RANGE`] block numbers, and the blocks grouped by
+	/// [`Self::with_commit_interval`] are only returned once committed.
+	pub async fn blocks_in_range(
+		&self,
+		range: RangeInclusive<u32>,
+		only_canon: bool,
+	) -> Result<Vec<IndexedBlock>, Error> {
+		if range.end().saturating_sub(*range.start()) >= MAX_BLOCKS_IN_RANGE {
+			return Err(Error::Protocol(format!(
+				"Block range #{}..=#{} spans more than {MAX_BLOCKS_IN_RANGE} blocks",
+				range.start(),
+				range.end(),
+			)));
+		}
+		Ok(sqlx::query(
+			"SELECT substrate_block_hash, block_number, is_canon FROM blocks
+			WHERE block_number BETWEEN ? AND ? AND (? = 0 OR is_canon = 1)
+			ORDER BY block_number ASC, is_canon DESC",
+		)
+		.bind(*range.start() as i64)
+		.bind(*range.end() as i64)
+		.bind(only_canon)
+		.fetch_all(self.pool())
+		.await?
+		.iter()
+		.map(indexed_block_from_row)
+		.collect())
+	}
+
+	/// Retrieve the given substrate block, if indexed and committed.
+	pub async fn block_by_hash(
+		&self,
+		substrate_block_hash: H256,
+	) -> Result<Option<IndexedBlock>, Error> {
+		Ok(sqlx::query(
+			"SELECT substrate_block_hash, block_number, is_canon FROM blocks
+			WHERE substrate_block_hash = ?",
+		)
+		.bind(substrate_block_hash.as_bytes())
+		.fetch_optional(self.pool())
+		.await?
+		.as_ref()
+		.map(indexed_block_from_row))
+	}
+
+	/// Retrieve the canonical logs emitted by the given contract within the block number
+	/// range, ordered by block, transaction and log index.
+	pub async fn logs_by_address(
+		&self,
+		address: H160,
+		range: RangeInclusive<u32>,
+	) -> Result<Vec<FilteredLog<Block>>, Error> {
+		let rows = sqlx::query(
+			"SELECT l.substrate_block_hash, b.ethereum_block_hash, b.block_number,
+				b.ethereum_storage_schema, l.transaction_index, l.log_index
+			FROM logs AS l
+			INNER JOIN blocks AS b ON b.substrate_block_hash = l.substrate_block_hash
+			WHERE l.address = ? AND l.block_number BETWEEN ? AND ? AND b.is_canon = 1
+			ORDER BY l.block_number, l.transaction_index, l.log_index",
+		)
+		.bind(self.log_column_encoding.column(&address))
+		.bind(*range.start() as i64)
+		.bind(*range.end() as i64)
+		.fetch_all(self.pool())
+		.await?;
+		rows.iter()
+			.map(|row| {
+				Ok(FilteredLog {
+					substrate_block_hash: H256::from_slice(&row.get::<Vec<u8>, _>(0)[..]),
+					ethereum_block_hash: H256::from_slice(&row.get::<Vec<u8>, _>(1)[..]),
+					block_number: row.get::<i32, _>(2) as u32,
+					ethereum_storage_schema: Decode::decode(&mut &row.get::<Vec<u8>, _>(3)[..])
+						.map_err(|_| {
+							Error::Protocol("Cannot decode EthereumStorageSchema".to_string())
+						})?,
+					transaction_index: row.get::<i64, _>(4) as u32,
+					log_index: row.get::<i64, _>(5) as u32,
+				})
+			})
+			.collect()
+	}
+
+	/// Retrieve the number of logs indexed for the given substrate block.
+	pub async fn log_count(&self, block_hash: H256) -> Result<u32, Error> {
+		let row = sqlx::query("SELECT COUNT(*) FROM logs WHERE substrate_block_hash = ?")
+			.bind(block_hash.as_bytes())
+			.fetch_one(self.pool())
+			.await?;
+		Ok(row.get::<i64, _>(0) as u32)
+	}
+
 	/// Sets the provided block as canon.
 	pub async fn set_block_as_canon(&self, block_hash: H256) -> Result<SqliteQueryResult, Error> {
-		sqlx::query("UPDATE blocks SET is_canon = 1 WHERE substrate_block_hash = ?")
+		self.flush_writes().await?;
+		let mut tx = self.pool().begin().await?;
+		let result = sqlx::query("UPDATE blocks SET is_canon = 1 WHERE substrate_block_hash = ?")
 			.bind(block_hash.as_bytes())
-			.execute(self.pool())
-			.await
+			.execute(&mut *tx)
+			.await?;
+		sqlx::query("UPDATE block_summary SET is_canon = 1 WHERE substrate_block_hash = ?")
+			.bind(block_hash.as_bytes())
+			.execute(&mut *tx)
+			.await?;
+		tx.commit().await?;
+		Ok(result)
 	}
 
 	/// Retrieves the first missing canonical block number in decreasing order that hasn't been indexed yet.
 	/// If no unindexed block exists or the table or the rows do not exist, then the function
-	/// returns `None`.
+	/// returns `None`. Pruned blocks are not missing.
 	pub async fn get_first_missing_canon_block(&self) -> Option<u32> {
 		match sqlx::query(
 			"SELECT b1.block_number-1
@@ -603,8 +2508,10 @@ where
 				AND b1.is_canon=1
 				AND b2.is_canon=1
 			)
+			AND b1.block_number-1 >= IFNULL((SELECT value FROM indexer_meta WHERE key = ?), 0)
 			ORDER BY block_number LIMIT 1",
 		)
+		.bind(PRUNED_BELOW_KEY)
 		.fetch_optional(self.pool())
 		.await
 		{
@@ -622,6 +2529,30 @@ where
 		None
 	}
 
+	/// Retrieves the inclusive ranges of block numbers without an indexed canonical block,
+	/// from genesis up to the highest indexed canonical block, in ascending order. A height
+	/// with retracted blocks only is missing, several canonical rows count once. Pruned
+	/// blocks are not missing.
+	pub async fn find_gaps(&self) -> Result<Vec<(u32, u32)>, Error> {
+		let rows = sqlx::query(
+			"SELECT MAX(previous + 1, pruned_below), block_number - 1 FROM (
+				SELECT block_number,
+					LAG(block_number, 1, -1) OVER (ORDER BY block_number) AS previous,
+					IFNULL((SELECT value FROM indexer_meta WHERE key = ?), 0) AS pruned_below
+				FROM (SELECT DISTINCT block_number FROM blocks WHERE is_canon = 1)
+			)
+			WHERE block_number - previous > 1 AND block_number > pruned_below
+			ORDER BY block_number",
+		)
+		.bind(PRUNED_BELOW_KEY)
+		.fetch_all(self.pool())
+		.await?;
+		Ok(rows
+			.iter()
+			.map(|row| (row.get::<i64, _>(0) as u32, row.get::<i64, _>(1) as u32))
+			.collect())
+	}
+
 	/// Retrieves the first pending canonical block hash in decreasing order that hasn't had
 	// its logs indexed yet. If no unindexed block exists or the table or the rows do not exist,
 	/// then the function returns `None`.
@@ -630,7 +2561,7 @@ where
 			"SELECT s.substrate_block_hash FROM sync_status AS s
 			INNER JOIN blocks as b
 			ON s.substrate_block_hash = b.substrate_block_hash
-			WHERE b.is_canon = 1 AND s.status = 0
+			WHERE b.is_canon = 1 AND s.metadata_done = 1 AND s.logs_done = 0
 			ORDER BY b.block_number LIMIT 1",
 		)
 		.fetch_optional(self.pool())
@@ -651,1088 +2582,5356 @@ where
 		None
 	}
 
-	/// Retrieve the block hash for the last indexed canon block.
-	pub async fn last_indexed_canon_block(&self) -> Result<H256, Error> {
-		let row = sqlx::query(
-			"SELECT b.substrate_block_hash FROM blocks AS b
-			INNER JOIN sync_status AS s
+	/// Retrieves the blocks whose metadata was committed but whose logs were not indexed yet,
+	/// e.g. because the node stopped in between, in increasing block number order.
+	pub async fn blocks_pending_logs(&self) -> Result<Vec<H256>, Error> {
+		Ok(sqlx::query(
+			"SELECT s.substrate_block_hash FROM sync_status AS s
+			INNER JOIN blocks AS b
 			ON s.substrate_block_hash = b.substrate_block_hash
-			WHERE b.is_canon=1 AND s.status = 1
-			ORDER BY b.id DESC LIMIT 1",
+			WHERE s.metadata_done = 1 AND s.logs_done = 0
+			ORDER BY b.block_number",
 		)
-		.fetch_one(self.pool())
-		.await?;
-		Ok(H256::from_slice(
-			&row.try_get::<Vec<u8>, _>(0).unwrap_or_default()[..],
-		))
+		.fetch_all(self.pool())
+		.await?
+		.iter()
+		.map(|row| H256::from_slice(&row.get::<Vec<u8>, _>(0)[..]))
+		.collect())
 	}
 
-	/// Create the Sqlite database if it does not already exist.
-	async fn create_database_if_not_exists(pool: &SqlitePool) -> Result<SqliteQueryResult, Error> {
+	/// Store a transaction of the given substrate block. The fee fields are only known
+	/// when the transaction was decoded from the block extrinsics, the receipt fields when
+	/// its receipt was available.
+	#[allow(clippy::too_many_arguments)]
+	async fn insert_transaction(
+		conn: &mut sqlx::SqliteConnection,
+		substrate_block_hash: H256,
+		ethereum_block_hash: H256,
+		ethereum_transaction_index: i64,
+		ethereum_transaction_hash: H256,
+		transaction: Option<&ethereum::TransactionV2>,
+		sender: Option<H160>,
+		cumulative_gas_used: Option<U256>,
+		status_code: Option<u8>,
+	) -> Result<SqliteQueryResult, Error> {
+		let (max_fee_per_gas, max_priority_fee_per_gas) = match transaction {
+			Some(ethereum::TransactionV2::EIP1559(t)) => (
+				Some(u256_to_bytes(t.max_fee_per_gas)),
+				Some(u256_to_bytes(t.max_priority_fee_per_gas)),
+			),
+			_ => (None, None),
+		};
+		let (v, y_parity, r, s) = match transaction {
+			Some(ethereum::TransactionV2::Legacy(t)) => (
+				Some(t.signature.v() as i64),
+				None,
+				Some(*t.signature.r()),
+				Some(*t.signature.s()),
+			),
+			Some(ethereum::TransactionV2::EIP2930(t)) => {
+				(None, Some(t.odd_y_parity), Some(t.r), Some(t.s))
+			}
+			Some(ethereum::TransactionV2::EIP1559(t)) => {
+				(None, Some(t.odd_y_parity), Some(t.r), Some(t.s))
+			}
+			None => (None, None, None, None),
+		};
+		let cumulative_gas_used = cumulative_gas_used.map(u256_to_bytes);
 		sqlx::query(
-			"BEGIN;
-			CREATE TABLE IF NOT EXISTS logs (
-				id INTEGER PRIMARY KEY,
-				address BLOB NOT NULL,
-				topic_1 BLOB,
-				topic_2 BLOB,
-				topic_3 BLOB,
-				topic_4 BLOB,
-				log_index INTEGER NOT NULL,
-				transaction_index INTEGER NOT NULL,
-				substrate_block_hash BLOB NOT NULL,
-				UNIQUE (
-					log_index,
-					transaction_index,
-					substrate_block_hash
-				)
-			);
-			CREATE TABLE IF NOT EXISTS sync_status (
-				id INTEGER PRIMARY KEY,
-				substrate_block_hash BLOB NOT NULL,
-				status INTEGER DEFAULT 0 NOT NULL,
-				UNIQUE (
-					substrate_block_hash
-				)
-			);
-			CREATE TABLE IF NOT EXISTS blocks (
-				id INTEGER PRIMARY KEY,
-				block_number INTEGER NOT NULL,
-				ethereum_block_hash BLOB NOT NULL,
-				substrate_block_hash BLOB NOT NULL,
-				ethereum_storage_schema BLOB NOT NULL,
-				is_canon INTEGER NOT NULL,
-				UNIQUE (
-					ethereum_block_hash,
-					substrate_block_hash
-				)
-			);
-			CREATE TABLE IF NOT EXISTS transactions (
-				id INTEGER PRIMARY KEY,
-				ethereum_transaction_hash BLOB NOT NULL,
-				substrate_block_hash BLOB NOT NULL,
-				ethereum_block_hash BLOB NOT NULL,
-				ethereum_transaction_index INTEGER NOT NULL,
-				UNIQUE (
-					ethereum_transaction_hash,
-					substrate_block_hash
-				)
-			);
-			COMMIT;",
+			"INSERT OR IGNORE INTO transactions(
+				ethereum_transaction_hash,
+				substrate_block_hash,
+				ethereum_block_hash,
+				ethereum_transaction_index,
+				max_fee_per_gas,
+				max_priority_fee_per_gas,
+				sender,
+				v,
+				y_parity,
+				r,
+				s,
+				cumulative_gas_used,
+				status_code)
+			VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
 		)
-		.execute(pool)
+		.bind(ethereum_transaction_hash.as_bytes())
+		.bind(substrate_block_hash.as_bytes())
+		.bind(ethereum_block_hash.as_bytes())
+		.bind(non_negative_index(
+			"ethereum_transaction_index",
+			ethereum_transaction_index,
+		)?)
+		.bind(max_fee_per_gas.as_ref().map(|v| &v[..]))
+		.bind(max_priority_fee_per_gas.as_ref().map(|v| &v[..]))
+		.bind(sender.as_ref().map(|v| v.as_bytes()))
+		.bind(v)
+		.bind(y_parity)
+		.bind(r.as_ref().map(|v| v.as_bytes()))
+		.bind(s.as_ref().map(|v| v.as_bytes()))
+		.bind(cumulative_gas_used.as_ref().map(|v| &v[..]))
+		.bind(status_code)
+		.execute(conn)
 		.await
 	}
 
-	/// Create the Sqlite database indices if it does not already exist.
-	async fn create_indexes_if_not_exist(pool: &SqlitePool) -> Result<SqliteQueryResult, Error> {
-		sqlx::query(
-			"BEGIN;
-			CREATE INDEX IF NOT EXISTS logs_main_idx ON logs (
-				address,
-				topic_1,
-				topic_2,
-				topic_3,
-				topic_4
-			);
-			CREATE INDEX IF NOT EXISTS logs_substrate_index ON logs (
-				substrate_block_hash
-			);
-			CREATE INDEX IF NOT EXISTS blocks_number_index ON blocks (
-				block_number
-			);
-			CREATE INDEX IF NOT EXISTS blocks_substrate_index ON blocks (
-				substrate_block_hash
-			);
-			CREATE INDEX IF NOT EXISTS eth_block_hash_idx ON blocks (
-				ethereum_block_hash
-			);
-			CREATE INDEX IF NOT EXISTS eth_tx_hash_idx ON transactions (
-				ethereum_transaction_hash
-			);
-			CREATE INDEX IF NOT EXISTS eth_tx_hash_2_idx ON transactions (
-				ethereum_block_hash,
-				ethereum_transaction_index
-			);
-			COMMIT;",
+	/// Retrieve the placement of the given transaction in the canonical chain. A transaction
+	/// re-included after a re-org has a row per including block, the canonical one wins.
+	pub async fn canonical_transaction(
+		&self,
+		ethereum_transaction_hash: H256,
+	) -> Result<Option<TransactionMetadata<Block>>, Error> {
+		let row = sqlx::query(
+			"SELECT t.substrate_block_hash, t.ethereum_block_hash, t.ethereum_transaction_index
+			FROM transactions AS t
+			INNER JOIN blocks AS b ON b.substrate_block_hash = t.substrate_block_hash
+			WHERE t.ethereum_transaction_hash = ? AND b.is_canon = 1
+			LIMIT 1",
 		)
-		.execute(pool)
-		.await
+		.bind(ethereum_transaction_hash.as_bytes())
+		.fetch_optional(self.pool())
+		.await?;
+		Ok(row.map(|row| TransactionMetadata {
+			substrate_block_hash: H256::from_slice(&row.get::<Vec<u8>, _>(0)[..]),
+			ethereum_block_hash: H256::from_slice(&row.get::<Vec<u8>, _>(1)[..]),
+			ethereum_index: row.get::<i64, _>(2) as u32,
+		}))
 	}
-}
 
-#[async_trait::async_trait]
-impl<Block: BlockT<Hash = H256>> fc_api::Backend<Block> for Backend<Block> {
-	async fn block_hash(
+	/// Retrieve the EIP-1559 fee fields of the given transaction. Both fields are `None` for
+	/// legacy and EIP-2930 transactions, or if the transaction was not decoded.
+	pub async fn transaction_fees(
 		&self,
-		ethereum_block_hash: &H256,
-	) -> Result<Option<Vec<Block::Hash>>, String> {
-		let ethereum_block_hash = ethereum_block_hash.as_bytes();
-		let res =
-			sqlx::query("SELECT substrate_block_hash FROM blocks WHERE ethereum_block_hash = ?")
-				.bind(ethereum_block_hash)
-				.fetch_all(&self.pool)
-				.await
-				.ok()
-				.map(|rows| {
-					rows.iter()
-						.map(|row| {
-							H256::from_slice(&row.try_get::<Vec<u8>, _>(0).unwrap_or_default()[..])
-						})
-						.collect()
-				});
-		Ok(res)
+		ethereum_transaction_hash: H256,
+	) -> Result<Option<TransactionFees>, Error> {
+		let row = sqlx::query(
+			"SELECT max_fee_per_gas, max_priority_fee_per_gas FROM transactions
+			WHERE ethereum_transaction_hash = ? LIMIT 1",
+		)
+		.bind(ethereum_transaction_hash.as_bytes())
+		.fetch_optional(self.pool())
+		.await?;
+		Ok(row.map(|row| {
+			let fee = |column: &str| {
+				row.try_get::<Option<Vec<u8>>, _>(column)
+					.ok()
+					.flatten()
+					.map(|bytes| U256::from_big_endian(&bytes))
+			};
+			TransactionFees {
+				max_fee_per_gas: fee("max_fee_per_gas"),
+				max_priority_fee_per_gas: fee("max_priority_fee_per_gas"),
+			}
+		}))
 	}
 
-	async fn transaction_metadata(
-		&self,
-		ethereum_transaction_hash: &H256,
-	) -> Result<Vec<TransactionMetadata<Block>>, String> {
-		let ethereum_transaction_hash = ethereum_transaction_hash.as_bytes();
-		let out = sqlx::query(
-			"SELECT
-				substrate_block_hash, ethereum_block_hash, ethereum_transaction_index
-			FROM transactions WHERE ethereum_transaction_hash = ?",
+	/// Retrieve the receipts of the given block in transaction order, with their logs in log
+	/// order. Returns an empty list if the block is not indexed.
+	pub async fn block_receipts(&self, substrate_block_hash: H256) -> Result<Vec<Receipt>, Error> {
+		let mut receipts = sqlx::query(
+			"SELECT ethereum_transaction_hash, ethereum_transaction_index, sender,
+				cumulative_gas_used, status_code
+			FROM transactions WHERE substrate_block_hash = ?
+			ORDER BY ethereum_transaction_index",
 		)
-		.bind(ethereum_transaction_hash)
-		.fetch_all(&self.pool)
-		.await
-		.unwrap_or_default()
+		.bind(substrate_block_hash.as_bytes())
+		.fetch_all(self.pool())
+		.await?
 		.iter()
 		.map(|row| {
-			let substrate_block_hash =
-				H256::from_slice(&row.try_get::<Vec<u8>, _>(0).unwrap_or_default()[..]);
-			let ethereum_block_hash =
-				H256::from_slice(&row.try_get::<Vec<u8>, _>(1).unwrap_or_default()[..]);
-			let ethereum_transaction_index = row.try_get::<i32, _>(2).unwrap_or_default() as u32;
-			TransactionMetadata {
-				substrate_block_hash,
-				ethereum_block_hash,
-				ethereum_index: ethereum_transaction_index,
+			let bytes = |index: usize| row.try_get::<Option<Vec<u8>>, _>(index).ok().flatten();
+			Receipt {
+				transaction_hash: H256::from_slice(&row.get::<Vec<u8>, _>(0)[..]),
+				transaction_index: row.get::<u32, _>(1),
+				sender: bytes(2).map(|sender| H160::from_slice(&sender[..])),
+				cumulative_gas_used: bytes(3).map(|gas| U256::from_big_endian(&gas)),
+				gas_used: None,
+				status_code: row.get::<Option<u8>, _>(4),
+				logs: vec![],
 			}
 		})
-		.collect();
+		.collect::<Vec<Receipt>>();
 
-		Ok(out)
-	}
+		let mut previous_cumulative_gas_used = Some(U256::zero());
+		for receipt in receipts.iter_mut() {
+			receipt.gas_used = match (receipt.cumulative_gas_used, previous_cumulative_gas_used) {
+				(Some(cumulative), Some(previous)) => cumulative.checked_sub(previous),
+				_ => None,
+			};
+			previous_cumulative_gas_used = receipt.cumulative_gas_used;
+		}
 
-	fn log_indexer(&self) -> &dyn fc_api::LogIndexerBackend<Block> {
-		self
+		for (transaction_index, log) in self.block_logs(substrate_block_hash).await? {
+			if let Some(receipt) = receipts
+				.iter_mut()
+				.find(|receipt| receipt.transaction_index == transaction_index)
+			{
+				receipt.logs.push(log);
+			}
+		}
+		Ok(receipts)
 	}
 
-	async fn latest_block_hash(&self) -> Result<Block::Hash, String> {
-		// Retrieves the block hash for the latest indexed block, maybe it's not canon.
-		sqlx::query("SELECT substrate_block_hash FROM blocks ORDER BY block_number DESC LIMIT 1")
-			.fetch_one(self.pool())
-			.await
-			.map(|row| H256::from_slice(&row.get::<Vec<u8>, _>(0)[..]))
-			.map_err(|e| format!("Failed to fetch best hash: {}", e))
+	/// Retrieve the logs indexed for the given block with their transaction index, in
+	/// transaction and log order, whether or not their transaction is indexed.
+	pub async fn block_logs(
+		&self,
+		substrate_block_hash: H256,
+	) -> Result<Vec<(u32, ReceiptLog)>, Error> {
+		let encoding = self.log_column_encoding;
+		let logs = sqlx::query(
+			"SELECT address, topic_1, topic_2, topic_3, topic_4, log_index, transaction_index
+			FROM logs WHERE substrate_block_hash = ?
+			ORDER BY transaction_index, log_index",
+		)
+		.bind(substrate_block_hash.as_bytes())
+		.fetch_all(self.pool())
+		.await?;
+		Ok(logs
+			.iter()
+			.filter_map(|row| {
+				let address = topic_column(row, 0, encoding).and_then(log_column_bytes)?;
+				let log = ReceiptLog {
+					address: H160::from_slice(&address[..]),
+					topics: (1..=4)
+						.filter_map(|index| topic_column(row, index, encoding))
+						.filter_map(log_column_bytes)
+						.map(|topic| H256::from_slice(&topic[..]))
+						.collect(),
+					log_index: row.get::<u32, _>(5),
+				};
+				Some((row.get::<u32, _>(6), log))
+			})
+			.collect())
 	}
-}
 
-#[async_trait::async_trait]
-impl<Block: BlockT<Hash = H256>> fc_api::LogIndexerBackend<Block> for Backend<Block> {
-	fn is_indexed(&self) -> bool {
-		true
+	/// Retrieve the recovered sender of the given transaction. Returns `None` if the transaction
+	/// is unknown, was not decoded, or its signature could not be recovered.
+	pub async fn transaction_sender(
+		&self,
+		ethereum_transaction_hash: H256,
+	) -> Result<Option<H160>, Error> {
+		let row = sqlx::query(
+			"SELECT sender FROM transactions
+			WHERE ethereum_transaction_hash = ? AND sender IS NOT NULL LIMIT 1",
+		)
+		.bind(ethereum_transaction_hash.as_bytes())
+		.fetch_optional(self.pool())
+		.await?;
+		Ok(row.and_then(|row| {
+			row.try_get::<Vec<u8>, _>(0)
+				.ok()
+				.map(|bytes| H160::from_slice(&bytes))
+		}))
 	}
 
-	async fn filter_logs(
+	/// Retrieve the signature components of the given transaction. Returns `None` if the
+	/// transaction is unknown or was not decoded from the block extrinsics.
+	pub async fn transaction_signature(
 		&self,
-		from_block: u64,
-		to_block: u64,
-		addresses: Vec<H160>,
-		topics: Vec<Vec<Option<H256>>>,
-	) -> Result<Vec<FilteredLog<Block>>, String> {
-		let mut unique_topics: [HashSet<H256>; 4] = [
-			HashSet::new(),
-			HashSet::new(),
-			HashSet::new(),
-			HashSet::new(),
-		];
-		for topic_combination in topics.into_iter() {
-			for (topic_index, topic) in topic_combination.into_iter().enumerate() {
-				if topic_index == MAX_TOPIC_COUNT as usize {
-					return Err("Invalid topic input. Maximum length is 4.".to_string());
-				}
-
-				if let Some(topic) = topic {
-					unique_topics[topic_index].insert(topic);
-				}
-			}
-		}
+		ethereum_transaction_hash: H256,
+	) -> Result<Option<TransactionSignatureComponents>, Error> {
+		let row = sqlx::query(
+			"SELECT v, y_parity, r, s FROM transactions
+			WHERE ethereum_transaction_hash = ? AND r IS NOT NULL LIMIT 1",
+		)
+		.bind(ethereum_transaction_hash.as_bytes())
+		.fetch_optional(self.pool())
+		.await?;
+		row.map(|row| {
+			Ok(TransactionSignatureComponents {
+				v: row.try_get::<Option<i64>, _>(0)?.map(|v| v as u64),
+				y_parity: row.try_get::<Option<bool>, _>(1)?,
+				r: H256::from_slice(&row.try_get::<Vec<u8>, _>(2)?[..]),
+				s: H256::from_slice(&row.try_get::<Vec<u8>, _>(3)?[..]),
+			})
+		})
+		.transpose()
+	}
 
-		let log_key = format!("{from_block}-{to_block}-{addresses:?}-{unique_topics:?}");
-		let mut qb = QueryBuilder::new("");
-		let query = build_query(&mut qb, from_block, to_block, addresses, unique_topics);
-		let sql = query.sql();
+	/// Store the full ethereum header of the given substrate block.
+	async fn insert_ethereum_header(
+		conn: &mut sqlx::SqliteConnection,
+		substrate_block_hash: H256,
+		header: &ethereum::Header,
+	) -> Result<SqliteQueryResult, Error> {
+		sqlx::query(
+			"INSERT OR IGNORE INTO block_headers(
+				substrate_block_hash,
+				parent_hash,
+				ommers_hash,
+				beneficiary,
+				state_root,
+				transactions_root,
+				receipts_root,
+				logs_bloom,
+				difficulty,
+				number,
+				gas_limit,
+				gas_used,
+				timestamp,
+				extra_data,
+				mix_hash,
+				nonce)
+			VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+		)
+		.bind(substrate_block_hash.as_bytes())
+		.bind(header.parent_hash.as_bytes())
+		.bind(header.ommers_hash.as_bytes())
+		.bind(header.beneficiary.as_bytes())
+		.bind(header.state_root.as_bytes())
+		.bind(header.transactions_root.as_bytes())
+		.bind(header.receipts_root.as_bytes())
+		.bind(&header.logs_bloom.0[..])
+		.bind(u256_to_bytes(header.difficulty))
+		.bind(u256_to_bytes(header.number))
+		.bind(u256_to_bytes(header.gas_limit))
+		.bind(u256_to_bytes(header.gas_used))
+		.bind(header.timestamp as i64)
+		.bind(&header.extra_data[..])
+		.bind(header.mix_hash.as_bytes())
+		.bind(header.nonce.as_bytes())
+		.execute(conn)
+		.await
+	}
 
-		let mut conn = self
-			.pool()
-			.acquire()
-			.await
-			.map_err(|err| format!("failed acquiring sqlite connection: {}", err))?;
-		let log_key2 = log_key.clone();
-		conn.lock_handle()
-			.await
-			.map_err(|err| format!("{:?}", err))?
-			.set_progress_handler(self.num_ops_timeout, move || {
-				log::debug!(target: "frontier-sql", "Sqlite progress_handler triggered for {log_key2}");
-				false
-			});
-		log::debug!(target: "frontier-sql", "Query: {sql:?} - {log_key}");
+	/// Store the EIP-2718 encoded receipt of a transaction of the given substrate block.
+	async fn insert_raw_receipt(
+		conn: &mut sqlx::SqliteConnection,
+		substrate_block_hash: H256,
+		transaction_index: i64,
+		receipt: &ethereum::ReceiptV3,
+	) -> Result<SqliteQueryResult, Error> {
+		sqlx::query(
+			"INSERT OR IGNORE INTO raw_receipts(substrate_block_hash, transaction_index, receipt)
+			VALUES (?, ?, ?)",
+		)
+		.bind(substrate_block_hash.as_bytes())
+		.bind(transaction_index)
+		.bind(ethereum::EnvelopedEncodable::encode(receipt).to_vec())
+		.execute(conn)
+		.await
+	}
 
-		let mut out: Vec<FilteredLog<Block>> = vec![];
-		let mut rows = query.fetch(&mut *conn);
-		let maybe_err = loop {
-			match rows.try_next().await {
-				Ok(Some(row)) => {
-					// Substrate block hash
-					let substrate_block_hash =
-						H256::from_slice(&row.try_get::<Vec<u8>, _>(0).unwrap_or_default()[..]);
-					// Ethereum block hash
-					let ethereum_block_hash =
-						H256::from_slice(&row.try_get::<Vec<u8>, _>(1).unwrap_or_default()[..]);
-					// Block number
-					let block_number = row.try_get::<i32, _>(2).unwrap_or_default() as u32;
-					// Ethereum storage schema
-					let ethereum_storage_schema: EthereumStorageSchema =
-						Decode::decode(&mut &row.try_get::<Vec<u8>, _>(3).unwrap_or_default()[..])
-							.map_err(|_| {
-								"Cannot decode EthereumStorageSchema for block".to_string()
-							})?;
-					// Transaction index
-					let transaction_index = row.try_get::<i32, _>(4).unwrap_or_default() as u32;
-					// Log index
-					let log_index = row.try_get::<i32, _>(5).unwrap_or_default() as u32;
-					out.push(FilteredLog {
-						substrate_block_hash,
-						ethereum_block_hash,
-						block_number,
-						ethereum_storage_schema,
-						transaction_index,
-						log_index,
-					});
-				}
-				Ok(None) => break None, // no more rows
-				Err(err) => break Some(err),
-			};
+	/// Build a proof that the `log_index`-th log of the given transaction was included in
+	/// its canonical block. Returns `None` if the transaction is not canonical, the log does
+	/// not exist, the header of its block is not indexed or its receipts are not stored, see
+	/// [`Backend::with_receipt_storage`].
+	pub async fn log_inclusion_proof(
+		&self,
+		ethereum_transaction_hash: H256,
+		log_index: u32,
+	) -> Result<Option<LogInclusionProof>, Error> {
+		let Some(transaction) = self
+			.canonical_transaction(ethereum_transaction_hash)
+			.await?
+		else {
+			return Ok(None);
 		};
-		drop(rows);
-		conn.lock_handle()
-			.await
-			.map_err(|err| format!("{:?}", err))?
-			.remove_progress_handler();
+		let substrate_block_hash = transaction.substrate_block_hash;
+		let Some(header) = self.ethereum_header(substrate_block_hash).await? else {
+			return Ok(None);
+		};
+		let receipts = sqlx::query(
+			"SELECT receipt FROM raw_receipts WHERE substrate_block_hash = ?
+			ORDER BY transaction_index ASC",
+		)
+		.bind(substrate_block_hash.as_bytes())
+		.fetch_all(self.pool())
+		.await?
+		.iter()
+		.map(|row| row.try_get::<Vec<u8>, _>(0))
+		.collect::<Result<Vec<_>, _>>()?;
 
-		if let Some(err) = maybe_err {
-			log::error!(target: "frontier-sql", "Failed to query sql db: {err:?} - {log_key}");
-			return Err("Failed to query sql db with statement".to_string());
+		let transaction_index = transaction.ethereum_index;
+		let Some(receipt) = receipts.get(transaction_index as usize) else {
+			return Ok(None);
+		};
+		let receipt: ethereum::ReceiptV3 = ethereum::EnvelopedDecodable::decode(receipt)
+			.map_err(|err| Error::Protocol(format!("Invalid raw receipt: {err:?}")))?;
+		if receipt_logs(&receipt).len() <= log_index as usize {
+			return Ok(None);
 		}
 
-		log::info!(target: "frontier-sql", "FILTER remove handler - {log_key}");
-		Ok(out)
+		let (_, receipt_proof) = proof::receipt_proof(&receipts, transaction_index as usize);
+		Ok(Some(LogInclusionProof {
+			substrate_block_hash,
+			ethereum_block_hash: transaction.ethereum_block_hash,
+			header,
+			transaction_index,
+			log_index,
+			receipt_proof,
+		}))
 	}
-}
 
-/// Build a SQL query to retrieve a list of logs given certain constraints.
-fn build_query<'a>(
-	qb: &'a mut QueryBuilder<Sqlite>,
-	from_block: u64,
-	to_block: u64,
-	addresses: Vec<H160>,
-	topics: [HashSet<H256>; 4],
-) -> Query<'a, Sqlite, SqliteArguments<'a>> {
-	qb.push(
-		"
-SELECT
-	l.substrate_block_hash,
-	b.ethereum_block_hash,
-	b.block_number,
-	b.ethereum_storage_schema,
-	l.transaction_index,
-	l.log_index
-FROM logs AS l
-INNER JOIN blocks AS b
-ON (b.block_number BETWEEN ",
-	);
-	qb.separated(" AND ")
-		.push_bind(from_block as i64)
-		.push_bind(to_block as i64)
-		.push_unseparated(")");
-	qb.push(" AND b.substrate_block_hash = l.substrate_block_hash")
-		.push(" AND b.is_canon = 1")
-		.push("\nWHERE 1");
+	/// Retrieve the full ethereum header of the given substrate block, if indexed.
+	pub async fn ethereum_header(
+		&self,
+		substrate_block_hash: H256,
+	) -> Result<Option<ethereum::Header>, Error> {
+		let row = sqlx::query(
+			"SELECT parent_hash, ommers_hash, beneficiary, state_root, transactions_root,
+				receipts_root, logs_bloom, difficulty, number, gas_limit, gas_used, timestamp,
+				extra_data, mix_hash, nonce
+			FROM block_headers WHERE substrate_block_hash = ?",
+		)
+		.bind(substrate_block_hash.as_bytes())
+		.fetch_optional(self.pool())
+		.await?;
 
-	if !addresses.is_empty() {
-		qb.push(" AND l.address IN (");
-		let mut qb_addr = qb.separated(", ");
-		addresses.iter().for_each(|addr| {
-			qb_addr.push_bind(addr.as_bytes().to_owned());
-		});
-		qb_addr.push_unseparated(")");
+		let Some(row) = row else {
+			return Ok(None);
+		};
+		let bytes = |column: &str| row.try_get::<Vec<u8>, _>(column);
+		let fixed = |column: &str, len: usize| -> Result<Vec<u8>, Error> {
+			let value = bytes(column)?;
+			if value.len() != len {
+				return Err(Error::Protocol(format!(
+					"Invalid length {} for header column {column}",
+					value.len()
+				)));
+			}
+			Ok(value)
+		};
+
+		Ok(Some(ethereum::Header {
+			parent_hash: H256::from_slice(&fixed("parent_hash", 32)?),
+			ommers_hash: H256::from_slice(&fixed("ommers_hash", 32)?),
+			beneficiary: H160::from_slice(&fixed("beneficiary", 20)?),
+			state_root: H256::from_slice(&fixed("state_root", 32)?),
+			transactions_root: H256::from_slice(&fixed("transactions_root", 32)?),
+			receipts_root: H256::from_slice(&fixed("receipts_root", 32)?),
+			logs_bloom: ethereum_types::Bloom::from_slice(&fixed("logs_bloom", 256)?),
+			difficulty: U256::from_big_endian(&fixed("difficulty", 32)?),
+			number: U256::from_big_endian(&fixed("number", 32)?),
+			gas_limit: U256::from_big_endian(&fixed("gas_limit", 32)?),
+			gas_used: U256::from_big_endian(&fixed("gas_used", 32)?),
+			timestamp: row.try_get::<i64, _>("timestamp")? as u64,
+			extra_data: bytes("extra_data")?,
+			mix_hash: H256::from_slice(&fixed("mix_hash", 32)?),
+			nonce: H64::from_slice(&fixed("nonce", 8)?),
+		}))
 	}
 
-	for (i, topic_options) in topics.iter().enumerate() {
-		match topic_options.len().cmp(&1) {
-			Ordering::Greater => {
-				qb.push(format!(" AND l.topic_{} IN (", i + 1));
-				let mut qb_topic = qb.separated(", ");
-				topic_options.iter().for_each(|t| {
-					qb_topic.push_bind(t.as_bytes().to_owned());
-				});
-				qb_topic.push_unseparated(")");
+	/// Record the last finalized block observed by the indexer.
+	pub async fn set_last_finalized(&self, block_hash: H256) -> Result<SqliteQueryResult, Error> {
+		sqlx::query(
+			"INSERT INTO indexer_meta(key, value) VALUES (?, ?)
+			ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+		)
+		.bind(LAST_FINALIZED_KEY)
+		.bind(block_hash.as_bytes())
+		.execute(self.pool())
+		.await
+	}
+
+	/// Retrieve the last finalized block observed by the indexer, if any.
+	pub async fn last_finalized(&self) -> Result<Option<H256>, Error> {
+		let row = sqlx::query("SELECT value FROM indexer_meta WHERE key = ?")
+			.bind(LAST_FINALIZED_KEY)
+			.fetch_optional(self.pool())
+			.await?;
+		Ok(row.map(|row| H256::from_slice(&row.get::<Vec<u8>, _>(0)[..])))
+	}
+
+	/// Record the highest canonical block indexed along with all of its ancestors, from which
+	/// the indexer resumes on restart.
+	pub async fn set_checkpoint(
+		&self,
+		block_number: u32,
+		block_hash: H256,
+	) -> Result<SqliteQueryResult, Error> {
+		if let Some(commit_group) = &self.commit_group {
+			let mut group = commit_group.lock().await;
+			if !group.is_empty() {
+				group.checkpoint = Some((block_number, block_hash));
+				return Ok(SqliteQueryResult::default());
 			}
-			Ordering::Equal => {
-				qb.push(format!(" AND l.topic_{} = ", i + 1)).push_bind(
-					topic_options
-						.iter()
-						.next()
-						.expect("length is 1, must exist; qed")
-						.as_bytes()
-						.to_owned(),
-				);
+		}
+		Self::set_meta(
+			&mut *self.pool().acquire().await?,
+			CHECKPOINT_KEY,
+			(block_number, block_hash).encode(),
+		)
+		.await
+	}
+
+	/// Upserts an `indexer_meta` value.
+	async fn set_meta<'q, T>(
+		conn: &mut sqlx::SqliteConnection,
+		key: &'q str,
+		value: T,
+	) -> Result<SqliteQueryResult, Error>
+	where
+		T: 'q + Send + sqlx::Encode<'q, Sqlite> + sqlx::Type<Sqlite>,
+	{
+		sqlx::query(
+			"INSERT INTO indexer_meta(key, value) VALUES (?, ?)
+			ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+		)
+		.bind(key)
+		.bind(value)
+		.execute(conn)
+		.await
+	}
+
+	/// Retrieve the number and hash of the checkpoint block, if any.
+	pub async fn checkpoint(&self) -> Result<Option<(u32, H256)>, Error> {
+		if let Some(commit_group) = &self.commit_group {
+			if let Some(checkpoint) = commit_group.lock().await.checkpoint {
+				return Ok(Some(checkpoint));
 			}
-			Ordering::Less => {}
 		}
+		let row = sqlx::query("SELECT value FROM indexer_meta WHERE key = ?")
+			.bind(CHECKPOINT_KEY)
+			.fetch_optional(self.pool())
+			.await?;
+		row.map(|row| {
+			<(u32, H256)>::decode(&mut &row.get::<Vec<u8>, _>(0)[..])
+				.map_err(|_| Error::Protocol("Cannot decode the indexer checkpoint".to_string()))
+		})
+		.transpose()
 	}
 
-	qb.push(
-		"
-ORDER BY b.block_number ASC, l.transaction_index ASC, l.log_index ASC
-LIMIT 10001",
-	);
+	/// Delete the non-canonical blocks outside of the retention window below the given
+	/// finalized block number, along with their transactions and logs. Returns the number
+	/// of deleted blocks, always `0` without a configured retention.
+	pub async fn prune_retracted_blocks(&self, finalized_number: u32) -> Result<u64, Error> {
+		self.flush_writes().await?;
+		let Some(max_block_number) = self
+			.retracted_block_retention
+			.and_then(|retention| finalized_number.checked_sub(retention))
+		else {
+			return Ok(0);
+		};
 
-	qb.build()
-}
+		self.throttle_write().await;
+		let mut tx = self.pool().begin().await?;
+		let mut pruned = 0;
+		for table in BLOCK_TABLES {
+			let result = sqlx::query(&format!(
+				"DELETE FROM {table} WHERE substrate_block_hash IN (
+					SELECT substrate_block_hash FROM blocks
+					WHERE is_canon = 0 AND block_number <= ?
+				)"
+			))
+			.bind(max_block_number as i32)
+			.execute(&mut *tx)
+			.await?;
+			pruned = result.rows_affected();
+		}
+		tx.commit().await?;
+		Ok(pruned)
+	}
 
-#[cfg(test)]
-mod test {
-	use super::*;
+	/// Delete the logs of the blocks outside of the log retention window below the given
+	/// finalized block number, applying the retention of each contract with an override.
+	/// Returns the number of deleted logs.
+	pub async fn prune_logs(&self, finalized_number: u32) -> Result<u64, Error> {
+		let cutoff = |retention: Option<u32>| {
+			retention.and_then(|blocks| finalized_number.checked_sub(blocks))
+		};
+		let global_cutoff = cutoff(self.log_retention);
+		let contract_cutoffs = self
+			.contract_log_retention
+			.iter()
+			.filter_map(|(address, retention)| Some((*address, cutoff(*retention)?)))
+			.collect::<Vec<_>>();
+		if global_cutoff.is_none() && contract_cutoffs.is_empty() {
+			return Ok(0);
+		}
 
-	use std::path::Path;
+		let encoding = self.log_column_encoding;
+		self.throttle_write().await;
+		let mut tx = self.pool().begin().await?;
+		let mut pruned = 0;
+		if let Some(max_block_number) = global_cutoff {
+			let mut qb = QueryBuilder::<Sqlite>::new(
+				"DELETE FROM logs WHERE substrate_block_hash IN (
+					SELECT substrate_block_hash FROM blocks WHERE block_number <= ",
+			);
+			qb.push_bind(max_block_number as i32).push(")");
+			if !self.contract_log_retention.is_empty() {
+				qb.push(" AND address NOT IN (");
+				let mut qb_addr = qb.separated(", ");
+				for address in self.contract_log_retention.keys() {
+					qb_addr.push_bind(encoding.column(address.as_bytes()));
+				}
+				qb_addr.push_unseparated(")");
+			}
+			pruned += qb.build().execute(&mut *tx).await?.rows_affected();
+		}
+		for &(address, max_block_number) in &contract_cutoffs {
+			let result = sqlx::query(
+				"DELETE FROM logs WHERE address = ? AND substrate_block_hash IN (
+					SELECT substrate_block_hash FROM blocks WHERE block_number <= ?
+				)",
+			)
+			.bind(encoding.column(address.as_bytes()))
+			.bind(max_block_number as i32)
+			.execute(&mut *tx)
+			.await?;
+			pruned += result.rows_affected();
+		}
+		if pruned > 0 {
+			// Every pruned block is below the highest cutoff.
+			let max_block_number = global_cutoff
+				.into_iter()
+				.chain(contract_cutoffs.iter().map(|(_, cutoff)| *cutoff))
+				.max();
+			sqlx::query(
+				"UPDATE block_summary SET log_count = (
+					SELECT COUNT(*) FROM logs AS l
+					WHERE l.substrate_block_hash = block_summary.substrate_block_hash
+				)
+				WHERE block_number <= ?",
+			)
+			.bind(max_block_number.map(|n| n as i32))
+			.execute(&mut *tx)
+			.await?;
+		}
+		tx.commit().await?;
+		Ok(pruned)
+	}
 
-	use maplit::hashset;
-	use scale_codec::Encode;
-	use sqlx::{sqlite::SqliteRow, QueryBuilder, Row, SqlitePool};
-	use tempfile::tempdir;
-	// Substrate
-	use sp_core::{H160, H256};
-	use sp_runtime::{
-		generic::{Block, Header},
-		traits::BlakeTwo256,
-	};
-	use substrate_test_runtime_client::{
-		DefaultTestClientBuilderExt, TestClientBuilder, TestClientBuilderExt,
-	};
-	// Frontier
-	use fc_api::Backend as BackendT;
-	use fc_storage::SchemaV3StorageOverride;
-	use fp_storage::{EthereumStorageSchema, PALLET_ETHEREUM_SCHEMA};
+	/// Record a block whose ancestors could not be indexed, to be backfilled later.
+	pub async fn record_gap(&self, block_hash: H256) -> Result<SqliteQueryResult, Error> {
+		sqlx::query("INSERT OR IGNORE INTO index_gaps(substrate_block_hash) VALUES (?)")
+			.bind(block_hash.as_bytes())
+			.execute(self.pool())
+			.await
+	}
 
-	type OpaqueBlock =
-		Block<Header<u64, BlakeTwo256>, substrate_test_runtime_client::runtime::Extrinsic>;
+	/// Retrieve the blocks whose ancestors are pending backfill.
+	pub async fn gaps(&self) -> Result<Vec<H256>, Error> {
+		Ok(sqlx::query("SELECT substrate_block_hash FROM index_gaps")
+			.fetch_all(self.pool())
+			.await?
+			.iter()
+			.map(|row| H256::from_slice(&row.get::<Vec<u8>, _>(0)[..]))
+			.collect())
+	}
 
-	struct TestFilter {
-		pub from_block: u64,
-		pub to_block: u64,
-		pub addresses: Vec<H160>,
-		pub topics: Vec<Vec<Option<H256>>>,
-		pub expected_result: Vec<FilteredLog<OpaqueBlock>>,
+	/// Retrieve the blocks left unindexed by the [`MalformedSignaturePolicy::DeadLetter`]
+	/// policy, with the reason they were set aside.
+	pub async fn dead_letter_blocks(&self) -> Result<Vec<(H256, String)>, Error> {
+		Ok(
+			sqlx::query("SELECT substrate_block_hash, reason FROM dead_letter_blocks")
+				.fetch_all(self.pool())
+				.await?
+				.iter()
+				.map(|row| {
+					(
+						H256::from_slice(&row.get::<Vec<u8>, _>(0)[..]),
+						row.get::<String, _>(1),
+					)
+				})
+				.collect(),
+		)
 	}
 
-	#[derive(Debug, Clone)]
-	struct Log {
+	/// Retrieve the indexed contracts, ordered by block number and address.
+	pub async fn contracts(&self) -> Result<Vec<IndexedContract>, Error> {
+		Ok(sqlx::query(
+			"SELECT address, substrate_block_hash, block_number, code_hash, code_size,
+				storage_slots
+			FROM contracts ORDER BY block_number, address",
+		)
+		.fetch_all(self.pool())
+		.await?
+		.iter()
+		.map(|row| IndexedContract {
+			address: H160::from_slice(&row.get::<Vec<u8>, _>(0)[..]),
+			substrate_block_hash: H256::from_slice(&row.get::<Vec<u8>, _>(1)[..]),
+			block_number: row.get::<u32, _>(2),
+			code_hash: H256::from_slice(&row.get::<Vec<u8>, _>(3)[..]),
+			code_size: row.get::<u32, _>(4),
+			storage_slots: row.get::<u32, _>(5),
+		})
+		.collect())
+	}
+
+	/// Remove a backfilled gap.
+	pub async fn remove_gap(&self, block_hash: H256) -> Result<SqliteQueryResult, Error> {
+		sqlx::query("DELETE FROM index_gaps WHERE substrate_block_hash = ?")
+			.bind(block_hash.as_bytes())
+			.execute(self.pool())
+			.await
+	}
+
+	/// Record the lowest block number reached by the ongoing ancestor descent started at
+	/// `descent`. Each descent has its own progress, so concurrent ones do not overwrite
+	/// each other.
+	pub async fn set_backfill_progress(
+		&self,
+		descent: H256,
 		block_number: u32,
-		address: H160,
-		topics: [H256; 4],
-		substrate_block_hash: H256,
-		ethereum_block_hash: H256,
-		transaction_index: u32,
-		log_index: u32,
+	) -> Result<SqliteQueryResult, Error> {
+		self.update_backfill_progress(descent, Some(block_number))
+			.await
 	}
 
-	#[allow(unused)]
-	struct TestData {
-		backend: Backend<OpaqueBlock>,
-		alice: H160,
-		bob: H160,
-		topics_a: H256,
-		topics_b: H256,
-		topics_c: H256,
-		topics_d: H256,
-		substrate_hash_1: H256,
-		substrate_hash_2: H256,
-		substrate_hash_3: H256,
-		ethereum_hash_1: H256,
-		ethereum_hash_2: H256,
-		ethereum_hash_3: H256,
-		log_1_abcd_0_0_alice: Log,
-		log_1_dcba_1_0_alice: Log,
-		log_1_badc_2_0_alice: Log,
-		log_2_abcd_0_0_bob: Log,
-		log_2_dcba_1_0_bob: Log,
-		log_2_badc_2_0_bob: Log,
-		log_3_abcd_0_0_bob: Log,
-		log_3_dcba_1_0_bob: Log,
-		log_3_badc_2_0_bob: Log,
+	/// Retrieve the lowest block number reached by each interrupted ancestor descent, keyed
+	/// by the block the descent started at.
+	pub async fn backfill_progress(&self) -> Result<Vec<(H256, u32)>, Error> {
+		let mut progress: HashMap<H256, Option<u32>> =
+			sqlx::query("SELECT descent_hash, block_number FROM backfill_progress")
+				.fetch_all(self.pool())
+				.await?
+				.iter()
+				.map(|row| {
+					(
+						H256::from_slice(&row.get::<Vec<u8>, _>(0)[..]),
+						Some(row.get::<u32, _>(1)),
+					)
+				})
+				.collect();
+		if let Some(commit_group) = &self.commit_group {
+			progress.extend(commit_group.lock().await.backfill_progress.clone());
+		}
+		let mut progress: Vec<(H256, u32)> = progress
+			.into_iter()
+			.filter_map(|(descent, block_number)| Some((descent, block_number?)))
+			.collect();
+		progress.sort_by_key(|(_, block_number)| std::cmp::Reverse(*block_number));
+		Ok(progress)
 	}
 
-	impl From<Log> for FilteredLog<OpaqueBlock> {
-		fn from(value: Log) -> Self {
-			Self {
-				substrate_block_hash: value.substrate_block_hash,
-				ethereum_block_hash: value.ethereum_block_hash,
-				block_number: value.block_number,
-				ethereum_storage_schema: EthereumStorageSchema::V3,
-				transaction_index: value.transaction_index,
-				log_index: value.log_index,
+	/// Clear the backfill progress of the descent started at `descent` once it completed.
+	pub async fn clear_backfill_progress(&self, descent: H256) -> Result<SqliteQueryResult, Error> {
+		self.update_backfill_progress(descent, None).await
+	}
+
+	async fn update_backfill_progress(
+		&self,
+		descent: H256,
+		progress: Option<u32>,
+	) -> Result<SqliteQueryResult, Error> {
+		if let Some(commit_group) = &self.commit_group {
+			let mut group = commit_group.lock().await;
+			if !group.is_empty() {
+				group.backfill_progress.insert(descent, progress);
+				return Ok(SqliteQueryResult::default());
 			}
 		}
+		Self::write_backfill_progress(&mut *self.pool().acquire().await?, descent, progress).await
 	}
 
-	async fn prepare() -> TestData {
-		let tmp = tempdir().expect("create a temporary directory");
-		// Initialize storage with schema V3
-		let builder = TestClientBuilder::new().add_extra_storage(
-			PALLET_ETHEREUM_SCHEMA.to_vec(),
-			Encode::encode(&EthereumStorageSchema::V3),
-		);
-		// Client
-		let (client, _) = builder
-			.build_with_native_executor::<substrate_test_runtime_client::runtime::RuntimeApi, _>(
-				None,
-			);
-		let client = Arc::new(client);
-		// Overrides
-		let storage_override = Arc::new(SchemaV3StorageOverride::new(client.clone()));
-		// Indexer backend
-		let indexer_backend = Backend::new(
-			BackendConfig::Sqlite(SqliteBackendConfig {
-				path: Path::new("sqlite:///")
-					.join(tmp.path())
-					.join("test.db3")
-					.to_str()
-					.unwrap(),
-				create_if_missing: true,
-				cache_size: 20480,
-				thread_count: 4,
-			}),
-			1,
-			None,
-			storage_override.clone(),
+	/// Writes the backfill progress of the descent, deleting it if `None`.
+	async fn write_backfill_progress(
+		conn: &mut sqlx::SqliteConnection,
+		descent: H256,
+		progress: Option<u32>,
+	) -> Result<SqliteQueryResult, Error> {
+		match progress {
+			Some(block_number) => {
+				sqlx::query(
+					"INSERT INTO backfill_progress(descent_hash, block_number) VALUES (?, ?)
+					ON CONFLICT(descent_hash) DO UPDATE SET block_number = excluded.block_number",
+				)
+				.bind(descent.as_bytes())
+				.bind(block_number)
+				.execute(conn)
+				.await
+			}
+			None => {
+				sqlx::query("DELETE FROM backfill_progress WHERE descent_hash = ?")
+					.bind(descent.as_bytes())
+					.execute(conn)
+					.await
+			}
+		}
+	}
+
+	/// Retrieve the block hash for the last indexed canon block.
+	pub async fn last_indexed_canon_block(&self) -> Result<H256, Error> {
+		let row = sqlx::query(
+			"SELECT b.substrate_block_hash FROM blocks AS b
+			INNER JOIN sync_status AS s
+			ON s.substrate_block_hash = b.substrate_block_hash
+			WHERE b.is_canon=1 AND s.logs_done = 1
+			ORDER BY b.id DESC LIMIT 1",
 		)
+		.fetch_one(self.pool())
+		.await?;
+		Ok(H256::from_slice(
+			&row.try_get::<Vec<u8>, _>(0).unwrap_or_default()[..],
+		))
+	}
+
+	/// Recompute the summary row of the given block from the detailed tables.
+	async fn refresh_block_summary(
+		conn: &mut sqlx::SqliteConnection,
+		substrate_block_hash: H256,
+	) -> Result<SqliteQueryResult, Error> {
+		sqlx::query(&format!(
+			"{SUMMARIZE_BLOCKS} WHERE b.substrate_block_hash = ?"
+		))
+		.bind(substrate_block_hash.as_bytes())
+		.execute(conn)
 		.await
-		.expect("indexer pool to be created");
+	}
 
-		// Prepare test db data
-		// Addresses
-		let alice = H160::repeat_byte(0x01);
-		let bob = H160::repeat_byte(0x02);
-		// Topics
-		let topics_a = H256::repeat_byte(0x01);
-		let topics_b = H256::repeat_byte(0x02);
-		let topics_c = H256::repeat_byte(0x03);
-		let topics_d = H256::repeat_byte(0x04);
-		// Substrate block hashes
-		let substrate_hash_1 = H256::repeat_byte(0x05);
-		let substrate_hash_2 = H256::repeat_byte(0x06);
-		let substrate_hash_3 = H256::repeat_byte(0x07);
-		// Ethereum block hashes
-		let ethereum_hash_1 = H256::repeat_byte(0x08);
-		let ethereum_hash_2 = H256::repeat_byte(0x09);
-		let ethereum_hash_3 = H256::repeat_byte(0x0a);
-		// Ethereum storage schema
-		let ethereum_storage_schema = EthereumStorageSchema::V3;
+	/// Retrieve the summary of the given substrate block, if indexed.
+	pub async fn block_summary(
+		&self,
+		substrate_block_hash: H256,
+	) -> Result<Option<BlockSummary>, Error> {
+		sqlx::query(
+			"SELECT substrate_block_hash, block_number, ethereum_block_hash, transaction_count,
+				log_count, gas_used, timestamp, is_canon
+			FROM block_summary WHERE substrate_block_hash = ?",
+		)
+		.bind(substrate_block_hash.as_bytes())
+		.fetch_optional(self.pool())
+		.await?
+		.map(|row| block_summary_from_row(&row))
+		.transpose()
+	}
 
-		let block_entries = vec![
-			// Block 1
-			(
-				1i32,
-				ethereum_hash_1,
-				substrate_hash_1,
-				ethereum_storage_schema,
-			),
-			// Block 2
-			(
-				2i32,
+	/// Retrieve the summary of the highest canonical indexed block, a single-row read.
+	pub async fn latest_block_summary(&self) -> Result<Option<BlockSummary>, Error> {
+		sqlx::query(
+			"SELECT substrate_block_hash, block_number, ethereum_block_hash, transaction_count,
+				log_count, gas_used, timestamp, is_canon
+			FROM block_summary WHERE is_canon = 1
+			ORDER BY block_number DESC LIMIT 1",
+		)
+		.fetch_optional(self.pool())
+		.await?
+		.map(|row| block_summary_from_row(&row))
+		.transpose()
+	}
+
+	/// Delete the logs whose block is missing from the `blocks` table, as left behind by
+	/// pruning or manual deletion. Returns the number of deleted logs.
+	pub async fn prune_orphan_logs(&self) -> Result<u64, Error> {
+		self.throttle_write().await;
+		let result = sqlx::query(
+			"DELETE FROM logs
+			WHERE NOT EXISTS (
+				SELECT 1 FROM blocks AS b
+				WHERE b.substrate_block_hash = logs.substrate_block_hash
+			)",
+		)
+		.execute(self.pool())
+		.await?;
+		Ok(result.rows_affected())
+	}
+
+	/// Delete every block in the `from..=to` height range, along with its rows in the other
+	/// tables, in a single transaction. Returns the number of deleted blocks.
+	pub async fn delete_blocks_in_range(&self, from: u32, to: u32) -> Result<u64, Error> {
+		self.flush_writes().await?;
+		self.throttle_write().await;
+		let mut tx = self.pool().begin().await?;
+		let mut deleted = 0;
+		for table in BLOCK_TABLES {
+			let statement = if table == "blocks" {
+				"DELETE FROM blocks WHERE block_number BETWEEN ? AND ?".to_string()
+			} else {
+				format!(
+					"DELETE FROM {table} WHERE substrate_block_hash IN
+					(SELECT substrate_block_hash FROM blocks WHERE block_number BETWEEN ? AND ?)"
+				)
+			};
+			// `blocks` comes last, its count is returned.
+			deleted = sqlx::query(&statement)
+				.bind(from)
+				.bind(to)
+				.execute(&mut *tx)
+				.await?
+				.rows_affected();
+		}
+		tx.commit().await?;
+		Ok(deleted)
+	}
+
+	/// Retrieve the height below which the blocks were pruned, `0` if none was.
+	pub async fn pruned_below(&self) -> Result<u32, Error> {
+		let row = sqlx::query("SELECT value FROM indexer_meta WHERE key = ?")
+			.bind(PRUNED_BELOW_KEY)
+			.fetch_optional(self.pool())
+			.await?;
+		Ok(row.map_or(0, |row| row.get::<i64, _>(0) as u32))
+	}
+
+	/// Delete the blocks below the given height, genesis excepted, along with their logs and
+	/// their rows in the other tables, in a single transaction. The pruned heights are no
+	/// longer reported as missing. Returns the number of deleted blocks.
+	pub async fn prune_logs_below(&self, block_number: u32) -> Result<u64, Error> {
+		self.flush_writes().await?;
+		self.throttle_write().await;
+		let mut tx = self.pool().begin().await?;
+		let mut deleted = 0;
+		for table in BLOCK_TABLES {
+			let statement = if table == "blocks" {
+				"DELETE FROM blocks WHERE block_number > 0 AND block_number < ?".to_string()
+			} else {
+				format!(
+					"DELETE FROM {table} WHERE substrate_block_hash IN
+					(SELECT substrate_block_hash FROM blocks
+					WHERE block_number > 0 AND block_number < ?)"
+				)
+			};
+			// `blocks` comes last, its count is returned.
+			deleted = sqlx::query(&statement)
+				.bind(block_number)
+				.execute(&mut *tx)
+				.await?
+				.rows_affected();
+		}
+		sqlx::query(
+			"INSERT INTO indexer_meta(key, value) VALUES (?, ?)
+			ON CONFLICT(key) DO UPDATE SET value = MAX(value, excluded.value)",
+		)
+		.bind(PRUNED_BELOW_KEY)
+		.bind(block_number as i64)
+		.execute(&mut *tx)
+		.await?;
+		tx.commit().await?;
+		Ok(deleted)
+	}
+
+	/// Create the Sqlite database if it does not already exist.
+	async fn create_database_if_not_exists(pool: &SqlitePool) -> Result<SqliteQueryResult, Error> {
+		sqlx::query(
+			"BEGIN;
+			CREATE TABLE IF NOT EXISTS logs (
+				id INTEGER PRIMARY KEY,
+				address BLOB NOT NULL,
+				topic_1 BLOB,
+				topic_2 BLOB,
+				topic_3 BLOB,
+				topic_4 BLOB,
+				log_index INTEGER NOT NULL,
+				transaction_index INTEGER NOT NULL,
+				substrate_block_hash BLOB NOT NULL,
+				block_number INTEGER,
+				topic_count INTEGER,
+				UNIQUE (
+					log_index,
+					transaction_index,
+					substrate_block_hash
+				)
+			);
+			CREATE TABLE IF NOT EXISTS sync_status (
+				id INTEGER PRIMARY KEY,
+				substrate_block_hash BLOB NOT NULL,
+				metadata_done INTEGER DEFAULT 0 NOT NULL,
+				logs_done INTEGER DEFAULT 0 NOT NULL,
+				UNIQUE (
+					substrate_block_hash
+				)
+			);
+			CREATE TABLE IF NOT EXISTS blocks (
+				id INTEGER PRIMARY KEY,
+				block_number INTEGER NOT NULL,
+				ethereum_block_hash BLOB NOT NULL,
+				substrate_block_hash BLOB NOT NULL,
+				ethereum_storage_schema BLOB NOT NULL,
+				is_canon INTEGER NOT NULL,
+				author BLOB,
+				UNIQUE (
+					ethereum_block_hash,
+					substrate_block_hash
+				)
+			);
+			CREATE TABLE IF NOT EXISTS transactions (
+				id INTEGER PRIMARY KEY,
+				ethereum_transaction_hash BLOB NOT NULL,
+				substrate_block_hash BLOB NOT NULL,
+				ethereum_block_hash BLOB NOT NULL,
+				ethereum_transaction_index INTEGER NOT NULL,
+				max_fee_per_gas BLOB,
+				max_priority_fee_per_gas BLOB,
+				sender BLOB,
+				v INTEGER,
+				y_parity BOOLEAN,
+				r BLOB,
+				s BLOB,
+				cumulative_gas_used BLOB,
+				status_code INTEGER,
+				UNIQUE (
+					ethereum_transaction_hash,
+					substrate_block_hash
+				)
+			);
+			CREATE TABLE IF NOT EXISTS indexer_meta (
+				key TEXT NOT NULL PRIMARY KEY,
+				value BLOB NOT NULL
+			);
+			CREATE TABLE IF NOT EXISTS backfill_progress (
+				descent_hash BLOB NOT NULL PRIMARY KEY,
+				block_number INTEGER NOT NULL
+			);
+			CREATE TABLE IF NOT EXISTS index_gaps (
+				substrate_block_hash BLOB NOT NULL PRIMARY KEY
+			);
+			CREATE TABLE IF NOT EXISTS contracts (
+				address BLOB NOT NULL,
+				substrate_block_hash BLOB NOT NULL,
+				block_number INTEGER NOT NULL,
+				code_hash BLOB NOT NULL,
+				code_size INTEGER NOT NULL,
+				storage_slots INTEGER NOT NULL,
+				PRIMARY KEY (address, substrate_block_hash)
+			);
+			CREATE TABLE IF NOT EXISTS dead_letter_blocks (
+				substrate_block_hash BLOB NOT NULL PRIMARY KEY,
+				reason TEXT NOT NULL
+			);
+			CREATE TABLE IF NOT EXISTS raw_receipts (
+				substrate_block_hash BLOB NOT NULL,
+				transaction_index INTEGER NOT NULL,
+				receipt BLOB NOT NULL,
+				PRIMARY KEY (substrate_block_hash, transaction_index)
+			);
+			CREATE TABLE IF NOT EXISTS block_headers (
+				substrate_block_hash BLOB NOT NULL PRIMARY KEY,
+				parent_hash BLOB NOT NULL,
+				ommers_hash BLOB NOT NULL,
+				beneficiary BLOB NOT NULL,
+				state_root BLOB NOT NULL,
+				transactions_root BLOB NOT NULL,
+				receipts_root BLOB NOT NULL,
+				logs_bloom BLOB NOT NULL,
+				difficulty BLOB NOT NULL,
+				number BLOB NOT NULL,
+				gas_limit BLOB NOT NULL,
+				gas_used BLOB NOT NULL,
+				timestamp INTEGER NOT NULL,
+				extra_data BLOB NOT NULL,
+				mix_hash BLOB NOT NULL,
+				nonce BLOB NOT NULL
+			);
+			CREATE TABLE IF NOT EXISTS block_summary (
+				substrate_block_hash BLOB NOT NULL PRIMARY KEY,
+				block_number INTEGER NOT NULL,
+				ethereum_block_hash BLOB NOT NULL,
+				transaction_count INTEGER NOT NULL,
+				log_count INTEGER NOT NULL,
+				gas_used BLOB,
+				timestamp INTEGER,
+				is_canon INTEGER NOT NULL
+			);
+			COMMIT;",
+		)
+		.execute(pool)
+		.await
+	}
+
+	/// Add the columns introduced after a table was first created to databases created by
+	/// older versions.
+	async fn create_columns_if_not_exist(pool: &SqlitePool) -> Result<(), Error> {
+		for (table, column, definition) in [
+			("transactions", "max_fee_per_gas", "BLOB"),
+			("transactions", "max_priority_fee_per_gas", "BLOB"),
+			("transactions", "sender", "BLOB"),
+			("transactions", "v", "INTEGER"),
+			("transactions", "y_parity", "BOOLEAN"),
+			("transactions", "r", "BLOB"),
+			("transactions", "s", "BLOB"),
+			("transactions", "cumulative_gas_used", "BLOB"),
+			("transactions", "status_code", "INTEGER"),
+			("blocks", "author", "BLOB"),
+		] {
+			let exists = sqlx::query("SELECT 1 FROM pragma_table_info(?) WHERE name = ?")
+				.bind(table)
+				.bind(column)
+				.fetch_optional(pool)
+				.await?
+				.is_some();
+			if !exists {
+				log::info!(target: "frontier-sql", "🛠️  Adding column {table}.{column}");
+				sqlx::query(&format!(
+					"ALTER TABLE {table} ADD COLUMN {column} {definition}"
+				))
+				.execute(pool)
+				.await?;
+			}
+		}
+		Ok(())
+	}
+
+	/// Replaces the single `status` column of a `sync_status` table created by an older
+	/// version with the independent `metadata_done` and `logs_done` phase columns. Rows only
+	/// ever existed once their metadata was committed, and `status` tracked the logs.
+	async fn split_sync_status_phases(pool: &SqlitePool) -> Result<(), Error> {
+		let split = sqlx::query("SELECT 1 FROM pragma_table_info('sync_status') WHERE name = ?")
+			.bind("logs_done")
+			.fetch_optional(pool)
+			.await?
+			.is_some();
+		if split {
+			return Ok(());
+		}
+		log::info!(target: "frontier-sql", "🛠️  Splitting sync_status into metadata and logs phases");
+		let mut tx = pool.begin().await?;
+		sqlx::query(
+			"ALTER TABLE sync_status ADD COLUMN metadata_done INTEGER DEFAULT 0 NOT NULL;
+			ALTER TABLE sync_status ADD COLUMN logs_done INTEGER DEFAULT 0 NOT NULL;
+			UPDATE sync_status SET metadata_done = 1, logs_done = status;",
+		)
+		.execute(&mut *tx)
+		.await?;
+		tx.commit().await
+	}
+
+	/// Adds the `block_number` column to a logs table created by an older version, copying
+	/// the numbers of the indexed blocks.
+	async fn number_logs(pool: &SqlitePool) -> Result<(), Error> {
+		let numbered = sqlx::query("SELECT 1 FROM pragma_table_info('logs') WHERE name = ?")
+			.bind("block_number")
+			.fetch_optional(pool)
+			.await?
+			.is_some();
+		if numbered {
+			return Ok(());
+		}
+		log::info!(target: "frontier-sql", "🛠️  Adding the block numbers of the indexed logs");
+		let mut tx = pool.begin().await?;
+		sqlx::query(
+			"ALTER TABLE logs ADD COLUMN block_number INTEGER;
+			UPDATE logs SET block_number = (
+				SELECT block_number FROM blocks
+				WHERE blocks.substrate_block_hash = logs.substrate_block_hash
+			);",
+		)
+		.execute(&mut *tx)
+		.await?;
+		tx.commit().await
+	}
+
+	/// Adds the `topic_count` column to a logs table created by an older version, counting
+	/// the topics of the indexed logs from their non-`NULL` topic columns.
+	async fn count_log_topics(pool: &SqlitePool) -> Result<(), Error> {
+		let counted = sqlx::query("SELECT 1 FROM pragma_table_info('logs') WHERE name = ?")
+			.bind("topic_count")
+			.fetch_optional(pool)
+			.await?
+			.is_some();
+		if counted {
+			return Ok(());
+		}
+		log::info!(target: "frontier-sql", "🛠️  Counting the topics of the indexed logs");
+		let mut tx = pool.begin().await?;
+		sqlx::query(
+			"ALTER TABLE logs ADD COLUMN topic_count INTEGER;
+			UPDATE logs SET topic_count = (topic_1 IS NOT NULL) + (topic_2 IS NOT NULL)
+				+ (topic_3 IS NOT NULL) + (topic_4 IS NOT NULL);",
+		)
+		.execute(&mut *tx)
+		.await?;
+		tx.commit().await
+	}
+
+	/// Fill the summary table of a database indexed by older versions, whose blocks have no
+	/// summary row.
+	async fn summarize_blocks(pool: &SqlitePool) -> Result<(), Error> {
+		let summarized = sqlx::query(
+			"SELECT NOT EXISTS (SELECT 1 FROM blocks)
+				OR EXISTS (SELECT 1 FROM block_summary)",
+		)
+		.fetch_one(pool)
+		.await?
+		.get::<bool, _>(0);
+		if summarized {
+			return Ok(());
+		}
+		log::info!(target: "frontier-sql", "🛠️  Summarizing the indexed blocks");
+		let mut tx = pool.begin().await?;
+		sqlx::query(SUMMARIZE_BLOCKS).execute(&mut *tx).await?;
+		tx.commit().await
+	}
+
+	/// Whether the logs table is deduplicated by the hashed `log_key` column.
+	async fn has_hashed_log_key(pool: &SqlitePool) -> Result<bool, Error> {
+		Ok(sqlx::query(
+			"SELECT 1 FROM pragma_index_list('logs') AS l, pragma_index_info(l.name) AS i
+			WHERE l.origin = 'u' AND i.name = 'log_key'",
+		)
+		.fetch_optional(pool)
+		.await?
+		.is_some())
+	}
+
+	/// Replaces the composite unique constraint of the logs table with the hashed
+	/// `log_key` column. SQLite cannot drop a table constraint, so the rows are copied to
+	/// a new table and their keys computed.
+	async fn rebuild_logs_with_hashed_key(&self) -> Result<(), Error> {
+		self.throttle_write().await;
+		let mut tx = self.pool().begin().await?;
+		sqlx::query(
+			"CREATE TABLE logs_hashed (
+				id INTEGER PRIMARY KEY,
+				address BLOB NOT NULL,
+				topic_1 BLOB,
+				topic_2 BLOB,
+				topic_3 BLOB,
+				topic_4 BLOB,
+				log_index INTEGER NOT NULL,
+				transaction_index INTEGER NOT NULL,
+				substrate_block_hash BLOB NOT NULL,
+				block_number INTEGER,
+				topic_count INTEGER,
+				log_key BLOB UNIQUE
+			);
+			INSERT INTO logs_hashed
+				SELECT id, address, topic_1, topic_2, topic_3, topic_4, log_index,
+					transaction_index, substrate_block_hash, block_number, topic_count, NULL
+				FROM logs;
+			DROP TABLE logs;
+			ALTER TABLE logs_hashed RENAME TO logs;",
+		)
+		.execute(&mut *tx)
+		.await?;
+
+		let rows =
+			sqlx::query("SELECT id, substrate_block_hash, transaction_index, log_index FROM logs")
+				.fetch_all(&mut *tx)
+				.await?;
+		for row in rows {
+			let substrate_block_hash = row.get::<Vec<u8>, _>(1);
+			let transaction_index = row.get::<i64, _>(2);
+			let log_index = row.get::<i64, _>(3);
+			let log_key = hashed_log_key(&substrate_block_hash, transaction_index, log_index);
+			// The rows are unique, a stored key can only be a collision.
+			Self::is_log_key_stored(
+				&mut *tx,
+				&log_key,
+				&substrate_block_hash,
+				transaction_index,
+				log_index,
+			)
+			.await?;
+			sqlx::query("UPDATE logs SET log_key = ? WHERE id = ?")
+				.bind(&log_key[..])
+				.bind(row.get::<i64, _>(0))
+				.execute(&mut *tx)
+				.await?;
+		}
+
+		if self.index_logs {
+			sqlx::query(
+				"CREATE INDEX IF NOT EXISTS logs_main_idx ON logs (
+					address,
+					topic_1,
+					topic_2,
+					topic_3,
+					topic_4
+				);
+				CREATE INDEX IF NOT EXISTS logs_substrate_index ON logs (
+					substrate_block_hash
+				);
+				CREATE INDEX IF NOT EXISTS logs_address_number_idx ON logs (
+					address,
+					block_number
+				);",
+			)
+			.execute(&mut *tx)
+			.await?;
+		}
+		tx.commit().await
+	}
+
+	/// Whether the log is already stored under its hashed key. Fails if the key is taken by
+	/// a different log.
+	async fn is_log_key_stored(
+		conn: &mut sqlx::SqliteConnection,
+		log_key: &[u8; 16],
+		substrate_block_hash: &[u8],
+		transaction_index: i64,
+		log_index: i64,
+	) -> Result<bool, Error> {
+		let existing = sqlx::query(
+			"SELECT substrate_block_hash, transaction_index, log_index FROM logs
+			WHERE log_key = ?",
+		)
+		.bind(&log_key[..])
+		.fetch_optional(&mut *conn)
+		.await?;
+		match existing {
+			None => Ok(false),
+			Some(row)
+				if row.get::<Vec<u8>, _>(0) == substrate_block_hash
+					&& row.get::<i64, _>(1) == transaction_index
+					&& row.get::<i64, _>(2) == log_index =>
+			{
+				Ok(true)
+			}
+			Some(_) => Err(Error::Protocol(format!(
+				"Log key collision for log {log_index} of transaction {transaction_index} in block 0x{}",
+				hex_encode(substrate_block_hash)
+			))),
+		}
+	}
+
+	/// Create the Sqlite database indices if it does not already exist.
+	async fn create_indexes_if_not_exist(pool: &SqlitePool) -> Result<SqliteQueryResult, Error> {
+		sqlx::query(
+			"BEGIN;
+			CREATE INDEX IF NOT EXISTS blocks_number_index ON blocks (
+				block_number
+			);
+			CREATE INDEX IF NOT EXISTS blocks_substrate_index ON blocks (
+				substrate_block_hash
+			);
+			CREATE INDEX IF NOT EXISTS eth_block_hash_idx ON blocks (
+				ethereum_block_hash
+			);
+			CREATE INDEX IF NOT EXISTS eth_tx_hash_idx ON transactions (
+				ethereum_transaction_hash
+			);
+			CREATE INDEX IF NOT EXISTS eth_tx_hash_2_idx ON transactions (
+				ethereum_block_hash,
+				ethereum_transaction_index
+			);
+			CREATE INDEX IF NOT EXISTS blocks_author_idx ON blocks (
+				author,
+				block_number
+			);
+			CREATE INDEX IF NOT EXISTS block_summary_head_idx ON block_summary (
+				is_canon,
+				block_number
+			);
+			COMMIT;",
+		)
+		.execute(pool)
+		.await
+	}
+
+	/// Builds the log indexes the indexer filters with if the logs are indexed, and drops
+	/// those of an existing database otherwise.
+	async fn create_log_indexes_if_not_exist(
+		pool: &SqlitePool,
+		index_logs: bool,
+	) -> Result<SqliteQueryResult, Error> {
+		let statements = if index_logs {
+			"BEGIN;
+			CREATE INDEX IF NOT EXISTS logs_main_idx ON logs (
+				address,
+				topic_1,
+				topic_2,
+				topic_3,
+				topic_4
+			);
+			CREATE INDEX IF NOT EXISTS logs_substrate_index ON logs (
+				substrate_block_hash
+			);
+			COMMIT;"
+		} else {
+			"BEGIN;
+			DROP INDEX IF EXISTS logs_main_idx;
+			DROP INDEX IF EXISTS logs_substrate_index;
+			DROP INDEX IF EXISTS logs_address_number_idx;
+			COMMIT;"
+		};
+		sqlx::query(statements).execute(pool).await
+	}
+}
+
+#[async_trait::async_trait]
+impl<Block: BlockT<Hash = H256>> fc_api::Backend<Block> for Backend<Block> {
+	async fn block_hash(
+		&self,
+		ethereum_block_hash: &H256,
+	) -> Result<Option<Vec<Block::Hash>>, String> {
+		let ethereum_block_hash = ethereum_block_hash.as_bytes();
+		let res =
+			sqlx::query("SELECT substrate_block_hash FROM blocks WHERE ethereum_block_hash = ?")
+				.bind(ethereum_block_hash)
+				.fetch_all(&self.pool)
+				.await
+				.ok()
+				.map(|rows| {
+					rows.iter()
+						.map(|row| {
+							H256::from_slice(&row.try_get::<Vec<u8>, _>(0).unwrap_or_default()[..])
+						})
+						.collect()
+				});
+		Ok(res)
+	}
+
+	async fn transaction_metadata(
+		&self,
+		ethereum_transaction_hash: &H256,
+	) -> Result<Vec<TransactionMetadata<Block>>, String> {
+		let ethereum_transaction_hash = ethereum_transaction_hash.as_bytes();
+		let out = sqlx::query(
+			"SELECT
+				t.substrate_block_hash, t.ethereum_block_hash, t.ethereum_transaction_index
+			FROM transactions AS t
+			LEFT JOIN blocks AS b ON b.substrate_block_hash = t.substrate_block_hash
+			WHERE t.ethereum_transaction_hash = ?
+			ORDER BY b.is_canon DESC",
+		)
+		.bind(ethereum_transaction_hash)
+		.fetch_all(&self.pool)
+		.await
+		.unwrap_or_default()
+		.iter()
+		.map(|row| {
+			let substrate_block_hash =
+				H256::from_slice(&row.try_get::<Vec<u8>, _>(0).unwrap_or_default()[..]);
+			let ethereum_block_hash =
+				H256::from_slice(&row.try_get::<Vec<u8>, _>(1).unwrap_or_default()[..]);
+			let ethereum_transaction_index = row.try_get::<i64, _>(2).unwrap_or_default() as u32;
+			TransactionMetadata {
+				substrate_block_hash,
+				ethereum_block_hash,
+				ethereum_index: ethereum_transaction_index,
+			}
+		})
+		.collect();
+
+		Ok(out)
+	}
+
+	fn log_indexer(&self) -> &dyn fc_api::LogIndexerBackend<Block> {
+		self
+	}
+
+	async fn latest_block_hash(&self) -> Result<Block::Hash, String> {
+		// Retrieves the block hash for the latest indexed block, maybe it's not canon.
+		sqlx::query("SELECT substrate_block_hash FROM blocks ORDER BY block_number DESC LIMIT 1")
+			.fetch_one(self.pool())
+			.await
+			.map(|row| H256::from_slice(&row.get::<Vec<u8>, _>(0)[..]))
+			.map_err(|e| format!("Failed to fetch best hash: {}", e))
+	}
+}
+
+#[async_trait::async_trait]
+impl<Block: BlockT<Hash = H256>> fc_api::LogIndexerBackend<Block> for Backend<Block> {
+	fn is_indexed(&self) -> bool {
+		true
+	}
+
+	async fn filter_logs(
+		&self,
+		from_block: u64,
+		to_block: u64,
+		addresses: Vec<H160>,
+		topics: Vec<Vec<Option<H256>>>,
+		limit: u32,
+	) -> Result<Vec<FilteredLog<Block>>, String> {
+		if !self.index_logs {
+			return Err(LOGS_NOT_INDEXED.to_string());
+		}
+
+		let addresses: Vec<H160> = addresses
+			.into_iter()
+			.collect::<HashSet<_>>()
+			.into_iter()
+			.collect();
+		if addresses.len() > self.max_filter_addresses as usize {
+			return Err(format!(
+				"Invalid address input. Maximum length is {}.",
+				self.max_filter_addresses
+			));
+		}
+
+		let mut unique_topics: [HashSet<H256>; 4] = [
+			HashSet::new(),
+			HashSet::new(),
+			HashSet::new(),
+			HashSet::new(),
+		];
+		for topic_combination in topics.into_iter() {
+			for (topic_index, topic) in topic_combination.into_iter().enumerate() {
+				if topic_index == MAX_TOPIC_COUNT as usize {
+					return Err("Invalid topic input. Maximum length is 4.".to_string());
+				}
+
+				if let Some(topic) = topic {
+					unique_topics[topic_index].insert(topic);
+				}
+			}
+		}
+		if let Some(topic_index) = unique_topics
+			.iter()
+			.position(|alternatives| alternatives.len() > self.max_topic_alternatives as usize)
+		{
+			return Err(format!(
+				"Invalid topic input. Maximum alternatives at position {topic_index} is {}.",
+				self.max_topic_alternatives
+			));
+		}
+
+		let log_key = format!("{from_block}-{to_block}-{addresses:?}-{unique_topics:?}");
+		let (sql_topics, memory_topics) = plan_topic_filters(
+			self.topic_filter_strategy,
+			!addresses.is_empty(),
+			unique_topics,
+		);
+		let encoding = self.log_column_encoding;
+		let memory_topics: Vec<(usize, HashSet<LogColumn>)> = memory_topics
+			.into_iter()
+			.enumerate()
+			.filter(|(_, alternatives)| !alternatives.is_empty())
+			.map(|(i, alternatives)| {
+				let columns = alternatives
+					.iter()
+					.map(|topic| encoding.column(topic.as_bytes()))
+					.collect();
+				(i, columns)
+			})
+			.collect();
+
+		let mut conn = self
+			.pool()
+			.acquire()
+			.await
+			.map_err(|err| format!("failed acquiring sqlite connection: {}", err))?;
+		let log_key2 = log_key.clone();
+		conn.lock_handle()
+			.await
+			.map_err(|err| format!("{:?}", err))?
+			.set_progress_handler(self.num_ops_timeout, move || {
+				log::debug!(target: "frontier-sql", "Sqlite progress_handler triggered for {log_key2}");
+				false
+			});
+
+		// The result set is fetched in chunks, resuming each chunk after the last seen
+		// `(block_number, transaction_index, log_index)` position. The chunks are read in a
+		// single transaction so that they all see the same snapshot of the index.
+		let mut snapshot = sqlx::Connection::begin(&mut *conn)
+			.await
+			.map_err(|err| format!("failed beginning sqlite read transaction: {}", err))?;
+		let mut out: Vec<FilteredLog<Block>> = vec![];
+		let mut cursor: Option<(i32, i64, i64)> = None;
+		let maybe_err = loop {
+			let chunk_size = self
+				.log_fetch_chunk_size
+				.min(limit.saturating_sub(out.len() as u32));
+			if chunk_size == 0 {
+				break None;
+			}
+			let mut qb = QueryBuilder::new("");
+			let query = build_query(
+				&mut qb,
+				from_block,
+				to_block,
+				&addresses,
+				&sql_topics,
+				cursor,
+				chunk_size,
+				encoding,
+			);
+			log::debug!(target: "frontier-sql", "Query: {:?} - {log_key}", query.sql());
+
+			let mut fetched: u32 = 0;
+			let mut rows = query.fetch(&mut *snapshot);
+			let maybe_err = loop {
+				match rows.try_next().await {
+					Ok(Some(row)) => {
+						// Substrate block hash
+						let substrate_block_hash =
+							H256::from_slice(&row.try_get::<Vec<u8>, _>(0).unwrap_or_default()[..]);
+						// Ethereum block hash
+						let ethereum_block_hash =
+							H256::from_slice(&row.try_get::<Vec<u8>, _>(1).unwrap_or_default()[..]);
+						// Block number
+						let block_number = row.try_get::<i32, _>(2).unwrap_or_default();
+						// Ethereum storage schema
+						let ethereum_storage_schema: EthereumStorageSchema = Decode::decode(
+							&mut &row.try_get::<Vec<u8>, _>(3).unwrap_or_default()[..],
+						)
+						.map_err(|_| "Cannot decode EthereumStorageSchema for block".to_string())?;
+						// Transaction index
+						let transaction_index = row.try_get::<i64, _>(4).unwrap_or_default();
+						// Log index
+						let log_index = row.try_get::<i64, _>(5).unwrap_or_default();
+						cursor = Some((block_number, transaction_index, log_index));
+						fetched += 1;
+						// Topics left out of the query
+						let matches = memory_topics.iter().all(|(i, alternatives)| {
+							topic_column(&row, 6 + i, encoding)
+								.is_some_and(|topic| alternatives.contains(&topic))
+						});
+						if !matches {
+							continue;
+						}
+						out.push(FilteredLog {
+							substrate_block_hash,
+							ethereum_block_hash,
+							block_number: block_number as u32,
+							ethereum_storage_schema,
+							transaction_index: transaction_index as u32,
+							log_index: log_index as u32,
+						});
+					}
+					Ok(None) => break None, // no more rows
+					Err(err) => break Some(err),
+				};
+			};
+			drop(rows);
+			self.log_fetch_peak
+				.fetch_max(fetched as u64, AtomicOrdering::Relaxed);
+
+			if maybe_err.is_some() {
+				break maybe_err;
+			}
+			// Either the result set is exhausted or the result-count cap is reached.
+			if fetched < chunk_size || out.len() >= limit as usize {
+				break None;
+			}
+		};
+		// Nothing was written, ending the read transaction cannot lose anything.
+		let _ = snapshot.rollback().await;
+		conn.lock_handle()
+			.await
+			.map_err(|err| format!("{:?}", err))?
+			.remove_progress_handler();
+
+		if let Some(err) = maybe_err {
+			log::error!(target: "frontier-sql", "Failed to query sql db: {err:?} - {log_key}");
+			return Err("Failed to query sql db with statement".to_string());
+		}
+
+		log::info!(target: "frontier-sql", "FILTER remove handler - {log_key}");
+		Ok(out)
+	}
+}
+
+/// Rejects the negative transaction and log indexes, which can only come from a buggy
+/// index computation.
+fn non_negative_index(column: &str, index: i64) -> Result<i64, Error> {
+	if index < 0 {
+		return Err(Error::Protocol(format!(
+			"Refusing to store negative {column} {index}"
+		)));
+	}
+	Ok(index)
+}
+
+/// The [`LogKey::Hashed`] key of a log.
+fn hashed_log_key(substrate_block_hash: &[u8], transaction_index: i64, log_index: i64) -> [u8; 16] {
+	blake2_128(
+		&[
+			substrate_block_hash,
+			&transaction_index.to_le_bytes(),
+			&log_index.to_le_bytes(),
+		]
+		.concat(),
+	)
+}
+
+/// Whether the error persists across retries: a constraint violation fails again on the
+/// same data, and a closed pool or failed migration leaves no usable database.
+fn is_fatal_error(err: &Error) -> bool {
+	// The primary result code of every (extended) constraint violation.
+	const SQLITE_CONSTRAINT: i32 = 19;
+	match err {
+		Error::Database(err) => err
+			.code()
+			.and_then(|code| code.parse::<i32>().ok())
+			.is_some_and(|code| code & 0xff == SQLITE_CONSTRAINT),
+		Error::Migrate(_) | Error::PoolClosed | Error::WorkerCrashed => true,
+		_ => false,
+	}
+}
+
+/// Whether the error is expected to go away on retry, like the database being locked by
+/// another connection past the busy timeout, or no pooled connection being available.
+pub fn is_transient_error(err: &Error) -> bool {
+	const SQLITE_BUSY: i32 = 5;
+	const SQLITE_LOCKED: i32 = 6;
+	match err {
+		Error::Database(err) => err
+			.code()
+			.and_then(|code| code.parse::<i32>().ok())
+			.is_some_and(|code| matches!(code & 0xff, SQLITE_BUSY | SQLITE_LOCKED)),
+		Error::PoolTimedOut => true,
+		_ => false,
+	}
+}
+
+/// Decodes a `blocks` row selecting the columns of an [`IndexedBlock`].
+fn indexed_block_from_row(row: &SqliteRow) -> IndexedBlock {
+	IndexedBlock {
+		substrate_block_hash: H256::from_slice(&row.get::<Vec<u8>, _>(0)[..]),
+		block_number: row.get::<i32, _>(1) as u32,
+		is_canon: row.get::<i32, _>(2) != 0,
+	}
+}
+
+/// Decodes a `block_summary` row.
+fn block_summary_from_row(row: &SqliteRow) -> Result<BlockSummary, Error> {
+	let hash = |column: &str| -> Result<H256, Error> {
+		let bytes = row.try_get::<Vec<u8>, _>(column)?;
+		if bytes.len() != 32 {
+			return Err(Error::Protocol(format!(
+				"Invalid length {} for summary column {column}",
+				bytes.len()
+			)));
+		}
+		Ok(H256::from_slice(&bytes))
+	};
+	Ok(BlockSummary {
+		substrate_block_hash: hash("substrate_block_hash")?,
+		block_number: row.try_get::<i32, _>("block_number")? as u32,
+		ethereum_block_hash: hash("ethereum_block_hash")?,
+		transaction_count: row.try_get::<i64, _>("transaction_count")? as u32,
+		log_count: row.try_get::<i64, _>("log_count")? as u32,
+		gas_used: row
+			.try_get::<Option<Vec<u8>>, _>("gas_used")?
+			.map(|bytes| U256::from_big_endian(&bytes)),
+		timestamp: row
+			.try_get::<Option<i64>, _>("timestamp")?
+			.map(|timestamp| timestamp as u64),
+		is_canon: row.try_get::<i32, _>("is_canon")? == 1,
+	})
+}
+
+/// Encodes the bytes as a lowercase hex string, without prefix.
+fn hex_encode(bytes: &[u8]) -> String {
+	bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Encodes a `U256` as 32 big-endian bytes.
+fn u256_to_bytes(value: U256) -> [u8; 32] {
+	let mut bytes = [0u8; 32];
+	value.to_big_endian(&mut bytes);
+	bytes
+}
+
+/// The gas used by the block up to and including the transaction of the given receipt.
+fn receipt_cumulative_gas_used(receipt: &ethereum::ReceiptV3) -> U256 {
+	match receipt {
+		ethereum::ReceiptV3::Legacy(data)
+		| ethereum::ReceiptV3::EIP2930(data)
+		| ethereum::ReceiptV3::EIP1559(data) => data.used_gas,
+	}
+}
+
+/// The EIP-658 status code of the given receipt, `1` on success and `0` on failure.
+fn receipt_status_code(receipt: &ethereum::ReceiptV3) -> u8 {
+	match receipt {
+		ethereum::ReceiptV3::Legacy(data)
+		| ethereum::ReceiptV3::EIP2930(data)
+		| ethereum::ReceiptV3::EIP1559(data) => data.status_code,
+	}
+}
+
+/// The logs emitted by the transaction of the given receipt.
+fn receipt_logs(receipt: &ethereum::ReceiptV3) -> &[ethereum::Log] {
+	match receipt {
+		ethereum::ReceiptV3::Legacy(data)
+		| ethereum::ReceiptV3::EIP2930(data)
+		| ethereum::ReceiptV3::EIP1559(data) => &data.logs,
+	}
+}
+
+/// Pairs the decoded ethereum transactions with their receipts by index.
+fn pair_transactions_with_receipts(
+	transactions: Vec<ethereum::TransactionV2>,
+	receipts: Vec<ethereum::ReceiptV3>,
+	senders: Vec<Option<H160>>,
+) -> Vec<IndexedTransaction> {
+	let mut receipts = receipts.into_iter();
+	let mut senders = senders.into_iter();
+	transactions
+		.into_iter()
+		.enumerate()
+		.map(|(i, transaction)| IndexedTransaction {
+			transaction_hash: transaction.hash(),
+			transaction_index: i as i64,
+			transaction,
+			receipt: receipts.next(),
+			sender: senders.next().flatten(),
+		})
+		.collect()
+}
+
+/// Replaces the ethereum hashes of the block metadata with the `mapped` ones. The decoded
+/// transactions are kept under the mapped hashes if they line up with them, else dropped.
+fn apply_mapped_hashes(metadata: &mut BlockMetadata, mapped: Hashes) {
+	let mismatched = metadata
+		.transactions
+		.as_ref()
+		.is_some_and(|transactions| transactions.len() != mapped.transaction_hashes.len());
+	if mismatched {
+		log::warn!(
+			target: "frontier-sql",
+			"[Metadata] Decoded transactions of block #{} differ from the {} mapped ones",
+			metadata.block_number,
+			mapped.transaction_hashes.len(),
+		);
+		metadata.transactions = None;
+	}
+	for (transaction, transaction_hash) in metadata
+		.transactions
+		.iter_mut()
+		.flatten()
+		.zip(&mapped.transaction_hashes)
+	{
+		transaction.transaction_hash = *transaction_hash;
+	}
+	metadata.post_hashes = mapped;
+}
+
+/// Splits the topic alternatives of a filter call into the ones queried and the ones
+/// filtered in memory, in this order.
+fn plan_topic_filters(
+	strategy: TopicFilterStrategy,
+	has_addresses: bool,
+	topics: [HashSet<H256>; 4],
+) -> ([HashSet<H256>; 4], [HashSet<H256>; 4]) {
+	let mut sql_topics: [HashSet<H256>; 4] = Default::default();
+	let mut memory_topics: [HashSet<H256>; 4] = Default::default();
+	for (i, alternatives) in topics.into_iter().enumerate() {
+		let in_sql = match strategy {
+			TopicFilterStrategy::Auto => alternatives.len() <= AUTO_MAX_SQL_TOPIC_ALTERNATIVES,
+			TopicFilterStrategy::Sql => true,
+			TopicFilterStrategy::Memory => false,
+		};
+		if in_sql {
+			sql_topics[i] = alternatives;
+		} else {
+			memory_topics[i] = alternatives;
+		}
+	}
+
+	// Without any queried predicate the whole block range would be fetched, so the most
+	// selective topic position is queried anyway.
+	let unconstrained = !has_addresses && sql_topics.iter().all(HashSet::is_empty);
+	if strategy == TopicFilterStrategy::Auto && unconstrained {
+		let most_selective = (0..4)
+			.filter(|i| !memory_topics[*i].is_empty())
+			.min_by_key(|i| memory_topics[*i].len());
+		if let Some(i) = most_selective {
+			sql_topics[i] = std::mem::take(&mut memory_topics[i]);
+		}
+	}
+	(sql_topics, memory_topics)
+}
+
+/// Decodes a log address or topic value from its storage representation.
+fn log_column_bytes(column: LogColumn) -> Option<Vec<u8>> {
+	match column {
+		LogColumn::Bytes(bytes) => Some(bytes),
+		LogColumn::Hex(hex) => {
+			let hex = hex.strip_prefix("0x")?;
+			if hex.len() % 2 != 0 {
+				return None;
+			}
+			(0..hex.len())
+				.step_by(2)
+				.map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+				.collect()
+		}
+	}
+}
+
+/// Reads a topic column of a filtered log row, `None` if the topic is not set.
+fn topic_column(row: &SqliteRow, index: usize, encoding: LogColumnEncoding) -> Option<LogColumn> {
+	match encoding {
+		LogColumnEncoding::Bytes => row
+			.try_get::<Option<Vec<u8>>, _>(index)
+			.ok()
+			.flatten()
+			.map(LogColumn::Bytes),
+		LogColumnEncoding::Hex => row
+			.try_get::<Option<String>, _>(index)
+			.ok()
+			.flatten()
+			.map(LogColumn::Hex),
+	}
+}
+
+/// Build a SQL query to retrieve a list of logs given certain constraints.
+///
+/// When a `cursor` is provided, only the logs positioned after the given
+/// `(block_number, transaction_index, log_index)` are selected.
+fn build_query<'a>(
+	qb: &'a mut QueryBuilder<Sqlite>,
+	from_block: u64,
+	to_block: u64,
+	addresses: &[H160],
+	topics: &[HashSet<H256>; 4],
+	cursor: Option<(i32, i64, i64)>,
+	limit: u32,
+	encoding: LogColumnEncoding,
+) -> Query<'a, Sqlite, SqliteArguments<'a>> {
+	qb.push(
+		"
+SELECT
+	l.substrate_block_hash,
+	b.ethereum_block_hash,
+	b.block_number,
+	b.ethereum_storage_schema,
+	l.transaction_index,
+	l.log_index,
+	l.topic_1,
+	l.topic_2,
+	l.topic_3,
+	l.topic_4
+FROM logs AS l
+INNER JOIN blocks AS b
+ON (b.block_number BETWEEN ",
+	);
+	qb.separated(" AND ")
+		.push_bind(from_block as i64)
+		.push_bind(to_block as i64)
+		.push_unseparated(")");
+	qb.push(" AND b.substrate_block_hash = l.substrate_block_hash")
+		.push(" AND b.is_canon = 1")
+		.push("\nWHERE 1");
+
+	if !addresses.is_empty() {
+		qb.push(" AND l.address IN (");
+		let mut qb_addr = qb.separated(", ");
+		addresses.iter().for_each(|addr| {
+			qb_addr.push_bind(encoding.column(addr.as_bytes()));
+		});
+		qb_addr.push_unseparated(")");
+	}
+
+	// Absent topics are `NULL` and never match, so a condition on the topic at position `i`
+	// only selects logs whose `topic_count` is greater than `i`.
+	for (i, topic_options) in topics.iter().enumerate() {
+		match topic_options.len().cmp(&1) {
+			Ordering::Greater => {
+				qb.push(format!(" AND l.topic_{} IN (", i + 1));
+				let mut qb_topic = qb.separated(", ");
+				topic_options.iter().for_each(|t| {
+					qb_topic.push_bind(encoding.column(t.as_bytes()));
+				});
+				qb_topic.push_unseparated(")");
+			}
+			Ordering::Equal => {
+				qb.push(format!(" AND l.topic_{} = ", i + 1)).push_bind(
+					encoding.column(
+						topic_options
+							.iter()
+							.next()
+							.expect("length is 1, must exist; qed")
+							.as_bytes(),
+					),
+				);
+			}
+			Ordering::Less => {}
+		}
+	}
+
+	if let Some((block_number, transaction_index, log_index)) = cursor {
+		qb.push(" AND (b.block_number, l.transaction_index, l.log_index) > (");
+		qb.separated(", ")
+			.push_bind(block_number)
+			.push_bind(transaction_index)
+			.push_bind(log_index)
+			.push_unseparated(")");
+	}
+
+	qb.push(
+		"
+ORDER BY b.block_number ASC, l.transaction_index ASC, l.log_index ASC
+LIMIT ",
+	)
+	.push_bind(limit as i64);
+
+	qb.build()
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	use std::path::Path;
+
+	use maplit::hashset;
+	use scale_codec::Encode;
+	use sqlx::{sqlite::SqliteRow, QueryBuilder, Row, SqlitePool};
+	use tempfile::tempdir;
+	// Substrate
+	use sp_core::{H160, H256};
+	use sp_runtime::{
+		generic::{Block, Header},
+		traits::BlakeTwo256,
+	};
+	use substrate_test_runtime_client::{
+		DefaultTestClientBuilderExt, TestClientBuilder, TestClientBuilderExt,
+	};
+	// Frontier
+	use fc_api::Backend as BackendT;
+	use fc_storage::SchemaV3StorageOverride;
+	use fp_storage::{EthereumStorageSchema, PALLET_ETHEREUM_SCHEMA};
+
+	type OpaqueBlock =
+		Block<Header<u64, BlakeTwo256>, substrate_test_runtime_client::runtime::Extrinsic>;
+
+	struct TestFilter {
+		pub from_block: u64,
+		pub to_block: u64,
+		pub addresses: Vec<H160>,
+		pub topics: Vec<Vec<Option<H256>>>,
+		pub expected_result: Vec<FilteredLog<OpaqueBlock>>,
+	}
+
+	#[derive(Debug, Clone)]
+	struct Log {
+		block_number: u32,
+		address: H160,
+		topics: [H256; 4],
+		substrate_block_hash: H256,
+		ethereum_block_hash: H256,
+		transaction_index: u32,
+		log_index: u32,
+	}
+
+	#[allow(unused)]
+	struct TestData {
+		backend: Backend<OpaqueBlock>,
+		alice: H160,
+		bob: H160,
+		topics_a: H256,
+		topics_b: H256,
+		topics_c: H256,
+		topics_d: H256,
+		substrate_hash_1: H256,
+		substrate_hash_2: H256,
+		substrate_hash_3: H256,
+		ethereum_hash_1: H256,
+		ethereum_hash_2: H256,
+		ethereum_hash_3: H256,
+		log_1_abcd_0_0_alice: Log,
+		log_1_dcba_1_0_alice: Log,
+		log_1_badc_2_0_alice: Log,
+		log_2_abcd_0_0_bob: Log,
+		log_2_dcba_1_0_bob: Log,
+		log_2_badc_2_0_bob: Log,
+		log_3_abcd_0_0_bob: Log,
+		log_3_dcba_1_0_bob: Log,
+		log_3_badc_2_0_bob: Log,
+	}
+
+	impl From<Log> for FilteredLog<OpaqueBlock> {
+		fn from(value: Log) -> Self {
+			Self {
+				substrate_block_hash: value.substrate_block_hash,
+				ethereum_block_hash: value.ethereum_block_hash,
+				block_number: value.block_number,
+				ethereum_storage_schema: EthereumStorageSchema::V3,
+				transaction_index: value.transaction_index,
+				log_index: value.log_index,
+			}
+		}
+	}
+
+	async fn prepare() -> TestData {
+		let tmp = tempdir().expect("create a temporary directory");
+		// Initialize storage with schema V3
+		let builder = TestClientBuilder::new().add_extra_storage(
+			PALLET_ETHEREUM_SCHEMA.to_vec(),
+			Encode::encode(&EthereumStorageSchema::V3),
+		);
+		// Client
+		let (client, _) = builder
+			.build_with_native_executor::<substrate_test_runtime_client::runtime::RuntimeApi, _>(
+				None,
+			);
+		let client = Arc::new(client);
+		// Overrides
+		let storage_override = Arc::new(SchemaV3StorageOverride::new(client.clone()));
+		// Indexer backend
+		let indexer_backend = Backend::new(
+			BackendConfig::Sqlite(SqliteBackendConfig {
+				path: Path::new("sqlite:///")
+					.join(tmp.path())
+					.join("test.db3")
+					.to_str()
+					.unwrap(),
+				create_if_missing: true,
+				cache_size: 20480,
+				thread_count: 4,
+				journal_mode: None,
+				synchronous: None,
+				busy_timeout: None,
+				index_logs: true,
+			}),
+			1,
+			None,
+			storage_override.clone(),
+		)
+		.await
+		.expect("indexer pool to be created");
+
+		// Prepare test db data
+		// Addresses
+		let alice = H160::repeat_byte(0x01);
+		let bob = H160::repeat_byte(0x02);
+		// Topics
+		let topics_a = H256::repeat_byte(0x01);
+		let topics_b = H256::repeat_byte(0x02);
+		let topics_c = H256::repeat_byte(0x03);
+		let topics_d = H256::repeat_byte(0x04);
+		// Substrate block hashes
+		let substrate_hash_1 = H256::repeat_byte(0x05);
+		let substrate_hash_2 = H256::repeat_byte(0x06);
+		let substrate_hash_3 = H256::repeat_byte(0x07);
+		// Ethereum block hashes
+		let ethereum_hash_1 = H256::repeat_byte(0x08);
+		let ethereum_hash_2 = H256::repeat_byte(0x09);
+		let ethereum_hash_3 = H256::repeat_byte(0x0a);
+		// Ethereum storage schema
+		let ethereum_storage_schema = EthereumStorageSchema::V3;
+
+		let block_entries = vec![
+			// Block 1
+			(
+				1i32,
+				ethereum_hash_1,
+				substrate_hash_1,
+				ethereum_storage_schema,
+			),
+			// Block 2
+			(
+				2i32,
 				ethereum_hash_2,
 				substrate_hash_2,
 				ethereum_storage_schema,
-			),
-			// Block 3
-			(
-				3i32,
-				ethereum_hash_3,
-				substrate_hash_3,
+			),
+			// Block 3
+			(
+				3i32,
+				ethereum_hash_3,
+				substrate_hash_3,
+				ethereum_storage_schema,
+			),
+		];
+		let mut builder = QueryBuilder::new(
+			"INSERT INTO blocks(
+				block_number,
+				ethereum_block_hash,
+				substrate_block_hash,
+				ethereum_storage_schema,
+				is_canon
+			)",
+		);
+		builder.push_values(block_entries, |mut b, entry| {
+			let block_number = entry.0;
+			let ethereum_block_hash = entry.1.as_bytes().to_owned();
+			let substrate_block_hash = entry.2.as_bytes().to_owned();
+			let ethereum_storage_schema = entry.3.encode();
+
+			b.push_bind(block_number);
+			b.push_bind(ethereum_block_hash);
+			b.push_bind(substrate_block_hash);
+			b.push_bind(ethereum_storage_schema);
+			b.push_bind(1i32);
+		});
+		let query = builder.build();
+		let _ = query
+			.execute(indexer_backend.pool())
+			.await
+			.expect("insert should succeed");
+
+		// log_{BLOCK}_{TOPICS}_{LOG_INDEX}_{TX_INDEX}
+		let log_1_abcd_0_0_alice = Log {
+			block_number: 1,
+			address: alice,
+			topics: [topics_a, topics_b, topics_c, topics_d],
+			log_index: 0,
+			transaction_index: 0,
+			substrate_block_hash: substrate_hash_1,
+			ethereum_block_hash: ethereum_hash_1,
+		};
+		let log_1_dcba_1_0_alice = Log {
+			block_number: 1,
+			address: alice,
+			topics: [topics_d, topics_c, topics_b, topics_a],
+			log_index: 1,
+			transaction_index: 0,
+			substrate_block_hash: substrate_hash_1,
+			ethereum_block_hash: ethereum_hash_1,
+		};
+		let log_1_badc_2_0_alice = Log {
+			block_number: 1,
+			address: alice,
+			topics: [topics_b, topics_a, topics_d, topics_c],
+			log_index: 2,
+			transaction_index: 0,
+			substrate_block_hash: substrate_hash_1,
+			ethereum_block_hash: ethereum_hash_1,
+		};
+		let log_2_abcd_0_0_bob = Log {
+			block_number: 2,
+			address: bob,
+			topics: [topics_a, topics_b, topics_c, topics_d],
+			log_index: 0,
+			transaction_index: 0,
+			substrate_block_hash: substrate_hash_2,
+			ethereum_block_hash: ethereum_hash_2,
+		};
+		let log_2_dcba_1_0_bob = Log {
+			block_number: 2,
+			address: bob,
+			topics: [topics_d, topics_c, topics_b, topics_a],
+			log_index: 1,
+			transaction_index: 0,
+			substrate_block_hash: substrate_hash_2,
+			ethereum_block_hash: ethereum_hash_2,
+		};
+		let log_2_badc_2_0_bob = Log {
+			block_number: 2,
+			address: bob,
+			topics: [topics_b, topics_a, topics_d, topics_c],
+			log_index: 2,
+			transaction_index: 0,
+			substrate_block_hash: substrate_hash_2,
+			ethereum_block_hash: ethereum_hash_2,
+		};
+
+		let log_3_abcd_0_0_bob = Log {
+			block_number: 3,
+			address: bob,
+			topics: [topics_a, topics_b, topics_c, topics_d],
+			log_index: 0,
+			transaction_index: 0,
+			substrate_block_hash: substrate_hash_3,
+			ethereum_block_hash: ethereum_hash_3,
+		};
+		let log_3_dcba_1_0_bob = Log {
+			block_number: 3,
+			address: bob,
+			topics: [topics_d, topics_c, topics_b, topics_a],
+			log_index: 1,
+			transaction_index: 0,
+			substrate_block_hash: substrate_hash_3,
+			ethereum_block_hash: ethereum_hash_3,
+		};
+		let log_3_badc_2_0_bob = Log {
+			block_number: 3,
+			address: bob,
+			topics: [topics_b, topics_a, topics_d, topics_c],
+			log_index: 2,
+			transaction_index: 0,
+			substrate_block_hash: substrate_hash_3,
+			ethereum_block_hash: ethereum_hash_3,
+		};
+
+		let log_entries = vec![
+			// Block 1
+			log_1_abcd_0_0_alice.clone(),
+			log_1_dcba_1_0_alice.clone(),
+			log_1_badc_2_0_alice.clone(),
+			// Block 2
+			log_2_abcd_0_0_bob.clone(),
+			log_2_dcba_1_0_bob.clone(),
+			log_2_badc_2_0_bob.clone(),
+			// Block 3
+			log_3_abcd_0_0_bob.clone(),
+			log_3_dcba_1_0_bob.clone(),
+			log_3_badc_2_0_bob.clone(),
+		];
+
+		let mut builder: QueryBuilder<sqlx::Sqlite> = QueryBuilder::new(
+			"INSERT INTO logs(
+				address,
+				topic_1,
+				topic_2,
+				topic_3,
+				topic_4,
+				log_index,
+				transaction_index,
+				substrate_block_hash
+			)",
+		);
+		builder.push_values(log_entries, |mut b, entry| {
+			let address = entry.address.as_bytes().to_owned();
+			let topic_1 = entry.topics[0].as_bytes().to_owned();
+			let topic_2 = entry.topics[1].as_bytes().to_owned();
+			let topic_3 = entry.topics[2].as_bytes().to_owned();
+			let topic_4 = entry.topics[3].as_bytes().to_owned();
+			let log_index = entry.log_index;
+			let transaction_index = entry.transaction_index;
+			let substrate_block_hash = entry.substrate_block_hash.as_bytes().to_owned();
+
+			b.push_bind(address);
+			b.push_bind(topic_1);
+			b.push_bind(topic_2);
+			b.push_bind(topic_3);
+			b.push_bind(topic_4);
+			b.push_bind(log_index);
+			b.push_bind(transaction_index);
+			b.push_bind(substrate_block_hash);
+		});
+		let query = builder.build();
+		let _ = query.execute(indexer_backend.pool()).await;
+
+		TestData {
+			alice,
+			bob,
+			topics_a,
+			topics_b,
+			topics_c,
+			topics_d,
+			substrate_hash_1,
+			substrate_hash_2,
+			substrate_hash_3,
+			ethereum_hash_1,
+			ethereum_hash_2,
+			ethereum_hash_3,
+			backend: indexer_backend,
+			log_1_abcd_0_0_alice,
+			log_1_dcba_1_0_alice,
+			log_1_badc_2_0_alice,
+			log_2_abcd_0_0_bob,
+			log_2_dcba_1_0_bob,
+			log_2_badc_2_0_bob,
+			log_3_abcd_0_0_bob,
+			log_3_dcba_1_0_bob,
+			log_3_badc_2_0_bob,
+		}
+	}
+
+	async fn run_test_case(
+		backend: Backend<OpaqueBlock>,
+		test_case: &TestFilter,
+	) -> Result<Vec<FilteredLog<OpaqueBlock>>, String> {
+		backend
+			.log_indexer()
+			.filter_logs(
+				test_case.from_block,
+				test_case.to_block,
+				test_case.addresses.clone(),
+				test_case.topics.clone(),
+				u32::MAX,
+			)
+			.await
+	}
+
+	async fn assert_blocks_canon(pool: &SqlitePool, expected: Vec<(H256, u32)>) {
+		let actual: Vec<(H256, u32)> =
+			sqlx::query("SELECT substrate_block_hash, is_canon FROM blocks")
+				.map(|row: SqliteRow| (H256::from_slice(&row.get::<Vec<u8>, _>(0)[..]), row.get(1)))
+				.fetch_all(pool)
+				.await
+				.expect("sql query must succeed");
+		assert_eq!(expected, actual);
+	}
+
+	#[tokio::test]
+	async fn genesis_works() {
+		let TestData { backend, .. } = prepare().await;
+		let filter = TestFilter {
+			from_block: 0,
+			to_block: 0,
+			addresses: vec![],
+			topics: vec![],
+			expected_result: vec![],
+		};
+		let result = run_test_case(backend, &filter).await.expect("must succeed");
+		assert_eq!(result, filter.expected_result);
+	}
+
+	#[tokio::test]
+	async fn unsanitized_input_works() {
+		let TestData { backend, .. } = prepare().await;
+		let filter = TestFilter {
+			from_block: 0,
+			to_block: 0,
+			addresses: vec![],
+			topics: vec![vec![None], vec![None, None, None]],
+			expected_result: vec![],
+		};
+		let result = run_test_case(backend, &filter).await.expect("must succeed");
+		assert_eq!(result, filter.expected_result);
+	}
+
+	#[tokio::test]
+	async fn invalid_topic_input_size_fails() {
+		let TestData {
+			backend, topics_a, ..
+		} = prepare().await;
+		let filter = TestFilter {
+			from_block: 0,
+			to_block: 0,
+			addresses: vec![],
+			topics: vec![
+				vec![Some(topics_a), None, None, None, None],
+				vec![Some(topics_a), None, None, None],
+			],
+			expected_result: vec![],
+		};
+		run_test_case(backend, &filter)
+			.await
+			.expect_err("Invalid topic input. Maximum length is 4.");
+	}
+
+	#[tokio::test]
+	async fn test_malformed_topic_cleans_invalid_options() {
+		let TestData {
+			backend,
+			topics_a,
+			topics_b,
+			topics_d,
+			log_1_badc_2_0_alice,
+			..
+		} = prepare().await;
+
+		// [(a,null,b), (a, null), (d,null), null] -> [(a,b), a, d]
+		let filter = TestFilter {
+			from_block: 0,
+			to_block: 1,
+			addresses: vec![],
+			topics: vec![
+				vec![Some(topics_a), None, Some(topics_d)],
+				vec![None], // not considered
+				vec![Some(topics_b), Some(topics_a), None],
+				vec![None, None, None, None], // not considered
+			],
+			expected_result: vec![log_1_badc_2_0_alice.into()],
+		};
+		let result = run_test_case(backend, &filter).await.expect("must succeed");
+		assert_eq!(result, filter.expected_result);
+	}
+
+	#[tokio::test]
+	async fn block_range_works() {
+		let TestData {
+			backend,
+			log_1_abcd_0_0_alice,
+			log_1_dcba_1_0_alice,
+			log_1_badc_2_0_alice,
+			log_2_abcd_0_0_bob,
+			log_2_dcba_1_0_bob,
+			log_2_badc_2_0_bob,
+			..
+		} = prepare().await;
+
+		let filter = TestFilter {
+			from_block: 0,
+			to_block: 2,
+			addresses: vec![],
+			topics: vec![],
+			expected_result: vec![
+				log_1_abcd_0_0_alice.into(),
+				log_1_dcba_1_0_alice.into(),
+				log_1_badc_2_0_alice.into(),
+				log_2_abcd_0_0_bob.into(),
+				log_2_dcba_1_0_bob.into(),
+				log_2_badc_2_0_bob.into(),
+			],
+		};
+		let result = run_test_case(backend, &filter).await.expect("must succeed");
+		assert_eq!(result, filter.expected_result);
+	}
+
+	#[tokio::test]
+	async fn address_filter_works() {
+		let TestData {
+			backend,
+			alice,
+			log_1_abcd_0_0_alice,
+			log_1_dcba_1_0_alice,
+			log_1_badc_2_0_alice,
+			..
+		} = prepare().await;
+		let filter = TestFilter {
+			from_block: 0,
+			to_block: 3,
+			addresses: vec![alice],
+			topics: vec![],
+			expected_result: vec![
+				log_1_abcd_0_0_alice.into(),
+				log_1_dcba_1_0_alice.into(),
+				log_1_badc_2_0_alice.into(),
+			],
+		};
+		let result = run_test_case(backend, &filter).await.expect("must succeed");
+		assert_eq!(result, filter.expected_result);
+	}
+
+	#[tokio::test]
+	async fn topic_filter_works() {
+		let TestData {
+			backend,
+			topics_d,
+			log_1_dcba_1_0_alice,
+			log_2_dcba_1_0_bob,
+			log_3_dcba_1_0_bob,
+			..
+		} = prepare().await;
+		let filter = TestFilter {
+			from_block: 0,
+			to_block: 3,
+			addresses: vec![],
+			topics: vec![vec![Some(topics_d)]],
+			expected_result: vec![
+				log_1_dcba_1_0_alice.into(),
+				log_2_dcba_1_0_bob.into(),
+				log_3_dcba_1_0_bob.into(),
+			],
+		};
+		let result = run_test_case(backend, &filter).await.expect("must succeed");
+		assert_eq!(result, filter.expected_result);
+	}
+
+	#[tokio::test]
+	async fn test_filters_address_and_topic() {
+		let TestData {
+			backend,
+			bob,
+			topics_b,
+			log_2_badc_2_0_bob,
+			log_3_badc_2_0_bob,
+			..
+		} = prepare().await;
+		let filter = TestFilter {
+			from_block: 0,
+			to_block: 3,
+			addresses: vec![bob],
+			topics: vec![vec![Some(topics_b)]],
+			expected_result: vec![log_2_badc_2_0_bob.into(), log_3_badc_2_0_bob.into()],
+		};
+		let result = run_test_case(backend, &filter).await.expect("must succeed");
+		assert_eq!(result, filter.expected_result);
+	}
+
+	#[tokio::test]
+	async fn test_filters_multi_address_and_topic() {
+		let TestData {
+			backend,
+			alice,
+			bob,
+			topics_b,
+			log_1_badc_2_0_alice,
+			log_2_badc_2_0_bob,
+			log_3_badc_2_0_bob,
+			..
+		} = prepare().await;
+		let filter = TestFilter {
+			from_block: 0,
+			to_block: 3,
+			addresses: vec![alice, bob],
+			topics: vec![vec![Some(topics_b)]],
+			expected_result: vec![
+				log_1_badc_2_0_alice.into(),
+				log_2_badc_2_0_bob.into(),
+				log_3_badc_2_0_bob.into(),
+			],
+		};
+		let result = run_test_case(backend, &filter).await.expect("must succeed");
+		assert_eq!(result, filter.expected_result);
+	}
+
+	#[tokio::test]
+	async fn test_filters_multi_address_and_multi_topic() {
+		let TestData {
+			backend,
+			alice,
+			bob,
+			topics_a,
+			topics_b,
+			log_1_abcd_0_0_alice,
+			log_2_abcd_0_0_bob,
+			log_3_abcd_0_0_bob,
+			..
+		} = prepare().await;
+		let filter = TestFilter {
+			from_block: 0,
+			to_block: 3,
+			addresses: vec![alice, bob],
+			topics: vec![vec![Some(topics_a), Some(topics_b)]],
+			expected_result: vec![
+				log_1_abcd_0_0_alice.into(),
+				log_2_abcd_0_0_bob.into(),
+				log_3_abcd_0_0_bob.into(),
+			],
+		};
+		let result = run_test_case(backend, &filter).await.expect("must succeed");
+		assert_eq!(result, filter.expected_result);
+	}
+
+	#[tokio::test]
+	async fn topic_alternatives_are_capped() {
+		let TestData {
+			backend,
+			alice,
+			topics_a,
+			topics_b,
+			log_1_abcd_0_0_alice,
+			log_1_badc_2_0_alice,
+			..
+		} = prepare().await;
+		let backend = backend.with_max_topic_alternatives(NonZeroU32::new(2).unwrap());
+
+		let filter = TestFilter {
+			from_block: 0,
+			to_block: 1,
+			addresses: vec![alice],
+			topics: vec![vec![Some(topics_a)], vec![Some(topics_b)]],
+			expected_result: vec![log_1_abcd_0_0_alice.into(), log_1_badc_2_0_alice.into()],
+		};
+		let result = run_test_case(backend.clone(), &filter)
+			.await
+			.expect("must succeed");
+		assert_eq!(result, filter.expected_result);
+
+		// A third alternative for the first topic exceeds the limit.
+		let filter = TestFilter {
+			topics: vec![
+				vec![Some(topics_a)],
+				vec![Some(topics_b)],
+				vec![Some(H256::repeat_byte(0x05))],
+			],
+			expected_result: vec![],
+			..filter
+		};
+		assert_eq!(
+			run_test_case(backend, &filter).await,
+			Err("Invalid topic input. Maximum alternatives at position 0 is 2.".to_string())
+		);
+	}
+
+	#[tokio::test]
+	async fn multi_address_filter_is_capped() {
+		let TestData {
+			backend,
+			alice,
+			bob,
+			log_1_abcd_0_0_alice,
+			log_1_dcba_1_0_alice,
+			log_1_badc_2_0_alice,
+			log_2_abcd_0_0_bob,
+			log_2_dcba_1_0_bob,
+			log_2_badc_2_0_bob,
+			log_3_abcd_0_0_bob,
+			log_3_dcba_1_0_bob,
+			log_3_badc_2_0_bob,
+			..
+		} = prepare().await;
+		let backend = backend.with_max_filter_addresses(NonZeroU32::new(2).unwrap());
+
+		// Duplicated addresses count once.
+		let filter = TestFilter {
+			from_block: 0,
+			to_block: 3,
+			addresses: vec![alice, bob, alice],
+			topics: vec![],
+			expected_result: vec![
+				log_1_abcd_0_0_alice.into(),
+				log_1_dcba_1_0_alice.into(),
+				log_1_badc_2_0_alice.into(),
+				log_2_abcd_0_0_bob.into(),
+				log_2_dcba_1_0_bob.into(),
+				log_2_badc_2_0_bob.into(),
+				log_3_abcd_0_0_bob.into(),
+				log_3_dcba_1_0_bob.into(),
+				log_3_badc_2_0_bob.into(),
+			],
+		};
+		let result = run_test_case(backend.clone(), &filter)
+			.await
+			.expect("must succeed");
+		assert_eq!(result, filter.expected_result);
+
+		let filter = TestFilter {
+			addresses: vec![alice, bob, H160::repeat_byte(0x03)],
+			expected_result: vec![],
+			..filter
+		};
+		assert_eq!(
+			run_test_case(backend, &filter).await,
+			Err("Invalid address input. Maximum length is 2.".to_string())
+		);
+	}
+
+	#[tokio::test]
+	async fn filter_with_topic_wildcards_works() {
+		let TestData {
+			backend,
+			alice,
+			bob,
+			topics_d,
+			topics_b,
+			log_1_dcba_1_0_alice,
+			log_2_dcba_1_0_bob,
+			log_3_dcba_1_0_bob,
+			..
+		} = prepare().await;
+		let filter = TestFilter {
+			from_block: 0,
+			to_block: 3,
+			addresses: vec![alice, bob],
+			topics: vec![vec![Some(topics_d), None, Some(topics_b)]],
+			expected_result: vec![
+				log_1_dcba_1_0_alice.into(),
+				log_2_dcba_1_0_bob.into(),
+				log_3_dcba_1_0_bob.into(),
+			],
+		};
+		let result = run_test_case(backend, &filter).await.expect("must succeed");
+		assert_eq!(result, filter.expected_result);
+	}
+
+	#[tokio::test]
+	async fn topic_filter_strategies_are_equivalent() {
+		let TestData {
+			backend,
+			alice,
+			bob,
+			topics_a,
+			topics_b,
+			topics_c,
+			topics_d,
+			log_1_abcd_0_0_alice,
+			log_1_badc_2_0_alice,
+			log_2_abcd_0_0_bob,
+			log_2_badc_2_0_bob,
+			log_3_abcd_0_0_bob,
+			log_3_badc_2_0_bob,
+			..
+		} = prepare().await;
+		let filter = TestFilter {
+			from_block: 0,
+			to_block: 3,
+			addresses: vec![alice, bob],
+			topics: vec![
+				vec![Some(topics_a), None, Some(topics_c)],
+				vec![Some(topics_b), None, Some(topics_d)],
+			],
+			expected_result: vec![
+				log_1_abcd_0_0_alice.into(),
+				log_1_badc_2_0_alice.into(),
+				log_2_abcd_0_0_bob.into(),
+				log_2_badc_2_0_bob.into(),
+				log_3_abcd_0_0_bob.into(),
+				log_3_badc_2_0_bob.into(),
+			],
+		};
+		for strategy in [
+			TopicFilterStrategy::Auto,
+			TopicFilterStrategy::Sql,
+			TopicFilterStrategy::Memory,
+		] {
+			let backend = backend
+				.clone()
+				.with_topic_filter_strategy(strategy)
+				.with_log_fetch_chunk_size(NonZeroU32::new(2).unwrap());
+			let result = run_test_case(backend, &filter).await.expect("must succeed");
+			assert_eq!(result, filter.expected_result, "{strategy:?}");
+		}
+	}
+
+	#[test]
+	fn auto_topic_plan_keeps_a_queried_predicate() {
+		let many: HashSet<H256> = (0..=AUTO_MAX_SQL_TOPIC_ALTERNATIVES as u64)
+			.map(H256::from_low_u64_be)
+			.collect();
+		let few = hashset![H256::repeat_byte(0x01)];
+
+		// The selective position is queried, the broad one filtered in memory.
+		let (sql_topics, memory_topics) = plan_topic_filters(
+			TopicFilterStrategy::Auto,
+			false,
+			[many.clone(), few.clone(), hashset![], hashset![]],
+		);
+		assert_eq!(sql_topics, [hashset![], few, hashset![], hashset![]]);
+		assert_eq!(
+			memory_topics,
+			[many.clone(), hashset![], hashset![], hashset![]]
+		);
+
+		// Without any other predicate, the broad position is queried anyway.
+		let (sql_topics, memory_topics) = plan_topic_filters(
+			TopicFilterStrategy::Auto,
+			false,
+			[many.clone(), hashset![], hashset![], hashset![]],
+		);
+		assert_eq!(
+			sql_topics,
+			[many.clone(), hashset![], hashset![], hashset![]]
+		);
+		assert!(memory_topics.iter().all(HashSet::is_empty));
+
+		// Unless the addresses narrow the query.
+		let (sql_topics, _) = plan_topic_filters(
+			TopicFilterStrategy::Auto,
+			true,
+			[many, hashset![], hashset![], hashset![]],
+		);
+		assert!(sql_topics.iter().all(HashSet::is_empty));
+	}
+
+	#[tokio::test]
+	async fn trailing_wildcard_is_useless_but_works() {
+		let TestData {
+			alice,
+			backend,
+			topics_b,
+			log_1_dcba_1_0_alice,
+			..
+		} = prepare().await;
+		let filter = TestFilter {
+			from_block: 0,
+			to_block: 1,
+			addresses: vec![alice],
+			topics: vec![vec![None, None, Some(topics_b), None]],
+			expected_result: vec![log_1_dcba_1_0_alice.into()],
+		};
+		let result = run_test_case(backend, &filter).await.expect("must succeed");
+		assert_eq!(result, filter.expected_result);
+	}
+
+	#[tokio::test]
+	async fn filter_with_multi_topic_options_works() {
+		let TestData {
+			backend,
+			topics_a,
+			topics_d,
+			log_1_abcd_0_0_alice,
+			log_1_dcba_1_0_alice,
+			log_2_abcd_0_0_bob,
+			log_2_dcba_1_0_bob,
+			log_3_abcd_0_0_bob,
+			log_3_dcba_1_0_bob,
+			..
+		} = prepare().await;
+		let filter = TestFilter {
+			from_block: 0,
+			to_block: 3,
+			addresses: vec![],
+			topics: vec![
+				vec![Some(topics_a)],
+				vec![Some(topics_d)],
+				vec![Some(topics_d)], // duplicate, ignored
+			],
+			expected_result: vec![
+				log_1_abcd_0_0_alice.into(),
+				log_1_dcba_1_0_alice.into(),
+				log_2_abcd_0_0_bob.into(),
+				log_2_dcba_1_0_bob.into(),
+				log_3_abcd_0_0_bob.into(),
+				log_3_dcba_1_0_bob.into(),
+			],
+		};
+		let result = run_test_case(backend, &filter).await.expect("must succeed");
+		assert_eq!(result, filter.expected_result);
+	}
+
+	#[tokio::test]
+	async fn filter_with_multi_topic_options_and_wildcards_works() {
+		let TestData {
+			backend,
+			bob,
+			topics_a,
+			topics_b,
+			topics_c,
+			topics_d,
+			log_2_dcba_1_0_bob,
+			log_2_badc_2_0_bob,
+			log_3_dcba_1_0_bob,
+			log_3_badc_2_0_bob,
+			..
+		} = prepare().await;
+		let filter = TestFilter {
+			from_block: 0,
+			to_block: 3,
+			addresses: vec![bob],
+			// Product on input [null,null,(b,d),(a,c)].
+			topics: vec![
+				vec![None, None, Some(topics_b), Some(topics_a)],
+				vec![None, None, Some(topics_b), Some(topics_c)],
+				vec![None, None, Some(topics_d), Some(topics_a)],
+				vec![None, None, Some(topics_d), Some(topics_c)],
+			],
+			expected_result: vec![
+				log_2_dcba_1_0_bob.into(),
+				log_2_badc_2_0_bob.into(),
+				log_3_dcba_1_0_bob.into(),
+				log_3_badc_2_0_bob.into(),
+			],
+		};
+		let result = run_test_case(backend, &filter).await.expect("must succeed");
+		assert_eq!(result, filter.expected_result);
+	}
+
+	#[tokio::test]
+	async fn test_canonicalize_sets_canon_flag_for_redacted_and_enacted_blocks_correctly() {
+		let TestData {
+			backend,
+			substrate_hash_1,
+			substrate_hash_2,
+			substrate_hash_3,
+			..
+		} = prepare().await;
+
+		// set block #1 to non canon
+		sqlx::query("UPDATE blocks SET is_canon = 0 WHERE substrate_block_hash = ?")
+			.bind(substrate_hash_1.as_bytes())
+			.execute(backend.pool())
+			.await
+			.expect("sql query must succeed");
+		assert_blocks_canon(
+			backend.pool(),
+			vec![
+				(substrate_hash_1, 0),
+				(substrate_hash_2, 1),
+				(substrate_hash_3, 1),
+			],
+		)
+		.await;
+
+		backend
+			.canonicalize(&[substrate_hash_2], &[substrate_hash_1])
+			.await
+			.expect("must succeed");
+
+		assert_blocks_canon(
+			backend.pool(),
+			vec![
+				(substrate_hash_1, 1),
+				(substrate_hash_2, 0),
+				(substrate_hash_3, 1),
+			],
+		)
+		.await;
+	}
+
+	#[tokio::test]
+	async fn canonical_status_follows_reorgs() {
+		let TestData {
+			backend,
+			substrate_hash_1,
+			substrate_hash_2,
+			substrate_hash_3,
+			..
+		} = prepare().await;
+
+		// A sibling of block 2, retracted from the start.
+		let sibling_substrate_hash = H256::repeat_byte(0x0b);
+		sqlx::query(
+			"INSERT INTO blocks(
+				block_number,
+				ethereum_block_hash,
+				substrate_block_hash,
+				ethereum_storage_schema,
+				is_canon)
+			VALUES (2, ?, ?, ?, 0)",
+		)
+		.bind(H256::repeat_byte(0x0c).as_bytes())
+		.bind(sibling_substrate_hash.as_bytes())
+		.bind(EthereumStorageSchema::V3.encode())
+		.execute(backend.pool())
+		.await
+		.expect("insert block");
+
+		assert_eq!(
+			backend.is_canon(substrate_hash_1).await.unwrap(),
+			Some(true)
+		);
+		assert_eq!(
+			backend.is_canon(substrate_hash_2).await.unwrap(),
+			Some(true)
+		);
+		assert_eq!(
+			backend.is_canon(sibling_substrate_hash).await.unwrap(),
+			Some(false)
+		);
+		assert_eq!(
+			backend.is_canon(H256::repeat_byte(0xff)).await.unwrap(),
+			None
+		);
+		assert_eq!(
+			backend.canonical_block_by_number(2).await.unwrap(),
+			Some(substrate_hash_2)
+		);
+
+		backend
+			.canonicalize(&[substrate_hash_2], &[sibling_substrate_hash])
+			.await
+			.expect("must succeed");
+
+		assert_eq!(
+			backend.is_canon(substrate_hash_2).await.unwrap(),
+			Some(false)
+		);
+		assert_eq!(
+			backend.is_canon(sibling_substrate_hash).await.unwrap(),
+			Some(true)
+		);
+		assert_eq!(
+			backend.canonical_block_by_number(2).await.unwrap(),
+			Some(sibling_substrate_hash)
+		);
+		assert_eq!(
+			backend.canonical_block_by_number(3).await.unwrap(),
+			Some(substrate_hash_3)
+		);
+		assert_eq!(backend.canonical_block_by_number(4).await.unwrap(), None);
+	}
+
+	#[tokio::test]
+	async fn block_summary_matches_detailed_data_and_follows_reorgs() {
+		let TestData {
+			backend,
+			substrate_hash_2,
+			substrate_hash_3,
+			ethereum_hash_3,
+			..
+		} = prepare().await;
+
+		// A sibling of block 3 carrying a transaction and a header.
+		let sibling_substrate_hash = H256::repeat_byte(0x0b);
+		let sibling_ethereum_hash = H256::repeat_byte(0x0c);
+		sqlx::query(
+			"INSERT INTO blocks(
+				block_number,
+				ethereum_block_hash,
+				substrate_block_hash,
 				ethereum_storage_schema,
-			),
+				is_canon)
+			VALUES (3, ?, ?, ?, 0)",
+		)
+		.bind(sibling_ethereum_hash.as_bytes())
+		.bind(sibling_substrate_hash.as_bytes())
+		.bind(EthereumStorageSchema::V3.encode())
+		.execute(backend.pool())
+		.await
+		.expect("insert block");
+		let header = ethereum::Header::new(
+			ethereum::PartialHeader {
+				parent_hash: H256::repeat_byte(0x11),
+				beneficiary: H160::repeat_byte(0x12),
+				state_root: H256::repeat_byte(0x13),
+				receipts_root: H256::repeat_byte(0x14),
+				logs_bloom: ethereum_types::Bloom::zero(),
+				difficulty: U256::zero(),
+				number: U256::from(3),
+				gas_limit: U256::from(75_000_000),
+				gas_used: U256::from(21_000),
+				timestamp: 1_700_000_000_000,
+				extra_data: vec![],
+				mix_hash: H256::zero(),
+				nonce: H64::zero(),
+			},
+			H256::repeat_byte(0x1a),
+			H256::repeat_byte(0x1b),
+		);
+		let mut conn = backend.pool().acquire().await.expect("connection");
+		Backend::<OpaqueBlock>::insert_ethereum_header(&mut conn, sibling_substrate_hash, &header)
+			.await
+			.expect("insert must succeed");
+		Backend::<OpaqueBlock>::insert_transaction(
+			&mut conn,
+			sibling_substrate_hash,
+			sibling_ethereum_hash,
+			0,
+			H256::repeat_byte(0x42),
+			None,
+			None,
+			None,
+			None,
+		)
+		.await
+		.expect("insert must succeed");
+		for hash in [substrate_hash_2, substrate_hash_3, sibling_substrate_hash] {
+			Backend::<OpaqueBlock>::refresh_block_summary(&mut conn, hash)
+				.await
+				.expect("refresh must succeed");
+		}
+		drop(conn);
+
+		let summary = backend
+			.latest_block_summary()
+			.await
+			.unwrap()
+			.expect("head summary");
+		assert_eq!(
+			summary,
+			BlockSummary {
+				substrate_block_hash: substrate_hash_3,
+				block_number: 3,
+				ethereum_block_hash: ethereum_hash_3,
+				transaction_count: 0,
+				log_count: backend.log_count(substrate_hash_3).await.unwrap(),
+				gas_used: None,
+				timestamp: None,
+				is_canon: true,
+			}
+		);
+		assert_eq!(summary.log_count, 3);
+
+		backend
+			.canonicalize(&[substrate_hash_3], &[sibling_substrate_hash])
+			.await
+			.expect("canonicalize");
+		assert!(
+			!backend
+				.block_summary(substrate_hash_3)
+				.await
+				.unwrap()
+				.expect("retracted summary")
+				.is_canon
+		);
+		assert_eq!(
+			backend.latest_block_summary().await.unwrap(),
+			Some(BlockSummary {
+				substrate_block_hash: sibling_substrate_hash,
+				block_number: 3,
+				ethereum_block_hash: sibling_ethereum_hash,
+				transaction_count: 1,
+				log_count: 0,
+				gas_used: Some(U256::from(21_000)),
+				timestamp: Some(1_700_000_000_000),
+				is_canon: true,
+			})
+		);
+	}
+
+	#[test]
+	fn test_query_should_be_generated_correctly() {
+		use sqlx::Execute;
+
+		let from_block: u64 = 100;
+		let to_block: u64 = 500;
+		let addresses: Vec<H160> = vec![
+			H160::repeat_byte(0x01),
+			H160::repeat_byte(0x02),
+			H160::repeat_byte(0x03),
 		];
-		let mut builder = QueryBuilder::new(
-			"INSERT INTO blocks(
-				block_number,
-				ethereum_block_hash,
-				substrate_block_hash,
-				ethereum_storage_schema,
-				is_canon
-			)",
+		let topics = [
+			hashset![
+				H256::repeat_byte(0x01),
+				H256::repeat_byte(0x02),
+				H256::repeat_byte(0x03),
+			],
+			hashset![H256::repeat_byte(0x04), H256::repeat_byte(0x05),],
+			hashset![],
+			hashset![H256::repeat_byte(0x06)],
+		];
+
+		let expected_query_sql = "
+SELECT
+	l.substrate_block_hash,
+	b.ethereum_block_hash,
+	b.block_number,
+	b.ethereum_storage_schema,
+	l.transaction_index,
+	l.log_index,
+	l.topic_1,
+	l.topic_2,
+	l.topic_3,
+	l.topic_4
+FROM logs AS l
+INNER JOIN blocks AS b
+ON (b.block_number BETWEEN ? AND ?) AND b.substrate_block_hash = l.substrate_block_hash AND b.is_canon = 1
+WHERE 1 AND l.address IN (?, ?, ?) AND l.topic_1 IN (?, ?, ?) AND l.topic_2 IN (?, ?) AND l.topic_4 = ?
+ORDER BY b.block_number ASC, l.transaction_index ASC, l.log_index ASC
+LIMIT ?";
+
+		let mut qb = QueryBuilder::new("");
+		let actual_query_sql = build_query(
+			&mut qb,
+			from_block,
+			to_block,
+			&addresses,
+			&topics,
+			None,
+			10001,
+			LogColumnEncoding::Bytes,
+		)
+		.sql();
+		assert_eq!(expected_query_sql, actual_query_sql);
+	}
+
+	#[tokio::test]
+	async fn chunked_fetch_returns_complete_result() {
+		let TestData {
+			backend,
+			log_1_abcd_0_0_alice,
+			log_1_dcba_1_0_alice,
+			log_1_badc_2_0_alice,
+			log_2_abcd_0_0_bob,
+			log_2_dcba_1_0_bob,
+			log_2_badc_2_0_bob,
+			log_3_abcd_0_0_bob,
+			log_3_dcba_1_0_bob,
+			log_3_badc_2_0_bob,
+			..
+		} = prepare().await;
+		let backend = backend.with_log_fetch_chunk_size(NonZeroU32::new(2).unwrap());
+		let filter = TestFilter {
+			from_block: 0,
+			to_block: 3,
+			addresses: vec![],
+			topics: vec![],
+			expected_result: vec![
+				log_1_abcd_0_0_alice.into(),
+				log_1_dcba_1_0_alice.into(),
+				log_1_badc_2_0_alice.into(),
+				log_2_abcd_0_0_bob.into(),
+				log_2_dcba_1_0_bob.into(),
+				log_2_badc_2_0_bob.into(),
+				log_3_abcd_0_0_bob.into(),
+				log_3_dcba_1_0_bob.into(),
+				log_3_badc_2_0_bob.into(),
+			],
+		};
+		let result = run_test_case(backend, &filter).await.expect("must succeed");
+		assert_eq!(result, filter.expected_result);
+	}
+
+	#[tokio::test]
+	async fn filter_logs_stops_at_the_limit() {
+		let TestData {
+			backend,
+			log_1_abcd_0_0_alice,
+			log_1_dcba_1_0_alice,
+			log_1_badc_2_0_alice,
+			log_2_abcd_0_0_bob,
+			..
+		} = prepare().await;
+		let backend = backend.with_log_fetch_chunk_size(NonZeroU32::new(3).unwrap());
+		// A range wider than the index, the limit cutting the second chunk short.
+		let result = backend
+			.log_indexer()
+			.filter_logs(0, 1_000_000, vec![], vec![], 4)
+			.await
+			.expect("must succeed");
+		assert_eq!(
+			result,
+			vec![
+				log_1_abcd_0_0_alice.into(),
+				log_1_dcba_1_0_alice.into(),
+				log_1_badc_2_0_alice.into(),
+				log_2_abcd_0_0_bob.into(),
+			]
 		);
-		builder.push_values(block_entries, |mut b, entry| {
-			let block_number = entry.0;
-			let ethereum_block_hash = entry.1.as_bytes().to_owned();
-			let substrate_block_hash = entry.2.as_bytes().to_owned();
-			let ethereum_storage_schema = entry.3.encode();
+		let result = backend
+			.log_indexer()
+			.filter_logs(0, 1_000_000, vec![], vec![], 0)
+			.await
+			.expect("must succeed");
+		assert_eq!(result, vec![]);
+	}
+
+	#[tokio::test]
+	async fn chunked_fetch_holds_at_most_a_chunk_of_rows() {
+		let TestData { backend, .. } = prepare().await;
+		let backend = backend.with_log_fetch_chunk_size(NonZeroU32::new(2).unwrap());
+		assert_eq!(backend.log_fetch_peak(), 0);
+		let result = backend
+			.log_indexer()
+			.filter_logs(0, 1_000_000, vec![], vec![], 10_000)
+			.await
+			.expect("must succeed");
+		// All nine indexed logs come back, never more than two rows read at once.
+		assert_eq!(result.len(), 9);
+		assert_eq!(backend.log_fetch_peak(), 2);
+	}
+
+	fn legacy_transaction(nonce: u64) -> ethereum::TransactionV2 {
+		ethereum::TransactionV2::Legacy(ethereum::LegacyTransaction {
+			nonce: nonce.into(),
+			gas_price: 1.into(),
+			gas_limit: 21_000.into(),
+			action: ethereum::TransactionAction::Call(H160::repeat_byte(0x02)),
+			value: 0.into(),
+			input: vec![],
+			signature: ethereum::TransactionSignature::new(
+				38,
+				H256::repeat_byte(0x01),
+				H256::repeat_byte(0x01),
+			)
+			.expect("valid signature"),
+		})
+	}
+
+	fn legacy_receipt(used_gas: u64) -> ethereum::ReceiptV3 {
+		ethereum::ReceiptV3::Legacy(ethereum::EIP658ReceiptData {
+			status_code: 1,
+			used_gas: used_gas.into(),
+			logs_bloom: Default::default(),
+			logs: vec![],
+		})
+	}
+
+	#[test]
+	fn decoded_transactions_are_paired_with_receipts_by_index() {
+		let transactions = vec![legacy_transaction(0), legacy_transaction(1)];
+		let receipts = vec![legacy_receipt(21_000), legacy_receipt(42_000)];
+
+		let senders = vec![Some(H160::repeat_byte(0x01)), None];
+
+		let paired = pair_transactions_with_receipts(
+			transactions.clone(),
+			receipts.clone(),
+			senders.clone(),
+		);
+
+		assert_eq!(paired.len(), 2);
+		for (i, indexed) in paired.iter().enumerate() {
+			assert_eq!(indexed.transaction_hash, transactions[i].hash());
+			assert_eq!(indexed.transaction_index, i as i64);
+			assert_eq!(indexed.transaction, transactions[i]);
+			assert_eq!(indexed.receipt.as_ref(), Some(&receipts[i]));
+			assert_eq!(indexed.sender, senders[i]);
+		}
+	}
+
+	#[tokio::test]
+	async fn malformed_signature_policy_is_applied() {
+		let transaction = legacy_transaction(0);
+		let metadata = |sender: Option<H160>| BlockMetadata {
+			substrate_block_hash: H256::repeat_byte(0x42),
+			block_number: 4,
+			post_hashes: Hashes {
+				block_hash: H256::repeat_byte(0x43),
+				transaction_hashes: vec![transaction.hash()],
+			},
+			schema: EthereumStorageSchema::V3,
+			is_canon: 1,
+			transactions: Some(pair_transactions_with_receipts(
+				vec![transaction.clone()],
+				vec![],
+				vec![sender],
+			)),
+			header: None,
+			author: None,
+		};
+		let recovered = metadata(Some(H160::repeat_byte(0x01)));
+		let malformed = metadata(None);
+
+		for policy in [
+			MalformedSignaturePolicy::Skip,
+			MalformedSignaturePolicy::DeadLetter,
+			MalformedSignaturePolicy::Halt,
+		] {
+			let backend = prepare()
+				.await
+				.backend
+				.with_malformed_signature_policy(policy);
+			assert!(backend.check_signatures(&recovered).await.unwrap());
+
+			let checked = backend.check_signatures(&malformed).await;
+			let dead_letters = backend.dead_letter_blocks().await.unwrap();
+			match policy {
+				MalformedSignaturePolicy::Skip => {
+					assert!(checked.unwrap());
+					assert!(dead_letters.is_empty());
+					assert!(backend.fatal_error().is_none());
+				}
+				MalformedSignaturePolicy::DeadLetter => {
+					assert!(!checked.unwrap());
+					assert_eq!(dead_letters.len(), 1);
+					assert_eq!(dead_letters[0].0, malformed.substrate_block_hash);
+					assert!(dead_letters[0]
+						.1
+						.contains(&format!("{:?}", transaction.hash())));
+					// The sync worker does not retry the block.
+					assert_eq!(
+						backend
+							.get_unindexed_blocks(&[malformed.substrate_block_hash])
+							.await
+							.unwrap(),
+						vec![]
+					);
+					assert!(backend.fatal_error().is_none());
+				}
+				MalformedSignaturePolicy::Halt => {
+					assert!(matches!(checked, Err(Error::Protocol(_))));
+					assert!(dead_letters.is_empty());
+					assert!(backend.fatal_error().is_some());
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn decoded_transactions_without_receipts_are_kept() {
+		let transactions = vec![legacy_transaction(0), legacy_transaction(1)];
+
+		let paired =
+			pair_transactions_with_receipts(transactions, vec![legacy_receipt(21_000)], vec![]);
+
+		assert_eq!(paired.len(), 2);
+		assert!(paired[0].receipt.is_some());
+		assert!(paired[1].receipt.is_none());
+	}
+
+	#[tokio::test]
+	async fn decoded_transactions_are_indexed_over_the_digest_hashes() {
+		let backend = prepare().await.backend;
+		let transactions = vec![legacy_transaction(0), legacy_transaction(1)];
+		let sender = H160::repeat_byte(0x01);
+		let metadata = BlockMetadata {
+			substrate_block_hash: H256::repeat_byte(0x42),
+			block_number: 4,
+			post_hashes: Hashes {
+				block_hash: H256::repeat_byte(0x43),
+				// A stale digest, the decoded extrinsics win.
+				transaction_hashes: vec![H256::repeat_byte(0x99)],
+			},
+			schema: EthereumStorageSchema::V3,
+			is_canon: 1,
+			transactions: Some(pair_transactions_with_receipts(
+				transactions.clone(),
+				vec![legacy_receipt(21_000), legacy_receipt(42_000)],
+				vec![Some(sender), Some(sender)],
+			)),
+			header: None,
+			author: None,
+		};
+		backend
+			.write_block_metadata(&metadata)
+			.await
+			.expect("write must succeed");
+
+		let receipts = backend
+			.block_receipts(metadata.substrate_block_hash)
+			.await
+			.expect("query must succeed");
+		assert_eq!(receipts.len(), 2);
+		for (i, receipt) in receipts.iter().enumerate() {
+			assert_eq!(receipt.transaction_hash, transactions[i].hash());
+			assert_eq!(receipt.transaction_index, i as u32);
+			assert_eq!(receipt.sender, Some(sender));
+			assert_eq!(receipt.gas_used, Some(U256::from(21_000)));
+			assert_eq!(receipt.status_code, Some(1));
+		}
+	}
+
+	#[tokio::test]
+	async fn write_rate_limiter_bounds_throughput() {
+		let ops_per_sec = 20;
+		let limiter = RateLimiter::new(NonZeroU32::new(ops_per_sec).unwrap());
+
+		// The bucket starts full, so the first `ops_per_sec` writes go through immediately
+		// and the remaining ones are spread at the configured rate.
+		let start = Instant::now();
+		for _ in 0..(ops_per_sec * 2) {
+			limiter.acquire().await;
+		}
+		let elapsed = start.elapsed();
+
+		assert!(elapsed >= Duration::from_millis(900), "elapsed {elapsed:?}");
+	}
+
+	#[test]
+	fn throughput_is_averaged_over_the_window() {
+		let mut rate = RollingRate::new(Duration::from_secs(10));
+		let start = Instant::now();
+
+		// 100 blocks evenly spread over the 10 seconds window.
+		for i in 0..100u64 {
+			rate.record(start + Duration::from_millis(i * 100), 1);
+		}
+		let now = start + Duration::from_millis(9_950);
+		assert!(
+			(rate.per_sec(now) - 10.0).abs() < 0.5,
+			"{}",
+			rate.per_sec(now)
+		);
+
+		// Half of them aged out of the window.
+		let now = start + Duration::from_millis(14_950);
+		assert!(
+			(rate.per_sec(now) - 5.0).abs() < 0.5,
+			"{}",
+			rate.per_sec(now)
+		);
+
+		let now = start + Duration::from_secs(30);
+		assert_eq!(rate.per_sec(now), 0.0);
+	}
+
+	#[tokio::test]
+	async fn state_read_limiter_does_not_block_queries() {
+		let TestData {
+			backend,
+			substrate_hash_1,
+			..
+		} = prepare().await;
+		let reads_per_sec = 10;
+		let backend = backend.with_max_state_reads_per_sec(NonZeroU32::new(reads_per_sec).unwrap());
+
+		let throttled = backend.indexer_storage_override();
+		let start = Instant::now();
+		let reads = tokio::task::spawn_blocking(move || {
+			for _ in 0..(reads_per_sec * 2) {
+				throttled.current_block(substrate_hash_1);
+			}
+			start.elapsed()
+		});
+
+		// Queries served to the RPC are not subject to the indexer's state read cap.
+		let query_start = Instant::now();
+		assert_eq!(
+			backend.block_number(substrate_hash_1).await.unwrap(),
+			Some(1)
+		);
+		assert!(query_start.elapsed() < Duration::from_millis(500));
+
+		let elapsed = reads.await.unwrap();
+		assert!(elapsed >= Duration::from_millis(900), "elapsed {elapsed:?}");
+	}
+
+	#[tokio::test]
+	async fn ethereum_header_roundtrip_works() {
+		let TestData {
+			backend,
+			substrate_hash_1,
+			..
+		} = prepare().await;
+		let partial_header = ethereum::PartialHeader {
+			parent_hash: H256::repeat_byte(0x11),
+			beneficiary: H160::repeat_byte(0x12),
+			state_root: H256::repeat_byte(0x13),
+			receipts_root: H256::repeat_byte(0x14),
+			logs_bloom: ethereum_types::Bloom::repeat_byte(0x15),
+			difficulty: U256::from(16),
+			number: U256::from(1),
+			gas_limit: U256::from(75_000_000),
+			gas_used: U256::from(21_000),
+			timestamp: 1_700_000_000_000,
+			extra_data: vec![0x17; 32],
+			mix_hash: H256::repeat_byte(0x18),
+			nonce: H64::repeat_byte(0x19),
+		};
+		let header = ethereum::Header::new(
+			partial_header,
+			H256::repeat_byte(0x1a),
+			H256::repeat_byte(0x1b),
+		);
+
+		assert_eq!(
+			backend
+				.ethereum_header(substrate_hash_1)
+				.await
+				.expect("query must succeed"),
+			None
+		);
+
+		let mut conn = backend.pool().acquire().await.expect("connection");
+		Backend::<OpaqueBlock>::insert_ethereum_header(&mut conn, substrate_hash_1, &header)
+			.await
+			.expect("insert must succeed");
+
+		let stored = backend
+			.ethereum_header(substrate_hash_1)
+			.await
+			.expect("query must succeed")
+			.expect("header must be stored");
+		assert_eq!(stored, header);
+		assert_eq!(stored.hash(), header.hash());
+	}
+
+	#[tokio::test]
+	async fn block_receipts_are_assembled_in_transaction_order() {
+		let TestData {
+			backend,
+			alice,
+			bob,
+			topics_a,
+			topics_b,
+			topics_c,
+			topics_d,
+			substrate_hash_1,
+			ethereum_hash_1,
+			..
+		} = prepare().await;
+		let transactions = [
+			legacy_transaction(0),
+			legacy_transaction(1),
+			legacy_transaction(2),
+		];
+		let cumulative_gas_used = [21_000u64, 50_000, 71_000];
+		let status_codes = [1u8, 0, 1];
+
+		// Insert the transactions out of order, the last one emitting a log.
+		let mut conn = backend.pool().acquire().await.expect("connection");
+		for i in (0..transactions.len()).rev() {
+			Backend::<OpaqueBlock>::insert_transaction(
+				&mut conn,
+				substrate_hash_1,
+				ethereum_hash_1,
+				i as i64,
+				transactions[i].hash(),
+				Some(&transactions[i]),
+				Some(alice),
+				Some(cumulative_gas_used[i].into()),
+				Some(status_codes[i]),
+			)
+			.await
+			.expect("insert must succeed");
+		}
+		backend
+			.insert_logs(
+				&mut conn,
+				vec![super::Log {
+					transaction_index: 2,
+					..indexed_log(bob, &[topics_c], 0, substrate_hash_1)
+				}],
+			)
+			.await
+			.expect("insert must succeed");
+
+		let receipts = backend.block_receipts(substrate_hash_1).await.unwrap();
+		assert_eq!(
+			receipts
+				.iter()
+				.map(|receipt| (
+					receipt.transaction_hash,
+					receipt.transaction_index,
+					receipt.sender,
+					receipt.cumulative_gas_used,
+					receipt.gas_used,
+				))
+				.collect::<Vec<_>>(),
+			vec![
+				(
+					transactions[0].hash(),
+					0,
+					Some(alice),
+					Some(21_000.into()),
+					Some(21_000.into())
+				),
+				(
+					transactions[1].hash(),
+					1,
+					Some(alice),
+					Some(50_000.into()),
+					Some(29_000.into())
+				),
+				(
+					transactions[2].hash(),
+					2,
+					Some(alice),
+					Some(71_000.into()),
+					Some(21_000.into())
+				),
+			]
+		);
+		assert_eq!(
+			receipts
+				.iter()
+				.map(|receipt| receipt.status_code)
+				.collect::<Vec<_>>(),
+			vec![Some(1), Some(0), Some(1)]
+		);
+		let log_topics = |receipt: &Receipt| {
+			receipt
+				.logs
+				.iter()
+				.map(|log| (log.log_index, log.address, log.topics.clone()))
+				.collect::<Vec<_>>()
+		};
+		assert_eq!(
+			log_topics(&receipts[0]),
+			vec![
+				(0, alice, vec![topics_a, topics_b, topics_c, topics_d]),
+				(1, alice, vec![topics_d, topics_c, topics_b, topics_a]),
+				(2, alice, vec![topics_b, topics_a, topics_d, topics_c]),
+			]
+		);
+		assert!(receipts[1].logs.is_empty());
+		assert_eq!(log_topics(&receipts[2]), vec![(0, bob, vec![topics_c])]);
+
+		assert!(backend
+			.block_receipts(H256::repeat_byte(0xff))
+			.await
+			.unwrap()
+			.is_empty());
+	}
 
-			b.push_bind(block_number);
-			b.push_bind(ethereum_block_hash);
-			b.push_bind(substrate_block_hash);
-			b.push_bind(ethereum_storage_schema);
-			b.push_bind(1i32);
+	#[tokio::test]
+	async fn transaction_fees_are_stored_for_eip1559_only() {
+		let TestData {
+			backend,
+			substrate_hash_1,
+			ethereum_hash_1,
+			..
+		} = prepare().await;
+		let signature = ethereum::TransactionSignature::new(
+			38,
+			H256::repeat_byte(0x01),
+			H256::repeat_byte(0x01),
+		)
+		.expect("valid signature");
+		let legacy = legacy_transaction(0);
+		let eip2930 = ethereum::TransactionV2::EIP2930(ethereum::EIP2930Transaction {
+			chain_id: 42,
+			nonce: 1.into(),
+			gas_price: 1.into(),
+			gas_limit: 21_000.into(),
+			action: ethereum::TransactionAction::Call(H160::repeat_byte(0x02)),
+			value: 0.into(),
+			input: vec![],
+			access_list: vec![],
+			odd_y_parity: false,
+			r: *signature.r(),
+			s: *signature.s(),
 		});
-		let query = builder.build();
-		let _ = query
-			.execute(indexer_backend.pool())
+		let eip1559 = ethereum::TransactionV2::EIP1559(ethereum::EIP1559Transaction {
+			chain_id: 42,
+			nonce: 2.into(),
+			max_priority_fee_per_gas: 2.into(),
+			max_fee_per_gas: 100.into(),
+			gas_limit: 21_000.into(),
+			action: ethereum::TransactionAction::Call(H160::repeat_byte(0x02)),
+			value: 0.into(),
+			input: vec![],
+			access_list: vec![],
+			odd_y_parity: false,
+			r: *signature.r(),
+			s: *signature.s(),
+		});
+
+		let mut conn = backend.pool().acquire().await.expect("connection");
+		for (i, transaction) in [&legacy, &eip2930, &eip1559].into_iter().enumerate() {
+			Backend::<OpaqueBlock>::insert_transaction(
+				&mut conn,
+				substrate_hash_1,
+				ethereum_hash_1,
+				i as i64,
+				transaction.hash(),
+				Some(transaction),
+				None,
+				None,
+				None,
+			)
 			.await
-			.expect("insert should succeed");
+			.expect("insert must succeed");
+		}
 
-		// log_{BLOCK}_{TOPICS}_{LOG_INDEX}_{TX_INDEX}
-		let log_1_abcd_0_0_alice = Log {
-			block_number: 1,
-			address: alice,
-			topics: [topics_a, topics_b, topics_c, topics_d],
-			log_index: 0,
-			transaction_index: 0,
-			substrate_block_hash: substrate_hash_1,
-			ethereum_block_hash: ethereum_hash_1,
-		};
-		let log_1_dcba_1_0_alice = Log {
-			block_number: 1,
-			address: alice,
-			topics: [topics_d, topics_c, topics_b, topics_a],
-			log_index: 1,
-			transaction_index: 0,
-			substrate_block_hash: substrate_hash_1,
-			ethereum_block_hash: ethereum_hash_1,
-		};
-		let log_1_badc_2_0_alice = Log {
-			block_number: 1,
-			address: alice,
-			topics: [topics_b, topics_a, topics_d, topics_c],
-			log_index: 2,
-			transaction_index: 0,
-			substrate_block_hash: substrate_hash_1,
-			ethereum_block_hash: ethereum_hash_1,
-		};
-		let log_2_abcd_0_0_bob = Log {
-			block_number: 2,
-			address: bob,
-			topics: [topics_a, topics_b, topics_c, topics_d],
-			log_index: 0,
-			transaction_index: 0,
-			substrate_block_hash: substrate_hash_2,
-			ethereum_block_hash: ethereum_hash_2,
-		};
-		let log_2_dcba_1_0_bob = Log {
-			block_number: 2,
-			address: bob,
-			topics: [topics_d, topics_c, topics_b, topics_a],
-			log_index: 1,
-			transaction_index: 0,
-			substrate_block_hash: substrate_hash_2,
-			ethereum_block_hash: ethereum_hash_2,
-		};
-		let log_2_badc_2_0_bob = Log {
-			block_number: 2,
-			address: bob,
-			topics: [topics_b, topics_a, topics_d, topics_c],
-			log_index: 2,
-			transaction_index: 0,
-			substrate_block_hash: substrate_hash_2,
-			ethereum_block_hash: ethereum_hash_2,
-		};
+		for transaction in [&legacy, &eip2930] {
+			assert_eq!(
+				backend.transaction_fees(transaction.hash()).await.unwrap(),
+				Some(TransactionFees::default())
+			);
+		}
+		assert_eq!(
+			backend.transaction_fees(eip1559.hash()).await.unwrap(),
+			Some(TransactionFees {
+				max_fee_per_gas: Some(100.into()),
+				max_priority_fee_per_gas: Some(2.into()),
+			})
+		);
+		assert_eq!(
+			backend
+				.transaction_fees(H256::repeat_byte(0xff))
+				.await
+				.unwrap(),
+			None
+		);
+	}
 
-		let log_3_abcd_0_0_bob = Log {
-			block_number: 3,
-			address: bob,
-			topics: [topics_a, topics_b, topics_c, topics_d],
-			log_index: 0,
-			transaction_index: 0,
-			substrate_block_hash: substrate_hash_3,
-			ethereum_block_hash: ethereum_hash_3,
+	#[tokio::test]
+	async fn transaction_signature_components_are_indexed() {
+		let TestData {
+			backend,
+			substrate_hash_1,
+			ethereum_hash_1,
+			..
+		} = prepare().await;
+		let secret = libsecp256k1::SecretKey::parse(&[0x11; 32]).unwrap();
+		let sign = |hash: H256| {
+			let message = libsecp256k1::Message::parse_slice(hash.as_bytes()).unwrap();
+			let (signature, recid) = libsecp256k1::sign(&message, &secret);
+			let rs = signature.serialize();
+			(
+				H256::from_slice(&rs[0..32]),
+				H256::from_slice(&rs[32..64]),
+				recid.serialize(),
+			)
 		};
-		let log_3_dcba_1_0_bob = Log {
-			block_number: 3,
-			address: bob,
-			topics: [topics_d, topics_c, topics_b, topics_a],
-			log_index: 1,
-			transaction_index: 0,
-			substrate_block_hash: substrate_hash_3,
-			ethereum_block_hash: ethereum_hash_3,
+
+		let legacy_message = ethereum::LegacyTransactionMessage {
+			nonce: 0.into(),
+			gas_price: 1.into(),
+			gas_limit: 21_000.into(),
+			action: ethereum::TransactionAction::Call(H160::repeat_byte(0x02)),
+			value: 0.into(),
+			input: vec![],
+			chain_id: Some(42),
 		};
-		let log_3_badc_2_0_bob = Log {
-			block_number: 3,
-			address: bob,
-			topics: [topics_b, topics_a, topics_d, topics_c],
-			log_index: 2,
-			transaction_index: 0,
-			substrate_block_hash: substrate_hash_3,
-			ethereum_block_hash: ethereum_hash_3,
+		let (legacy_r, legacy_s, legacy_recid) = sign(legacy_message.hash());
+		let legacy_v = legacy_recid as u64 + 42 * 2 + 35;
+		let legacy = ethereum::TransactionV2::Legacy(ethereum::LegacyTransaction {
+			nonce: legacy_message.nonce,
+			gas_price: legacy_message.gas_price,
+			gas_limit: legacy_message.gas_limit,
+			action: legacy_message.action,
+			value: legacy_message.value,
+			input: legacy_message.input,
+			signature: ethereum::TransactionSignature::new(legacy_v, legacy_r, legacy_s)
+				.expect("valid signature"),
+		});
+
+		let eip1559_message = ethereum::EIP1559TransactionMessage {
+			chain_id: 42,
+			nonce: 1.into(),
+			max_priority_fee_per_gas: 2.into(),
+			max_fee_per_gas: 100.into(),
+			gas_limit: 21_000.into(),
+			action: ethereum::TransactionAction::Call(H160::repeat_byte(0x02)),
+			value: 0.into(),
+			input: vec![],
+			access_list: vec![],
 		};
+		let (eip1559_r, eip1559_s, eip1559_recid) = sign(eip1559_message.hash());
+		let eip1559 = ethereum::TransactionV2::EIP1559(ethereum::EIP1559Transaction {
+			chain_id: eip1559_message.chain_id,
+			nonce: eip1559_message.nonce,
+			max_priority_fee_per_gas: eip1559_message.max_priority_fee_per_gas,
+			max_fee_per_gas: eip1559_message.max_fee_per_gas,
+			gas_limit: eip1559_message.gas_limit,
+			action: eip1559_message.action,
+			value: eip1559_message.value,
+			input: eip1559_message.input,
+			access_list: eip1559_message.access_list,
+			odd_y_parity: eip1559_recid == 1,
+			r: eip1559_r,
+			s: eip1559_s,
+		});
 
-		let log_entries = vec![
-			// Block 1
-			log_1_abcd_0_0_alice.clone(),
-			log_1_dcba_1_0_alice.clone(),
-			log_1_badc_2_0_alice.clone(),
-			// Block 2
-			log_2_abcd_0_0_bob.clone(),
-			log_2_dcba_1_0_bob.clone(),
-			log_2_badc_2_0_bob.clone(),
-			// Block 3
-			log_3_abcd_0_0_bob.clone(),
-			log_3_dcba_1_0_bob.clone(),
-			log_3_badc_2_0_bob.clone(),
-		];
+		let mut conn = backend.pool().acquire().await.expect("connection");
+		for (i, transaction) in [&legacy, &eip1559].into_iter().enumerate() {
+			Backend::<OpaqueBlock>::insert_transaction(
+				&mut conn,
+				substrate_hash_1,
+				ethereum_hash_1,
+				i as i64,
+				transaction.hash(),
+				Some(transaction),
+				None,
+				None,
+				None,
+			)
+			.await
+			.expect("insert must succeed");
+		}
 
-		let mut builder: QueryBuilder<sqlx::Sqlite> = QueryBuilder::new(
-			"INSERT INTO logs(
-				address,
-				topic_1,
-				topic_2,
-				topic_3,
-				topic_4,
-				log_index,
-				transaction_index,
-				substrate_block_hash
-			)",
+		assert_eq!(
+			backend.transaction_signature(legacy.hash()).await.unwrap(),
+			Some(TransactionSignatureComponents {
+				v: Some(legacy_v),
+				y_parity: None,
+				r: legacy_r,
+				s: legacy_s,
+			})
 		);
-		builder.push_values(log_entries, |mut b, entry| {
-			let address = entry.address.as_bytes().to_owned();
-			let topic_1 = entry.topics[0].as_bytes().to_owned();
-			let topic_2 = entry.topics[1].as_bytes().to_owned();
-			let topic_3 = entry.topics[2].as_bytes().to_owned();
-			let topic_4 = entry.topics[3].as_bytes().to_owned();
-			let log_index = entry.log_index;
-			let transaction_index = entry.transaction_index;
-			let substrate_block_hash = entry.substrate_block_hash.as_bytes().to_owned();
+		assert_eq!(
+			backend.transaction_signature(eip1559.hash()).await.unwrap(),
+			Some(TransactionSignatureComponents {
+				v: None,
+				y_parity: Some(eip1559_recid == 1),
+				r: eip1559_r,
+				s: eip1559_s,
+			})
+		);
+		assert_eq!(
+			backend
+				.transaction_signature(H256::repeat_byte(0xff))
+				.await
+				.unwrap(),
+			None
+		);
+	}
 
-			b.push_bind(address);
-			b.push_bind(topic_1);
-			b.push_bind(topic_2);
-			b.push_bind(topic_3);
-			b.push_bind(topic_4);
-			b.push_bind(log_index);
-			b.push_bind(transaction_index);
-			b.push_bind(substrate_block_hash);
-		});
-		let query = builder.build();
-		let _ = query.execute(indexer_backend.pool()).await;
+	#[tokio::test]
+	async fn last_finalized_is_overwritten() {
+		let TestData {
+			backend,
+			substrate_hash_1,
+			substrate_hash_2,
+			..
+		} = prepare().await;
+
+		assert_eq!(backend.last_finalized().await.unwrap(), None);
+		backend.set_last_finalized(substrate_hash_1).await.unwrap();
+		assert_eq!(
+			backend.last_finalized().await.unwrap(),
+			Some(substrate_hash_1)
+		);
+		backend.set_last_finalized(substrate_hash_2).await.unwrap();
+		assert_eq!(
+			backend.last_finalized().await.unwrap(),
+			Some(substrate_hash_2)
+		);
+	}
+
+	#[tokio::test]
+	async fn backfill_progress_is_kept_per_descent() {
+		let TestData {
+			backend,
+			substrate_hash_1,
+			substrate_hash_2,
+			..
+		} = prepare().await;
+
+		backend
+			.set_backfill_progress(substrate_hash_1, 6)
+			.await
+			.unwrap();
+		backend
+			.set_backfill_progress(substrate_hash_2, 9)
+			.await
+			.unwrap();
+		backend
+			.set_backfill_progress(substrate_hash_1, 5)
+			.await
+			.unwrap();
+		assert_eq!(
+			backend.backfill_progress().await.unwrap(),
+			vec![(substrate_hash_2, 9), (substrate_hash_1, 5)]
+		);
+		backend
+			.clear_backfill_progress(substrate_hash_2)
+			.await
+			.unwrap();
+		assert_eq!(
+			backend.backfill_progress().await.unwrap(),
+			vec![(substrate_hash_1, 5)]
+		);
+	}
+
+	#[tokio::test]
+	async fn negative_indexes_are_rejected() {
+		let TestData {
+			backend,
+			alice,
+			substrate_hash_1,
+			ethereum_hash_1,
+			..
+		} = prepare().await;
+		let mut conn = backend.pool().acquire().await.expect("connection");
+
+		let result = backend
+			.insert_logs(
+				&mut conn,
+				vec![indexed_log(alice, &[], -1, substrate_hash_1)],
+			)
+			.await;
+		assert!(matches!(result, Err(Error::Protocol(_))));
+
+		let transaction = legacy_transaction(0);
+		let result = Backend::<OpaqueBlock>::insert_transaction(
+			&mut conn,
+			substrate_hash_1,
+			ethereum_hash_1,
+			-1,
+			transaction.hash(),
+			Some(&transaction),
+			None,
+			None,
+			None,
+		)
+		.await;
+		assert!(matches!(result, Err(Error::Protocol(_))));
+		assert_eq!(
+			backend.transaction_fees(transaction.hash()).await.unwrap(),
+			None
+		);
+	}
+
+	#[tokio::test]
+	async fn reincluded_transaction_follows_the_canonical_block() {
+		let TestData {
+			backend,
+			substrate_hash_3,
+			ethereum_hash_3,
+			..
+		} = prepare().await;
+		let transaction_hash = H256::repeat_byte(0x42);
+
+		// A sibling of block 3, not canonical yet.
+		let sibling_substrate_hash = H256::repeat_byte(0x0b);
+		let sibling_ethereum_hash = H256::repeat_byte(0x0c);
+		sqlx::query(
+			"INSERT INTO blocks(
+				block_number,
+				ethereum_block_hash,
+				substrate_block_hash,
+				ethereum_storage_schema,
+				is_canon)
+			VALUES (3, ?, ?, ?, 0)",
+		)
+		.bind(sibling_ethereum_hash.as_bytes())
+		.bind(sibling_substrate_hash.as_bytes())
+		.bind(EthereumStorageSchema::V3.encode())
+		.execute(backend.pool())
+		.await
+		.expect("insert block");
+
+		// The transaction is included in both blocks, at different indexes.
+		let mut conn = backend.pool().acquire().await.expect("connection");
+		for (index, substrate_hash, ethereum_hash) in [
+			(0, substrate_hash_3, ethereum_hash_3),
+			(1, sibling_substrate_hash, sibling_ethereum_hash),
+		] {
+			Backend::<OpaqueBlock>::insert_transaction(
+				&mut conn,
+				substrate_hash,
+				ethereum_hash,
+				index,
+				transaction_hash,
+				None,
+				None,
+				None,
+				None,
+			)
+			.await
+			.expect("insert must succeed");
+		}
 
-		TestData {
-			alice,
-			bob,
-			topics_a,
-			topics_b,
-			topics_c,
-			topics_d,
+		let placement = backend
+			.canonical_transaction(transaction_hash)
+			.await
+			.unwrap()
+			.expect("canonical placement");
+		assert_eq!(placement.substrate_block_hash, substrate_hash_3);
+		assert_eq!(placement.ethereum_index, 0);
+
+		// The re-org moves the transaction to the enacted branch.
+		backend
+			.canonicalize(&[substrate_hash_3], &[sibling_substrate_hash])
+			.await
+			.expect("canonicalize");
+		let placement = backend
+			.canonical_transaction(transaction_hash)
+			.await
+			.unwrap()
+			.expect("canonical placement");
+		assert_eq!(placement.substrate_block_hash, sibling_substrate_hash);
+		assert_eq!(placement.ethereum_block_hash, sibling_ethereum_hash);
+		assert_eq!(placement.ethereum_index, 1);
+		let metadata = backend
+			.transaction_metadata(&transaction_hash)
+			.await
+			.unwrap();
+		assert_eq!(metadata.len(), 2);
+		assert_eq!(metadata[0].substrate_block_hash, sibling_substrate_hash);
+	}
+
+	#[tokio::test]
+	async fn retracted_blocks_are_pruned_once_finality_covers_them() {
+		let TestData {
+			backend,
 			substrate_hash_1,
 			substrate_hash_2,
 			substrate_hash_3,
-			ethereum_hash_1,
-			ethereum_hash_2,
 			ethereum_hash_3,
-			backend: indexer_backend,
-			log_1_abcd_0_0_alice,
-			log_1_dcba_1_0_alice,
-			log_1_badc_2_0_alice,
-			log_2_abcd_0_0_bob,
-			log_2_dcba_1_0_bob,
-			log_2_badc_2_0_bob,
-			log_3_abcd_0_0_bob,
-			log_3_dcba_1_0_bob,
-			log_3_badc_2_0_bob,
-		}
-	}
+			..
+		} = prepare().await;
+		let backend = backend.with_retracted_block_retention(2);
 
-	async fn run_test_case(
-		backend: Backend<OpaqueBlock>,
-		test_case: &TestFilter,
-	) -> Result<Vec<FilteredLog<OpaqueBlock>>, String> {
+		// Blocks 2 and 3 are retracted by a re-org.
 		backend
-			.log_indexer()
-			.filter_logs(
-				test_case.from_block,
-				test_case.to_block,
-				test_case.addresses.clone(),
-				test_case.topics.clone(),
-			)
+			.canonicalize(&[substrate_hash_2, substrate_hash_3], &[])
+			.await
+			.expect("canonicalize");
+		let mut conn = backend.pool().acquire().await.expect("connection");
+		Backend::<OpaqueBlock>::insert_transaction(
+			&mut conn,
+			substrate_hash_3,
+			ethereum_hash_3,
+			0,
+			H256::repeat_byte(0x42),
+			None,
+			None,
+			None,
+			None,
+		)
+		.await
+		.expect("insert must succeed");
+		drop(conn);
+
+		// Within the retention window of the finalized block, nothing is pruned.
+		assert_eq!(backend.prune_retracted_blocks(3).await.unwrap(), 0);
+		assert_eq!(
+			backend.block_number(substrate_hash_2).await.unwrap(),
+			Some(2)
+		);
+
+		// Finality covers block 2 only.
+		assert_eq!(backend.prune_retracted_blocks(4).await.unwrap(), 1);
+		assert_eq!(backend.block_number(substrate_hash_2).await.unwrap(), None);
+		assert_eq!(backend.log_count(substrate_hash_2).await.unwrap(), 0);
+		assert_eq!(
+			backend.block_number(substrate_hash_3).await.unwrap(),
+			Some(3)
+		);
+		assert_eq!(backend.log_count(substrate_hash_3).await.unwrap(), 3);
+
+		assert_eq!(backend.prune_retracted_blocks(5).await.unwrap(), 1);
+		assert_eq!(backend.block_number(substrate_hash_3).await.unwrap(), None);
+		assert_eq!(backend.log_count(substrate_hash_3).await.unwrap(), 0);
+		assert!(backend
+			.canonical_transaction(H256::repeat_byte(0x42))
 			.await
+			.unwrap()
+			.is_none());
+		let transactions = sqlx::query("SELECT COUNT(*) FROM transactions")
+			.fetch_one(backend.pool())
+			.await
+			.unwrap()
+			.get::<i64, _>(0);
+		assert_eq!(transactions, 0);
+
+		// The canonical block 1 is kept.
+		assert_eq!(
+			backend.block_number(substrate_hash_1).await.unwrap(),
+			Some(1)
+		);
 	}
 
-	async fn assert_blocks_canon(pool: &SqlitePool, expected: Vec<(H256, u32)>) {
-		let actual: Vec<(H256, u32)> =
-			sqlx::query("SELECT substrate_block_hash, is_canon FROM blocks")
-				.map(|row: SqliteRow| (H256::from_slice(&row.get::<Vec<u8>, _>(0)[..]), row.get(1)))
-				.fetch_all(pool)
+	#[tokio::test]
+	async fn orphan_logs_are_pruned() {
+		let TestData {
+			backend,
+			alice,
+			substrate_hash_1,
+			..
+		} = prepare().await;
+		let count_logs = || async {
+			sqlx::query("SELECT COUNT(*) FROM logs")
+				.fetch_one(backend.pool())
 				.await
-				.expect("sql query must succeed");
-		assert_eq!(expected, actual);
+				.expect("sql query must succeed")
+				.get::<i64, _>(0)
+		};
+		let valid_logs = count_logs().await;
+
+		// Seed logs referencing blocks that are not in the `blocks` table.
+		let orphan_hash = H256::repeat_byte(0xee);
+		for log_index in 0..3 {
+			sqlx::query(
+				"INSERT INTO logs(address, log_index, transaction_index, substrate_block_hash)
+				VALUES (?, ?, 0, ?)",
+			)
+			.bind(alice.as_bytes())
+			.bind(log_index)
+			.bind(orphan_hash.as_bytes())
+			.execute(backend.pool())
+			.await
+			.expect("sql query must succeed");
+		}
+		assert_eq!(count_logs().await, valid_logs + 3);
+
+		assert_eq!(backend.prune_orphan_logs().await.unwrap(), 3);
+		assert_eq!(count_logs().await, valid_logs);
+		assert!(backend.log_count(substrate_hash_1).await.unwrap() > 0);
+		// Nothing left to prune.
+		assert_eq!(backend.prune_orphan_logs().await.unwrap(), 0);
 	}
 
 	#[tokio::test]
-	async fn genesis_works() {
-		let TestData { backend, .. } = prepare().await;
+	async fn filtering_logs_fails_without_log_indexing() {
+		let tmp = tempdir().expect("create a temporary directory");
+		let path = Path::new("sqlite:///")
+			.join(tmp.path())
+			.join("test.db3")
+			.to_str()
+			.unwrap()
+			.to_string();
+		let (client, _) = TestClientBuilder::new()
+			.build_with_native_executor::<substrate_test_runtime_client::runtime::RuntimeApi, _>(
+			None,
+		);
+		let storage_override = Arc::new(SchemaV3StorageOverride::new(Arc::new(client)));
+		let open = |index_logs: bool| {
+			Backend::<OpaqueBlock>::new(
+				BackendConfig::Sqlite(SqliteBackendConfig {
+					path: &path,
+					create_if_missing: true,
+					cache_size: 20480,
+					thread_count: 4,
+					journal_mode: None,
+					synchronous: None,
+					busy_timeout: None,
+					index_logs,
+				}),
+				1,
+				None,
+				storage_override.clone(),
+			)
+		};
+		let log_indexes = |backend: Backend<OpaqueBlock>| async move {
+			sqlx::query(
+				"SELECT name FROM sqlite_master WHERE type = 'index' AND tbl_name = 'logs'
+				AND name NOT LIKE 'sqlite_autoindex_%'",
+			)
+			.map(|row: SqliteRow| row.get::<String, _>(0))
+			.fetch_all(backend.pool())
+			.await
+			.expect("sql query must succeed")
+		};
+
+		// The log indexes of a database populated with logs are dropped once.
+		let backend = open(true).await.expect("indexer pool to be created");
+		assert!(!log_indexes(backend).await.is_empty());
+		let backend = open(false).await.expect("indexer pool to be created");
+		assert!(!backend.logs_indexed());
+		assert!(log_indexes(backend.clone()).await.is_empty());
+
 		let filter = TestFilter {
 			from_block: 0,
-			to_block: 0,
+			to_block: 3,
 			addresses: vec![],
 			topics: vec![],
 			expected_result: vec![],
 		};
-		let result = run_test_case(backend, &filter).await.expect("must succeed");
-		assert_eq!(result, filter.expected_result);
+		assert_eq!(
+			run_test_case(backend, &filter).await,
+			Err(LOGS_NOT_INDEXED.to_string())
+		);
+
+		// And not built again on the next start.
+		let backend = open(false).await.expect("indexer pool to be created");
+		assert!(log_indexes(backend).await.is_empty());
 	}
 
 	#[tokio::test]
-	async fn unsanitized_input_works() {
-		let TestData { backend, .. } = prepare().await;
-		let filter = TestFilter {
-			from_block: 0,
-			to_block: 0,
-			addresses: vec![],
-			topics: vec![vec![None], vec![None, None, None]],
-			expected_result: vec![],
+	async fn read_snapshot_ignores_concurrent_inserts() {
+		let tmp = tempdir().expect("create a temporary directory");
+		let (client, _) = TestClientBuilder::new()
+			.build_with_native_executor::<substrate_test_runtime_client::runtime::RuntimeApi, _>(
+			None,
+		);
+		let storage_override = Arc::new(SchemaV3StorageOverride::new(Arc::new(client)));
+		let backend = Backend::<OpaqueBlock>::new(
+			BackendConfig::Sqlite(SqliteBackendConfig {
+				path: Path::new("sqlite:///")
+					.join(tmp.path())
+					.join("test.db3")
+					.to_str()
+					.unwrap(),
+				create_if_missing: true,
+				cache_size: 20480,
+				thread_count: 4,
+				journal_mode: None,
+				synchronous: None,
+				busy_timeout: None,
+				index_logs: true,
+			}),
+			2,
+			None,
+			storage_override,
+		)
+		.await
+		.expect("indexer pool to be created");
+
+		let substrate_hash = H256::repeat_byte(0x05);
+		let insert_logs = |log_indexes: std::ops::Range<i64>| {
+			let backend = &backend;
+			async move {
+				let mut conn = backend.pool().acquire().await.expect("connection");
+				let logs = log_indexes
+					.map(|i| indexed_log(H160::repeat_byte(0x01), &[], i, substrate_hash))
+					.collect();
+				backend
+					.insert_logs(&mut conn, logs)
+					.await
+					.expect("insert must succeed");
+			}
 		};
-		let result = run_test_case(backend, &filter).await.expect("must succeed");
-		assert_eq!(result, filter.expected_result);
+		let count = |row: SqliteRow| row.get::<i64, _>(0);
+		insert_logs(0..10).await;
+
+		let mut snapshot = backend.read_snapshot().await.expect("snapshot");
+		// The indexer keeps committing while the analytics run.
+		insert_logs(10..25).await;
+		let in_snapshot = sqlx::query("SELECT COUNT(*) FROM logs")
+			.fetch_one(&mut *snapshot)
+			.await
+			.map(count)
+			.expect("count");
+		insert_logs(25..30).await;
+		let in_snapshot_again = sqlx::query("SELECT COUNT(*) FROM logs")
+			.fetch_one(&mut *snapshot)
+			.await
+			.map(count)
+			.expect("count");
+		assert_eq!((in_snapshot, in_snapshot_again), (10, 10));
+		snapshot.rollback().await.expect("end snapshot");
+
+		let latest = sqlx::query("SELECT COUNT(*) FROM logs")
+			.fetch_one(backend.pool())
+			.await
+			.map(count)
+			.expect("count");
+		assert_eq!(latest, 30);
 	}
 
 	#[tokio::test]
-	async fn invalid_topic_input_size_fails() {
+	async fn log_column_encodings_are_equivalent() {
+		let tmp = tempdir().expect("create a temporary directory");
+		let (client, _) = TestClientBuilder::new()
+			.build_with_native_executor::<substrate_test_runtime_client::runtime::RuntimeApi, _>(
+			None,
+		);
+		let storage_override = Arc::new(SchemaV3StorageOverride::new(Arc::new(client)));
+
+		let substrate_hash = H256::repeat_byte(0x05);
+		let address = H160::repeat_byte(0x01);
+		let topic = H256::repeat_byte(0x02);
+		let mut results = vec![];
+		for encoding in [LogColumnEncoding::Bytes, LogColumnEncoding::Hex] {
+			let backend = Backend::<OpaqueBlock>::new(
+				BackendConfig::Sqlite(SqliteBackendConfig {
+					path: Path::new("sqlite:///")
+						.join(tmp.path())
+						.join(format!("{encoding:?}.db3"))
+						.to_str()
+						.unwrap(),
+					create_if_missing: true,
+					cache_size: 20480,
+					thread_count: 4,
+					journal_mode: None,
+					synchronous: None,
+					busy_timeout: None,
+					index_logs: true,
+				}),
+				1,
+				None,
+				storage_override.clone(),
+			)
+			.await
+			.expect("indexer pool to be created")
+			.with_log_column_encoding(encoding);
+
+			sqlx::query(
+				"INSERT INTO blocks(
+					block_number,
+					ethereum_block_hash,
+					substrate_block_hash,
+					ethereum_storage_schema,
+					is_canon)
+				VALUES (1, ?, ?, ?, 1)",
+			)
+			.bind(H256::repeat_byte(0x08).as_bytes())
+			.bind(substrate_hash.as_bytes())
+			.bind(EthereumStorageSchema::V3.encode())
+			.execute(backend.pool())
+			.await
+			.expect("insert block");
+			let mut conn = backend.pool().acquire().await.expect("connection");
+			backend
+				.insert_logs(
+					&mut conn,
+					vec![indexed_log(address, &[topic], 0, substrate_hash)],
+				)
+				.await
+				.expect("insert logs");
+
+			let expected_type = match encoding {
+				LogColumnEncoding::Bytes => "blob",
+				LogColumnEncoding::Hex => "text",
+			};
+			let stored_type: String = sqlx::query("SELECT typeof(topic_1) FROM logs")
+				.fetch_one(backend.pool())
+				.await
+				.expect("stored log")
+				.get(0);
+			assert_eq!(stored_type, expected_type);
+
+			let filter = TestFilter {
+				from_block: 0,
+				to_block: 1,
+				addresses: vec![address],
+				topics: vec![vec![Some(topic)]],
+				expected_result: vec![],
+			};
+			results.push(run_test_case(backend, &filter).await.expect("must succeed"));
+		}
+
+		assert_eq!(results[0].len(), 1);
+		assert_eq!(results[0], results[1]);
+	}
+
+	#[tokio::test]
+	async fn hashed_log_key_deduplicates_logs() {
 		let TestData {
-			backend, topics_a, ..
+			backend,
+			alice,
+			topics_a,
+			substrate_hash_1,
+			..
 		} = prepare().await;
-		let filter = TestFilter {
-			from_block: 0,
-			to_block: 0,
-			addresses: vec![],
-			topics: vec![
-				vec![Some(topics_a), None, None, None, None],
-				vec![Some(topics_a), None, None, None],
-			],
-			expected_result: vec![],
+		let count_logs = |pool: SqlitePool| async move {
+			sqlx::query("SELECT COUNT(*) FROM logs")
+				.fetch_one(&pool)
+				.await
+				.expect("sql query must succeed")
+				.get::<i64, _>(0)
 		};
-		run_test_case(backend, &filter)
+		let indexed_logs = count_logs(backend.pool().clone()).await;
+
+		let backend = backend
+			.with_log_key(LogKey::Hashed)
 			.await
-			.expect_err("Invalid topic input. Maximum length is 4.");
+			.expect("logs table rebuilt");
+		assert!(Backend::<OpaqueBlock>::has_hashed_log_key(backend.pool())
+			.await
+			.unwrap());
+		assert_eq!(count_logs(backend.pool().clone()).await, indexed_logs);
+		// Reopening does not rebuild again.
+		let backend = backend
+			.with_log_key(LogKey::Hashed)
+			.await
+			.expect("hashed key kept");
+
+		// Storing an already indexed log is a no-op.
+		let mut conn = backend.pool().acquire().await.expect("connection");
+		backend
+			.insert_logs(
+				&mut conn,
+				vec![indexed_log(alice, &[topics_a], 0, substrate_hash_1)],
+			)
+			.await
+			.expect("insert logs");
+		assert_eq!(count_logs(backend.pool().clone()).await, indexed_logs);
+
+		// A new log is stored once.
+		for _ in 0..2 {
+			backend
+				.insert_logs(
+					&mut conn,
+					vec![indexed_log(alice, &[topics_a], 9, substrate_hash_1)],
+				)
+				.await
+				.expect("insert logs");
+		}
+		assert_eq!(count_logs(backend.pool().clone()).await, indexed_logs + 1);
+
+		// A log whose key is taken by a different log is refused.
+		let colliding_key = hashed_log_key(substrate_hash_1.as_bytes(), 0, 10);
+		sqlx::query("UPDATE logs SET log_key = ? WHERE log_index = 9")
+			.bind(&colliding_key[..])
+			.execute(backend.pool())
+			.await
+			.expect("sql query must succeed");
+		assert!(backend
+			.insert_logs(
+				&mut conn,
+				vec![indexed_log(alice, &[topics_a], 10, substrate_hash_1)],
+			)
+			.await
+			.is_err());
+		assert_eq!(count_logs(backend.pool().clone()).await, indexed_logs + 1);
+	}
+
+	#[tokio::test]
+	async fn hashed_log_key_is_detected_on_reopen() {
+		let tmp = tempdir().expect("create a temporary directory");
+		let path = Path::new("sqlite:///")
+			.join(tmp.path())
+			.join("test.db3")
+			.to_str()
+			.unwrap()
+			.to_string();
+		let (client, _) = TestClientBuilder::new()
+			.build_with_native_executor::<substrate_test_runtime_client::runtime::RuntimeApi, _>(
+			None,
+		);
+		let storage_override = Arc::new(SchemaV3StorageOverride::new(Arc::new(client)));
+		let open = || {
+			Backend::<OpaqueBlock>::new(
+				BackendConfig::Sqlite(SqliteBackendConfig {
+					path: &path,
+					create_if_missing: true,
+					cache_size: 20480,
+					thread_count: 4,
+					journal_mode: None,
+					synchronous: None,
+					busy_timeout: None,
+					index_logs: true,
+				}),
+				1,
+				None,
+				storage_override.clone(),
+			)
+		};
+
+		let backend = open().await.expect("indexer pool to be created");
+		assert_eq!(backend.log_key, LogKey::Composite);
+		backend
+			.with_log_key(LogKey::Hashed)
+			.await
+			.expect("logs table rebuilt");
+
+		// The key is detected without being set again, and cannot be switched back.
+		let backend = open().await.expect("indexer pool to be created");
+		assert_eq!(backend.log_key, LogKey::Hashed);
+		let backend = backend
+			.with_log_key(LogKey::Composite)
+			.await
+			.expect("hashed key kept");
+		assert_eq!(backend.log_key, LogKey::Hashed);
+
+		// So an already indexed log is still deduplicated.
+		let substrate_hash = H256::repeat_byte(0x01);
+		let mut conn = backend.pool().acquire().await.expect("connection");
+		for _ in 0..2 {
+			backend
+				.insert_logs(
+					&mut conn,
+					vec![indexed_log(H160::repeat_byte(0x01), &[], 0, substrate_hash)],
+				)
+				.await
+				.expect("insert logs");
+		}
+		let count: i64 = sqlx::query("SELECT COUNT(*) FROM logs")
+			.fetch_one(backend.pool())
+			.await
+			.expect("sql query must succeed")
+			.get(0);
+		assert_eq!(count, 1);
+	}
+
+	#[tokio::test]
+	async fn hashed_log_key_shrinks_the_unique_index() {
+		let tmp = tempdir().expect("create a temporary directory");
+		let (client, _) = TestClientBuilder::new()
+			.build_with_native_executor::<substrate_test_runtime_client::runtime::RuntimeApi, _>(
+			None,
+		);
+		let storage_override = Arc::new(SchemaV3StorageOverride::new(Arc::new(client)));
+
+		let mut index_sizes = vec![];
+		for log_key in [LogKey::Composite, LogKey::Hashed] {
+			let backend = Backend::<OpaqueBlock>::new(
+				BackendConfig::Sqlite(SqliteBackendConfig {
+					path: Path::new("sqlite:///")
+						.join(tmp.path())
+						.join(format!("{log_key:?}.db3"))
+						.to_str()
+						.unwrap(),
+					create_if_missing: true,
+					cache_size: 20480,
+					thread_count: 4,
+					journal_mode: None,
+					synchronous: None,
+					busy_timeout: None,
+					index_logs: true,
+				}),
+				1,
+				None,
+				storage_override.clone(),
+			)
+			.await
+			.expect("indexer pool to be created")
+			.with_log_key(log_key)
+			.await
+			.expect("log key set");
+
+			let mut conn = backend.pool().acquire().await.expect("connection");
+			for block in 0..1_000u64 {
+				let substrate_hash = H256::from_low_u64_be(block);
+				let logs = (0..10)
+					.map(|log_index| {
+						indexed_log(H160::repeat_byte(0x01), &[], log_index, substrate_hash)
+					})
+					.collect();
+				backend
+					.insert_logs(&mut conn, logs)
+					.await
+					.expect("insert logs");
+			}
+
+			let index_size: i64 = sqlx::query(
+				"SELECT SUM(pgsize) FROM dbstat WHERE name IN
+				(SELECT name FROM pragma_index_list('logs') WHERE origin = 'u')",
+			)
+			.fetch_one(backend.pool())
+			.await
+			.expect("dbstat query")
+			.get(0);
+			index_sizes.push(index_size);
+		}
+		assert!(index_sizes[1] < index_sizes[0]);
+	}
+
+	fn indexed_log(
+		address: H160,
+		topics: &[H256],
+		log_index: i64,
+		substrate_hash: H256,
+	) -> super::Log {
+		super::Log {
+			address: address.as_bytes().to_owned(),
+			topic_1: topics.first().map(|t| t.as_bytes().to_owned()),
+			topic_2: topics.get(1).map(|t| t.as_bytes().to_owned()),
+			topic_3: topics.get(2).map(|t| t.as_bytes().to_owned()),
+			topic_4: topics.get(3).map(|t| t.as_bytes().to_owned()),
+			log_index,
+			transaction_index: 0,
+			substrate_block_hash: substrate_hash.as_bytes().to_owned(),
+			topic_count: topics.len() as i32,
+		}
 	}
 
 	#[tokio::test]
-	async fn test_malformed_topic_cleans_invalid_options() {
+	async fn contract_log_retention_overrides_the_global_one() {
 		let TestData {
 			backend,
-			topics_a,
-			topics_b,
-			topics_d,
-			log_1_badc_2_0_alice,
+			alice,
+			bob,
+			substrate_hash_1,
+			substrate_hash_2,
+			substrate_hash_3,
 			..
 		} = prepare().await;
-
-		// [(a,null,b), (a, null), (d,null), null] -> [(a,b), a, d]
-		let filter = TestFilter {
-			from_block: 0,
-			to_block: 1,
-			addresses: vec![],
-			topics: vec![
-				vec![Some(topics_a), None, Some(topics_d)],
-				vec![None], // not considered
-				vec![Some(topics_b), Some(topics_a), None],
-				vec![None, None, None, None], // not considered
-			],
-			expected_result: vec![log_1_badc_2_0_alice.into()],
+		// Alice's logs are kept forever, Carol's ones for longer than the others.
+		let carol = H160::repeat_byte(0x03);
+		for substrate_hash in [substrate_hash_1, substrate_hash_2] {
+			sqlx::query(
+				"INSERT INTO logs(address, log_index, transaction_index, substrate_block_hash)
+				VALUES (?, 10, 0, ?)",
+			)
+			.bind(carol.as_bytes())
+			.bind(substrate_hash.as_bytes())
+			.execute(backend.pool())
+			.await
+			.expect("sql query must succeed");
+		}
+		let backend = backend
+			.with_log_retention(1)
+			.with_contract_log_retention(alice, None)
+			.with_contract_log_retention(carol, Some(3));
+		let count_logs = |address: H160| {
+			let backend = &backend;
+			async move {
+				sqlx::query("SELECT COUNT(*) FROM logs WHERE address = ?")
+					.bind(address.as_bytes())
+					.fetch_one(backend.pool())
+					.await
+					.expect("sql query must succeed")
+					.get::<i64, _>(0)
+			}
 		};
-		let result = run_test_case(backend, &filter).await.expect("must succeed");
-		assert_eq!(result, filter.expected_result);
+
+		// Bob's logs up to block 2 are pruned, Alice's block 1 logs are protected.
+		assert_eq!(backend.prune_logs(3).await.unwrap(), 3);
+		assert_eq!(count_logs(alice).await, 3);
+		assert_eq!(count_logs(bob).await, 3);
+		assert_eq!(count_logs(carol).await, 2);
+
+		// Carol's block 1 log falls out of its longer window along with Bob's block 3 logs.
+		assert_eq!(backend.prune_logs(4).await.unwrap(), 4);
+		assert_eq!(count_logs(alice).await, 3);
+		assert_eq!(count_logs(bob).await, 0);
+		assert_eq!(backend.log_count(substrate_hash_3).await.unwrap(), 0);
+		assert_eq!(count_logs(carol).await, 1);
 	}
 
 	#[tokio::test]
-	async fn block_range_works() {
+	async fn block_summary_is_backfilled_and_follows_pruning() {
 		let TestData {
 			backend,
-			log_1_abcd_0_0_alice,
-			log_1_dcba_1_0_alice,
-			log_1_badc_2_0_alice,
-			log_2_abcd_0_0_bob,
-			log_2_dcba_1_0_bob,
-			log_2_badc_2_0_bob,
+			substrate_hash_1,
+			substrate_hash_2,
+			substrate_hash_3,
 			..
 		} = prepare().await;
+		let log_count = |substrate_hash: H256| {
+			let backend = &backend;
+			async move {
+				backend
+					.block_summary(substrate_hash)
+					.await
+					.unwrap()
+					.map(|summary| summary.log_count)
+			}
+		};
 
-		let filter = TestFilter {
-			from_block: 0,
-			to_block: 2,
-			addresses: vec![],
-			topics: vec![],
-			expected_result: vec![
-				log_1_abcd_0_0_alice.into(),
-				log_1_dcba_1_0_alice.into(),
-				log_1_badc_2_0_alice.into(),
-				log_2_abcd_0_0_bob.into(),
-				log_2_dcba_1_0_bob.into(),
-				log_2_badc_2_0_bob.into(),
-			],
+		// The blocks indexed before the summary table existed are summarized on startup.
+		assert_eq!(log_count(substrate_hash_3).await, None);
+		Backend::<OpaqueBlock>::summarize_blocks(backend.pool())
+			.await
+			.expect("summarize blocks");
+		for substrate_hash in [substrate_hash_1, substrate_hash_2, substrate_hash_3] {
+			assert_eq!(
+				log_count(substrate_hash).await,
+				Some(backend.log_count(substrate_hash).await.unwrap())
+			);
+		}
+		assert_eq!(log_count(substrate_hash_2).await, Some(3));
+
+		// Pruning the logs of blocks 1 and 2 updates their summaries.
+		let backend = backend.with_log_retention(1);
+		assert_eq!(backend.prune_logs(3).await.unwrap(), 6);
+		assert_eq!(log_count(substrate_hash_1).await, Some(0));
+		assert_eq!(log_count(substrate_hash_2).await, Some(0));
+		assert_eq!(log_count(substrate_hash_3).await, Some(3));
+
+		// Pruning the blocks removes their summaries.
+		backend.prune_logs_below(3).await.unwrap();
+		assert_eq!(log_count(substrate_hash_2).await, None);
+		assert_eq!(log_count(substrate_hash_3).await, Some(3));
+	}
+
+	/// The metadata of a block whose n-th transaction emits n logs, each built by the returned
+	/// closure from its index.
+	fn block_with_logs(
+		alice: H160,
+		topics: Vec<H256>,
+	) -> (BlockMetadata, impl Fn(u8) -> ethereum::Log) {
+		let log = move |data: u8| ethereum::Log {
+			address: alice,
+			topics: topics.clone(),
+			data: vec![data; 40],
 		};
-		let result = run_test_case(backend, &filter).await.expect("must succeed");
-		assert_eq!(result, filter.expected_result);
+		let transactions = (0..3).map(legacy_transaction).collect::<Vec<_>>();
+		let receipts = (0..3u8)
+			.map(|i| match legacy_receipt(21_000 * (i as u64 + 1)) {
+				ethereum::ReceiptV3::Legacy(data) => {
+					ethereum::ReceiptV3::Legacy(ethereum::EIP658ReceiptData {
+						logs: (0..i).map(&log).collect(),
+						..data
+					})
+				}
+				_ => unreachable!(),
+			})
+			.collect::<Vec<_>>();
+		let header = ethereum::Header::new(
+			ethereum::PartialHeader {
+				parent_hash: H256::repeat_byte(0x11),
+				beneficiary: H160::repeat_byte(0x12),
+				state_root: H256::repeat_byte(0x13),
+				receipts_root: ethereum::util::ordered_trie_root(
+					receipts.iter().map(ethereum::EnvelopedEncodable::encode),
+				),
+				logs_bloom: Default::default(),
+				difficulty: U256::zero(),
+				number: U256::from(4),
+				gas_limit: U256::from(75_000_000),
+				gas_used: U256::from(63_000),
+				timestamp: 1_700_000_000_000,
+				extra_data: vec![],
+				mix_hash: H256::zero(),
+				nonce: H64::zero(),
+			},
+			H256::repeat_byte(0x1a),
+			H256::repeat_byte(0x1b),
+		);
+		let metadata = BlockMetadata {
+			substrate_block_hash: H256::repeat_byte(0x42),
+			block_number: 4,
+			post_hashes: Hashes {
+				block_hash: header.hash(),
+				transaction_hashes: transactions.iter().map(|t| t.hash()).collect(),
+			},
+			schema: EthereumStorageSchema::V3,
+			is_canon: 1,
+			transactions: Some(pair_transactions_with_receipts(
+				transactions,
+				receipts,
+				vec![Some(alice); 3],
+			)),
+			header: Some(header),
+			author: None,
+		};
+		(metadata, log)
 	}
 
 	#[tokio::test]
-	async fn address_filter_works() {
+	async fn log_inclusion_proofs_verify_against_the_header() {
 		let TestData {
 			backend,
 			alice,
-			log_1_abcd_0_0_alice,
-			log_1_dcba_1_0_alice,
-			log_1_badc_2_0_alice,
+			topics_a,
+			topics_b,
 			..
 		} = prepare().await;
-		let filter = TestFilter {
-			from_block: 0,
-			to_block: 3,
-			addresses: vec![alice],
-			topics: vec![],
-			expected_result: vec![
-				log_1_abcd_0_0_alice.into(),
-				log_1_dcba_1_0_alice.into(),
-				log_1_badc_2_0_alice.into(),
-			],
-		};
-		let result = run_test_case(backend, &filter).await.expect("must succeed");
-		assert_eq!(result, filter.expected_result);
+		let backend = backend.with_receipt_storage();
+		let (metadata, log) = block_with_logs(alice, vec![topics_a, topics_b]);
+		let header = metadata.header.clone().unwrap();
+		let transactions = metadata.transactions.as_ref().unwrap();
+		let transaction_hash = |i: usize| transactions[i].transaction_hash;
+		// Through the indexing path of `insert_block_metadata`.
+		backend
+			.commit_block_metadata(&metadata)
+			.await
+			.expect("write must succeed");
+
+		let proof = backend
+			.log_inclusion_proof(transaction_hash(2), 1)
+			.await
+			.expect("query must succeed")
+			.expect("log must be proven");
+		assert_eq!(proof.ethereum_block_hash, header.hash());
+		assert_eq!(proof.header, header);
+		assert_eq!(proof.verify(), Some(log(1)));
+
+		// Missing logs and transactions are not proven.
+		assert_eq!(
+			backend
+				.log_inclusion_proof(transaction_hash(2), 2)
+				.await
+				.unwrap(),
+			None
+		);
+		assert_eq!(
+			backend
+				.log_inclusion_proof(transaction_hash(0), 0)
+				.await
+				.unwrap(),
+			None
+		);
+		assert_eq!(
+			backend
+				.log_inclusion_proof(H256::repeat_byte(0x3f), 0)
+				.await
+				.unwrap(),
+			None
+		);
+
+		// A proof does not verify against another header, nor another block hash.
+		let mut tampered = proof.clone();
+		tampered.header.receipts_root = H256::repeat_byte(0x14);
+		assert_eq!(tampered.verify(), None);
+		tampered.ethereum_block_hash = tampered.header.hash();
+		assert_eq!(tampered.verify(), None);
+		let mut tampered = proof.clone();
+		tampered.ethereum_block_hash = H256::repeat_byte(0x3f);
+		assert_eq!(tampered.verify(), None);
+		// Nor for another log or transaction of the block.
+		let mut tampered = proof.clone();
+		tampered.transaction_index = 1;
+		tampered.log_index = 0;
+		assert_eq!(tampered.verify(), None);
 	}
 
 	#[tokio::test]
-	async fn topic_filter_works() {
+	async fn log_inclusion_proofs_need_receipt_storage() {
 		let TestData {
 			backend,
-			topics_d,
-			log_1_dcba_1_0_alice,
-			log_2_dcba_1_0_bob,
-			log_3_dcba_1_0_bob,
+			alice,
+			topics_a,
 			..
 		} = prepare().await;
-		let filter = TestFilter {
-			from_block: 0,
-			to_block: 3,
-			addresses: vec![],
-			topics: vec![vec![Some(topics_d)]],
-			expected_result: vec![
-				log_1_dcba_1_0_alice.into(),
-				log_2_dcba_1_0_bob.into(),
-				log_3_dcba_1_0_bob.into(),
-			],
-		};
-		let result = run_test_case(backend, &filter).await.expect("must succeed");
-		assert_eq!(result, filter.expected_result);
+		let (metadata, _) = block_with_logs(alice, vec![topics_a]);
+		backend
+			.commit_block_metadata(&metadata)
+			.await
+			.expect("write must succeed");
+
+		let transaction_hash = metadata.transactions.as_ref().unwrap()[2].transaction_hash;
+		assert!(backend
+			.canonical_transaction(transaction_hash)
+			.await
+			.unwrap()
+			.is_some());
+		assert_eq!(
+			backend
+				.log_inclusion_proof(transaction_hash, 0)
+				.await
+				.unwrap(),
+			None
+		);
 	}
 
 	#[tokio::test]
-	async fn test_filters_address_and_topic() {
-		let TestData {
-			backend,
-			bob,
-			topics_b,
-			log_2_badc_2_0_bob,
-			log_3_badc_2_0_bob,
-			..
-		} = prepare().await;
-		let filter = TestFilter {
-			from_block: 0,
-			to_block: 3,
-			addresses: vec![bob],
-			topics: vec![vec![Some(topics_b)]],
-			expected_result: vec![log_2_badc_2_0_bob.into(), log_3_badc_2_0_bob.into()],
+	async fn wide_reorgs_are_canonicalized_in_chunks() {
+		let TestData { backend, .. } = prepare().await;
+		let backend = backend.with_canonicalize_chunk_size(NonZeroU32::new(4).unwrap());
+		// Two forks of 10 blocks, the first one canonical.
+		let fork = |byte: u8| {
+			(0..10u8)
+				.map(|i| H256::repeat_byte(byte + i))
+				.collect::<Vec<_>>()
 		};
-		let result = run_test_case(backend, &filter).await.expect("must succeed");
-		assert_eq!(result, filter.expected_result);
+		let (retracted, enacted) = (fork(0x40), fork(0x60));
+		for (i, (retracted_hash, enacted_hash)) in retracted.iter().zip(&enacted).enumerate() {
+			for (hash, is_canon) in [(retracted_hash, 1), (enacted_hash, 0)] {
+				sqlx::query(
+					"INSERT INTO blocks(
+						ethereum_block_hash, substrate_block_hash, block_number,
+						ethereum_storage_schema, is_canon)
+					VALUES (?, ?, ?, ?, ?)",
+				)
+				.bind(hash.as_bytes())
+				.bind(hash.as_bytes())
+				.bind(i as i32 + 10)
+				.bind(EthereumStorageSchema::V3.encode())
+				.bind(is_canon)
+				.execute(backend.pool())
+				.await
+				.expect("sql query must succeed");
+			}
+		}
+
+		// 3 chunks for each of the 2 tables, retracted and enacted, instead of a statement
+		// per block.
+		assert_eq!(
+			backend
+				.canonicalize_in_chunks(&retracted, &enacted)
+				.await
+				.unwrap(),
+			12
+		);
+		let canon = |hashes: Vec<H256>| {
+			let backend = &backend;
+			async move {
+				let mut flags = vec![];
+				for hash in hashes {
+					let is_canon =
+						sqlx::query("SELECT is_canon FROM blocks WHERE substrate_block_hash = ?")
+							.bind(hash.as_bytes())
+							.fetch_one(backend.pool())
+							.await
+							.expect("sql query must succeed")
+							.get::<i32, _>(0);
+					flags.push(is_canon);
+				}
+				flags
+			}
+		};
+		assert_eq!(canon(retracted.clone()).await, vec![0; 10]);
+		assert_eq!(canon(enacted.clone()).await, vec![1; 10]);
+
+		// Nothing is executed for an empty side of the re-org.
+		assert_eq!(
+			backend
+				.canonicalize_in_chunks(&[], &retracted)
+				.await
+				.unwrap(),
+			6
+		);
+		assert_eq!(canon(retracted).await, vec![1; 10]);
 	}
 
 	#[tokio::test]
-	async fn test_filters_multi_address_and_topic() {
+	async fn gaps_count_canonical_blocks_only() {
 		let TestData {
 			backend,
-			alice,
-			bob,
-			topics_b,
-			log_1_badc_2_0_alice,
-			log_2_badc_2_0_bob,
-			log_3_badc_2_0_bob,
+			substrate_hash_2,
 			..
 		} = prepare().await;
-		let filter = TestFilter {
-			from_block: 0,
-			to_block: 3,
-			addresses: vec![alice, bob],
-			topics: vec![vec![Some(topics_b)]],
-			expected_result: vec![
-				log_1_badc_2_0_alice.into(),
-				log_2_badc_2_0_bob.into(),
-				log_3_badc_2_0_bob.into(),
-			],
+		let insert_block = |substrate_hash: H256, block_number: i32, is_canon: i32| {
+			let backend = &backend;
+			async move {
+				sqlx::query(
+					"INSERT INTO blocks(
+						ethereum_block_hash, substrate_block_hash, block_number,
+						ethereum_storage_schema, is_canon)
+					VALUES (?, ?, ?, ?, ?)",
+				)
+				.bind(substrate_hash.as_bytes())
+				.bind(substrate_hash.as_bytes())
+				.bind(block_number)
+				.bind(EthereumStorageSchema::V3.encode())
+				.bind(is_canon)
+				.execute(backend.pool())
+				.await
+				.expect("sql query must succeed");
+			}
 		};
-		let result = run_test_case(backend, &filter).await.expect("must succeed");
-		assert_eq!(result, filter.expected_result);
+		// Blocks 1 to 3 are indexed, genesis is not.
+		assert_eq!(backend.find_gaps().await.unwrap(), vec![(0, 0)]);
+
+		// Block 2 is retracted, its sibling is not indexed yet.
+		backend
+			.canonicalize(&[substrate_hash_2], &[])
+			.await
+			.expect("canonicalize");
+		assert_eq!(backend.find_gaps().await.unwrap(), vec![(0, 0), (2, 2)]);
+		insert_block(H256::repeat_byte(0x42), 2, 0).await;
+		assert_eq!(backend.find_gaps().await.unwrap(), vec![(0, 0), (2, 2)]);
+
+		// The enacted sibling fills the gap, a stale canonical row at the height counts once.
+		insert_block(H256::repeat_byte(0x43), 2, 1).await;
+		insert_block(H256::repeat_byte(0x44), 2, 1).await;
+		assert_eq!(backend.find_gaps().await.unwrap(), vec![(0, 0)]);
+
+		// Missing ranges above are reported as a whole.
+		insert_block(H256::repeat_byte(0x47), 7, 1).await;
+		assert_eq!(backend.find_gaps().await.unwrap(), vec![(0, 0), (4, 6)]);
 	}
 
 	#[tokio::test]
-	async fn test_filters_multi_address_and_multi_topic() {
-		let TestData {
-			backend,
-			alice,
-			bob,
-			topics_a,
-			topics_b,
-			log_1_abcd_0_0_alice,
-			log_2_abcd_0_0_bob,
-			log_3_abcd_0_0_bob,
-			..
-		} = prepare().await;
-		let filter = TestFilter {
-			from_block: 0,
-			to_block: 3,
-			addresses: vec![alice, bob],
-			topics: vec![vec![Some(topics_a), Some(topics_b)]],
-			expected_result: vec![
-				log_1_abcd_0_0_alice.into(),
-				log_2_abcd_0_0_bob.into(),
-				log_3_abcd_0_0_bob.into(),
-			],
+	async fn block_metadata_writes_are_retried_while_the_database_is_locked() {
+		let tmp = tempdir().expect("create a temporary directory");
+		let (client, _) = TestClientBuilder::new()
+			.build_with_native_executor::<substrate_test_runtime_client::runtime::RuntimeApi, _>(
+			None,
+		);
+		let storage_override = Arc::new(SchemaV3StorageOverride::new(Arc::new(client)));
+		let path = Path::new("sqlite:///").join(tmp.path()).join("test.db3");
+		let path = path.to_str().unwrap();
+		let backend = Backend::<OpaqueBlock>::new(
+			BackendConfig::Sqlite(SqliteBackendConfig {
+				path,
+				create_if_missing: true,
+				cache_size: 20480,
+				thread_count: 4,
+				journal_mode: None,
+				synchronous: None,
+				busy_timeout: None,
+				index_logs: true,
+			}),
+			1,
+			None,
+			storage_override,
+		)
+		.await
+		.expect("indexer pool to be created");
+		// Fail right away on the lock held below, instead of waiting for the busy timeout.
+		sqlx::query("PRAGMA busy_timeout = 0")
+			.execute(backend.pool())
+			.await
+			.expect("sql query must succeed");
+		let metadata = BlockMetadata {
+			substrate_block_hash: H256::repeat_byte(0x42),
+			block_number: 4,
+			post_hashes: Hashes {
+				block_hash: H256::repeat_byte(0x43),
+				transaction_hashes: vec![],
+			},
+			schema: EthereumStorageSchema::V3,
+			is_canon: 1,
+			transactions: None,
+			header: None,
+			author: None,
 		};
-		let result = run_test_case(backend, &filter).await.expect("must succeed");
-		assert_eq!(result, filter.expected_result);
+
+		// Another writer holds the database lock.
+		let mut writer = SqliteConnectOptions::from_str(path)
+			.unwrap()
+			.connect()
+			.await
+			.expect("connect");
+		sqlx::query("BEGIN IMMEDIATE")
+			.execute(&mut writer)
+			.await
+			.expect("lock the database");
+
+		let single_attempt = backend
+			.clone()
+			.with_insert_retries(NonZeroU32::new(1).unwrap(), Duration::ZERO);
+		let err = single_attempt
+			.commit_block_metadata(&metadata)
+			.await
+			.expect_err("the database is locked");
+		assert!(is_transient_error(&err), "{err:?}");
+		assert!(
+			!backend
+				.is_block_indexed(metadata.substrate_block_hash)
+				.await
+		);
+
+		let release = tokio::spawn(async move {
+			tokio::time::sleep(Duration::from_millis(300)).await;
+			sqlx::query("COMMIT")
+				.execute(&mut writer)
+				.await
+				.expect("unlock the database");
+		});
+		let backend =
+			backend.with_insert_retries(NonZeroU32::new(8).unwrap(), Duration::from_millis(20));
+		backend
+			.commit_block_metadata(&metadata)
+			.await
+			.expect("written once the lock is released");
+		release.await.unwrap();
+		assert!(
+			backend
+				.is_block_indexed(metadata.substrate_block_hash)
+				.await
+		);
 	}
 
 	#[tokio::test]
-	async fn filter_with_topic_wildcards_works() {
+	async fn reorgs_deeper_than_their_route_are_repaired() {
 		let TestData {
 			backend,
-			alice,
-			bob,
-			topics_d,
-			topics_b,
-			log_1_dcba_1_0_alice,
-			log_2_dcba_1_0_bob,
-			log_3_dcba_1_0_bob,
+			substrate_hash_3,
 			..
 		} = prepare().await;
-		let filter = TestFilter {
-			from_block: 0,
-			to_block: 3,
-			addresses: vec![alice, bob],
-			topics: vec![vec![Some(topics_d), None, Some(topics_b)]],
-			expected_result: vec![
-				log_1_dcba_1_0_alice.into(),
-				log_2_dcba_1_0_bob.into(),
-				log_3_dcba_1_0_bob.into(),
-			],
-		};
-		let result = run_test_case(backend, &filter).await.expect("must succeed");
-		assert_eq!(result, filter.expected_result);
+		// A fork replacing the canonical blocks 1 to 3.
+		let fork = [0x71, 0x72, 0x73].map(H256::repeat_byte);
+		for (i, hash) in fork.iter().enumerate() {
+			sqlx::query(
+				"INSERT INTO blocks(
+					ethereum_block_hash, substrate_block_hash, block_number,
+					ethereum_storage_schema, is_canon)
+				VALUES (?, ?, ?, ?, 0)",
+			)
+			.bind(hash.as_bytes())
+			.bind(hash.as_bytes())
+			.bind(i as i32 + 1)
+			.bind(EthereumStorageSchema::V3.encode())
+			.execute(backend.pool())
+			.await
+			.expect("sql query must succeed");
+		}
+
+		// The route only reports the top of the retracted chain.
+		backend
+			.canonicalize(&[substrate_hash_3], &fork)
+			.await
+			.expect("canonicalize");
+
+		for number in 1..=3 {
+			let canonical = sqlx::query(
+				"SELECT substrate_block_hash FROM blocks WHERE block_number = ? AND is_canon = 1",
+			)
+			.bind(number)
+			.fetch_all(backend.pool())
+			.await
+			.expect("sql query must succeed")
+			.iter()
+			.map(|row| H256::from_slice(&row.get::<Vec<u8>, _>(0)[..]))
+			.collect::<Vec<_>>();
+			assert_eq!(canonical, vec![fork[number as usize - 1]]);
+		}
+		// Nothing is left to repair.
+		assert_eq!(backend.repair_canonical_heights(&fork).await.unwrap(), 0);
 	}
 
 	#[tokio::test]
-	async fn trailing_wildcard_is_useless_but_works() {
+	async fn unindexed_blocks_are_looked_up_in_chunks() {
 		let TestData {
-			alice,
 			backend,
-			topics_b,
-			log_1_dcba_1_0_alice,
+			substrate_hash_1,
+			substrate_hash_2,
 			..
 		} = prepare().await;
-		let filter = TestFilter {
-			from_block: 0,
-			to_block: 1,
-			addresses: vec![alice],
-			topics: vec![vec![None, None, Some(topics_b), None]],
-			expected_result: vec![log_1_dcba_1_0_alice.into()],
-		};
-		let result = run_test_case(backend, &filter).await.expect("must succeed");
-		assert_eq!(result, filter.expected_result);
+		for hash in [substrate_hash_1, substrate_hash_2] {
+			sqlx::query("INSERT OR IGNORE INTO sync_status(substrate_block_hash) VALUES (?)")
+				.bind(hash.as_bytes())
+				.execute(backend.pool())
+				.await
+				.expect("sql query must succeed");
+		}
+		// More hashes than bound parameters allowed in a single statement.
+		let unknown = (0..1200u64).map(H256::from_low_u64_be).collect::<Vec<_>>();
+		let mut hashes = unknown.clone();
+		hashes.insert(0, substrate_hash_1);
+		hashes.push(substrate_hash_2);
+
+		assert_eq!(
+			backend.get_unindexed_blocks(&hashes).await.unwrap(),
+			unknown
+		);
+		assert_eq!(backend.get_unindexed_blocks(&[]).await.unwrap(), vec![]);
 	}
 
 	#[tokio::test]
-	async fn filter_with_multi_topic_options_works() {
-		let TestData {
-			backend,
-			topics_a,
-			topics_d,
-			log_1_abcd_0_0_alice,
-			log_1_dcba_1_0_alice,
-			log_2_abcd_0_0_bob,
-			log_2_dcba_1_0_bob,
-			log_3_abcd_0_0_bob,
-			log_3_dcba_1_0_bob,
-			..
-		} = prepare().await;
-		let filter = TestFilter {
-			from_block: 0,
-			to_block: 3,
-			addresses: vec![],
-			topics: vec![
-				vec![Some(topics_a)],
-				vec![Some(topics_d)],
-				vec![Some(topics_d)], // duplicate, ignored
-			],
-			expected_result: vec![
-				log_1_abcd_0_0_alice.into(),
-				log_1_dcba_1_0_alice.into(),
-				log_2_abcd_0_0_bob.into(),
-				log_2_dcba_1_0_bob.into(),
-				log_3_abcd_0_0_bob.into(),
-				log_3_dcba_1_0_bob.into(),
-			],
-		};
-		let result = run_test_case(backend, &filter).await.expect("must succeed");
-		assert_eq!(result, filter.expected_result);
+	async fn logs_are_looked_up_by_address() {
+		let TestData { backend, .. } = prepare().await;
+		let (contract_a, contract_b) = (H160::repeat_byte(0xa1), H160::repeat_byte(0xb2));
+		let blocks = (0..10u8)
+			.map(|i| H256::repeat_byte(0x80 + i))
+			.collect::<Vec<_>>();
+		let mut logs = vec![];
+		for (i, hash) in blocks.iter().enumerate() {
+			sqlx::query(
+				"INSERT INTO blocks(
+					ethereum_block_hash, substrate_block_hash, block_number,
+					ethereum_storage_schema, is_canon)
+				VALUES (?, ?, ?, ?, 1)",
+			)
+			.bind(hash.as_bytes())
+			.bind(hash.as_bytes())
+			.bind(i as i32 + 10)
+			.bind(EthereumStorageSchema::V3.encode())
+			.execute(backend.pool())
+			.await
+			.expect("sql query must succeed");
+			for (log_index, address) in [contract_b, contract_a].iter().enumerate() {
+				logs.push(super::Log {
+					address: address.as_bytes().to_vec(),
+					topic_1: None,
+					topic_2: None,
+					topic_3: None,
+					topic_4: None,
+					log_index: log_index as i64,
+					transaction_index: 0,
+					substrate_block_hash: hash.as_bytes().to_vec(),
+					topic_count: 0,
+				});
+			}
+		}
+		// Indexed in reverse, the order comes from the block numbers.
+		logs.reverse();
+		let mut conn = backend.pool().acquire().await.unwrap();
+		backend.insert_logs(&mut conn, logs).await.unwrap();
+		drop(conn);
+
+		let found = backend.logs_by_address(contract_a, 12..=16).await.unwrap();
+		assert_eq!(
+			found
+				.iter()
+				.map(|log| (log.block_number, log.substrate_block_hash, log.log_index))
+				.collect::<Vec<_>>(),
+			(12..=16)
+				.map(|number| (number, blocks[number as usize - 10], 1))
+				.collect::<Vec<_>>()
+		);
+		assert_eq!(
+			backend
+				.logs_by_address(contract_b, 0..=100)
+				.await
+				.unwrap()
+				.len(),
+			10
+		);
+		// Building the indexes again is a no-op.
+		backend.create_indexes().await.unwrap();
 	}
 
 	#[tokio::test]
-	async fn filter_with_multi_topic_options_and_wildcards_works() {
+	async fn journal_mode_is_configurable() {
+		let tmp = tempdir().expect("create a temporary directory");
+		let (client, _) = TestClientBuilder::new()
+			.build_with_native_executor::<substrate_test_runtime_client::runtime::RuntimeApi, _>(
+			None,
+		);
+		let storage_override = Arc::new(SchemaV3StorageOverride::new(Arc::new(client)));
+		for (journal_mode, expected) in [
+			(SqliteJournalMode::Wal, "wal"),
+			(SqliteJournalMode::Delete, "delete"),
+		] {
+			let backend = Backend::<OpaqueBlock>::new(
+				BackendConfig::Sqlite(SqliteBackendConfig {
+					path: Path::new("sqlite:///")
+						.join(tmp.path())
+						.join(format!("{expected}.db3"))
+						.to_str()
+						.unwrap(),
+					create_if_missing: true,
+					cache_size: 20480,
+					thread_count: 4,
+					journal_mode: Some(journal_mode),
+					synchronous: Some(SqliteSynchronous::Full),
+					busy_timeout: Some(Duration::from_secs(1)),
+					index_logs: true,
+				}),
+				1,
+				None,
+				storage_override.clone(),
+			)
+			.await
+			.expect("indexer pool to be created");
+
+			let mode = sqlx::query("PRAGMA journal_mode")
+				.fetch_one(backend.pool())
+				.await
+				.expect("sql query must succeed")
+				.get::<String, _>(0);
+			assert_eq!(mode, expected);
+			assert_eq!(
+				backend.read_snapshot().await.is_ok(),
+				matches!(journal_mode, SqliteJournalMode::Wal)
+			);
+			// https://www.sqlite.org/pragma.html#pragma_synchronous
+			let synchronous = sqlx::query("PRAGMA synchronous")
+				.fetch_one(backend.pool())
+				.await
+				.expect("sql query must succeed")
+				.get::<i64, _>(0);
+			assert_eq!(synchronous, 2);
+		}
+	}
+
+	#[tokio::test]
+	async fn topic_count_round_trips() {
 		let TestData {
 			backend,
-			bob,
+			alice,
 			topics_a,
 			topics_b,
 			topics_c,
 			topics_d,
-			log_2_dcba_1_0_bob,
-			log_2_badc_2_0_bob,
-			log_3_dcba_1_0_bob,
-			log_3_badc_2_0_bob,
+			substrate_hash_1,
 			..
 		} = prepare().await;
-		let filter = TestFilter {
-			from_block: 0,
-			to_block: 3,
-			addresses: vec![bob],
-			// Product on input [null,null,(b,d),(a,c)].
-			topics: vec![
-				vec![None, None, Some(topics_b), Some(topics_a)],
-				vec![None, None, Some(topics_b), Some(topics_c)],
-				vec![None, None, Some(topics_d), Some(topics_a)],
-				vec![None, None, Some(topics_d), Some(topics_c)],
-			],
-			expected_result: vec![
-				log_2_dcba_1_0_bob.into(),
-				log_2_badc_2_0_bob.into(),
-				log_3_dcba_1_0_bob.into(),
-				log_3_badc_2_0_bob.into(),
-			],
+		let topics = [topics_a, topics_b, topics_c, topics_d];
+		let topic_counts = |pool: SqlitePool| async move {
+			sqlx::query(
+				"SELECT topic_count, topic_1, topic_2, topic_3, topic_4 FROM logs
+				WHERE log_index >= 100 ORDER BY log_index ASC",
+			)
+			.fetch_all(&pool)
+			.await
+			.expect("sql query must succeed")
+			.iter()
+			.map(|row| {
+				let present = (1..=4)
+					.filter(|&i| row.get::<Option<Vec<u8>>, _>(i).is_some())
+					.count();
+				(row.get::<i32, _>(0), present as i32)
+			})
+			.collect::<Vec<_>>()
 		};
-		let result = run_test_case(backend, &filter).await.expect("must succeed");
-		assert_eq!(result, filter.expected_result);
+
+		let mut conn = backend.pool().acquire().await.expect("connection");
+		backend
+			.insert_logs(
+				&mut conn,
+				(0..=4)
+					.map(|n| indexed_log(alice, &topics[..n], 100 + n as i64, substrate_hash_1))
+					.collect(),
+			)
+			.await
+			.expect("insert logs");
+		let expected = (0..=4).map(|n| (n, n)).collect::<Vec<_>>();
+		assert_eq!(topic_counts(backend.pool().clone()).await, expected);
+
+		// Counts are kept when rebuilding the table with the hashed key.
+		let backend = backend
+			.with_log_key(LogKey::Hashed)
+			.await
+			.expect("logs table rebuilt");
+		assert_eq!(topic_counts(backend.pool().clone()).await, expected);
+
+		// Logs indexed before the column existed are counted from their topic columns.
+		sqlx::query("ALTER TABLE logs DROP COLUMN topic_count")
+			.execute(backend.pool())
+			.await
+			.expect("sql query must succeed");
+		Backend::<OpaqueBlock>::count_log_topics(backend.pool())
+			.await
+			.expect("topics counted");
+		assert_eq!(topic_counts(backend.pool().clone()).await, expected);
+	}
+
+	#[test]
+	fn adaptive_logs_batch_follows_the_commit_latency() {
+		let target = Duration::from_millis(100);
+		let mut batch = LogsBatch::new(LogsBatchSize::Adaptive {
+			min: 4,
+			max: 256,
+			target,
+		});
+		assert_eq!(batch.size, 4);
+
+		// Partial batches are not grown.
+		batch.record(1, Duration::from_millis(1));
+		assert_eq!(batch.size, 4);
+
+		// Fast inserts grow the batch up to `max`.
+		for _ in 0..10 {
+			batch.record(batch.size, Duration::from_millis(10));
+		}
+		assert_eq!(batch.size, 256);
+
+		// Inserts near the target keep it.
+		batch.record(batch.size, Duration::from_millis(80));
+		assert_eq!(batch.size, 256);
+
+		// Slow inserts shrink it down to `min`.
+		for _ in 0..10 {
+			batch.record(batch.size, Duration::from_millis(500));
+		}
+		assert_eq!(batch.size, 4);
+
+		// A fixed size is kept whatever the latency.
+		let mut batch = LogsBatch::new(LogsBatchSize::default());
+		batch.record(batch.size, Duration::from_secs(10));
+		assert_eq!(batch.size, DEFAULT_LOGS_BATCH_SIZE);
 	}
 
 	#[tokio::test]
-	async fn test_canonicalize_sets_canon_flag_for_redacted_and_enacted_blocks_correctly() {
+	async fn blocks_in_range_follow_reorgs() {
 		let TestData {
 			backend,
 			substrate_hash_1,
@@ -1741,77 +7940,96 @@ mod test {
 			..
 		} = prepare().await;
 
-		// set block #1 to non canon
-		sqlx::query("UPDATE blocks SET is_canon = 0 WHERE substrate_block_hash = ?")
-			.bind(substrate_hash_1.as_bytes())
+		// A fork of blocks 2 and 3, retracted from the start.
+		let fork = [H256::repeat_byte(0x0b), H256::repeat_byte(0x0c)];
+		for (i, hash) in fork.iter().enumerate() {
+			sqlx::query(
+				"INSERT INTO blocks(
+					block_number,
+					ethereum_block_hash,
+					substrate_block_hash,
+					ethereum_storage_schema,
+					is_canon)
+				VALUES (?, ?, ?, ?, 0)",
+			)
+			.bind(i as i32 + 2)
+			.bind(hash.as_bytes())
+			.bind(hash.as_bytes())
+			.bind(EthereumStorageSchema::V3.encode())
 			.execute(backend.pool())
 			.await
-			.expect("sql query must succeed");
-		assert_blocks_canon(
-			backend.pool(),
+			.expect("insert block");
+		}
+		let block = |substrate_block_hash, block_number, is_canon| IndexedBlock {
+			substrate_block_hash,
+			block_number,
+			is_canon,
+		};
+
+		assert_eq!(
+			backend.blocks_in_range(1..=3, true).await.unwrap(),
 			vec![
-				(substrate_hash_1, 0),
-				(substrate_hash_2, 1),
-				(substrate_hash_3, 1),
-			],
-		)
-		.await;
+				block(substrate_hash_1, 1, true),
+				block(substrate_hash_2, 2, true),
+				block(substrate_hash_3, 3, true),
+			]
+		);
+		assert_eq!(
+			backend.blocks_in_range(2..=2, false).await.unwrap(),
+			vec![block(substrate_hash_2, 2, true), block(fork[0], 2, false)]
+		);
 
+		// The fork becomes canonical, orphaning blocks 2 and 3.
 		backend
-			.canonicalize(&[substrate_hash_2], &[substrate_hash_1])
+			.canonicalize(&[substrate_hash_2, substrate_hash_3], &fork)
 			.await
 			.expect("must succeed");
 
-		assert_blocks_canon(
-			backend.pool(),
+		assert_eq!(
+			backend.blocks_in_range(1..=3, true).await.unwrap(),
 			vec![
-				(substrate_hash_1, 1),
-				(substrate_hash_2, 0),
-				(substrate_hash_3, 1),
-			],
-		)
-		.await;
-	}
-
-	#[test]
-	fn test_query_should_be_generated_correctly() {
-		use sqlx::Execute;
-
-		let from_block: u64 = 100;
-		let to_block: u64 = 500;
-		let addresses: Vec<H160> = vec![
-			H160::repeat_byte(0x01),
-			H160::repeat_byte(0x02),
-			H160::repeat_byte(0x03),
-		];
-		let topics = [
-			hashset![
-				H256::repeat_byte(0x01),
-				H256::repeat_byte(0x02),
-				H256::repeat_byte(0x03),
-			],
-			hashset![H256::repeat_byte(0x04), H256::repeat_byte(0x05),],
-			hashset![],
-			hashset![H256::repeat_byte(0x06)],
-		];
-
-		let expected_query_sql = "
-SELECT
-	l.substrate_block_hash,
-	b.ethereum_block_hash,
-	b.block_number,
-	b.ethereum_storage_schema,
-	l.transaction_index,
-	l.log_index
-FROM logs AS l
-INNER JOIN blocks AS b
-ON (b.block_number BETWEEN ? AND ?) AND b.substrate_block_hash = l.substrate_block_hash AND b.is_canon = 1
-WHERE 1 AND l.address IN (?, ?, ?) AND l.topic_1 IN (?, ?, ?) AND l.topic_2 IN (?, ?) AND l.topic_4 = ?
-ORDER BY b.block_number ASC, l.transaction_index ASC, l.log_index ASC
-LIMIT 10001";
+				block(substrate_hash_1, 1, true),
+				block(fork[0], 2, true),
+				block(fork[1], 3, true),
+			]
+		);
+		assert_eq!(
+			backend.blocks_in_range(3..=4, false).await.unwrap(),
+			vec![block(fork[1], 3, true), block(substrate_hash_3, 3, false)]
+		);
+		assert_eq!(
+			backend.block_by_hash(substrate_hash_2).await.unwrap(),
+			Some(block(substrate_hash_2, 2, false))
+		);
+		assert_eq!(
+			backend.block_by_hash(fork[0]).await.unwrap(),
+			Some(block(fork[0], 2, true))
+		);
+		assert_eq!(
+			backend
+				.block_by_hash(H256::repeat_byte(0xff))
+				.await
+				.unwrap(),
+			None
+		);
+		assert_eq!(
+			backend.is_canon(substrate_hash_2).await.unwrap(),
+			Some(false)
+		);
+		assert_eq!(backend.is_canon(fork[0]).await.unwrap(), Some(true));
 
-		let mut qb = QueryBuilder::new("");
-		let actual_query_sql = build_query(&mut qb, from_block, to_block, addresses, topics).sql();
-		assert_eq!(expected_query_sql, actual_query_sql);
+		// The range spans a bounded number of blocks.
+		assert_eq!(
+			backend
+				.blocks_in_range(1..=MAX_BLOCKS_IN_RANGE, true)
+				.await
+				.unwrap()
+				.len(),
+			3
+		);
+		assert!(backend
+			.blocks_in_range(0..=MAX_BLOCKS_IN_RANGE, true)
+			.await
+			.is_err());
 	}
 }
@@ -0,0 +1,219 @@
+// This file is part of Frontier.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use rlp::{Rlp, RlpStream};
+use sp_core::{hashing::keccak_256, H256};
+
+/// Builds the receipts trie of a block from its EIP-2718 encoded receipts, keyed by the RLP
+/// encoded transaction index, and returns its root along with the nodes on the path to the
+/// receipt at `index`, root first.
+pub(crate) fn receipt_proof(receipts: &[Vec<u8>], index: usize) -> (H256, Vec<Vec<u8>>) {
+	let mut entries = receipts
+		.iter()
+		.enumerate()
+		.map(|(i, receipt)| (nibbles(&rlp::encode(&i)), &receipt[..]))
+		.collect::<Vec<_>>();
+	entries.sort_by(|a, b| a.0.cmp(&b.0));
+	let target = nibbles(&rlp::encode(&index));
+	let mut proof = vec![];
+	let root = if entries.is_empty() {
+		rlp::NULL_RLP.to_vec()
+	} else {
+		encode_node(&entries, 0, Some(&target), &mut proof)
+	};
+	proof.reverse();
+	(H256(keccak_256(&root)), proof)
+}
+
+/// Verifies a receipts trie proof against the receipts root of a block, returning the
+/// EIP-2718 encoded receipt at `index` if the proof is valid.
+pub fn verify_receipt_proof(
+	receipts_root: H256,
+	index: usize,
+	proof: &[Vec<u8>],
+) -> Option<Vec<u8>> {
+	let find = |hash: &[u8]| {
+		proof
+			.iter()
+			.find(|node| keccak_256(node)[..] == *hash)
+			.cloned()
+	};
+	let key = nibbles(&rlp::encode(&index));
+	let mut key = &key[..];
+	let mut node = find(receipts_root.as_bytes())?;
+	loop {
+		let rlp = Rlp::new(&node);
+		let child = match rlp.item_count().ok()? {
+			17 => {
+				let Some((nibble, rest)) = key.split_first() else {
+					return rlp.at(16).ok()?.data().ok().map(<[u8]>::to_vec);
+				};
+				key = rest;
+				rlp.at(*nibble as usize).ok()?
+			}
+			2 => {
+				let (path, is_leaf) = decode_hex_prefix(rlp.at(0).ok()?.data().ok()?)?;
+				key = key.strip_prefix(&path[..])?;
+				if is_leaf {
+					if !key.is_empty() {
+						return None;
+					}
+					return rlp.at(1).ok()?.data().ok().map(<[u8]>::to_vec);
+				}
+				rlp.at(1).ok()?
+			}
+			_ => return None,
+		};
+		// Nodes shorter than a hash are embedded in their parent.
+		let next = if child.is_list() {
+			child.as_raw().to_vec()
+		} else {
+			find(child.data().ok().filter(|hash| hash.len() == 32)?)?
+		};
+		node = next;
+	}
+}
+
+/// Encodes the node of the given sorted entries, whose keys share their first `depth`
+/// nibbles. The nodes on the path to `target` referenced by hash are pushed to `proof`,
+/// deepest first.
+fn encode_node(
+	entries: &[(Vec<u8>, &[u8])],
+	depth: usize,
+	target: Option<&[u8]>,
+	proof: &mut Vec<Vec<u8>>,
+) -> Vec<u8> {
+	let encoded = match entries {
+		[(key, value)] => {
+			let mut stream = RlpStream::new_list(2);
+			stream.append(&hex_prefix(&key[depth..], true));
+			stream.append(&value.to_vec());
+			stream.out().to_vec()
+		}
+		[(first, _), .., (last, _)] => {
+			// Sorted keys, the first and last ones share the shortest prefix.
+			let shared = first[depth..]
+				.iter()
+				.zip(&last[depth..])
+				.take_while(|(a, b)| a == b)
+				.count();
+			if shared > 0 {
+				let child = encode_node(entries, depth + shared, target, proof);
+				let mut stream = RlpStream::new_list(2);
+				stream.append(&hex_prefix(&first[depth..depth + shared], false));
+				append_child(&mut stream, &child);
+				stream.out().to_vec()
+			} else {
+				let mut stream = RlpStream::new_list(17);
+				// A key ending at this node sorts first.
+				let (value, mut rest) = match entries.split_first() {
+					Some(((key, value), rest)) if key.len() == depth => (value.to_vec(), rest),
+					_ => (vec![], entries),
+				};
+				for nibble in 0..16u8 {
+					let end = rest
+						.iter()
+						.position(|(key, _)| key[depth] != nibble)
+						.unwrap_or(rest.len());
+					let (children, tail) = rest.split_at(end);
+					rest = tail;
+					if children.is_empty() {
+						stream.append_empty_data();
+					} else {
+						let target = target.filter(|target| target.get(depth) == Some(&nibble));
+						let child = encode_node(children, depth + 1, target, proof);
+						append_child(&mut stream, &child);
+					}
+				}
+				stream.append(&value);
+				stream.out().to_vec()
+			}
+		}
+		[] => rlp::NULL_RLP.to_vec(),
+	};
+	if target.is_some() && (depth == 0 || encoded.len() >= 32) {
+		proof.push(encoded.clone());
+	}
+	encoded
+}
+
+/// Appends a reference to a child node, embedded if shorter than a hash.
+fn append_child(stream: &mut RlpStream, child: &[u8]) {
+	if child.len() < 32 {
+		stream.append_raw(child, 1);
+	} else {
+		stream.append(&keccak_256(child).to_vec());
+	}
+}
+
+fn nibbles(bytes: &[u8]) -> Vec<u8> {
+	bytes.iter().flat_map(|b| [b >> 4, b & 0x0f]).collect()
+}
+
+/// Hex-prefix encoding of a node path, flagging leaves and odd lengths.
+fn hex_prefix(nibbles: &[u8], is_leaf: bool) -> Vec<u8> {
+	let flag = if is_leaf { 2 } else { 0 };
+	let (mut encoded, rest) = match nibbles.split_first() {
+		Some((first, rest)) if nibbles.len() % 2 == 1 => (vec![((flag + 1) << 4) | first], rest),
+		_ => (vec![flag << 4], nibbles),
+	};
+	encoded.extend(rest.chunks(2).map(|pair| (pair[0] << 4) | pair[1]));
+	encoded
+}
+
+fn decode_hex_prefix(encoded: &[u8]) -> Option<(Vec<u8>, bool)> {
+	let (first, rest) = encoded.split_first()?;
+	let flag = first >> 4;
+	let mut path = match flag & 1 {
+		1 => vec![first & 0x0f],
+		_ => vec![],
+	};
+	path.extend(nibbles(rest));
+	Some((path, flag & 2 == 2))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn receipt_proofs_match_the_ordered_trie_root() {
+		// Enough receipts for branch, extension and embedded nodes.
+		let receipts = (0..300u32)
+			.map(|i| match i % 3 {
+				0 => vec![i as u8],
+				_ => i.to_be_bytes().repeat(i as usize % 20 + 1),
+			})
+			.collect::<Vec<Vec<u8>>>();
+		let receipts_root = ethereum::util::ordered_trie_root(receipts.iter().cloned());
+
+		for index in [0, 1, 2, 127, 128, 129, 299] {
+			let (root, proof) = receipt_proof(&receipts, index);
+			assert_eq!(root, receipts_root);
+			assert_eq!(
+				verify_receipt_proof(receipts_root, index, &proof),
+				Some(receipts[index].clone())
+			);
+			// The proof is bound to the root and to its nodes.
+			assert_eq!(verify_receipt_proof(H256::zero(), index, &proof), None);
+			let mut tampered = proof.clone();
+			tampered.last_mut().unwrap().push(0);
+			assert_eq!(verify_receipt_proof(receipts_root, index, &tampered), None);
+		}
+	}
+}
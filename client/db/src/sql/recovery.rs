@@ -0,0 +1,172 @@
+// This file is part of Frontier.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use sp_core::{hashing::keccak_256, H160, H256};
+
+/// Recovers the sender of the ethereum transactions indexed by the SQL backend.
+pub trait SignerRecovery: Send + Sync {
+	/// Recovers the sender of a single transaction, `None` if the signature is invalid.
+	fn recover(&self, transaction: &ethereum::TransactionV2) -> Option<H160>;
+
+	/// Recovers the senders of a block's transactions. Implementations able to verify
+	/// signatures in batches should override it.
+	fn recover_batch(&self, transactions: &[ethereum::TransactionV2]) -> Vec<Option<H160>> {
+		transactions.iter().map(|t| self.recover(t)).collect()
+	}
+}
+
+/// Pure Rust recovery, based on `libsecp256k1`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SoftwareSignerRecovery;
+
+impl SignerRecovery for SoftwareSignerRecovery {
+	fn recover(&self, transaction: &ethereum::TransactionV2) -> Option<H160> {
+		let (sig, msg) = signature_and_message(transaction);
+		let message = libsecp256k1::Message::parse(&msg);
+		let signature = libsecp256k1::Signature::parse_standard_slice(&sig[0..64]).ok()?;
+		let recovery_id = libsecp256k1::RecoveryId::parse(sig[64]).ok()?;
+		let public_key = libsecp256k1::recover(&message, &signature, &recovery_id).ok()?;
+		Some(public_key_address(&public_key.serialize()[1..65]))
+	}
+}
+
+/// Recovery through the node's native host functions, backed by the C `secp256k1` library.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NativeSignerRecovery;
+
+impl SignerRecovery for NativeSignerRecovery {
+	fn recover(&self, transaction: &ethereum::TransactionV2) -> Option<H160> {
+		let (sig, msg) = signature_and_message(transaction);
+		let public_key = sp_io::crypto::secp256k1_ecdsa_recover(&sig, &msg).ok()?;
+		Some(public_key_address(&public_key[..]))
+	}
+}
+
+/// Returns the `r || s || v` signature and the signed message hash of the transaction.
+fn signature_and_message(transaction: &ethereum::TransactionV2) -> ([u8; 65], [u8; 32]) {
+	let mut sig = [0u8; 65];
+	let mut msg = [0u8; 32];
+	match transaction {
+		ethereum::TransactionV2::Legacy(t) => {
+			sig[0..32].copy_from_slice(&t.signature.r()[..]);
+			sig[32..64].copy_from_slice(&t.signature.s()[..]);
+			sig[64] = t.signature.standard_v();
+			msg.copy_from_slice(&ethereum::LegacyTransactionMessage::from(t.clone()).hash()[..]);
+		}
+		ethereum::TransactionV2::EIP2930(t) => {
+			sig[0..32].copy_from_slice(&t.r[..]);
+			sig[32..64].copy_from_slice(&t.s[..]);
+			sig[64] = t.odd_y_parity as u8;
+			msg.copy_from_slice(&ethereum::EIP2930TransactionMessage::from(t.clone()).hash()[..]);
+		}
+		ethereum::TransactionV2::EIP1559(t) => {
+			sig[0..32].copy_from_slice(&t.r[..]);
+			sig[32..64].copy_from_slice(&t.s[..]);
+			sig[64] = t.odd_y_parity as u8;
+			msg.copy_from_slice(&ethereum::EIP1559TransactionMessage::from(t.clone()).hash()[..]);
+		}
+	}
+	(sig, msg)
+}
+
+fn public_key_address(uncompressed: &[u8]) -> H160 {
+	H160::from(H256::from(keccak_256(uncompressed)))
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	use std::time::Instant;
+
+	fn signed_legacy_transaction(
+		secret: &libsecp256k1::SecretKey,
+		nonce: u64,
+	) -> ethereum::TransactionV2 {
+		let message = ethereum::LegacyTransactionMessage {
+			nonce: nonce.into(),
+			gas_price: 1.into(),
+			gas_limit: 21_000.into(),
+			action: ethereum::TransactionAction::Call(H160::repeat_byte(0x02)),
+			value: 0.into(),
+			input: vec![],
+			chain_id: Some(42),
+		};
+		let signing_message = libsecp256k1::Message::parse_slice(&message.hash()[..]).unwrap();
+		let (signature, recid) = libsecp256k1::sign(&signing_message, secret);
+		let rs = signature.serialize();
+		let signature = ethereum::TransactionSignature::new(
+			recid.serialize() as u64 + 42 * 2 + 35,
+			H256::from_slice(&rs[0..32]),
+			H256::from_slice(&rs[32..64]),
+		)
+		.expect("valid signature");
+		ethereum::TransactionV2::Legacy(ethereum::LegacyTransaction {
+			nonce: message.nonce,
+			gas_price: message.gas_price,
+			gas_limit: message.gas_limit,
+			action: message.action,
+			value: message.value,
+			input: message.input,
+			signature,
+		})
+	}
+
+	fn fixture(count: u64) -> (H160, Vec<ethereum::TransactionV2>) {
+		let secret = libsecp256k1::SecretKey::parse(&[0x11; 32]).unwrap();
+		let public_key = libsecp256k1::PublicKey::from_secret_key(&secret);
+		let address = public_key_address(&public_key.serialize()[1..65]);
+		let transactions = (0..count)
+			.map(|nonce| signed_legacy_transaction(&secret, nonce))
+			.collect();
+		(address, transactions)
+	}
+
+	#[test]
+	fn recovery_implementations_agree() {
+		let (address, transactions) = fixture(4);
+
+		let software = SoftwareSignerRecovery.recover_batch(&transactions);
+		let native = NativeSignerRecovery.recover_batch(&transactions);
+
+		assert_eq!(software, vec![Some(address); 4]);
+		assert_eq!(software, native);
+	}
+
+	/// Compares the throughput of the implementations, run with `--ignored --nocapture`.
+	#[test]
+	#[ignore]
+	fn bench_recovery_implementations() {
+		let (_, transactions) = fixture(1_000);
+		let implementations: [(&str, &dyn SignerRecovery); 2] = [
+			("software", &SoftwareSignerRecovery),
+			("native", &NativeSignerRecovery),
+		];
+		for (name, recovery) in implementations {
+			let start = Instant::now();
+			let recovered = recovery.recover_batch(&transactions);
+			let elapsed = start.elapsed();
+			assert!(recovered.iter().all(Option::is_some));
+			println!(
+				"{name}: {} recoveries in {elapsed:?} ({:?}/recovery)",
+				transactions.len(),
+				elapsed / transactions.len() as u32,
+			);
+		}
+	}
+}
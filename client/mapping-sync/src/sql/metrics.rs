@@ -0,0 +1,86 @@
+// This file is part of Frontier.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Prometheus metrics of the SQL indexer worker.
+
+use std::time::Duration;
+
+use prometheus_endpoint::{
+	register, Counter, Gauge, Histogram, HistogramOpts, PrometheusError, Registry, U64,
+};
+
+use super::SyncState;
+
+/// The metrics updated by the SQL indexer worker.
+#[derive(Clone)]
+pub struct SyncMetrics {
+	indexed_block_number: Gauge<U64>,
+	sync_lag: Gauge<U64>,
+	batch_duration: Histogram,
+	reorgs: Counter<U64>,
+}
+
+impl SyncMetrics {
+	pub fn register(registry: &Registry) -> Result<Self, PrometheusError> {
+		Ok(Self {
+			indexed_block_number: register(
+				Gauge::new(
+					"frontier_sql_indexed_block_number",
+					"Number of the highest indexed canonical block.",
+				)?,
+				registry,
+			)?,
+			sync_lag: register(
+				Gauge::new(
+					"frontier_sql_sync_lag",
+					"Number of best blocks not yet indexed with their logs.",
+				)?,
+				registry,
+			)?,
+			batch_duration: register(
+				Histogram::with_opts(HistogramOpts::new(
+					"frontier_sql_batch_duration_seconds",
+					"Time spent by the worker executing an indexing command.",
+				))?,
+				registry,
+			)?,
+			reorgs: register(
+				Counter::new(
+					"frontier_sql_reorgs_total",
+					"Number of re-orgs canonicalized in the index.",
+				)?,
+				registry,
+			)?,
+		})
+	}
+
+	pub(crate) fn observe_batch(&self, duration: Duration) {
+		self.batch_duration.observe(duration.as_secs_f64());
+	}
+
+	pub(crate) fn record_reorg(&self) {
+		self.reorgs.inc();
+	}
+
+	/// Refreshes the indexed block number and the lag of the index behind the best block.
+	pub(crate) fn update(&self, state: &SyncState) {
+		self.indexed_block_number.set(state.indexed_number as u64);
+		self.sync_lag
+			.set(state.best_number.saturating_sub(state.indexed_number) as u64);
+	}
+}
@@ -16,21 +16,47 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
-use std::{ops::DerefMut, sync::Arc, time::Duration};
+use std::{
+	ops::DerefMut,
+	sync::Arc,
+	time::{Duration, Instant},
+};
 
 use futures::prelude::*;
 // Substrate
-use sc_client_api::backend::{Backend as BackendT, StorageProvider};
+use sc_client_api::{
+	backend::{Backend as BackendT, StorageProvider},
+	BlockBackend, BlockchainEvents,
+};
 use sp_api::ProvideRuntimeApi;
 use sp_blockchain::{Backend, HeaderBackend};
 use sp_consensus::SyncOracle;
 use sp_core::H256;
 use sp_runtime::traits::{Block as BlockT, Header as HeaderT, UniqueSaturatedInto};
+use tracing::Instrument;
 // Frontier
 use fp_rpc::EthereumRuntimeRPCApi;
 
 use crate::{EthereumBlockNotification, EthereumBlockNotificationSinks, SyncStrategy};
 
+pub mod metrics;
+#[cfg(feature = "ws-feed")]
+pub mod ws_feed;
+
+/// Number of retries of a failed parent header fetch during the ancestor descent.
+const HEADER_FETCH_RETRIES: u32 = 3;
+
+/// Delay before the first retry of a failed parent header fetch, doubled on each retry.
+const HEADER_FETCH_BACKOFF: Duration = Duration::from_millis(50);
+
+/// Default re-org depth past which the index is canonicalized in chunks, well above the
+/// depth of the re-orgs of a live chain.
+pub const DEFAULT_MAX_REORG_DEPTH: u32 = 4096;
+
+/// Maximum number of queued import notifications read along with the one being handled,
+/// only the latest new best block among them being followed.
+const NOTIFICATION_LOOKAHEAD: usize = 64;
+
 /// Defines the commands for the sync worker.
 #[derive(Debug)]
 pub enum WorkerCommand {
@@ -50,12 +76,94 @@ pub enum WorkerCommand {
 	/// Check for any canon blocks that haven't had their logs indexed.
 	/// Check for any missing parent blocks from the latest canon block.
 	CheckIndexedBlocks,
+	/// Persist the last finalized block reported via finality notifications.
+	RecordFinalized(H256),
+	/// Index the blocks missing from every gap of the indexed canonical block range.
+	IndexGaps,
+	/// Stop the worker once the commands queued so far are executed, acknowledging on the
+	/// given channel.
+	Shutdown(futures::channel::oneshot::Sender<()>),
 }
 
 /// Config parameters for the SyncWorker.
 pub struct SyncWorkerConfig {
 	pub check_indexed_blocks_interval: Duration,
 	pub read_notification_timeout: Duration,
+	/// Return from [`SyncWorker::run`] once the indexer backend records a fatal error,
+	/// instead of indexing on.
+	pub exit_on_fatal: bool,
+	/// The window over which the re-orgs following a first one are coalesced into a single
+	/// canonicalization to the final best block. Re-orgs are applied at once if zero.
+	pub reorg_debounce: Duration,
+	/// Polls the client's best and finalized blocks at this interval instead of reading the
+	/// import and finality notifications, for clients that do not emit them.
+	pub poll_interval: Option<Duration>,
+	/// Index every gap of the indexed canonical block range on each check interval, instead
+	/// of the lowest one only.
+	pub index_all_gaps: bool,
+	/// Keeps the blocks and logs of the last `prune_depth` blocks only, pruning the older
+	/// ones but genesis as the best block moves. Everything is kept if `None`.
+	pub prune_depth: Option<u32>,
+	/// Re-orgs retracting more blocks are canonicalized in chunks of at most this many
+	/// enacted or retracted blocks, each in its own transaction, instead of at once.
+	pub max_reorg_depth: u32,
+	/// Waits this long before indexing anything, so that a node just started imports its
+	/// first blocks without the indexer competing for resources. Starts at once if zero.
+	pub initial_delay: Duration,
+}
+
+impl Default for SyncWorkerConfig {
+	fn default() -> Self {
+		Self {
+			check_indexed_blocks_interval: Duration::from_secs(60),
+			read_notification_timeout: Duration::from_secs(30),
+			exit_on_fatal: false,
+			reorg_debounce: Duration::ZERO,
+			poll_interval: None,
+			index_all_gaps: false,
+			prune_depth: None,
+			max_reorg_depth: DEFAULT_MAX_REORG_DEPTH,
+			initial_delay: Duration::ZERO,
+		}
+	}
+}
+
+/// The progress of the index towards the client's best block, reported by the worker after
+/// each command.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct SyncState {
+	/// Number of the highest indexed canonical block.
+	pub indexed_number: u32,
+	/// Number of the client's best block.
+	pub best_number: u32,
+	/// Whether the best block is indexed as canonical.
+	pub is_synced: bool,
+}
+
+impl SyncState {
+	async fn current<Block, Client>(
+		client: &Client,
+		indexer_backend: &fc_db::sql::Backend<Block>,
+	) -> Option<Self>
+	where
+		Block: BlockT<Hash = H256>,
+		Client: HeaderBackend<Block>,
+	{
+		let indexed_number = indexer_backend
+			.latest_block_summary()
+			.await
+			.ok()??
+			.block_number;
+		let info = client.info();
+		let best_status = indexer_backend
+			.block_indexed_and_canon_status(info.best_hash)
+			.await;
+		Some(Self {
+			indexed_number,
+			best_number: info.best_number.unique_saturated_into(),
+			is_synced: best_status.indexed && best_status.canon,
+		})
+	}
 }
 
 /// Implements an indexer that imports blocks and their transactions.
@@ -68,7 +176,7 @@ where
 	Block: BlockT<Hash = H256>,
 	Client: ProvideRuntimeApi<Block>,
 	Client::Api: EthereumRuntimeRPCApi<Block>,
-	Client: HeaderBackend<Block> + StorageProvider<Block, Backend> + 'static,
+	Client: HeaderBackend<Block> + BlockBackend<Block> + StorageProvider<Block, Backend> + 'static,
 	Backend: BackendT<Block> + 'static,
 {
 	/// Spawn the indexing worker. The worker can be given commands via the sender channel.
@@ -80,38 +188,106 @@ where
 		pubsub_notification_sinks: Arc<
 			EthereumBlockNotificationSinks<EthereumBlockNotification<Block>>,
 		>,
+		metrics: Option<metrics::SyncMetrics>,
+		sync_state: Option<tokio::sync::watch::Sender<SyncState>>,
+		prune_depth: Option<u32>,
+		max_reorg_depth: u32,
 	) -> tokio::sync::mpsc::Sender<WorkerCommand> {
 		let (tx, mut rx) = tokio::sync::mpsc::channel(100);
 		tokio::task::spawn(async move {
-			while let Some(cmd) = rx.recv().await {
+			// The last best block notified to the pubsub sinks.
+			let mut notified_best = client.info().best_hash;
+			loop {
+				// Grouped writes span a burst of commands, committed once the queue is empty.
+				let cmd = match rx.try_recv() {
+					Ok(cmd) => cmd,
+					Err(tokio::sync::mpsc::error::TryRecvError::Empty) => {
+						flush_writes(&indexer_backend).await;
+						match rx.recv().await {
+							Some(cmd) => cmd,
+							None => break,
+						}
+					}
+					Err(tokio::sync::mpsc::error::TryRecvError::Disconnected) => break,
+				};
 				log::debug!(target: "frontier-sql", "💬 Recv Worker Command {cmd:?}");
+				let started = Instant::now();
+				if !matches!(
+					cmd,
+					WorkerCommand::IndexBestBlock(_) | WorkerCommand::IndexLeaves(_)
+				) {
+					flush_writes(&indexer_backend).await;
+				}
 				match cmd {
+					WorkerCommand::Shutdown(done) => {
+						done.send(()).ok();
+						break;
+					}
 					WorkerCommand::ResumeSync => {
-						// Attempt to resume from last indexed block. If there is no data in the db, sync genesis.
-						match indexer_backend.last_indexed_canon_block().await.ok() {
-							Some(last_block_hash) => {
-								log::debug!(target: "frontier-sql", "Resume from last block {last_block_hash:?}");
-								if let Some(parent_hash) = client
-									.header(last_block_hash)
-									.ok()
-									.flatten()
-									.map(|header| *header.parent_hash())
-								{
-									index_canonical_block_and_ancestors(
-										client.clone(),
-										substrate_backend.clone(),
-										indexer_backend.clone(),
-										parent_hash,
-									)
-									.await;
-								}
+						// Complete the logs of blocks whose metadata was committed last run.
+						index_pending_logs(indexer_backend.clone()).await;
+						// Finish any ancestor descent interrupted by the last shutdown.
+						resume_backfill(
+							client.clone(),
+							substrate_backend.clone(),
+							indexer_backend.clone(),
+						)
+						.await;
+						// Resume forward from the checkpoint while it is still canonical.
+						let checkpoint = match indexer_backend.checkpoint().await {
+							Ok(checkpoint) => {
+								checkpoint.filter(|(_, hash)| is_canonical(&*client, *hash))
 							}
-							None => {
-								index_genesis_block(client.clone(), indexer_backend.clone()).await;
+							Err(err) => {
+								log::error!(target: "frontier-sql", "Failed retrieving the checkpoint: {err:?}");
+								None
 							}
 						};
+						if let Some((number, hash)) = checkpoint {
+							log::debug!(target: "frontier-sql", "Resume from checkpoint #{number} {hash:?}");
+							resume_from_checkpoint(
+								client.clone(),
+								substrate_backend.clone(),
+								indexer_backend.clone(),
+								number,
+							)
+							.await;
+						} else {
+							// Attempt to resume from last indexed block. If there is no data in the db, sync genesis.
+							match indexer_backend.last_indexed_canon_block().await.ok() {
+								Some(last_block_hash) => {
+									log::debug!(target: "frontier-sql", "Resume from last block {last_block_hash:?}");
+									if let Some(parent_hash) = client
+										.header(last_block_hash)
+										.ok()
+										.flatten()
+										.map(|header| *header.parent_hash())
+									{
+										index_canonical_block_and_ancestors(
+											client.clone(),
+											substrate_backend.clone(),
+											indexer_backend.clone(),
+											parent_hash,
+										)
+										.await;
+									}
+								}
+								None => {
+									index_genesis_block(client.clone(), indexer_backend.clone())
+										.await;
+								}
+							};
+						}
 					}
 					WorkerCommand::IndexLeaves(leaves) => {
+						// Skip the indexed leaves with a single lookup.
+						let leaves = match indexer_backend.get_unindexed_blocks(&leaves).await {
+							Ok(unindexed) => unindexed,
+							Err(err) => {
+								log::debug!(target: "frontier-sql", "Failed looking up indexed leaves: {err:?}");
+								leaves
+							}
+						};
 						for leaf in leaves {
 							index_block_and_ancestors(
 								client.clone(),
@@ -123,28 +299,55 @@ where
 						}
 					}
 					WorkerCommand::IndexBestBlock(block_hash) => {
-						index_canonical_block_and_ancestors(
+						// Blocks left without their logs keep the checkpoint below them, their
+						// logs being indexed again by the next check.
+						if index_canonical_block_and_ancestors(
 							client.clone(),
 							substrate_backend.clone(),
 							indexer_backend.clone(),
 							block_hash,
 						)
-						.await;
+						.await
+						{
+							record_checkpoint(&*client, &indexer_backend, block_hash).await;
+						}
+						if let Some(prune_depth) = prune_depth {
+							prune_below_depth(&*client, &indexer_backend, prune_depth).await;
+						}
+						// The best blocks read ahead are indexed at once, each of them being
+						// notified nonetheless.
+						let enacted = enacted_since(&*substrate_backend, notified_best, block_hash);
 						let sinks = &mut pubsub_notification_sinks.lock();
-						for sink in sinks.iter() {
-							let _ = sink.unbounded_send(EthereumBlockNotification {
-								is_new_best: true,
-								hash: block_hash,
-							});
+						for hash in enacted {
+							for sink in sinks.iter() {
+								let _ = sink.unbounded_send(EthereumBlockNotification {
+									is_new_best: true,
+									hash,
+								});
+							}
 						}
+						notified_best = block_hash;
 					}
 					WorkerCommand::Canonicalize {
 						common,
 						enacted,
 						retracted,
 					} => {
-						canonicalize_blocks(indexer_backend.clone(), common, enacted, retracted)
-							.await;
+						canonicalize_blocks(
+							client.clone(),
+							indexer_backend.clone(),
+							common,
+							enacted,
+							retracted,
+							max_reorg_depth,
+						)
+						.await;
+						if let Some(metrics) = &metrics {
+							metrics.record_reorg();
+						}
+						if let Some(prune_depth) = prune_depth {
+							prune_below_depth(&*client, &indexer_backend, prune_depth).await;
+						}
 					}
 					WorkerCommand::CheckIndexedBlocks => {
 						// Fix any indexed blocks that did not have their logs indexed
@@ -152,9 +355,17 @@ where
 							indexer_backend.get_first_pending_canon_block().await
 						{
 							log::debug!(target: "frontier-sql", "Indexing pending canonical block {block_hash:?}");
-							indexer_backend.index_block_logs(block_hash).await;
+							let _ = indexer_backend.index_block_logs(block_hash).await;
 						}
 
+						// Backfill the ancestors of the blocks where a descent was interrupted
+						fill_gaps(
+							client.clone(),
+							substrate_backend.clone(),
+							indexer_backend.clone(),
+						)
+						.await;
+
 						// Fix any missing blocks
 						index_missing_blocks(
 							client.clone(),
@@ -163,14 +374,72 @@ where
 						)
 						.await;
 					}
+					WorkerCommand::RecordFinalized(block_hash) => {
+						if let Err(err) = indexer_backend.set_last_finalized(block_hash).await {
+							log::error!(target: "frontier-sql", "Failed recording finalized block {block_hash:?}: {err:?}");
+						}
+						// Finality may have moved past retracted blocks due for pruning.
+						if let Ok(Some(number)) = client.number(block_hash) {
+							let number = UniqueSaturatedInto::<u32>::unique_saturated_into(number);
+							match indexer_backend.prune_retracted_blocks(number).await {
+								Ok(0) => {}
+								Ok(pruned) => {
+									log::debug!(target: "frontier-sql", "Pruned {pruned} retracted blocks below #{number}");
+								}
+								Err(err) => {
+									log::error!(target: "frontier-sql", "Failed pruning retracted blocks below #{number}: {err:?}");
+								}
+							}
+							match indexer_backend.prune_logs(number).await {
+								Ok(0) => {}
+								Ok(pruned) => {
+									log::debug!(target: "frontier-sql", "Pruned {pruned} logs below #{number}");
+								}
+								Err(err) => {
+									log::error!(target: "frontier-sql", "Failed pruning logs below #{number}: {err:?}");
+								}
+							}
+						}
+					}
+					WorkerCommand::IndexGaps => {
+						index_gaps(
+							client.clone(),
+							substrate_backend.clone(),
+							indexer_backend.clone(),
+						)
+						.await;
+					}
+				}
+				if let Some(metrics) = &metrics {
+					metrics.observe_batch(started.elapsed());
+				}
+				if metrics.is_none() && sync_state.is_none() {
+					continue;
+				}
+				if let Some(state) = SyncState::current(&*client, &indexer_backend).await {
+					if let Some(metrics) = &metrics {
+						metrics.update(&state);
+					}
+					if let Some(sync_state) = &sync_state {
+						sync_state.send_if_modified(|current| {
+							let modified = *current != state;
+							*current = state;
+							modified
+						});
+					}
 				}
 			}
+			flush_writes(&indexer_backend).await;
 		});
 
 		tx
 	}
 
-	/// Start the worker.
+	/// Start the worker. Runs until `shutdown` resolves, or a fatal indexing error if
+	/// `exit_on_fatal` is set. On shutdown, the commands already queued are executed before
+	/// returning, so that no block notified so far is left unindexed. The progress of the
+	/// index is sent to `sync_state`, if any.
+	#[allow(clippy::too_many_arguments)]
 	pub async fn run(
 		client: Arc<Client>,
 		substrate_backend: Arc<Backend>,
@@ -182,12 +451,38 @@ where
 		pubsub_notification_sinks: Arc<
 			EthereumBlockNotificationSinks<EthereumBlockNotification<Block>>,
 		>,
-	) {
+		prometheus_registry: Option<prometheus_endpoint::Registry>,
+		sync_state: Option<tokio::sync::watch::Sender<SyncState>>,
+		shutdown: impl Future<Output = ()>,
+	) where
+		Client: BlockchainEvents<Block>,
+	{
+		let metrics = prometheus_registry.and_then(|registry| {
+			metrics::SyncMetrics::register(&registry)
+				.map_err(|err| {
+					log::error!(target: "frontier-sql", "Failed to register metrics: {err:?}");
+				})
+				.ok()
+		});
+		let shutdown = shutdown.fuse();
+		futures::pin_mut!(shutdown);
+		let initial_delay = worker_config.initial_delay;
+		if !initial_delay.is_zero() {
+			log::debug!(target: "frontier-sql", "Delaying the indexer by {initial_delay:?}");
+			futures::select! {
+				_ = shutdown => return,
+				_ = futures_timer::Delay::new(initial_delay).fuse() => {}
+			}
+		}
 		let tx = Self::spawn_worker(
 			client.clone(),
 			substrate_backend.clone(),
 			indexer_backend.clone(),
 			pubsub_notification_sinks.clone(),
+			metrics,
+			sync_state,
+			worker_config.prune_depth,
+			worker_config.max_reorg_depth,
 		)
 		.await;
 
@@ -195,19 +490,53 @@ where
 		tx.send(WorkerCommand::ResumeSync).await.ok();
 		// check missing blocks every interval
 		let tx2 = tx.clone();
-		tokio::task::spawn(async move {
+		let checker = tokio::task::spawn(async move {
 			loop {
 				futures_timer::Delay::new(worker_config.check_indexed_blocks_interval).await;
 				tx2.send(WorkerCommand::CheckIndexedBlocks).await.ok();
+				if worker_config.index_all_gaps {
+					tx2.send(WorkerCommand::IndexGaps).await.ok();
+				}
 			}
 		});
 
-		// check notifications
-		let mut notifications = import_notifications.fuse();
+		// check notifications, or poll the client if it does not emit them
+		let (notifications, finality_notifications) = match worker_config.poll_interval {
+			Some(_) => (stream::pending().boxed(), stream::pending().boxed()),
+			None => (
+				import_notifications.boxed(),
+				client.finality_notification_stream().boxed(),
+			),
+		};
+		let (mut notifications, mut finality_notifications) =
+			(notifications.fuse(), finality_notifications.fuse());
+		let mut poll = match worker_config.poll_interval {
+			Some(interval) => futures_timer::Delay::new(interval).fuse(),
+			None => future::Fuse::terminated(),
+		};
+		let mut last_finalized_hash: Option<H256> = None;
+		let mut followed = FollowedBest::default();
+		// The end of an ongoing burst of re-orgs.
+		let mut reorg_flush = future::Fuse::terminated();
 		loop {
+			if worker_config.exit_on_fatal {
+				if let Some(err) = indexer_backend.fatal_error() {
+					log::error!(target: "frontier-sql", "💀  Stopping the indexer on fatal error: {err}");
+					return;
+				}
+			}
 			let mut timeout =
 				futures_timer::Delay::new(worker_config.read_notification_timeout).fuse();
 			futures::select! {
+				_ = shutdown => {
+					log::info!(target: "frontier-sql", "🛑  Stopping the indexer once the queued commands are executed");
+					checker.abort();
+					let (done_tx, done_rx) = futures::channel::oneshot::channel();
+					if tx.send(WorkerCommand::Shutdown(done_tx)).await.is_ok() {
+						done_rx.await.ok();
+					}
+					return;
+				}
 				_ = timeout => {
 					if let Ok(leaves) = substrate_backend.blockchain().leaves() {
 						tx.send(WorkerCommand::IndexLeaves(leaves)).await.ok();
@@ -220,52 +549,264 @@ where
 					}
 				}
 				notification = notifications.next() => if let Some(notification) = notification {
+					// The best block is re-derived from the client, so the notifications
+					// superseded by a newer best one need not be indexed on their own. The
+					// worker still notifies every block enacted up to the new best one.
+					let (best, read_ahead) = read_ahead(
+						notification,
+						&mut notifications,
+						|notification| notification.is_new_best,
+					);
+					if read_ahead > 0 {
+						log::debug!(target: "frontier-sql", "⏩  Read {read_ahead} queued notifications ahead");
+					}
+					if let Some(notification) = best {
+						log::debug!(
+							target: "frontier-sql",
+							"📣  New best notification: #{} {:?} (parent {})",
+							notification.header.number(),
+							notification.hash,
+							notification.header.parent_hash(),
+						);
+						let imported = (notification.hash, notification.tree_route.as_deref());
+						let reorg_started = follow_best_block(
+							&*client,
+							&*substrate_backend,
+							&tx,
+							worker_config.reorg_debounce,
+							&mut followed,
+							Some(imported),
+						)
+						.await;
+						if reorg_started {
+							let debounce = worker_config.reorg_debounce;
+							reorg_flush = futures_timer::Delay::new(debounce).fuse();
+						}
+					}
+				},
+				notification = finality_notifications.next() => if let Some(notification) = notification {
 					log::debug!(
 						target: "frontier-sql",
-						"📣  New notification: #{} {:?} (parent {}), best = {}",
+						"🏁  Finalized: #{} {:?}",
 						notification.header.number(),
 						notification.hash,
-						notification.header.parent_hash(),
-						notification.is_new_best,
 					);
-					if notification.is_new_best {
-						if let Some(tree_route) = notification.tree_route {
+					tx.send(WorkerCommand::RecordFinalized(notification.hash)).await.ok();
+				},
+				_ = poll => {
+					let reorg_started = follow_best_block(
+						&*client,
+						&*substrate_backend,
+						&tx,
+						worker_config.reorg_debounce,
+						&mut followed,
+						None,
+					)
+					.await;
+					if reorg_started {
+						let debounce = worker_config.reorg_debounce;
+						reorg_flush = futures_timer::Delay::new(debounce).fuse();
+					}
+					let finalized_hash = client.info().finalized_hash;
+					if last_finalized_hash != Some(finalized_hash) {
+						log::debug!(target: "frontier-sql", "🏁  Polled finalized: {finalized_hash:?}");
+						tx.send(WorkerCommand::RecordFinalized(finalized_hash)).await.ok();
+						last_finalized_hash = Some(finalized_hash);
+					}
+					if let Some(interval) = worker_config.poll_interval {
+						poll = futures_timer::Delay::new(interval).fuse();
+					}
+				},
+				_ = reorg_flush => if let Some(from_hash) = followed.pending_reorg.take() {
+					// Canonicalize once from the best block before the burst to the final one.
+					let best_hash = client.info().best_hash;
+					let tree_route =
+						sp_blockchain::tree_route(substrate_backend.blockchain(), from_hash, best_hash);
+					match tree_route {
+						Ok(tree_route) if !tree_route.retracted().is_empty() => {
 							log::debug!(
 								target: "frontier-sql",
-								"🔀  Re-org happened at new best {}, proceeding to canonicalize db",
-								notification.hash
+								"🔀  Re-orgs settled at new best {best_hash}, proceeding to canonicalize db",
+							);
+							tx.send(canonicalize_command(&tree_route)).await.ok();
+						}
+						Ok(_) => {}
+						Err(err) => {
+							log::warn!(
+								target: "frontier-sql",
+								"Failed computing tree route from {from_hash:?} to {best_hash:?}: {err:?}",
 							);
-							let retracted = tree_route
-								.retracted()
-								.iter()
-								.map(|hash_and_number| hash_and_number.hash)
-								.collect::<Vec<_>>();
-							let enacted = tree_route
-								.enacted()
-								.iter()
-								.map(|hash_and_number| hash_and_number.hash)
-								.collect::<Vec<_>>();
-
-							let common = tree_route.common_block().hash;
-							tx.send(WorkerCommand::Canonicalize {
-								common,
-								enacted,
-								retracted,
-							}).await.ok();
 						}
-
-						tx.send(WorkerCommand::IndexBestBlock(notification.hash)).await.ok();
 					}
+					tx.send(WorkerCommand::IndexBestBlock(best_hash)).await.ok();
+					followed.hash = Some(best_hash);
 				}
 			}
 		}
 	}
 }
 
+/// Commits the indexing writes grouped by the indexer backend, if any.
+async fn flush_writes<Block: BlockT<Hash = H256>>(indexer_backend: &fc_db::sql::Backend<Block>) {
+	if let Err(err) = indexer_backend.flush_writes().await {
+		indexer_backend.record_error(&err);
+		log::error!(target: "frontier-sql", "Failed committing the grouped writes: {err:?}");
+	}
+}
+
+/// Reads the notifications already queued after `first`, up to [`NOTIFICATION_LOOKAHEAD`],
+/// without waiting for new ones. Returns the latest new best one, if any, along with the
+/// number of notifications read ahead.
+fn read_ahead<T>(
+	first: T,
+	notifications: &mut (impl Stream<Item = T> + Unpin),
+	is_new_best: impl Fn(&T) -> bool,
+) -> (Option<T>, usize) {
+	let mut best = Some(first).filter(&is_new_best);
+	let mut read = 0;
+	while read < NOTIFICATION_LOOKAHEAD {
+		let Some(Some(notification)) = notifications.next().now_or_never() else {
+			break;
+		};
+		read += 1;
+		if is_new_best(&notification) {
+			best = Some(notification);
+		}
+	}
+	(best, read)
+}
+
+/// The best block followed by [`SyncWorker::run`].
+#[derive(Default)]
+struct FollowedBest {
+	/// The best block the index was last canonicalized to.
+	hash: Option<H256>,
+	/// The best block before an ongoing burst of re-orgs.
+	pending_reorg: Option<H256>,
+}
+
+/// Indexes the client's best block if it changed, canonicalizing the index first on a re-org.
+/// The route reported by the import notification of the best block is used until a first
+/// best block is followed. Returns whether a burst of re-orgs started, to be canonicalized
+/// once the debounce delay elapsed.
+async fn follow_best_block<Block, Backend, Client>(
+	client: &Client,
+	substrate_backend: &Backend,
+	tx: &tokio::sync::mpsc::Sender<WorkerCommand>,
+	reorg_debounce: Duration,
+	followed: &mut FollowedBest,
+	imported: Option<(H256, Option<&sp_blockchain::TreeRoute<Block>>)>,
+) -> bool
+where
+	Block: BlockT<Hash = H256>,
+	Client: HeaderBackend<Block>,
+	Backend: BackendT<Block>,
+{
+	// Best block notifications for competing siblings may arrive out of order,
+	// so the canonical chain is re-derived from the client's current best block.
+	let best_hash = client.info().best_hash;
+	if followed.hash == Some(best_hash) {
+		return false;
+	}
+	let tree_route = match (followed.hash, imported) {
+		(Some(last_best_hash), _) => {
+			sp_blockchain::tree_route(substrate_backend.blockchain(), last_best_hash, best_hash)
+				.map_err(|err| {
+					log::warn!(
+						target: "frontier-sql",
+						"Failed computing tree route from {last_best_hash:?} to {best_hash:?}: {err:?}",
+					);
+				})
+				.ok()
+		}
+		(None, Some((hash, tree_route))) if hash == best_hash => tree_route.cloned(),
+		(None, _) => None,
+	};
+	let tree_route = tree_route.filter(|route| !route.retracted().is_empty());
+	let mut reorg_started = false;
+	if followed.pending_reorg.is_some() {
+		// Applied along with the rest of the burst once it ends.
+	} else if let Some(tree_route) = tree_route {
+		if reorg_debounce.is_zero() {
+			log::debug!(
+				target: "frontier-sql",
+				"🔀  Re-org happened at new best {best_hash}, proceeding to canonicalize db",
+			);
+			tx.send(canonicalize_command(&tree_route)).await.ok();
+			tx.send(WorkerCommand::IndexBestBlock(best_hash)).await.ok();
+		} else {
+			log::debug!(
+				target: "frontier-sql",
+				"🔀  Re-org happened at new best {best_hash}, waiting for the chain to settle",
+			);
+			followed.pending_reorg = Some(tree_route.retracted()[0].hash);
+			reorg_started = true;
+		}
+	} else {
+		tx.send(WorkerCommand::IndexBestBlock(best_hash)).await.ok();
+	}
+	followed.hash = Some(best_hash);
+	reorg_started
+}
+
+/// The blocks enacted from `last_best` to `best_hash`, in ascending order, or `best_hash`
+/// alone if there is no route from it or no block enacted.
+fn enacted_since<Block, Backend>(
+	substrate_backend: &Backend,
+	last_best: H256,
+	best_hash: H256,
+) -> Vec<H256>
+where
+	Block: BlockT<Hash = H256>,
+	Backend: BackendT<Block>,
+{
+	match sp_blockchain::tree_route(substrate_backend.blockchain(), last_best, best_hash) {
+		Ok(tree_route) if !tree_route.enacted().is_empty() => tree_route
+			.enacted()
+			.iter()
+			.map(|hash_and_number| hash_and_number.hash)
+			.collect(),
+		Ok(_) => vec![best_hash],
+		Err(err) => {
+			log::warn!(
+				target: "frontier-sql",
+				"Failed computing tree route from {last_best:?} to {best_hash:?}: {err:?}",
+			);
+			vec![best_hash]
+		}
+	}
+}
+
+/// The command canonicalizing the index along the given tree route.
+fn canonicalize_command<Block: BlockT<Hash = H256>>(
+	tree_route: &sp_blockchain::TreeRoute<Block>,
+) -> WorkerCommand {
+	WorkerCommand::Canonicalize {
+		common: tree_route.common_block().hash,
+		enacted: tree_route
+			.enacted()
+			.iter()
+			.map(|hash_and_number| hash_and_number.hash)
+			.collect(),
+		retracted: tree_route
+			.retracted()
+			.iter()
+			.map(|hash_and_number| hash_and_number.hash)
+			.collect(),
+	}
+}
+
 /// Index the provided blocks. The function loops over the ancestors of the provided nodes
 /// until it encounters the genesis block, or a block that has already been imported, or
 /// is already in the active set. The `hashes` parameter is populated with any parent blocks
 /// that is scheduled to be indexed.
+#[tracing::instrument(
+	name = "index_blocks",
+	target = "frontier-sql",
+	level = "debug",
+	skip_all,
+	fields(start_hash = ?hash, len = tracing::field::Empty),
+)]
 async fn index_block_and_ancestors<Block, Backend, Client>(
 	client: Arc<Client>,
 	substrate_backend: Arc<Backend>,
@@ -275,64 +816,168 @@ async fn index_block_and_ancestors<Block, Backend, Client>(
 	Block: BlockT<Hash = H256>,
 	Client: ProvideRuntimeApi<Block>,
 	Client::Api: EthereumRuntimeRPCApi<Block>,
-	Client: HeaderBackend<Block> + StorageProvider<Block, Backend> + 'static,
+	Client: HeaderBackend<Block> + BlockBackend<Block> + StorageProvider<Block, Backend> + 'static,
 	Backend: BackendT<Block> + 'static,
 {
 	let blockchain_backend = substrate_backend.blockchain();
+	let pruned_below = pruned_below(&indexer_backend).await;
 	let mut hashes = vec![hash];
+	// The last block of the descent, whose parent is being indexed.
+	let mut child = None;
+	let mut len = 0;
 	while let Some(hash) = hashes.pop() {
 		// exit if genesis block is reached
 		if hash == H256::default() {
 			break;
 		}
 
+		// exit if the pruned blocks are reached
+		if is_pruned(&*client, pruned_below, hash) {
+			break;
+		}
+
 		// exit if block is already imported
 		if indexer_backend.is_block_indexed(hash).await {
 			log::debug!(target: "frontier-sql", "🔴 Block {hash:?} already imported");
 			break;
 		}
 
+		// skip orphans, only their canonical ancestors are stored in canonical-only mode
+		if indexer_backend.is_canonical_only() && !is_canonical(&*client, hash) {
+			log::debug!(target: "frontier-sql", "Skipping non-canonical block {hash:?}");
+			if let Some(parent_hash) =
+				parent_hash_or_record_gap(blockchain_backend, &indexer_backend, hash).await
+			{
+				hashes.push(parent_hash);
+				child = Some(hash);
+			}
+			continue;
+		}
+
 		log::debug!(target: "frontier-sql", "🛠️  Importing {hash:?}");
-		let _ = indexer_backend
+		len += 1;
+		if let Err(err) = indexer_backend
 			.insert_block_metadata(client.clone(), hash)
+			.instrument(insert_block_metadata_span(hash))
 			.await
-			.map_err(|e| {
-				log::error!(target: "frontier-sql", "{e}");
-			});
+		{
+			indexer_backend.record_error(&err);
+			log::error!(target: "frontier-sql", "{err}");
+			if fc_db::sql::is_transient_error(&err) {
+				record_descent_gap(&indexer_backend, child).await;
+				break;
+			}
+		}
 		log::debug!(target: "frontier-sql", "Inserted block metadata");
-		indexer_backend.index_block_logs(hash).await;
+		let _ = indexer_backend
+			.index_block_logs(hash)
+			.instrument(index_logs_span(1))
+			.await;
 
-		if let Ok(Some(header)) = blockchain_backend.header(hash) {
-			let parent_hash = header.parent_hash();
-			hashes.push(*parent_hash);
+		if let Some(parent_hash) =
+			parent_hash_or_record_gap(blockchain_backend, &indexer_backend, hash).await
+		{
+			hashes.push(parent_hash);
+			child = Some(hash);
 		}
 	}
+	tracing::Span::current().record("len", len);
+}
+
+/// The span of the metadata insertion of a block, nested in the span of its indexing.
+fn insert_block_metadata_span(hash: H256) -> tracing::Span {
+	tracing::debug_span!(target: "frontier-sql", "insert_block_metadata", hash = ?hash)
+}
+
+/// The span of the logs indexing of a number of blocks.
+fn index_logs_span(len: usize) -> tracing::Span {
+	tracing::debug_span!(target: "frontier-sql", "index_logs", len)
+}
+
+/// The height below which the indexed blocks were pruned, not to be indexed again by the
+/// ancestor descents.
+async fn pruned_below<Block>(indexer_backend: &fc_db::sql::Backend<Block>) -> u32
+where
+	Block: BlockT<Hash = H256>,
+{
+	indexer_backend.pruned_below().await.unwrap_or_else(|err| {
+		log::error!(target: "frontier-sql", "Failed retrieving the pruned height: {err:?}");
+		0
+	})
+}
+
+/// Whether the block is below the pruned height.
+fn is_pruned<Block, Client>(client: &Client, pruned_below: u32, hash: H256) -> bool
+where
+	Block: BlockT<Hash = H256>,
+	Client: HeaderBackend<Block>,
+{
+	matches!(
+		client.number(hash),
+		Ok(Some(number)) if UniqueSaturatedInto::<u32>::unique_saturated_into(number) < pruned_below
+	)
+}
+
+/// Whether the block is on the client's canonical chain.
+fn is_canonical<Block, Client>(client: &Client, hash: H256) -> bool
+where
+	Block: BlockT<Hash = H256>,
+	Client: HeaderBackend<Block>,
+{
+	matches!(
+		client.number(hash),
+		Ok(Some(number)) if client.hash(number).ok().flatten() == Some(hash)
+	)
 }
 
 /// Index the provided known canonical blocks. The function loops over the ancestors of the provided nodes
 /// until it encounters the genesis block, or a block that has already been imported, or
 /// is already in the active set. The `hashes` parameter is populated with any parent blocks
-/// that is scheduled to be indexed.
+/// that is scheduled to be indexed. Returns whether the logs of every block indexed by the
+/// descent were committed, failed ones being left pending.
+#[tracing::instrument(
+	name = "index_blocks",
+	target = "frontier-sql",
+	level = "debug",
+	skip_all,
+	fields(start_hash = ?hash, len = tracing::field::Empty),
+)]
 async fn index_canonical_block_and_ancestors<Block, Backend, Client>(
 	client: Arc<Client>,
 	substrate_backend: Arc<Backend>,
 	indexer_backend: Arc<fc_db::sql::Backend<Block>>,
 	hash: H256,
-) where
+) -> bool
+where
 	Block: BlockT<Hash = H256>,
 	Client: ProvideRuntimeApi<Block>,
 	Client::Api: EthereumRuntimeRPCApi<Block>,
-	Client: HeaderBackend<Block> + StorageProvider<Block, Backend> + 'static,
+	Client: HeaderBackend<Block> + BlockBackend<Block> + StorageProvider<Block, Backend> + 'static,
 	Backend: BackendT<Block> + 'static,
 {
 	let blockchain_backend = substrate_backend.blockchain();
+	let start_hash = hash;
+	// Set once the descent goes past its first block and starts recording its progress.
+	let mut backfilling = false;
+	// Blocks whose metadata is indexed, their logs are indexed in batches.
+	let mut pending_logs = vec![];
+	let mut logs_indexed = true;
 	let mut hashes = vec![hash];
+	// The last block of the descent, whose parent is being indexed.
+	let mut child = None;
+	let mut len = 0;
+	let pruned_below = pruned_below(&indexer_backend).await;
 	while let Some(hash) = hashes.pop() {
 		// exit if genesis block is reached
 		if hash == H256::default() {
 			break;
 		}
 
+		// exit if the pruned blocks are reached
+		if is_pruned(&*client, pruned_below, hash) {
+			break;
+		}
+
 		let status = indexer_backend.block_indexed_and_canon_status(hash).await;
 
 		// exit if canonical block is already imported
@@ -351,109 +996,536 @@ async fn index_canonical_block_and_ancestors<Block, Backend, Client>(
 			log::debug!(target: "frontier-sql", "🛠️  Marked block as canon {hash:?}");
 
 			// Check parent block
-			if let Ok(Some(header)) = blockchain_backend.header(hash) {
-				let parent_hash = header.parent_hash();
-				hashes.push(*parent_hash);
+			if let Some(parent_hash) =
+				parent_hash_or_record_gap(blockchain_backend, &indexer_backend, hash).await
+			{
+				hashes.push(parent_hash);
+				child = Some(hash);
 			}
 			continue;
 		}
 
 		// Else, import the new block
 		log::debug!(target: "frontier-sql", "🛠️  Importing {hash:?}");
-		let _ = indexer_backend
+		len += 1;
+		if let Err(err) = indexer_backend
 			.insert_block_metadata(client.clone(), hash)
+			.instrument(insert_block_metadata_span(hash))
 			.await
-			.map_err(|e| {
-				log::error!(target: "frontier-sql", "{e}");
-			});
+		{
+			indexer_backend.record_error(&err);
+			log::error!(target: "frontier-sql", "{err}");
+			if fc_db::sql::is_transient_error(&err) {
+				record_descent_gap(&indexer_backend, child).await;
+				break;
+			}
+		}
 		log::debug!(target: "frontier-sql", "Inserted block metadata  {hash:?}");
-		indexer_backend.index_block_logs(hash).await;
+		pending_logs.push(hash);
+		if pending_logs.len() >= indexer_backend.logs_batch_size() {
+			logs_indexed &= indexer_backend
+				.index_blocks_logs(&pending_logs)
+				.instrument(index_logs_span(pending_logs.len()))
+				.await
+				.is_ok();
+			pending_logs.clear();
+		}
+
+		if hash != start_hash {
+			if let Ok(Some(block_number)) = client.number(hash) {
+				backfilling = true;
+				if let Err(err) = indexer_backend
+					.set_backfill_progress(start_hash, block_number.unique_saturated_into())
+					.await
+				{
+					log::error!(target: "frontier-sql", "Failed recording backfill progress at {hash:?}: {err:?}");
+				}
+			}
+		}
+
+		if let Some(parent_hash) =
+			parent_hash_or_record_gap(blockchain_backend, &indexer_backend, hash).await
+		{
+			hashes.push(parent_hash);
+			child = Some(hash);
+		}
+	}
+
+	if !pending_logs.is_empty() {
+		logs_indexed &= indexer_backend
+			.index_blocks_logs(&pending_logs)
+			.instrument(index_logs_span(pending_logs.len()))
+			.await
+			.is_ok();
+	}
+	tracing::Span::current().record("len", len);
 
-		if let Ok(Some(header)) = blockchain_backend.header(hash) {
-			let parent_hash = header.parent_hash();
-			hashes.push(*parent_hash);
+	if backfilling {
+		if let Err(err) = indexer_backend.clear_backfill_progress(start_hash).await {
+			log::error!(target: "frontier-sql", "Failed clearing backfill progress: {err:?}");
 		}
 	}
+	logs_indexed
 }
 
-/// Canonicalizes the database by setting the `is_canon` field for the retracted blocks to `0`,
-/// and `1` if they are enacted.
-async fn canonicalize_blocks<Block: BlockT<Hash = H256>>(
-	indexer_backend: Arc<fc_db::sql::Backend<Block>>,
-	common: H256,
-	enacted: Vec<H256>,
-	retracted: Vec<H256>,
-) {
-	if (indexer_backend.canonicalize(&retracted, &enacted).await).is_err() {
-		log::error!(
-			target: "frontier-sql",
-			"❌  Canonicalization failed for common ancestor {}, potentially corrupted db. Retracted: {:?}, Enacted: {:?}",
-			common,
-			retracted,
-			enacted,
-		);
+/// Records the block as the checkpoint once it is indexed as canonical, its ancestors having
+/// been indexed by the descent.
+async fn record_checkpoint<Block, Client>(
+	client: &Client,
+	indexer_backend: &fc_db::sql::Backend<Block>,
+	hash: H256,
+) where
+	Block: BlockT<Hash = H256>,
+	Client: HeaderBackend<Block>,
+{
+	let status = indexer_backend.block_indexed_and_canon_status(hash).await;
+	if !(status.indexed && status.canon) {
+		return;
+	}
+	if let Ok(Some(number)) = client.number(hash) {
+		if let Err(err) = indexer_backend
+			.set_checkpoint(number.unique_saturated_into(), hash)
+			.await
+		{
+			log::error!(target: "frontier-sql", "Failed recording checkpoint {hash:?}: {err:?}");
+		}
 	}
 }
 
-/// Attempts to index any missing blocks that are in the past. This fixes any gaps that may
-/// be present in the indexing strategy, since the indexer only walks the parent hashes until
-/// it finds the first ancestor that has already been indexed.
-async fn index_missing_blocks<Block, Client, Backend>(
-	client: Arc<Client>,
-	substrate_backend: Arc<Backend>,
-	indexer_backend: Arc<fc_db::sql::Backend<Block>>,
+/// Prunes the indexed blocks deeper than `prune_depth` below the best block.
+async fn prune_below_depth<Block, Client>(
+	client: &Client,
+	indexer_backend: &fc_db::sql::Backend<Block>,
+	prune_depth: u32,
 ) where
 	Block: BlockT<Hash = H256>,
-	Client: ProvideRuntimeApi<Block>,
-	Client::Api: EthereumRuntimeRPCApi<Block>,
-	Client: HeaderBackend<Block> + StorageProvider<Block, Backend> + 'static,
-	Backend: BackendT<Block> + 'static,
+	Client: HeaderBackend<Block>,
 {
-	if let Some(block_number) = indexer_backend.get_first_missing_canon_block().await {
-		log::debug!(target: "frontier-sql", "Missing {block_number:?}");
-		if block_number == 0 {
-			index_genesis_block(client.clone(), indexer_backend.clone()).await;
-		} else if let Ok(Some(block_hash)) = client.hash(block_number.unique_saturated_into()) {
-			log::debug!(
-				target: "frontier-sql",
-				"Indexing past canonical blocks from #{} {:?}",
-				block_number,
-				block_hash,
-			);
-			index_canonical_block_and_ancestors(
-				client.clone(),
-				substrate_backend.clone(),
-				indexer_backend.clone(),
-				block_hash,
-			)
-			.await;
-		} else {
-			log::debug!(target: "frontier-sql", "Failed retrieving hash for block #{block_number}");
+	let best_number: u32 = client.info().best_number.unique_saturated_into();
+	let Some(block_number) = best_number.saturating_add(1).checked_sub(prune_depth) else {
+		return;
+	};
+	match indexer_backend.prune_logs_below(block_number).await {
+		Ok(0) => {}
+		Ok(pruned) => {
+			log::debug!(target: "frontier-sql", "Pruned {pruned} blocks below #{block_number}");
+		}
+		Err(err) => {
+			log::error!(target: "frontier-sql", "Failed pruning blocks below #{block_number}: {err:?}");
 		}
 	}
 }
 
-/// Attempts to index any missing blocks that are in the past. This fixes any gaps that may
-/// be present in the indexing strategy, since the indexer only walks the parent hashes until
-/// it finds the first ancestor that has already been indexed.
-async fn index_genesis_block<Block, Client, Backend>(
+/// Indexes the canonical blocks above the checkpoint in ascending order, up to the best
+/// block, each one moving the checkpoint forward.
+async fn resume_from_checkpoint<Block, Backend, Client>(
 	client: Arc<Client>,
+	substrate_backend: Arc<Backend>,
 	indexer_backend: Arc<fc_db::sql::Backend<Block>>,
+	checkpoint: u32,
 ) where
 	Block: BlockT<Hash = H256>,
 	Client: ProvideRuntimeApi<Block>,
 	Client::Api: EthereumRuntimeRPCApi<Block>,
-	Client: HeaderBackend<Block> + StorageProvider<Block, Backend> + 'static,
+	Client: HeaderBackend<Block> + BlockBackend<Block> + StorageProvider<Block, Backend> + 'static,
 	Backend: BackendT<Block> + 'static,
 {
-	log::info!(
-		target: "frontier-sql",
-		"Import genesis",
+	let best_number: u32 = client.info().best_number.unique_saturated_into();
+	for block_number in checkpoint.saturating_add(1)..=best_number {
+		let Ok(Some(hash)) = client.hash(block_number.into()) else {
+			break;
+		};
+		if !index_canonical_block_and_ancestors(
+			client.clone(),
+			substrate_backend.clone(),
+			indexer_backend.clone(),
+			hash,
+		)
+		.await
+		{
+			break;
+		}
+		record_checkpoint(&*client, &indexer_backend, hash).await;
+	}
+}
+
+/// Indexes the logs of every block left with its metadata but without its logs, without
+/// touching the already committed metadata.
+async fn index_pending_logs<Block>(indexer_backend: Arc<fc_db::sql::Backend<Block>>)
+where
+	Block: BlockT<Hash = H256>,
+{
+	match indexer_backend.blocks_pending_logs().await {
+		Ok(block_hashes) => {
+			log::debug!(target: "frontier-sql", "Resuming logs of {} blocks", block_hashes.len());
+			let mut block_hashes = &block_hashes[..];
+			while !block_hashes.is_empty() {
+				let batch_size = indexer_backend.logs_batch_size().min(block_hashes.len());
+				let (batch, rest) = block_hashes.split_at(batch_size);
+				if indexer_backend.index_blocks_logs(batch).await.is_err() {
+					break;
+				}
+				block_hashes = rest;
+			}
+		}
+		Err(err) => {
+			log::error!(target: "frontier-sql", "Failed retrieving blocks pending logs: {err:?}");
+		}
+	}
+}
+
+/// Resumes the canonical ancestor descents interrupted by a shutdown, each from the parent of
+/// the lowest block it had indexed.
+async fn resume_backfill<Block, Backend, Client>(
+	client: Arc<Client>,
+	substrate_backend: Arc<Backend>,
+	indexer_backend: Arc<fc_db::sql::Backend<Block>>,
+) where
+	Block: BlockT<Hash = H256>,
+	Client: ProvideRuntimeApi<Block>,
+	Client::Api: EthereumRuntimeRPCApi<Block>,
+	Client: HeaderBackend<Block> + BlockBackend<Block> + StorageProvider<Block, Backend> + 'static,
+	Backend: BackendT<Block> + 'static,
+{
+	let progress = match indexer_backend.backfill_progress().await {
+		Ok(progress) => progress,
+		Err(err) => {
+			log::error!(target: "frontier-sql", "Failed retrieving backfill progress: {err:?}");
+			return;
+		}
+	};
+	for (descent, block_number) in progress {
+		let parent_hash = client
+			.hash(block_number.unique_saturated_into())
+			.ok()
+			.flatten()
+			.and_then(|hash| client.header(hash).ok().flatten())
+			.map(|header| *header.parent_hash());
+		if let Some(parent_hash) = parent_hash {
+			log::debug!(target: "frontier-sql", "Resuming backfill of {descent:?} below #{block_number}");
+			index_canonical_block_and_ancestors(
+				client.clone(),
+				substrate_backend.clone(),
+				indexer_backend.clone(),
+				parent_hash,
+			)
+			.await;
+		}
+		if let Err(err) = indexer_backend.clear_backfill_progress(descent).await {
+			log::error!(target: "frontier-sql", "Failed clearing backfill progress: {err:?}");
+		}
+	}
+}
+
+/// Fetches the parent hash of the given block to continue an ancestor descent, retrying
+/// transient header fetch errors with an exponential backoff. If the header still cannot be
+/// fetched, the block is recorded as a gap to be backfilled by [`fill_gaps`].
+async fn parent_hash_or_record_gap<Block, Blockchain>(
+	blockchain_backend: &Blockchain,
+	indexer_backend: &fc_db::sql::Backend<Block>,
+	hash: H256,
+) -> Option<H256>
+where
+	Block: BlockT<Hash = H256>,
+	Blockchain: HeaderBackend<Block> + ?Sized,
+{
+	let mut backoff = HEADER_FETCH_BACKOFF;
+	let mut retries = 0;
+	loop {
+		match blockchain_backend.header(hash) {
+			Ok(header) => return header.map(|header| *header.parent_hash()),
+			Err(err) if retries < HEADER_FETCH_RETRIES => {
+				log::debug!(
+					target: "frontier-sql",
+					"Failed fetching header {hash:?}, retrying in {backoff:?}: {err:?}",
+				);
+				futures_timer::Delay::new(backoff).await;
+				backoff *= 2;
+				retries += 1;
+			}
+			Err(err) => {
+				log::warn!(
+					target: "frontier-sql",
+					"Failed fetching header {hash:?}, recording gap: {err:?}",
+				);
+				if let Err(err) = indexer_backend.record_gap(hash).await {
+					log::error!(target: "frontier-sql", "Failed recording gap at {hash:?}: {err:?}");
+				}
+				return None;
+			}
+		}
+	}
+}
+
+/// Records a gap at the child of a block that could not be written to the database, so that
+/// the next check resumes the descent from that block. Without a child, the descent started at
+/// that block and is retried with the leaves.
+async fn record_descent_gap<Block>(
+	indexer_backend: &fc_db::sql::Backend<Block>,
+	child: Option<H256>,
+) where
+	Block: BlockT<Hash = H256>,
+{
+	if let Some(child) = child {
+		if let Err(err) = indexer_backend.record_gap(child).await {
+			log::error!(target: "frontier-sql", "Failed recording gap at {child:?}: {err:?}");
+		}
+	}
+}
+
+/// Resumes the ancestor descents interrupted by a header fetch failure.
+async fn fill_gaps<Block, Backend, Client>(
+	client: Arc<Client>,
+	substrate_backend: Arc<Backend>,
+	indexer_backend: Arc<fc_db::sql::Backend<Block>>,
+) where
+	Block: BlockT<Hash = H256>,
+	Client: ProvideRuntimeApi<Block>,
+	Client::Api: EthereumRuntimeRPCApi<Block>,
+	Client: HeaderBackend<Block> + BlockBackend<Block> + StorageProvider<Block, Backend> + 'static,
+	Backend: BackendT<Block> + 'static,
+{
+	let gaps = match indexer_backend.gaps().await {
+		Ok(gaps) => gaps,
+		Err(err) => {
+			log::error!(target: "frontier-sql", "Failed retrieving gaps: {err:?}");
+			return;
+		}
+	};
+	for hash in gaps {
+		let parent_hash = match substrate_backend.blockchain().header(hash) {
+			Ok(header) => header.map(|header| *header.parent_hash()),
+			Err(err) => {
+				log::debug!(target: "frontier-sql", "Gap at {hash:?} still not fillable: {err:?}");
+				continue;
+			}
+		};
+		log::debug!(target: "frontier-sql", "Filling gap at {hash:?}");
+		if let Err(err) = indexer_backend.remove_gap(hash).await {
+			log::error!(target: "frontier-sql", "Failed removing gap at {hash:?}: {err:?}");
+			continue;
+		}
+		if let Some(parent_hash) = parent_hash {
+			index_block_and_ancestors(
+				client.clone(),
+				substrate_backend.clone(),
+				indexer_backend.clone(),
+				parent_hash,
+			)
+			.await;
+		}
+	}
+}
+
+/// Canonicalizes the database by setting the `is_canon` field for the retracted blocks to `0`,
+/// and `1` if they are enacted. Enacted blocks that were never indexed (e.g. they were on the
+/// formerly orphaned side of a deep re-org) are indexed and marked as canon.
+#[tracing::instrument(
+	name = "canonicalize",
+	target = "frontier-sql",
+	level = "debug",
+	skip_all,
+	fields(common = ?common, enacted = enacted.len(), retracted = retracted.len()),
+)]
+async fn canonicalize_blocks<Block, Backend, Client>(
+	client: Arc<Client>,
+	indexer_backend: Arc<fc_db::sql::Backend<Block>>,
+	common: H256,
+	enacted: Vec<H256>,
+	retracted: Vec<H256>,
+	max_reorg_depth: u32,
+) where
+	Block: BlockT<Hash = H256>,
+	Client: ProvideRuntimeApi<Block>,
+	Client::Api: EthereumRuntimeRPCApi<Block>,
+	Client: HeaderBackend<Block> + BlockBackend<Block> + StorageProvider<Block, Backend> + 'static,
+	Backend: BackendT<Block> + 'static,
+{
+	if retracted.len() > max_reorg_depth as usize {
+		log::error!(
+			target: "frontier-sql",
+			"❌  Re-org of depth {} at common ancestor {common:?} exceeds {max_reorg_depth}, canonicalizing in chunks",
+			retracted.len(),
+		);
+	}
+	for (retracted, enacted) in canonicalization_steps(&retracted, &enacted, max_reorg_depth) {
+		if let Err(err) = indexer_backend.canonicalize(retracted, enacted).await {
+			indexer_backend.record_error(&err);
+			log::error!(
+				target: "frontier-sql",
+				"❌  Canonicalization failed for common ancestor {}, potentially corrupted db. Retracted: {:?}, Enacted: {:?}",
+				common,
+				retracted,
+				enacted,
+			);
+		}
+	}
+
+	let missing = match indexer_backend.get_unindexed_blocks(&enacted).await {
+		Ok(missing) => missing,
+		Err(err) => {
+			log::error!(target: "frontier-sql", "Failed checking enacted blocks for common ancestor {common:?}: {err:?}");
+			return;
+		}
+	};
+	for hash in missing {
+		log::debug!(target: "frontier-sql", "🛠️  Importing missing enacted block {hash:?}");
+		index_block(client.clone(), indexer_backend.clone(), hash).await;
+		if let Err(err) = indexer_backend.set_block_as_canon(hash).await {
+			log::error!(target: "frontier-sql", "Failed setting block {hash:?} as canon: {err:?}");
+		}
+	}
+}
+
+/// Splits a canonicalization into the `(retracted, enacted)` steps each committed in its own
+/// transaction: a single step if at most `max_reorg_depth` blocks are retracted, else every
+/// retracted chunk before the enacted ones, so that no two blocks are left canonical at the
+/// same height in between.
+fn canonicalization_steps<'a>(
+	retracted: &'a [H256],
+	enacted: &'a [H256],
+	max_reorg_depth: u32,
+) -> Vec<(&'a [H256], &'a [H256])> {
+	let max_reorg_depth = max_reorg_depth.max(1) as usize;
+	if retracted.len() <= max_reorg_depth {
+		return vec![(retracted, enacted)];
+	}
+	retracted
+		.chunks(max_reorg_depth)
+		.map(|chunk| (chunk, &[][..]))
+		.chain(
+			enacted
+				.chunks(max_reorg_depth)
+				.map(|chunk| (&[][..], chunk)),
+		)
+		.collect()
+}
+
+/// Index the block metadata and logs for a single block.
+async fn index_block<Block, Backend, Client>(
+	client: Arc<Client>,
+	indexer_backend: Arc<fc_db::sql::Backend<Block>>,
+	hash: H256,
+) where
+	Block: BlockT<Hash = H256>,
+	Client: ProvideRuntimeApi<Block>,
+	Client::Api: EthereumRuntimeRPCApi<Block>,
+	Client: HeaderBackend<Block> + BlockBackend<Block> + StorageProvider<Block, Backend> + 'static,
+	Backend: BackendT<Block> + 'static,
+{
+	let _ = indexer_backend
+		.insert_block_metadata(client, hash)
+		.instrument(insert_block_metadata_span(hash))
+		.await
+		.map_err(|e| {
+			indexer_backend.record_error(&e);
+			log::error!(target: "frontier-sql", "{e}");
+		});
+	log::debug!(target: "frontier-sql", "Inserted block metadata  {hash:?}");
+	let _ = indexer_backend
+		.index_block_logs(hash)
+		.instrument(index_logs_span(1))
+		.await;
+}
+
+/// Indexes the blocks missing from every gap of the indexed canonical block range, walking
+/// down from the top of each gap until the indexed block below it.
+async fn index_gaps<Block, Client, Backend>(
+	client: Arc<Client>,
+	substrate_backend: Arc<Backend>,
+	indexer_backend: Arc<fc_db::sql::Backend<Block>>,
+) where
+	Block: BlockT<Hash = H256>,
+	Client: ProvideRuntimeApi<Block>,
+	Client::Api: EthereumRuntimeRPCApi<Block>,
+	Client: HeaderBackend<Block> + BlockBackend<Block> + StorageProvider<Block, Backend> + 'static,
+	Backend: BackendT<Block> + 'static,
+{
+	let gaps = match indexer_backend.find_gaps().await {
+		Ok(gaps) => gaps,
+		Err(err) => {
+			log::error!(target: "frontier-sql", "Failed finding gaps in the indexed blocks: {err:?}");
+			return;
+		}
+	};
+	for (start, end) in gaps {
+		log::debug!(target: "frontier-sql", "Indexing gap #{start}..=#{end}");
+		if end == 0 {
+			index_genesis_block(client.clone(), indexer_backend.clone()).await;
+		} else if let Ok(Some(block_hash)) = client.hash(end.unique_saturated_into()) {
+			index_canonical_block_and_ancestors(
+				client.clone(),
+				substrate_backend.clone(),
+				indexer_backend.clone(),
+				block_hash,
+			)
+			.await;
+		} else {
+			log::debug!(target: "frontier-sql", "Failed retrieving hash for block #{end}");
+		}
+	}
+}
+
+/// Attempts to index any missing blocks that are in the past. This fixes any gaps that may
+/// be present in the indexing strategy, since the indexer only walks the parent hashes until
+/// it finds the first ancestor that has already been indexed.
+async fn index_missing_blocks<Block, Client, Backend>(
+	client: Arc<Client>,
+	substrate_backend: Arc<Backend>,
+	indexer_backend: Arc<fc_db::sql::Backend<Block>>,
+) where
+	Block: BlockT<Hash = H256>,
+	Client: ProvideRuntimeApi<Block>,
+	Client::Api: EthereumRuntimeRPCApi<Block>,
+	Client: HeaderBackend<Block> + BlockBackend<Block> + StorageProvider<Block, Backend> + 'static,
+	Backend: BackendT<Block> + 'static,
+{
+	if let Some(block_number) = indexer_backend.get_first_missing_canon_block().await {
+		log::debug!(target: "frontier-sql", "Missing {block_number:?}");
+		if block_number == 0 {
+			index_genesis_block(client.clone(), indexer_backend.clone()).await;
+		} else if let Ok(Some(block_hash)) = client.hash(block_number.unique_saturated_into()) {
+			log::debug!(
+				target: "frontier-sql",
+				"Indexing past canonical blocks from #{} {:?}",
+				block_number,
+				block_hash,
+			);
+			index_canonical_block_and_ancestors(
+				client.clone(),
+				substrate_backend.clone(),
+				indexer_backend.clone(),
+				block_hash,
+			)
+			.await;
+		} else {
+			log::debug!(target: "frontier-sql", "Failed retrieving hash for block #{block_number}");
+		}
+	}
+}
+
+/// Attempts to index any missing blocks that are in the past. This fixes any gaps that may
+/// be present in the indexing strategy, since the indexer only walks the parent hashes until
+/// it finds the first ancestor that has already been indexed.
+async fn index_genesis_block<Block, Client, Backend>(
+	client: Arc<Client>,
+	indexer_backend: Arc<fc_db::sql::Backend<Block>>,
+) where
+	Block: BlockT<Hash = H256>,
+	Client: ProvideRuntimeApi<Block>,
+	Client::Api: EthereumRuntimeRPCApi<Block>,
+	Client: HeaderBackend<Block> + BlockBackend<Block> + StorageProvider<Block, Backend> + 'static,
+	Backend: BackendT<Block> + 'static,
+{
+	log::info!(
+		target: "frontier-sql",
+		"Import genesis",
 	);
 	if let Ok(Some(substrate_genesis_hash)) = indexer_backend
 		.insert_genesis_block_metadata(client.clone())
 		.await
 		.map_err(|e| {
+			indexer_backend.record_error(&e);
 			log::error!(target: "frontier-sql", "💔  Cannot sync genesis block: {e}");
 		}) {
 		log::debug!(target: "frontier-sql", "Imported genesis block {substrate_genesis_hash:?}");
@@ -475,7 +1547,7 @@ mod test {
 	use tempfile::tempdir;
 	// Substrate
 	use sc_block_builder::BlockBuilderBuilder;
-	use sc_client_api::{BlockchainEvents, HeaderBackend};
+	use sc_client_api::{BlockchainEvents, Finalizer, HeaderBackend};
 	use sp_consensus::BlockOrigin;
 	use sp_core::{H160, H256, U256};
 	use sp_io::hashing::twox_128;
@@ -487,7 +1559,7 @@ mod test {
 		prelude::*, DefaultTestClientBuilderExt, TestClientBuilder, TestClientBuilderExt,
 	};
 	// Frontier
-	use fc_storage::SchemaV3StorageOverride;
+	use fc_storage::{SchemaV3StorageOverride, StorageOverrideHandler};
 	use fp_storage::{constants::*, EthereumStorageSchema, PALLET_ETHEREUM_SCHEMA};
 
 	type OpaqueBlock = sp_runtime::generic::Block<
@@ -534,6 +1606,30 @@ mod test {
 		)
 	}
 
+	/// A digest carrying the whole ethereum block, authored by the given beneficiary.
+	fn ethereum_block_digest(beneficiary: H160) -> DigestItem {
+		let partial_header = ethereum::PartialHeader {
+			parent_hash: H256::random(),
+			beneficiary,
+			state_root: H256::default(),
+			receipts_root: H256::default(),
+			logs_bloom: ethereum_types::Bloom::default(),
+			difficulty: U256::zero(),
+			number: U256::zero(),
+			gas_limit: U256::zero(),
+			gas_used: U256::zero(),
+			timestamp: 0u64,
+			extra_data: Vec::new(),
+			mix_hash: H256::default(),
+			nonce: ethereum_types::H64::default(),
+		};
+		let ethereum_block = ethereum::Block::new(partial_header, vec![], vec![]);
+		DigestItem::Consensus(
+			fp_consensus::FRONTIER_ENGINE_ID,
+			fp_consensus::PostLog::Block(ethereum_block).encode(),
+		)
+	}
+
 	#[tokio::test]
 	async fn interval_indexing_works() {
 		let tmp = tempdir().expect("create a temporary directory");
@@ -561,6 +1657,10 @@ mod test {
 				create_if_missing: true,
 				cache_size: 204800,
 				thread_count: 4,
+				journal_mode: None,
+				synchronous: None,
+				busy_timeout: None,
+				index_logs: true,
 			}),
 			100,
 			None,
@@ -634,9 +1734,10 @@ mod test {
 					topic_2: Some(topics_1_2.as_bytes().to_owned()),
 					topic_3: None,
 					topic_4: None,
-					log_index: 0i32,
-					transaction_index: 0i32,
+					log_index: 0i64,
+					transaction_index: 0i64,
 					substrate_block_hash: block_hash.as_bytes().to_owned(),
+					topic_count: 2,
 				},
 			));
 			logs.push((
@@ -647,9 +1748,10 @@ mod test {
 					topic_2: Some(topics_2_2.as_bytes().to_owned()),
 					topic_3: Some(topics_2_3.as_bytes().to_owned()),
 					topic_4: Some(topics_2_4.as_bytes().to_owned()),
-					log_index: 0i32,
-					transaction_index: 1i32,
+					log_index: 0i64,
+					transaction_index: 1i64,
 					substrate_block_hash: block_hash.as_bytes().to_owned(),
+					topic_count: 4,
 				},
 			));
 		}
@@ -673,11 +1775,14 @@ mod test {
 				client.clone().import_notification_stream(),
 				SyncWorkerConfig {
 					read_notification_timeout: Duration::from_secs(1),
-					check_indexed_blocks_interval: Duration::from_secs(60),
+					..Default::default()
 				},
 				SyncStrategy::Parachain,
 				Arc::new(test_sync_oracle),
 				pubsub_notification_sinks_inner,
+				None,
+				None,
+				future::pending(),
 			)
 			.await
 		});
@@ -696,7 +1801,8 @@ mod test {
 					topic_4,
 					log_index,
 					transaction_index,
-					a.substrate_block_hash
+					a.substrate_block_hash,
+					topic_count
 				FROM logs AS a INNER JOIN blocks AS b ON a.substrate_block_hash = b.substrate_block_hash
 				ORDER BY b.block_number ASC, log_index ASC, transaction_index ASC",
 		)
@@ -711,9 +1817,10 @@ mod test {
 			let topic_2 = row.get::<Option<Vec<u8>>, _>(3);
 			let topic_3 = row.get::<Option<Vec<u8>>, _>(4);
 			let topic_4 = row.get::<Option<Vec<u8>>, _>(5);
-			let log_index = row.get::<i32, _>(6);
-			let transaction_index = row.get::<i32, _>(7);
+			let log_index = row.get::<i64, _>(6);
+			let transaction_index = row.get::<i64, _>(7);
 			let substrate_block_hash = row.get::<Vec<u8>, _>(8);
+			let topic_count = row.get::<i32, _>(9);
 			(
 				block_number,
 				fc_db::sql::Log {
@@ -725,6 +1832,7 @@ mod test {
 					log_index,
 					transaction_index,
 					substrate_block_hash,
+					topic_count,
 				},
 			)
 		})
@@ -764,6 +1872,10 @@ mod test {
 				create_if_missing: true,
 				cache_size: 204800,
 				thread_count: 4,
+				journal_mode: None,
+				synchronous: None,
+				busy_timeout: None,
+				index_logs: true,
 			}),
 			100,
 			None,
@@ -795,11 +1907,14 @@ mod test {
 				notification_stream,
 				SyncWorkerConfig {
 					read_notification_timeout: Duration::from_secs(10),
-					check_indexed_blocks_interval: Duration::from_secs(60),
+					..Default::default()
 				},
 				SyncStrategy::Parachain,
 				Arc::new(test_sync_oracle),
 				pubsub_notification_sinks_inner,
+				None,
+				None,
+				future::pending(),
 			)
 			.await
 		});
@@ -867,9 +1982,10 @@ mod test {
 					topic_2: Some(topics_1_2.as_bytes().to_owned()),
 					topic_3: None,
 					topic_4: None,
-					log_index: 0i32,
-					transaction_index: 0i32,
+					log_index: 0i64,
+					transaction_index: 0i64,
 					substrate_block_hash: block_hash.as_bytes().to_owned(),
+					topic_count: 2,
 				},
 			));
 			logs.push((
@@ -880,9 +1996,10 @@ mod test {
 					topic_2: Some(topics_2_2.as_bytes().to_owned()),
 					topic_3: Some(topics_2_3.as_bytes().to_owned()),
 					topic_4: Some(topics_2_4.as_bytes().to_owned()),
-					log_index: 0i32,
-					transaction_index: 1i32,
+					log_index: 0i64,
+					transaction_index: 1i64,
 					substrate_block_hash: block_hash.as_bytes().to_owned(),
+					topic_count: 4,
 				},
 			));
 			// Let's not notify too quickly
@@ -900,7 +2017,8 @@ mod test {
 					topic_4,
 					log_index,
 					transaction_index,
-					a.substrate_block_hash
+					a.substrate_block_hash,
+					topic_count
 				FROM logs AS a INNER JOIN blocks AS b ON a.substrate_block_hash = b.substrate_block_hash
 				ORDER BY b.block_number ASC, log_index ASC, transaction_index ASC",
 		)
@@ -915,9 +2033,10 @@ mod test {
 			let topic_2 = row.get::<Option<Vec<u8>>, _>(3);
 			let topic_3 = row.get::<Option<Vec<u8>>, _>(4);
 			let topic_4 = row.get::<Option<Vec<u8>>, _>(5);
-			let log_index = row.get::<i32, _>(6);
-			let transaction_index = row.get::<i32, _>(7);
+			let log_index = row.get::<i64, _>(6);
+			let transaction_index = row.get::<i64, _>(7);
 			let substrate_block_hash = row.get::<Vec<u8>, _>(8);
+			let topic_count = row.get::<i32, _>(9);
 			(
 				block_number,
 				fc_db::sql::Log {
@@ -929,6 +2048,7 @@ mod test {
 					log_index,
 					transaction_index,
 					substrate_block_hash,
+					topic_count,
 				},
 			)
 		})
@@ -968,6 +2088,10 @@ mod test {
 				create_if_missing: true,
 				cache_size: 204800,
 				thread_count: 4,
+				journal_mode: None,
+				synchronous: None,
+				busy_timeout: None,
+				index_logs: true,
 			}),
 			100,
 			None,
@@ -998,11 +2122,14 @@ mod test {
 				notification_stream,
 				SyncWorkerConfig {
 					read_notification_timeout: Duration::from_secs(10),
-					check_indexed_blocks_interval: Duration::from_secs(60),
+					..Default::default()
 				},
 				SyncStrategy::Parachain,
 				Arc::new(test_sync_oracle),
 				pubsub_notification_sinks_inner,
+				None,
+				None,
+				future::pending(),
 			)
 			.await
 		});
@@ -1108,22 +2235,17 @@ mod test {
 	}
 
 	#[tokio::test]
-	async fn resuming_from_last_indexed_block_works() {
+	async fn canonical_only_mode_never_stores_orphans() {
 		let tmp = tempdir().expect("create a temporary directory");
-		// Initialize storage with schema V3
 		let builder = TestClientBuilder::new().add_extra_storage(
 			PALLET_ETHEREUM_SCHEMA.to_vec(),
 			Encode::encode(&EthereumStorageSchema::V3),
 		);
-		// Backend
 		let backend = builder.backend();
-		// Client
 		let (client, _) =
 			builder.build_with_native_executor::<frontier_template_runtime::RuntimeApi, _>(None);
 		let mut client = Arc::new(client);
-		// Overrides
 		let storage_override = Arc::new(SchemaV3StorageOverride::new(client.clone()));
-		// Indexer backend
 		let indexer_backend = fc_db::sql::Backend::new(
 			fc_db::sql::BackendConfig::Sqlite(fc_db::sql::SqliteBackendConfig {
 				path: Path::new("sqlite:///")
@@ -1134,38 +2256,198 @@ mod test {
 				create_if_missing: true,
 				cache_size: 204800,
 				thread_count: 4,
+				journal_mode: None,
+				synchronous: None,
+				busy_timeout: None,
+				index_logs: true,
 			}),
 			100,
 			None,
 			storage_override.clone(),
 		)
 		.await
-		.expect("indexer pool to be created");
+		.expect("indexer pool to be created")
+		.canonical_only();
 
 		// Pool
 		let pool = indexer_backend.pool().clone();
 
-		// Create 5 blocks, storing them newest first.
-		let mut parent_hash = client
-			.hash(sp_runtime::traits::Zero::zero())
-			.unwrap()
-			.expect("genesis hash");
-		let mut best_block_hashes: Vec<H256> = vec![];
-		for _block_number in 1..=5 {
-			let mut builder = BlockBuilderBuilder::new(&*client)
-				.on_parent_block(parent_hash)
-				.fetch_parent_block_number(&*client)
-				.unwrap()
-				.build()
-				.unwrap();
-			builder
-				.push_deposit_log_digest_item(ethereum_digest())
-				.expect("deposit log");
-			let block = builder.build().unwrap().block;
-			let block_hash = block.header.hash();
-			executor::block_on(client.import(BlockOrigin::Own, block)).unwrap();
-			best_block_hashes.insert(0, block_hash);
-			parent_hash = block_hash;
+		// Spawn indexer task, sweeping the leaves frequently so the fork leaves are visited.
+		let pubsub_notification_sinks: EthereumBlockNotificationSinks<
+			EthereumBlockNotification<OpaqueBlock>,
+		> = Default::default();
+		let notification_stream = client.clone().import_notification_stream();
+		let client_inner = client.clone();
+		tokio::task::spawn(async move {
+			crate::sql::SyncWorker::run(
+				client_inner,
+				backend.clone(),
+				Arc::new(indexer_backend),
+				notification_stream,
+				SyncWorkerConfig {
+					read_notification_timeout: Duration::from_millis(100),
+					..Default::default()
+				},
+				SyncStrategy::Parachain,
+				Arc::new(TestSyncOracleNotSyncing {}),
+				Arc::new(pubsub_notification_sinks),
+				None,
+				None,
+				future::pending(),
+			)
+			.await
+		});
+
+		let indexed_blocks = |pool: sqlx::SqlitePool| async move {
+			sqlx::query("SELECT substrate_block_hash, is_canon FROM blocks")
+				.fetch_all(&pool)
+				.await
+				.expect("test query result")
+				.iter()
+				.map(|row| {
+					(
+						H256::from_slice(&row.get::<Vec<u8>, _>(0)[..]),
+						row.get::<i32, _>(1),
+					)
+				})
+				.collect::<Vec<(H256, i32)>>()
+		};
+
+		// Create 10 blocks saving the common ancestor for branching.
+		let mut parent_hash = client
+			.hash(sp_runtime::traits::Zero::zero())
+			.unwrap()
+			.expect("genesis hash");
+		let mut common_ancestor = parent_hash;
+		let mut hashes_to_be_orphaned: Vec<H256> = vec![];
+		for block_number in 1..11 {
+			let mut builder = BlockBuilderBuilder::new(&*client)
+				.on_parent_block(parent_hash)
+				.fetch_parent_block_number(&*client)
+				.unwrap()
+				.build()
+				.unwrap();
+			builder
+				.push_deposit_log_digest_item(ethereum_digest())
+				.expect("deposit log");
+			let block = builder.build().unwrap().block;
+			let block_hash = block.header.hash();
+			executor::block_on(client.import(BlockOrigin::Own, block)).unwrap();
+			if block_number == 8 {
+				common_ancestor = block_hash;
+			}
+			if block_number == 9 || block_number == 10 {
+				hashes_to_be_orphaned.push(block_hash);
+			}
+			parent_hash = block_hash;
+			// Let's not notify too quickly
+			futures_timer::Delay::new(Duration::from_millis(100)).await;
+		}
+
+		// Create a competing fork of 10 blocks on top of the common ancestor.
+		parent_hash = common_ancestor;
+		let mut fork_hashes: Vec<H256> = vec![];
+		for _ in 1..11 {
+			let mut builder = BlockBuilderBuilder::new(&*client)
+				.on_parent_block(parent_hash)
+				.fetch_parent_block_number(&*client)
+				.unwrap()
+				.build()
+				.unwrap();
+			builder
+				.push_deposit_log_digest_item(ethereum_digest())
+				.expect("deposit log");
+			let block = builder.build().unwrap().block;
+			let block_hash = block.header.hash();
+			executor::block_on(client.import(BlockOrigin::Own, block)).unwrap();
+			fork_hashes.push(block_hash);
+			parent_hash = block_hash;
+			futures_timer::Delay::new(Duration::from_millis(100)).await;
+
+			// While not longer than the original chain, the fork leaves are never stored.
+			if fork_hashes.len() == 2 {
+				let blocks = indexed_blocks(pool.clone()).await;
+				assert_eq!(blocks.len(), 10);
+				assert!(blocks.iter().all(|(hash, _)| !fork_hashes.contains(hash)));
+			}
+		}
+		futures_timer::Delay::new(Duration::from_millis(500)).await;
+
+		// The retracted blocks were deleted, every stored block is canon.
+		let blocks = indexed_blocks(pool.clone()).await;
+		assert_eq!(blocks.len(), 18);
+		assert!(blocks.iter().all(|(_, is_canon)| *is_canon == 1));
+		assert!(blocks
+			.iter()
+			.all(|(hash, _)| !hashes_to_be_orphaned.contains(hash)));
+		assert!(fork_hashes
+			.iter()
+			.all(|hash| blocks.iter().any(|(indexed, _)| indexed == hash)));
+	}
+
+	#[tokio::test]
+	async fn resuming_from_last_indexed_block_works() {
+		let tmp = tempdir().expect("create a temporary directory");
+		// Initialize storage with schema V3
+		let builder = TestClientBuilder::new().add_extra_storage(
+			PALLET_ETHEREUM_SCHEMA.to_vec(),
+			Encode::encode(&EthereumStorageSchema::V3),
+		);
+		// Backend
+		let backend = builder.backend();
+		// Client
+		let (client, _) =
+			builder.build_with_native_executor::<frontier_template_runtime::RuntimeApi, _>(None);
+		let mut client = Arc::new(client);
+		// Overrides
+		let storage_override = Arc::new(SchemaV3StorageOverride::new(client.clone()));
+		// Indexer backend
+		let indexer_backend = fc_db::sql::Backend::new(
+			fc_db::sql::BackendConfig::Sqlite(fc_db::sql::SqliteBackendConfig {
+				path: Path::new("sqlite:///")
+					.join(tmp.path())
+					.join("test.db3")
+					.to_str()
+					.unwrap(),
+				create_if_missing: true,
+				cache_size: 204800,
+				thread_count: 4,
+				journal_mode: None,
+				synchronous: None,
+				busy_timeout: None,
+				index_logs: true,
+			}),
+			100,
+			None,
+			storage_override.clone(),
+		)
+		.await
+		.expect("indexer pool to be created");
+
+		// Pool
+		let pool = indexer_backend.pool().clone();
+
+		// Create 5 blocks, storing them newest first.
+		let mut parent_hash = client
+			.hash(sp_runtime::traits::Zero::zero())
+			.unwrap()
+			.expect("genesis hash");
+		let mut best_block_hashes: Vec<H256> = vec![];
+		for _block_number in 1..=5 {
+			let mut builder = BlockBuilderBuilder::new(&*client)
+				.on_parent_block(parent_hash)
+				.fetch_parent_block_number(&*client)
+				.unwrap()
+				.build()
+				.unwrap();
+			builder
+				.push_deposit_log_digest_item(ethereum_digest())
+				.expect("deposit log");
+			let block = builder.build().unwrap().block;
+			let block_hash = block.header.hash();
+			executor::block_on(client.import(BlockOrigin::Own, block)).unwrap();
+			best_block_hashes.insert(0, block_hash);
+			parent_hash = block_hash;
 		}
 
 		// Mark the block as canon and indexed
@@ -1177,7 +2459,9 @@ mod test {
 			.execute(&pool)
 			.await
 			.expect("sql query must succeed");
-		sqlx::query("INSERT INTO sync_status(substrate_block_hash, status) VALUES (?, 1)")
+		sqlx::query(
+			"INSERT INTO sync_status(substrate_block_hash, metadata_done, logs_done) VALUES (?, 1, 1)",
+		)
 			.bind(block_resume_at.as_bytes())
 			.execute(&pool)
 			.await
@@ -1201,11 +2485,14 @@ mod test {
 				client.clone().import_notification_stream(),
 				SyncWorkerConfig {
 					read_notification_timeout: Duration::from_secs(10),
-					check_indexed_blocks_interval: Duration::from_secs(60),
+					..Default::default()
 				},
 				SyncStrategy::Parachain,
 				Arc::new(test_sync_oracle),
 				pubsub_notification_sinks_inner,
+				None,
+				None,
+				future::pending(),
 			)
 			.await
 		});
@@ -1278,6 +2565,10 @@ mod test {
 				create_if_missing: true,
 				cache_size: 204800,
 				thread_count: 4,
+				journal_mode: None,
+				synchronous: None,
+				busy_timeout: None,
+				index_logs: true,
 			}),
 			100,
 			None,
@@ -1305,11 +2596,14 @@ mod test {
 				client_inner.import_notification_stream(),
 				SyncWorkerConfig {
 					read_notification_timeout: Duration::from_secs(10),
-					check_indexed_blocks_interval: Duration::from_secs(60),
+					..Default::default()
 				},
 				SyncStrategy::Normal,
 				Arc::new(sync_oracle),
 				pubsub_notification_sinks.clone(),
+				None,
+				None,
+				future::pending(),
 			)
 			.await
 		});
@@ -1379,6 +2673,10 @@ mod test {
 				create_if_missing: true,
 				cache_size: 204800,
 				thread_count: 4,
+				journal_mode: None,
+				synchronous: None,
+				busy_timeout: None,
+				index_logs: true,
 			}),
 			100,
 			None,
@@ -1406,11 +2704,14 @@ mod test {
 				client_inner.import_notification_stream(),
 				SyncWorkerConfig {
 					read_notification_timeout: Duration::from_secs(10),
-					check_indexed_blocks_interval: Duration::from_secs(60),
+					..Default::default()
 				},
 				SyncStrategy::Normal,
 				Arc::new(sync_oracle),
 				pubsub_notification_sinks.clone(),
+				None,
+				None,
+				future::pending(),
 			)
 			.await
 		});
@@ -1494,6 +2795,10 @@ mod test {
 				create_if_missing: true,
 				cache_size: 204800,
 				thread_count: 4,
+				journal_mode: None,
+				synchronous: None,
+				busy_timeout: None,
+				index_logs: true,
 			}),
 			100,
 			None,
@@ -1521,11 +2826,14 @@ mod test {
 				client_inner.import_notification_stream(),
 				SyncWorkerConfig {
 					read_notification_timeout: Duration::from_secs(10),
-					check_indexed_blocks_interval: Duration::from_secs(60),
+					..Default::default()
 				},
 				SyncStrategy::Parachain,
 				Arc::new(sync_oracle),
 				pubsub_notification_sinks.clone(),
+				None,
+				None,
+				future::pending(),
 			)
 			.await
 		});
@@ -1595,6 +2903,10 @@ mod test {
 				create_if_missing: true,
 				cache_size: 204800,
 				thread_count: 4,
+				journal_mode: None,
+				synchronous: None,
+				busy_timeout: None,
+				index_logs: true,
 			}),
 			100,
 			None,
@@ -1622,11 +2934,14 @@ mod test {
 				client_inner.import_notification_stream(),
 				SyncWorkerConfig {
 					read_notification_timeout: Duration::from_secs(10),
-					check_indexed_blocks_interval: Duration::from_secs(60),
+					..Default::default()
 				},
 				SyncStrategy::Parachain,
 				Arc::new(sync_oracle),
 				pubsub_notification_sinks.clone(),
+				None,
+				None,
+				future::pending(),
 			)
 			.await
 		});
@@ -1710,6 +3025,10 @@ mod test {
 				create_if_missing: true,
 				cache_size: 204800,
 				thread_count: 4,
+				journal_mode: None,
+				synchronous: None,
+				busy_timeout: None,
+				index_logs: true,
 			}),
 			100,
 			None,
@@ -1737,11 +3056,14 @@ mod test {
 				client_inner.import_notification_stream(),
 				SyncWorkerConfig {
 					read_notification_timeout: Duration::from_secs(10),
-					check_indexed_blocks_interval: Duration::from_secs(60),
+					..Default::default()
 				},
 				SyncStrategy::Normal,
 				Arc::new(sync_oracle),
 				pubsub_notification_sinks.clone(),
+				None,
+				None,
+				future::pending(),
 			)
 			.await
 		});
@@ -1811,6 +3133,10 @@ mod test {
 				create_if_missing: true,
 				cache_size: 204800,
 				thread_count: 4,
+				journal_mode: None,
+				synchronous: None,
+				busy_timeout: None,
+				index_logs: true,
 			}),
 			100,
 			None,
@@ -1838,11 +3164,14 @@ mod test {
 				client_inner.import_notification_stream(),
 				SyncWorkerConfig {
 					read_notification_timeout: Duration::from_secs(10),
-					check_indexed_blocks_interval: Duration::from_secs(60),
+					..Default::default()
 				},
 				SyncStrategy::Parachain,
 				Arc::new(sync_oracle),
 				pubsub_notification_sinks.clone(),
+				None,
+				None,
+				future::pending(),
 			)
 			.await
 		});
@@ -1889,4 +3218,3030 @@ mod test {
 		let expected_imported_blocks = Vec::<H256>::new();
 		assert_eq!(expected_imported_blocks, actual_imported_blocks);
 	}
+
+	#[tokio::test]
+	async fn canonicalize_indexes_missing_enacted_blocks() {
+		let tmp = tempdir().expect("create a temporary directory");
+		let builder = TestClientBuilder::new().add_extra_storage(
+			PALLET_ETHEREUM_SCHEMA.to_vec(),
+			Encode::encode(&EthereumStorageSchema::V3),
+		);
+		let backend = builder.backend();
+		let (client, _) =
+			builder.build_with_native_executor::<frontier_template_runtime::RuntimeApi, _>(None);
+		let mut client = Arc::new(client);
+		let storage_override = Arc::new(SchemaV3StorageOverride::new(client.clone()));
+		let indexer_backend = fc_db::sql::Backend::new(
+			fc_db::sql::BackendConfig::Sqlite(fc_db::sql::SqliteBackendConfig {
+				path: Path::new("sqlite:///")
+					.join(tmp.path())
+					.join("test.db3")
+					.to_str()
+					.unwrap(),
+				create_if_missing: true,
+				cache_size: 204800,
+				thread_count: 4,
+				journal_mode: None,
+				synchronous: None,
+				busy_timeout: None,
+				index_logs: true,
+			}),
+			100,
+			None,
+			storage_override.clone(),
+		)
+		.await
+		.expect("indexer pool to be created");
+		let indexer_backend = Arc::new(indexer_backend);
+
+		// Pool
+		let pool = indexer_backend.pool().clone();
+
+		// Import two sibling blocks on top of genesis, the first one stays the best.
+		let genesis_hash = client
+			.hash(sp_runtime::traits::Zero::zero())
+			.unwrap()
+			.expect("genesis hash");
+		let mut sibling_hashes: Vec<H256> = vec![];
+		for _ in 0..2 {
+			let mut builder = BlockBuilderBuilder::new(&*client)
+				.on_parent_block(genesis_hash)
+				.fetch_parent_block_number(&*client)
+				.unwrap()
+				.build()
+				.unwrap();
+			builder
+				.push_deposit_log_digest_item(ethereum_digest())
+				.expect("deposit log");
+			let block = builder.build().unwrap().block;
+			sibling_hashes.push(block.header.hash());
+			executor::block_on(client.import(BlockOrigin::Own, block)).unwrap();
+		}
+		let (retracted_hash, enacted_hash) = (sibling_hashes[0], sibling_hashes[1]);
+
+		// Only the retracted block was ever indexed.
+		indexer_backend
+			.insert_block_metadata(client.clone(), retracted_hash)
+			.await
+			.expect("insert block metadata");
+
+		let pubsub_notification_sinks: crate::EthereumBlockNotificationSinks<
+			crate::EthereumBlockNotification<OpaqueBlock>,
+		> = Default::default();
+		let tx = crate::sql::SyncWorker::spawn_worker(
+			client.clone(),
+			backend.clone(),
+			indexer_backend.clone(),
+			Arc::new(pubsub_notification_sinks),
+			None,
+			None,
+			None,
+			DEFAULT_MAX_REORG_DEPTH,
+		)
+		.await;
+		tx.send(WorkerCommand::Canonicalize {
+			common: genesis_hash,
+			enacted: vec![enacted_hash],
+			retracted: vec![retracted_hash],
+		})
+		.await
+		.expect("send command");
+
+		// Enough time for canonicalization
+		futures_timer::Delay::new(Duration::from_millis(500)).await;
+
+		let actual = sqlx::query("SELECT substrate_block_hash, is_canon FROM blocks")
+			.fetch_all(&pool)
+			.await
+			.expect("test query result")
+			.iter()
+			.map(|row| {
+				(
+					H256::from_slice(&row.get::<Vec<u8>, _>(0)[..]),
+					row.get::<i32, _>(1),
+				)
+			})
+			.collect::<Vec<(H256, i32)>>();
+		assert_eq!(actual, vec![(retracted_hash, 0), (enacted_hash, 1)]);
+	}
+
+	#[tokio::test]
+	async fn out_of_order_best_notifications_follow_client_best() {
+		let tmp = tempdir().expect("create a temporary directory");
+		let builder = TestClientBuilder::new().add_extra_storage(
+			PALLET_ETHEREUM_SCHEMA.to_vec(),
+			Encode::encode(&EthereumStorageSchema::V3),
+		);
+		let backend = builder.backend();
+		let (client, _) =
+			builder.build_with_native_executor::<frontier_template_runtime::RuntimeApi, _>(None);
+		let mut client = Arc::new(client);
+		let storage_override = Arc::new(SchemaV3StorageOverride::new(client.clone()));
+		let indexer_backend = fc_db::sql::Backend::new(
+			fc_db::sql::BackendConfig::Sqlite(fc_db::sql::SqliteBackendConfig {
+				path: Path::new("sqlite:///")
+					.join(tmp.path())
+					.join("test.db3")
+					.to_str()
+					.unwrap(),
+				create_if_missing: true,
+				cache_size: 204800,
+				thread_count: 4,
+				journal_mode: None,
+				synchronous: None,
+				busy_timeout: None,
+				index_logs: true,
+			}),
+			100,
+			None,
+			storage_override.clone(),
+		)
+		.await
+		.expect("indexer pool to be created");
+
+		// Pool
+		let pool = indexer_backend.pool().clone();
+
+		// Import two sibling blocks on top of genesis, the second one becomes the best.
+		let mut import_notifications = client.import_notification_stream();
+		let genesis_hash = client
+			.hash(sp_runtime::traits::Zero::zero())
+			.unwrap()
+			.expect("genesis hash");
+		let mut sibling_hashes: Vec<H256> = vec![];
+		for _ in 0..2 {
+			let mut builder = BlockBuilderBuilder::new(&*client)
+				.on_parent_block(genesis_hash)
+				.fetch_parent_block_number(&*client)
+				.unwrap()
+				.build()
+				.unwrap();
+			builder
+				.push_deposit_log_digest_item(ethereum_digest())
+				.expect("deposit log");
+			let block = builder.build().unwrap().block;
+			sibling_hashes.push(block.header.hash());
+			executor::block_on(client.import_as_best(BlockOrigin::Own, block)).unwrap();
+		}
+		let (stale_hash, best_hash) = (sibling_hashes[0], sibling_hashes[1]);
+		assert_eq!(client.info().best_hash, best_hash);
+
+		// Deliver the best block notifications in reverse order.
+		let first = import_notifications
+			.next()
+			.await
+			.expect("first notification");
+		let second = import_notifications
+			.next()
+			.await
+			.expect("second notification");
+		let (sink, notification_stream) =
+			sc_utils::mpsc::tracing_unbounded("test_import_notifications", 10);
+		sink.unbounded_send(second).expect("send notification");
+		sink.unbounded_send(first).expect("send notification");
+
+		let pubsub_notification_sinks: EthereumBlockNotificationSinks<
+			EthereumBlockNotification<OpaqueBlock>,
+		> = Default::default();
+		let client_inner = client.clone();
+		tokio::task::spawn(async move {
+			crate::sql::SyncWorker::run(
+				client_inner,
+				backend.clone(),
+				Arc::new(indexer_backend),
+				notification_stream,
+				SyncWorkerConfig {
+					read_notification_timeout: Duration::from_secs(10),
+					..Default::default()
+				},
+				SyncStrategy::Parachain,
+				Arc::new(TestSyncOracleNotSyncing {}),
+				Arc::new(pubsub_notification_sinks),
+				None,
+				None,
+				future::pending(),
+			)
+			.await
+		});
+
+		// Enough time for indexing
+		futures_timer::Delay::new(Duration::from_millis(1500)).await;
+
+		let canon_at_height_one = sqlx::query(
+			"SELECT substrate_block_hash FROM blocks WHERE block_number = 1 AND is_canon = 1",
+		)
+		.fetch_all(&pool)
+		.await
+		.expect("test query result")
+		.iter()
+		.map(|row| H256::from_slice(&row.get::<Vec<u8>, _>(0)[..]))
+		.collect::<Vec<H256>>();
+		assert_eq!(canon_at_height_one, vec![best_hash]);
+		assert_ne!(stale_hash, best_hash);
+	}
+
+	#[tokio::test]
+	async fn finality_notifications_advance_last_finalized() {
+		let tmp = tempdir().expect("create a temporary directory");
+		let builder = TestClientBuilder::new().add_extra_storage(
+			PALLET_ETHEREUM_SCHEMA.to_vec(),
+			Encode::encode(&EthereumStorageSchema::V3),
+		);
+		let backend = builder.backend();
+		let (client, _) =
+			builder.build_with_native_executor::<frontier_template_runtime::RuntimeApi, _>(None);
+		let mut client = Arc::new(client);
+		let storage_override = Arc::new(SchemaV3StorageOverride::new(client.clone()));
+		let indexer_backend = fc_db::sql::Backend::new(
+			fc_db::sql::BackendConfig::Sqlite(fc_db::sql::SqliteBackendConfig {
+				path: Path::new("sqlite:///")
+					.join(tmp.path())
+					.join("test.db3")
+					.to_str()
+					.unwrap(),
+				create_if_missing: true,
+				cache_size: 204800,
+				thread_count: 4,
+				journal_mode: None,
+				synchronous: None,
+				busy_timeout: None,
+				index_logs: true,
+			}),
+			100,
+			None,
+			storage_override.clone(),
+		)
+		.await
+		.expect("indexer pool to be created");
+		let indexer_backend = Arc::new(indexer_backend);
+
+		let pubsub_notification_sinks: EthereumBlockNotificationSinks<
+			EthereumBlockNotification<OpaqueBlock>,
+		> = Default::default();
+		let notification_stream = client.clone().import_notification_stream();
+		let client_inner = client.clone();
+		let indexer_backend_inner = indexer_backend.clone();
+		tokio::task::spawn(async move {
+			crate::sql::SyncWorker::run(
+				client_inner,
+				backend.clone(),
+				indexer_backend_inner,
+				notification_stream,
+				SyncWorkerConfig {
+					read_notification_timeout: Duration::from_secs(10),
+					..Default::default()
+				},
+				SyncStrategy::Parachain,
+				Arc::new(TestSyncOracleNotSyncing {}),
+				Arc::new(pubsub_notification_sinks),
+				None,
+				None,
+				future::pending(),
+			)
+			.await
+		});
+
+		// Import two blocks
+		let mut block_hashes: Vec<H256> = vec![];
+		for _ in 0..2 {
+			let chain = client.chain_info();
+			let mut builder = BlockBuilderBuilder::new(&*client)
+				.on_parent_block(chain.best_hash)
+				.with_parent_block_number(chain.best_number)
+				.build()
+				.unwrap();
+			builder
+				.push_deposit_log_digest_item(ethereum_digest())
+				.expect("deposit log");
+			let block = builder.build().unwrap().block;
+			block_hashes.push(block.header.hash());
+			executor::block_on(client.import(BlockOrigin::Own, block)).unwrap();
+		}
+		futures_timer::Delay::new(Duration::from_millis(500)).await;
+		assert_eq!(indexer_backend.last_finalized().await.unwrap(), None);
+
+		for hash in block_hashes {
+			client
+				.finalize_block(hash, None, true)
+				.expect("finalize block");
+			// Enough time for the worker to record it
+			futures_timer::Delay::new(Duration::from_millis(500)).await;
+			assert_eq!(indexer_backend.last_finalized().await.unwrap(), Some(hash));
+		}
+	}
+
+	#[tokio::test]
+	async fn fatal_error_stops_the_worker() {
+		let tmp = tempdir().expect("create a temporary directory");
+		let builder = TestClientBuilder::new().add_extra_storage(
+			PALLET_ETHEREUM_SCHEMA.to_vec(),
+			Encode::encode(&EthereumStorageSchema::V3),
+		);
+		let backend = builder.backend();
+		let (client, _) =
+			builder.build_with_native_executor::<frontier_template_runtime::RuntimeApi, _>(None);
+		let client = Arc::new(client);
+		let storage_override = Arc::new(SchemaV3StorageOverride::new(client.clone()));
+		let indexer_backend = fc_db::sql::Backend::new(
+			fc_db::sql::BackendConfig::Sqlite(fc_db::sql::SqliteBackendConfig {
+				path: Path::new("sqlite:///")
+					.join(tmp.path())
+					.join("test.db3")
+					.to_str()
+					.unwrap(),
+				create_if_missing: true,
+				cache_size: 204800,
+				thread_count: 4,
+				journal_mode: None,
+				synchronous: None,
+				busy_timeout: None,
+				index_logs: true,
+			}),
+			100,
+			None,
+			storage_override.clone(),
+		)
+		.await
+		.expect("indexer pool to be created");
+
+		// Every block insert violates a constraint, starting with the genesis block.
+		sqlx::query(
+			"CREATE TRIGGER reject_blocks BEFORE INSERT ON blocks
+			BEGIN SELECT RAISE(ABORT, 'injected failure'); END;",
+		)
+		.execute(indexer_backend.pool())
+		.await
+		.expect("create trigger");
+		let indexer_backend = Arc::new(indexer_backend);
+
+		let pubsub_notification_sinks: EthereumBlockNotificationSinks<
+			EthereumBlockNotification<OpaqueBlock>,
+		> = Default::default();
+		let run = crate::sql::SyncWorker::run(
+			client.clone(),
+			backend.clone(),
+			indexer_backend.clone(),
+			client.clone().import_notification_stream(),
+			SyncWorkerConfig {
+				read_notification_timeout: Duration::from_millis(100),
+				exit_on_fatal: true,
+				..Default::default()
+			},
+			SyncStrategy::Parachain,
+			Arc::new(TestSyncOracleNotSyncing {}),
+			Arc::new(pubsub_notification_sinks),
+			None,
+			None,
+			future::pending(),
+		);
+
+		tokio::time::timeout(Duration::from_secs(10), run)
+			.await
+			.expect("the worker must stop on a fatal error");
+		assert!(indexer_backend
+			.fatal_error()
+			.expect("fatal error recorded")
+			.contains("injected failure"));
+	}
+
+	#[tokio::test]
+	async fn no_logs_mode_indexes_blocks_only() {
+		let tmp = tempdir().expect("create a temporary directory");
+		let builder = TestClientBuilder::new().add_extra_storage(
+			PALLET_ETHEREUM_SCHEMA.to_vec(),
+			Encode::encode(&EthereumStorageSchema::V3),
+		);
+		let backend = builder.backend();
+		let (client, _) =
+			builder.build_with_native_executor::<frontier_template_runtime::RuntimeApi, _>(None);
+		let mut client = Arc::new(client);
+		let storage_override = Arc::new(SchemaV3StorageOverride::new(client.clone()));
+		let indexer_backend = fc_db::sql::Backend::new(
+			fc_db::sql::BackendConfig::Sqlite(fc_db::sql::SqliteBackendConfig {
+				path: Path::new("sqlite:///")
+					.join(tmp.path())
+					.join("test.db3")
+					.to_str()
+					.unwrap(),
+				create_if_missing: true,
+				cache_size: 204800,
+				thread_count: 4,
+				journal_mode: None,
+				synchronous: None,
+				busy_timeout: None,
+				index_logs: false,
+			}),
+			100,
+			None,
+			storage_override.clone(),
+		)
+		.await
+		.expect("indexer pool to be created");
+		let pool = indexer_backend.pool().clone();
+
+		// Create 3 blocks, each with a receipt emitting a log
+		let mut block_hashes: Vec<H256> = vec![];
+		for _ in 0..3 {
+			let chain = client.chain_info();
+			let mut builder = BlockBuilderBuilder::new(&*client)
+				.on_parent_block(chain.best_hash)
+				.with_parent_block_number(chain.best_number)
+				.build()
+				.unwrap();
+			builder
+				.push_deposit_log_digest_item(ethereum_digest())
+				.expect("deposit log");
+			let receipts = Encode::encode(&vec![ethereum::ReceiptV3::EIP1559(
+				ethereum::EIP1559ReceiptData {
+					status_code: 0u8,
+					used_gas: U256::zero(),
+					logs_bloom: ethereum_types::Bloom::zero(),
+					logs: vec![ethereum::Log {
+						address: H160::repeat_byte(0x01),
+						topics: vec![H256::repeat_byte(0x01)],
+						data: vec![],
+					}],
+				},
+			)]);
+			builder
+				.push_storage_change(
+					storage_prefix_build(PALLET_ETHEREUM, ETHEREUM_CURRENT_RECEIPTS),
+					Some(receipts),
+				)
+				.unwrap();
+			let block = builder.build().unwrap().block;
+			block_hashes.push(block.header.hash());
+			executor::block_on(client.import(BlockOrigin::Own, block)).unwrap();
+		}
+
+		let pubsub_notification_sinks: EthereumBlockNotificationSinks<
+			EthereumBlockNotification<OpaqueBlock>,
+		> = Default::default();
+		tokio::task::spawn(async move {
+			crate::sql::SyncWorker::run(
+				client.clone(),
+				backend.clone(),
+				Arc::new(indexer_backend),
+				client.clone().import_notification_stream(),
+				SyncWorkerConfig {
+					read_notification_timeout: Duration::from_secs(1),
+					..Default::default()
+				},
+				SyncStrategy::Parachain,
+				Arc::new(TestSyncOracleNotSyncing {}),
+				Arc::new(pubsub_notification_sinks),
+				None,
+				None,
+				future::pending(),
+			)
+			.await
+		});
+
+		// Enough time for interval to run
+		futures_timer::Delay::new(Duration::from_millis(1500)).await;
+
+		let log_count = sqlx::query("SELECT COUNT(*) FROM logs")
+			.fetch_one(&pool)
+			.await
+			.expect("test query result")
+			.get::<i64, _>(0);
+		assert_eq!(log_count, 0);
+
+		// Every block is indexed and marked as processed.
+		for (i, hash) in block_hashes.into_iter().enumerate() {
+			let row = sqlx::query(
+				"SELECT b.block_number, s.status FROM blocks AS b
+				INNER JOIN sync_status AS s ON b.substrate_block_hash = s.substrate_block_hash
+				WHERE b.substrate_block_hash = ?",
+			)
+			.bind(hash.as_bytes())
+			.fetch_one(&pool)
+			.await
+			.expect("block indexed");
+			assert_eq!(row.get::<i32, _>(0), i as i32 + 1);
+			assert_eq!(row.get::<i32, _>(1), 1);
+		}
+	}
+
+	#[tokio::test]
+	async fn header_fetch_failure_records_gap_that_is_later_filled() {
+		// Fails the header reads, the chain info is the wrapped client's.
+		struct FailingHeaderBackend<C>(Arc<C>);
+		impl<C: HeaderBackend<OpaqueBlock>> HeaderBackend<OpaqueBlock> for FailingHeaderBackend<C> {
+			fn header(
+				&self,
+				_hash: H256,
+			) -> sp_blockchain::Result<Option<<OpaqueBlock as BlockT>::Header>> {
+				Err(sp_blockchain::Error::Backend("header fetch failed".into()))
+			}
+			fn info(&self) -> sp_blockchain::Info<OpaqueBlock> {
+				self.0.info()
+			}
+			fn status(&self, _hash: H256) -> sp_blockchain::Result<sp_blockchain::BlockStatus> {
+				Err(sp_blockchain::Error::Backend("header fetch failed".into()))
+			}
+			fn number(&self, _hash: H256) -> sp_blockchain::Result<Option<u64>> {
+				Err(sp_blockchain::Error::Backend("header fetch failed".into()))
+			}
+			fn hash(&self, _number: u64) -> sp_blockchain::Result<Option<H256>> {
+				Err(sp_blockchain::Error::Backend("header fetch failed".into()))
+			}
+		}
+
+		let tmp = tempdir().expect("create a temporary directory");
+		let builder = TestClientBuilder::new().add_extra_storage(
+			PALLET_ETHEREUM_SCHEMA.to_vec(),
+			Encode::encode(&EthereumStorageSchema::V3),
+		);
+		let backend = builder.backend();
+		let (client, _) =
+			builder.build_with_native_executor::<frontier_template_runtime::RuntimeApi, _>(None);
+		let mut client = Arc::new(client);
+		let storage_override = Arc::new(SchemaV3StorageOverride::new(client.clone()));
+		let indexer_backend = fc_db::sql::Backend::new(
+			fc_db::sql::BackendConfig::Sqlite(fc_db::sql::SqliteBackendConfig {
+				path: Path::new("sqlite:///")
+					.join(tmp.path())
+					.join("test.db3")
+					.to_str()
+					.unwrap(),
+				create_if_missing: true,
+				cache_size: 204800,
+				thread_count: 4,
+				journal_mode: None,
+				synchronous: None,
+				busy_timeout: None,
+				index_logs: true,
+			}),
+			100,
+			None,
+			storage_override.clone(),
+		)
+		.await
+		.expect("indexer pool to be created");
+		let indexer_backend = Arc::new(indexer_backend);
+
+		// Import three blocks
+		let mut block_hashes: Vec<H256> = vec![];
+		for _ in 0..3 {
+			let chain = client.chain_info();
+			let mut builder = BlockBuilderBuilder::new(&*client)
+				.on_parent_block(chain.best_hash)
+				.with_parent_block_number(chain.best_number)
+				.build()
+				.unwrap();
+			builder
+				.push_deposit_log_digest_item(ethereum_digest())
+				.expect("deposit log");
+			let block = builder.build().unwrap().block;
+			block_hashes.push(block.header.hash());
+			executor::block_on(client.import(BlockOrigin::Own, block)).unwrap();
+		}
+
+		// The descent from the tip fails to fetch the parent header.
+		let tip = block_hashes[2];
+		assert_eq!(
+			parent_hash_or_record_gap(&FailingHeaderBackend(client.clone()), &indexer_backend, tip)
+				.await,
+			None
+		);
+		assert_eq!(indexer_backend.gaps().await.unwrap(), vec![tip]);
+
+		// Once headers are available again, the ancestors are backfilled.
+		fill_gaps(client.clone(), backend.clone(), indexer_backend.clone()).await;
+		assert!(indexer_backend.gaps().await.unwrap().is_empty());
+		assert!(indexer_backend.is_block_indexed(block_hashes[0]).await);
+		assert!(indexer_backend.is_block_indexed(block_hashes[1]).await);
+	}
+
+	#[tokio::test]
+	async fn blocks_are_queryable_by_author() {
+		let tmp = tempdir().expect("create a temporary directory");
+		let builder = TestClientBuilder::new().add_extra_storage(
+			PALLET_ETHEREUM_SCHEMA.to_vec(),
+			Encode::encode(&EthereumStorageSchema::V3),
+		);
+		let (client, _) =
+			builder.build_with_native_executor::<frontier_template_runtime::RuntimeApi, _>(None);
+		let mut client = Arc::new(client);
+		let storage_override = Arc::new(SchemaV3StorageOverride::new(client.clone()));
+		let indexer_backend = fc_db::sql::Backend::new(
+			fc_db::sql::BackendConfig::Sqlite(fc_db::sql::SqliteBackendConfig {
+				path: Path::new("sqlite:///")
+					.join(tmp.path())
+					.join("test.db3")
+					.to_str()
+					.unwrap(),
+				create_if_missing: true,
+				cache_size: 204800,
+				thread_count: 4,
+				journal_mode: None,
+				synchronous: None,
+				busy_timeout: None,
+				index_logs: true,
+			}),
+			100,
+			None,
+			storage_override.clone(),
+		)
+		.await
+		.expect("indexer pool to be created");
+
+		// Blocks #1 and #3 are authored by alice, #2 by bob.
+		let alice = H160::repeat_byte(0x01);
+		let bob = H160::repeat_byte(0x02);
+		let mut block_hashes: Vec<H256> = vec![];
+		for author in [alice, bob, alice] {
+			let chain = client.chain_info();
+			let mut builder = BlockBuilderBuilder::new(&*client)
+				.on_parent_block(chain.best_hash)
+				.with_parent_block_number(chain.best_number)
+				.build()
+				.unwrap();
+			builder
+				.push_deposit_log_digest_item(ethereum_block_digest(author))
+				.expect("deposit log");
+			let block = builder.build().unwrap().block;
+			let block_hash = block.header.hash();
+			executor::block_on(client.import(BlockOrigin::Own, block)).unwrap();
+			indexer_backend
+				.insert_block_metadata(client.clone(), block_hash)
+				.await
+				.expect("insert block metadata");
+			block_hashes.push(block_hash);
+		}
+
+		assert_eq!(
+			indexer_backend
+				.blocks_by_author(alice, 0..=10)
+				.await
+				.unwrap(),
+			vec![block_hashes[0], block_hashes[2]]
+		);
+		assert_eq!(
+			indexer_backend.blocks_by_author(bob, 0..=10).await.unwrap(),
+			vec![block_hashes[1]]
+		);
+		assert_eq!(
+			indexer_backend
+				.blocks_by_author(alice, 2..=3)
+				.await
+				.unwrap(),
+			vec![block_hashes[2]]
+		);
+		assert!(indexer_backend
+			.blocks_by_author(H160::repeat_byte(0x03), 0..=10)
+			.await
+			.unwrap()
+			.is_empty());
+	}
+
+	#[tokio::test]
+	async fn interrupted_backfill_resumes_from_lowest_indexed_block() {
+		let tmp = tempdir().expect("create a temporary directory");
+		let builder = TestClientBuilder::new().add_extra_storage(
+			PALLET_ETHEREUM_SCHEMA.to_vec(),
+			Encode::encode(&EthereumStorageSchema::V3),
+		);
+		let backend = builder.backend();
+		let (client, _) =
+			builder.build_with_native_executor::<frontier_template_runtime::RuntimeApi, _>(None);
+		let mut client = Arc::new(client);
+		let storage_override = Arc::new(SchemaV3StorageOverride::new(client.clone()));
+		let indexer_backend = fc_db::sql::Backend::new(
+			fc_db::sql::BackendConfig::Sqlite(fc_db::sql::SqliteBackendConfig {
+				path: Path::new("sqlite:///")
+					.join(tmp.path())
+					.join("test.db3")
+					.to_str()
+					.unwrap(),
+				create_if_missing: true,
+				cache_size: 204800,
+				thread_count: 4,
+				journal_mode: None,
+				synchronous: None,
+				busy_timeout: None,
+				index_logs: true,
+			}),
+			100,
+			None,
+			storage_override.clone(),
+		)
+		.await
+		.expect("indexer pool to be created");
+		let indexer_backend = Arc::new(indexer_backend);
+
+		// Create 10 blocks.
+		let mut block_hashes: Vec<H256> = vec![];
+		for _ in 1..=10 {
+			let chain = client.chain_info();
+			let mut builder = BlockBuilderBuilder::new(&*client)
+				.on_parent_block(chain.best_hash)
+				.with_parent_block_number(chain.best_number)
+				.build()
+				.unwrap();
+			builder
+				.push_deposit_log_digest_item(ethereum_digest())
+				.expect("deposit log");
+			let block = builder.build().unwrap().block;
+			block_hashes.push(block.header.hash());
+			executor::block_on(client.import(BlockOrigin::Own, block)).unwrap();
+		}
+
+		// A descent from #9 was interrupted after indexing #6, then #10 got indexed.
+		for block_hash in block_hashes[5..9].iter().rev().chain(&block_hashes[9..]) {
+			indexer_backend
+				.insert_block_metadata(client.clone(), *block_hash)
+				.await
+				.expect("insert block metadata");
+			indexer_backend
+				.index_block_logs(*block_hash)
+				.await
+				.expect("index block logs");
+		}
+		indexer_backend
+			.set_backfill_progress(block_hashes[8], 6)
+			.await
+			.expect("record backfill progress");
+
+		let pubsub_notification_sinks: crate::EthereumBlockNotificationSinks<
+			crate::EthereumBlockNotification<OpaqueBlock>,
+		> = Default::default();
+		let tx = crate::sql::SyncWorker::spawn_worker(
+			client.clone(),
+			backend.clone(),
+			indexer_backend.clone(),
+			Arc::new(pubsub_notification_sinks),
+			None,
+			None,
+			None,
+			DEFAULT_MAX_REORG_DEPTH,
+		)
+		.await;
+		tx.send(WorkerCommand::ResumeSync)
+			.await
+			.expect("send command");
+
+		// Enough time for the backfill
+		futures_timer::Delay::new(Duration::from_millis(1500)).await;
+
+		for block_hash in &block_hashes[0..5] {
+			assert!(indexer_backend.is_block_indexed(*block_hash).await);
+		}
+		assert_eq!(indexer_backend.backfill_progress().await.unwrap(), vec![]);
+	}
+
+	#[tokio::test]
+	async fn rapid_reorgs_are_canonicalized_once() {
+		let tmp = tempdir().expect("create a temporary directory");
+		let builder = TestClientBuilder::new().add_extra_storage(
+			PALLET_ETHEREUM_SCHEMA.to_vec(),
+			Encode::encode(&EthereumStorageSchema::V3),
+		);
+		let backend = builder.backend();
+		let (client, _) =
+			builder.build_with_native_executor::<frontier_template_runtime::RuntimeApi, _>(None);
+		let mut client = Arc::new(client);
+		let storage_override = Arc::new(SchemaV3StorageOverride::new(client.clone()));
+		let indexer_backend = fc_db::sql::Backend::new(
+			fc_db::sql::BackendConfig::Sqlite(fc_db::sql::SqliteBackendConfig {
+				path: Path::new("sqlite:///")
+					.join(tmp.path())
+					.join("test.db3")
+					.to_str()
+					.unwrap(),
+				create_if_missing: true,
+				cache_size: 204800,
+				thread_count: 4,
+				journal_mode: None,
+				synchronous: None,
+				busy_timeout: None,
+				index_logs: true,
+			}),
+			100,
+			None,
+			storage_override.clone(),
+		)
+		.await
+		.expect("indexer pool to be created");
+
+		// Pool
+		let pool = indexer_backend.pool().clone();
+
+		let pubsub_notification_sinks: EthereumBlockNotificationSinks<
+			EthereumBlockNotification<OpaqueBlock>,
+		> = Default::default();
+		let notification_stream = client.clone().import_notification_stream();
+		let client_inner = client.clone();
+		tokio::task::spawn(async move {
+			crate::sql::SyncWorker::run(
+				client_inner,
+				backend.clone(),
+				Arc::new(indexer_backend),
+				notification_stream,
+				SyncWorkerConfig {
+					read_notification_timeout: Duration::from_secs(10),
+					reorg_debounce: Duration::from_millis(500),
+					..Default::default()
+				},
+				SyncStrategy::Parachain,
+				Arc::new(TestSyncOracleNotSyncing {}),
+				Arc::new(pubsub_notification_sinks),
+				None,
+				None,
+				future::pending(),
+			)
+			.await
+		});
+
+		let genesis_hash = client
+			.hash(sp_runtime::traits::Zero::zero())
+			.unwrap()
+			.expect("genesis hash");
+
+		// Builds a branch of `length` blocks on top of `parent`, each one imported as best.
+		let mut import_branch = |parent: H256, length: usize, author: H160| -> Vec<H256> {
+			let mut parent = parent;
+			let mut hashes = vec![];
+			for _ in 0..length {
+				let mut builder = BlockBuilderBuilder::new(&*client)
+					.on_parent_block(parent)
+					.fetch_parent_block_number(&*client)
+					.unwrap()
+					.build()
+					.unwrap();
+				builder
+					.push_deposit_log_digest_item(ethereum_block_digest(author))
+					.expect("deposit log");
+				let block = builder.build().unwrap().block;
+				parent = block.header.hash();
+				hashes.push(parent);
+				executor::block_on(client.import_as_best(BlockOrigin::Own, block)).unwrap();
+			}
+			hashes
+		};
+
+		// Index the main chain #1 - #2.
+		let main = import_branch(genesis_hash, 2, H160::repeat_byte(0x01));
+		futures_timer::Delay::new(Duration::from_millis(1500)).await;
+
+		// Record every block that stops being canonical.
+		sqlx::query(
+			"CREATE TABLE retractions (substrate_block_hash BLOB NOT NULL);
+			CREATE TRIGGER record_retractions AFTER UPDATE OF is_canon ON blocks
+			WHEN OLD.is_canon = 1 AND NEW.is_canon = 0
+			BEGIN INSERT INTO retractions VALUES (NEW.substrate_block_hash); END;",
+		)
+		.execute(&pool)
+		.await
+		.expect("create trigger");
+
+		// Three re-orgs from #1 in a row, each onto a longer branch.
+		import_branch(main[0], 2, H160::repeat_byte(0x02));
+		import_branch(main[0], 3, H160::repeat_byte(0x03));
+		let last = import_branch(main[0], 4, H160::repeat_byte(0x04));
+
+		// Enough time for the burst to settle and be indexed
+		futures_timer::Delay::new(Duration::from_millis(2000)).await;
+
+		let retractions = sqlx::query("SELECT substrate_block_hash FROM retractions")
+			.fetch_all(&pool)
+			.await
+			.expect("test query result")
+			.iter()
+			.map(|row| H256::from_slice(&row.get::<Vec<u8>, _>(0)[..]))
+			.collect::<Vec<H256>>();
+		assert_eq!(retractions, vec![main[1]]);
+
+		let canon = sqlx::query(
+			"SELECT substrate_block_hash FROM blocks WHERE is_canon = 1 ORDER BY block_number ASC",
+		)
+		.fetch_all(&pool)
+		.await
+		.expect("test query result")
+		.iter()
+		.map(|row| H256::from_slice(&row.get::<Vec<u8>, _>(0)[..]))
+		.collect::<Vec<H256>>();
+		let mut expected = vec![genesis_hash, main[0]];
+		expected.extend(last);
+		assert_eq!(canon, expected);
+	}
+
+	#[tokio::test]
+	async fn logs_phase_resumes_after_a_crash() {
+		let tmp = tempdir().expect("create a temporary directory");
+		let builder = TestClientBuilder::new().add_extra_storage(
+			PALLET_ETHEREUM_SCHEMA.to_vec(),
+			Encode::encode(&EthereumStorageSchema::V3),
+		);
+		let backend = builder.backend();
+		let (client, _) =
+			builder.build_with_native_executor::<frontier_template_runtime::RuntimeApi, _>(None);
+		let mut client = Arc::new(client);
+		let storage_override = Arc::new(SchemaV3StorageOverride::new(client.clone()));
+		let indexer_backend = fc_db::sql::Backend::new(
+			fc_db::sql::BackendConfig::Sqlite(fc_db::sql::SqliteBackendConfig {
+				path: Path::new("sqlite:///")
+					.join(tmp.path())
+					.join("test.db3")
+					.to_str()
+					.unwrap(),
+				create_if_missing: true,
+				cache_size: 204800,
+				thread_count: 4,
+				journal_mode: None,
+				synchronous: None,
+				busy_timeout: None,
+				index_logs: true,
+			}),
+			100,
+			None,
+			storage_override.clone(),
+		)
+		.await
+		.expect("indexer pool to be created");
+		let indexer_backend = Arc::new(indexer_backend);
+
+		// Pool
+		let pool = indexer_backend.pool().clone();
+
+		// Create 3 blocks.
+		let mut block_hashes: Vec<H256> = vec![];
+		for _ in 1..=3 {
+			let chain = client.chain_info();
+			let mut builder = BlockBuilderBuilder::new(&*client)
+				.on_parent_block(chain.best_hash)
+				.with_parent_block_number(chain.best_number)
+				.build()
+				.unwrap();
+			builder
+				.push_deposit_log_digest_item(ethereum_digest())
+				.expect("deposit log");
+			let block = builder.build().unwrap().block;
+			block_hashes.push(block.header.hash());
+			executor::block_on(client.import(BlockOrigin::Own, block)).unwrap();
+		}
+
+		// The node stopped after committing the metadata of #2 and #3, before their logs.
+		for block_hash in block_hashes.iter() {
+			indexer_backend
+				.insert_block_metadata(client.clone(), *block_hash)
+				.await
+				.expect("insert block metadata");
+		}
+		indexer_backend
+			.index_block_logs(block_hashes[0])
+			.await
+			.expect("index block logs");
+		assert_eq!(
+			indexer_backend.blocks_pending_logs().await.unwrap(),
+			block_hashes[1..].to_vec()
+		);
+
+		// The metadata phase must not be redone.
+		sqlx::query(
+			"CREATE TRIGGER reject_metadata BEFORE INSERT ON sync_status
+			BEGIN SELECT RAISE(ABORT, 'metadata redone'); END;",
+		)
+		.execute(&pool)
+		.await
+		.expect("create trigger");
+
+		let pubsub_notification_sinks: crate::EthereumBlockNotificationSinks<
+			crate::EthereumBlockNotification<OpaqueBlock>,
+		> = Default::default();
+		let tx = crate::sql::SyncWorker::spawn_worker(
+			client.clone(),
+			backend.clone(),
+			indexer_backend.clone(),
+			Arc::new(pubsub_notification_sinks),
+			None,
+			None,
+			None,
+			DEFAULT_MAX_REORG_DEPTH,
+		)
+		.await;
+		tx.send(WorkerCommand::ResumeSync)
+			.await
+			.expect("send command");
+
+		// Enough time for the logs phase
+		futures_timer::Delay::new(Duration::from_millis(500)).await;
+
+		assert!(indexer_backend
+			.blocks_pending_logs()
+			.await
+			.unwrap()
+			.is_empty());
+		let phases = sqlx::query(
+			"SELECT substrate_block_hash, metadata_done, logs_done FROM sync_status ORDER BY id",
+		)
+		.fetch_all(&pool)
+		.await
+		.expect("test query result")
+		.iter()
+		.map(|row| {
+			(
+				H256::from_slice(&row.get::<Vec<u8>, _>(0)[..]),
+				row.get::<i32, _>(1),
+				row.get::<i32, _>(2),
+			)
+		})
+		.collect::<Vec<(H256, i32, i32)>>();
+		assert_eq!(
+			phases,
+			block_hashes
+				.iter()
+				.map(|hash| (*hash, 1, 1))
+				.collect::<Vec<(H256, i32, i32)>>()
+		);
+		assert!(indexer_backend.fatal_error().is_none());
+	}
+
+	#[tokio::test]
+	async fn indexing_throughput_is_reported() {
+		let tmp = tempdir().expect("create a temporary directory");
+		let builder = TestClientBuilder::new().add_extra_storage(
+			PALLET_ETHEREUM_SCHEMA.to_vec(),
+			Encode::encode(&EthereumStorageSchema::V3),
+		);
+		let (client, _) =
+			builder.build_with_native_executor::<frontier_template_runtime::RuntimeApi, _>(None);
+		let mut client = Arc::new(client);
+		let storage_override = Arc::new(SchemaV3StorageOverride::new(client.clone()));
+		let indexer_backend = fc_db::sql::Backend::new(
+			fc_db::sql::BackendConfig::Sqlite(fc_db::sql::SqliteBackendConfig {
+				path: Path::new("sqlite:///")
+					.join(tmp.path())
+					.join("test.db3")
+					.to_str()
+					.unwrap(),
+				create_if_missing: true,
+				cache_size: 204800,
+				thread_count: 4,
+				journal_mode: None,
+				synchronous: None,
+				busy_timeout: None,
+				index_logs: true,
+			}),
+			100,
+			None,
+			storage_override.clone(),
+		)
+		.await
+		.expect("indexer pool to be created")
+		.with_throughput_window(Duration::from_secs(10));
+
+		// Index 5 blocks within the window.
+		for _ in 0..5 {
+			let chain = client.chain_info();
+			let mut builder = BlockBuilderBuilder::new(&*client)
+				.on_parent_block(chain.best_hash)
+				.with_parent_block_number(chain.best_number)
+				.build()
+				.unwrap();
+			builder
+				.push_deposit_log_digest_item(ethereum_digest())
+				.expect("deposit log");
+			let block = builder.build().unwrap().block;
+			let block_hash = block.header.hash();
+			executor::block_on(client.import(BlockOrigin::Own, block)).unwrap();
+			indexer_backend
+				.insert_block_metadata(client.clone(), block_hash)
+				.await
+				.expect("insert block metadata");
+			indexer_backend
+				.index_block_logs(block_hash)
+				.await
+				.expect("index block logs");
+		}
+
+		let throughput = indexer_backend.throughput();
+		assert!(
+			(throughput.blocks_per_sec - 0.5).abs() < 0.01,
+			"{throughput:?}"
+		);
+		assert_eq!(throughput.logs_per_sec, 0.0);
+	}
+
+	#[tokio::test]
+	async fn genesis_contracts_are_indexed() {
+		let tmp = tempdir().expect("create a temporary directory");
+		let blake2_128_concat =
+			|bytes: &[u8]| -> Vec<u8> { [&sp_io::hashing::blake2_128(bytes)[..], bytes].concat() };
+		// A contract with two storage slots is seeded in the genesis state.
+		let contract = H160::repeat_byte(0x42);
+		let code = vec![0x60, 0x00, 0x60, 0x00, 0xf3];
+		let storage_key = |slot: u8| -> Vec<u8> {
+			[
+				storage_prefix_build(PALLET_EVM, EVM_ACCOUNT_STORAGES),
+				blake2_128_concat(contract.as_bytes()),
+				blake2_128_concat(H256::repeat_byte(slot).as_bytes()),
+			]
+			.concat()
+		};
+		let builder = TestClientBuilder::new()
+			.add_extra_storage(
+				PALLET_ETHEREUM_SCHEMA.to_vec(),
+				Encode::encode(&EthereumStorageSchema::V3),
+			)
+			.add_extra_storage(
+				[
+					storage_prefix_build(PALLET_EVM, EVM_ACCOUNT_CODES),
+					blake2_128_concat(contract.as_bytes()),
+				]
+				.concat(),
+				code.encode(),
+			)
+			.add_extra_storage(storage_key(0x01), H256::repeat_byte(0xaa).encode())
+			.add_extra_storage(storage_key(0x02), H256::repeat_byte(0xbb).encode());
+		let (client, _) =
+			builder.build_with_native_executor::<frontier_template_runtime::RuntimeApi, _>(None);
+		let client = Arc::new(client);
+		let storage_override = Arc::new(SchemaV3StorageOverride::new(client.clone()));
+		let indexer_backend = fc_db::sql::Backend::new(
+			fc_db::sql::BackendConfig::Sqlite(fc_db::sql::SqliteBackendConfig {
+				path: Path::new("sqlite:///")
+					.join(tmp.path())
+					.join("test.db3")
+					.to_str()
+					.unwrap(),
+				create_if_missing: true,
+				cache_size: 204800,
+				thread_count: 4,
+				journal_mode: None,
+				synchronous: None,
+				busy_timeout: None,
+				index_logs: true,
+			}),
+			100,
+			None,
+			storage_override.clone(),
+		)
+		.await
+		.expect("indexer pool to be created")
+		.with_genesis_contracts();
+
+		let genesis_hash = indexer_backend
+			.insert_genesis_block_metadata(client.clone())
+			.await
+			.expect("insert genesis block metadata")
+			.expect("genesis block indexed");
+
+		assert_eq!(
+			indexer_backend.contracts().await.unwrap(),
+			vec![fc_db::sql::IndexedContract {
+				address: contract,
+				substrate_block_hash: genesis_hash,
+				block_number: 0,
+				code_hash: H256::from(sp_io::hashing::keccak_256(&code)),
+				code_size: code.len() as u32,
+				storage_slots: 2,
+			}]
+		);
+	}
+
+	#[tokio::test]
+	async fn polling_mode_indexes_without_notifications() {
+		let tmp = tempdir().expect("create a temporary directory");
+		let builder = TestClientBuilder::new().add_extra_storage(
+			PALLET_ETHEREUM_SCHEMA.to_vec(),
+			Encode::encode(&EthereumStorageSchema::V3),
+		);
+		let backend = builder.backend();
+		let (client, _) =
+			builder.build_with_native_executor::<frontier_template_runtime::RuntimeApi, _>(None);
+		let mut client = Arc::new(client);
+		let storage_override = Arc::new(SchemaV3StorageOverride::new(client.clone()));
+		let indexer_backend = fc_db::sql::Backend::new(
+			fc_db::sql::BackendConfig::Sqlite(fc_db::sql::SqliteBackendConfig {
+				path: Path::new("sqlite:///")
+					.join(tmp.path())
+					.join("test.db3")
+					.to_str()
+					.unwrap(),
+				create_if_missing: true,
+				cache_size: 204800,
+				thread_count: 4,
+				journal_mode: None,
+				synchronous: None,
+				busy_timeout: None,
+				index_logs: true,
+			}),
+			100,
+			None,
+			storage_override.clone(),
+		)
+		.await
+		.expect("indexer pool to be created");
+		let indexer_backend = Arc::new(indexer_backend);
+
+		// Pool
+		let pool = indexer_backend.pool().clone();
+
+		// A notification stream that never yields, its sink is kept alive until the end.
+		let (_sink, notification_stream) =
+			sc_utils::mpsc::tracing_unbounded("test_import_notifications", 10);
+		let pubsub_notification_sinks: EthereumBlockNotificationSinks<
+			EthereumBlockNotification<OpaqueBlock>,
+		> = Default::default();
+		let client_inner = client.clone();
+		let indexer_backend_inner = indexer_backend.clone();
+		tokio::task::spawn(async move {
+			crate::sql::SyncWorker::run(
+				client_inner,
+				backend.clone(),
+				indexer_backend_inner,
+				notification_stream,
+				SyncWorkerConfig {
+					read_notification_timeout: Duration::from_secs(10),
+					poll_interval: Some(Duration::from_millis(100)),
+					..Default::default()
+				},
+				SyncStrategy::Parachain,
+				Arc::new(TestSyncOracleNotSyncing {}),
+				Arc::new(pubsub_notification_sinks),
+				None,
+				None,
+				future::pending(),
+			)
+			.await
+		});
+
+		// Import three blocks
+		let mut block_hashes: Vec<H256> = vec![];
+		for _ in 0..3 {
+			let chain = client.chain_info();
+			let mut builder = BlockBuilderBuilder::new(&*client)
+				.on_parent_block(chain.best_hash)
+				.with_parent_block_number(chain.best_number)
+				.build()
+				.unwrap();
+			builder
+				.push_deposit_log_digest_item(ethereum_digest())
+				.expect("deposit log");
+			let block = builder.build().unwrap().block;
+			block_hashes.push(block.header.hash());
+			executor::block_on(client.import(BlockOrigin::Own, block)).unwrap();
+		}
+
+		// Enough time for a few polls
+		futures_timer::Delay::new(Duration::from_millis(1500)).await;
+
+		let canon = sqlx::query(
+			"SELECT substrate_block_hash FROM blocks WHERE block_number > 0 AND is_canon = 1 ORDER BY block_number ASC",
+		)
+		.fetch_all(&pool)
+		.await
+		.expect("test query result")
+		.iter()
+		.map(|row| H256::from_slice(&row.get::<Vec<u8>, _>(0)[..]))
+		.collect::<Vec<H256>>();
+		assert_eq!(canon, block_hashes);
+
+		// Finality is polled as well.
+		client
+			.finalize_block(block_hashes[1], None, true)
+			.expect("finalize block");
+		futures_timer::Delay::new(Duration::from_millis(500)).await;
+		assert_eq!(
+			indexer_backend.last_finalized().await.unwrap(),
+			Some(block_hashes[1])
+		);
+	}
+
+	#[tokio::test]
+	async fn metrics_report_the_indexed_block_number() {
+		let tmp = tempdir().expect("create a temporary directory");
+		let builder = TestClientBuilder::new().add_extra_storage(
+			PALLET_ETHEREUM_SCHEMA.to_vec(),
+			Encode::encode(&EthereumStorageSchema::V3),
+		);
+		let backend = builder.backend();
+		let (client, _) =
+			builder.build_with_native_executor::<frontier_template_runtime::RuntimeApi, _>(None);
+		let mut client = Arc::new(client);
+		let storage_override = Arc::new(SchemaV3StorageOverride::new(client.clone()));
+		let indexer_backend = fc_db::sql::Backend::new(
+			fc_db::sql::BackendConfig::Sqlite(fc_db::sql::SqliteBackendConfig {
+				path: Path::new("sqlite:///")
+					.join(tmp.path())
+					.join("test.db3")
+					.to_str()
+					.unwrap(),
+				create_if_missing: true,
+				cache_size: 204800,
+				thread_count: 4,
+				journal_mode: None,
+				synchronous: None,
+				busy_timeout: None,
+				index_logs: true,
+			}),
+			100,
+			None,
+			storage_override.clone(),
+		)
+		.await
+		.expect("indexer pool to be created");
+
+		let registry = prometheus_endpoint::Registry::new();
+		let indexed_block_number = || {
+			registry
+				.gather()
+				.iter()
+				.find(|family| family.get_name() == "frontier_sql_indexed_block_number")
+				.map(|family| family.get_metric()[0].get_gauge().get_value())
+		};
+
+		let pubsub_notification_sinks: EthereumBlockNotificationSinks<
+			EthereumBlockNotification<OpaqueBlock>,
+		> = Default::default();
+		let notification_stream = client.clone().import_notification_stream();
+		let client_inner = client.clone();
+		let registry_inner = registry.clone();
+		tokio::task::spawn(async move {
+			crate::sql::SyncWorker::run(
+				client_inner,
+				backend.clone(),
+				Arc::new(indexer_backend),
+				notification_stream,
+				SyncWorkerConfig {
+					read_notification_timeout: Duration::from_secs(10),
+					..Default::default()
+				},
+				SyncStrategy::Parachain,
+				Arc::new(TestSyncOracleNotSyncing {}),
+				Arc::new(pubsub_notification_sinks),
+				Some(registry_inner),
+				None,
+				future::pending(),
+			)
+			.await
+		});
+
+		// The genesis block is indexed on resume.
+		futures_timer::Delay::new(Duration::from_millis(500)).await;
+		assert_eq!(indexed_block_number(), Some(0.0));
+
+		for expected in 1..=3 {
+			let chain = client.chain_info();
+			let mut builder = BlockBuilderBuilder::new(&*client)
+				.on_parent_block(chain.best_hash)
+				.with_parent_block_number(chain.best_number)
+				.build()
+				.unwrap();
+			builder
+				.push_deposit_log_digest_item(ethereum_digest())
+				.expect("deposit log");
+			let block = builder.build().unwrap().block;
+			executor::block_on(client.import(BlockOrigin::Own, block)).unwrap();
+			// Enough time for indexing
+			futures_timer::Delay::new(Duration::from_millis(500)).await;
+			assert_eq!(indexed_block_number(), Some(expected as f64));
+		}
+	}
+
+	#[tokio::test]
+	async fn all_gaps_are_indexed_on_check() {
+		let tmp = tempdir().expect("create a temporary directory");
+		let builder = TestClientBuilder::new().add_extra_storage(
+			PALLET_ETHEREUM_SCHEMA.to_vec(),
+			Encode::encode(&EthereumStorageSchema::V3),
+		);
+		let backend = builder.backend();
+		let (client, _) =
+			builder.build_with_native_executor::<frontier_template_runtime::RuntimeApi, _>(None);
+		let mut client = Arc::new(client);
+		let storage_override = Arc::new(SchemaV3StorageOverride::new(client.clone()));
+		let indexer_backend = fc_db::sql::Backend::new(
+			fc_db::sql::BackendConfig::Sqlite(fc_db::sql::SqliteBackendConfig {
+				path: Path::new("sqlite:///")
+					.join(tmp.path())
+					.join("test.db3")
+					.to_str()
+					.unwrap(),
+				create_if_missing: true,
+				cache_size: 204800,
+				thread_count: 4,
+				journal_mode: None,
+				synchronous: None,
+				busy_timeout: None,
+				index_logs: true,
+			}),
+			100,
+			None,
+			storage_override.clone(),
+		)
+		.await
+		.expect("indexer pool to be created");
+
+		// Pool
+		let pool = indexer_backend.pool().clone();
+
+		// Index a chain of 6 blocks, then drop blocks 2 and 4 from the index.
+		let mut block_hashes: Vec<H256> = vec![];
+		for _ in 0..6 {
+			let chain = client.chain_info();
+			let mut builder = BlockBuilderBuilder::new(&*client)
+				.on_parent_block(chain.best_hash)
+				.with_parent_block_number(chain.best_number)
+				.build()
+				.unwrap();
+			builder
+				.push_deposit_log_digest_item(ethereum_digest())
+				.expect("deposit log");
+			let block = builder.build().unwrap().block;
+			let block_hash = block.header.hash();
+			executor::block_on(client.import(BlockOrigin::Own, block)).unwrap();
+			indexer_backend
+				.insert_block_metadata(client.clone(), block_hash)
+				.await
+				.expect("insert block metadata");
+			indexer_backend
+				.index_block_logs(block_hash)
+				.await
+				.expect("index block logs");
+			block_hashes.push(block_hash);
+		}
+		for block_hash in [block_hashes[1], block_hashes[3]] {
+			for table in ["blocks", "sync_status"] {
+				sqlx::query(&format!(
+					"DELETE FROM {table} WHERE substrate_block_hash = ?"
+				))
+				.bind(block_hash.as_bytes())
+				.execute(&pool)
+				.await
+				.expect("sql query must succeed");
+			}
+		}
+		assert_eq!(
+			indexer_backend.find_gaps().await.unwrap(),
+			vec![(0, 0), (2, 2), (4, 4)]
+		);
+
+		let pubsub_notification_sinks: EthereumBlockNotificationSinks<
+			EthereumBlockNotification<OpaqueBlock>,
+		> = Default::default();
+		let notification_stream = client.clone().import_notification_stream();
+		let client_inner = client.clone();
+		let indexer_backend = Arc::new(indexer_backend);
+		let indexer_backend_inner = indexer_backend.clone();
+		tokio::task::spawn(async move {
+			crate::sql::SyncWorker::run(
+				client_inner,
+				backend.clone(),
+				indexer_backend_inner,
+				notification_stream,
+				SyncWorkerConfig {
+					read_notification_timeout: Duration::from_secs(10),
+					check_indexed_blocks_interval: Duration::from_secs(1),
+					index_all_gaps: true,
+					..Default::default()
+				},
+				SyncStrategy::Parachain,
+				Arc::new(TestSyncOracleNotSyncing {}),
+				Arc::new(pubsub_notification_sinks),
+				None,
+				None,
+				future::pending(),
+			)
+			.await
+		});
+
+		// Enough time for a single check, which refills every gap.
+		futures_timer::Delay::new(Duration::from_millis(1500)).await;
+		assert_eq!(indexer_backend.find_gaps().await.unwrap(), vec![]);
+		for block_hash in block_hashes {
+			assert!(indexer_backend.is_block_indexed(block_hash).await);
+		}
+	}
+
+	#[tokio::test]
+	async fn shutdown_executes_the_queued_commands() {
+		let tmp = tempdir().expect("create a temporary directory");
+		let builder = TestClientBuilder::new().add_extra_storage(
+			PALLET_ETHEREUM_SCHEMA.to_vec(),
+			Encode::encode(&EthereumStorageSchema::V3),
+		);
+		let backend = builder.backend();
+		let (client, _) =
+			builder.build_with_native_executor::<frontier_template_runtime::RuntimeApi, _>(None);
+		let mut client = Arc::new(client);
+		let storage_override = Arc::new(SchemaV3StorageOverride::new(client.clone()));
+		let indexer_backend = fc_db::sql::Backend::new(
+			fc_db::sql::BackendConfig::Sqlite(fc_db::sql::SqliteBackendConfig {
+				path: Path::new("sqlite:///")
+					.join(tmp.path())
+					.join("test.db3")
+					.to_str()
+					.unwrap(),
+				create_if_missing: true,
+				cache_size: 204800,
+				thread_count: 4,
+				journal_mode: None,
+				synchronous: None,
+				busy_timeout: None,
+				index_logs: true,
+			}),
+			100,
+			None,
+			storage_override.clone(),
+		)
+		.await
+		.expect("indexer pool to be created");
+		let indexer_backend = Arc::new(indexer_backend);
+
+		let pubsub_notification_sinks: EthereumBlockNotificationSinks<
+			EthereumBlockNotification<OpaqueBlock>,
+		> = Default::default();
+		let notification_stream = client.clone().import_notification_stream();
+		let client_inner = client.clone();
+		let indexer_backend_inner = indexer_backend.clone();
+		let (shutdown_tx, shutdown_rx) = futures::channel::oneshot::channel::<()>();
+		let worker = tokio::task::spawn(async move {
+			crate::sql::SyncWorker::run(
+				client_inner,
+				backend.clone(),
+				indexer_backend_inner,
+				notification_stream,
+				SyncWorkerConfig {
+					read_notification_timeout: Duration::from_secs(10),
+					..Default::default()
+				},
+				SyncStrategy::Parachain,
+				Arc::new(TestSyncOracleNotSyncing {}),
+				Arc::new(pubsub_notification_sinks),
+				None,
+				None,
+				shutdown_rx.map(|_| ()),
+			)
+			.await
+		});
+
+		// Import a chain of 10 blocks while the worker is running.
+		let mut block_hashes: Vec<H256> = vec![];
+		for _ in 0..10 {
+			let chain = client.chain_info();
+			let mut builder = BlockBuilderBuilder::new(&*client)
+				.on_parent_block(chain.best_hash)
+				.with_parent_block_number(chain.best_number)
+				.build()
+				.unwrap();
+			builder
+				.push_deposit_log_digest_item(ethereum_digest())
+				.expect("deposit log");
+			let block = builder.build().unwrap().block;
+			block_hashes.push(block.header.hash());
+			executor::block_on(client.import(BlockOrigin::Own, block)).unwrap();
+		}
+
+		// Stop the worker as soon as the notifications are queued, before they are indexed.
+		futures_timer::Delay::new(Duration::from_millis(100)).await;
+		shutdown_tx.send(()).expect("worker running");
+		tokio::time::timeout(Duration::from_secs(10), worker)
+			.await
+			.expect("the worker must stop on shutdown")
+			.expect("worker task");
+
+		// Every notified block was indexed along with its logs before returning.
+		for block_hash in block_hashes {
+			assert!(indexer_backend.is_block_indexed(block_hash).await);
+		}
+		assert_eq!(indexer_backend.get_first_pending_canon_block().await, None);
+	}
+
+	#[tokio::test]
+	async fn batched_log_indexing_matches_the_serial_one() {
+		let tmp = tempdir().expect("create a temporary directory");
+		let builder = TestClientBuilder::new().add_extra_storage(
+			PALLET_ETHEREUM_SCHEMA.to_vec(),
+			Encode::encode(&EthereumStorageSchema::V3),
+		);
+		let (client, _) =
+			builder.build_with_native_executor::<frontier_template_runtime::RuntimeApi, _>(None);
+		let mut client = Arc::new(client);
+		let storage_override = Arc::new(SchemaV3StorageOverride::new(client.clone()));
+		let mut indexer_backends = vec![];
+		for name in ["serial.db3", "batched.db3"] {
+			let indexer_backend = fc_db::sql::Backend::new(
+				fc_db::sql::BackendConfig::Sqlite(fc_db::sql::SqliteBackendConfig {
+					path: Path::new("sqlite:///")
+						.join(tmp.path())
+						.join(name)
+						.to_str()
+						.unwrap(),
+					create_if_missing: true,
+					cache_size: 204800,
+					thread_count: 4,
+					journal_mode: None,
+					synchronous: None,
+					busy_timeout: None,
+					index_logs: true,
+				}),
+				100,
+				None,
+				storage_override.clone(),
+			)
+			.await
+			.expect("indexer pool to be created");
+			indexer_backends.push(indexer_backend);
+		}
+
+		// Create 200 blocks, 4 receipts each, 2 logs per receipt.
+		let mut block_hashes: Vec<H256> = vec![];
+		for block_number in 1..=200u8 {
+			let chain = client.chain_info();
+			let mut builder = BlockBuilderBuilder::new(&*client)
+				.on_parent_block(chain.best_hash)
+				.with_parent_block_number(chain.best_number)
+				.build()
+				.unwrap();
+			builder
+				.push_deposit_log_digest_item(ethereum_digest())
+				.expect("deposit log");
+			let receipts = (0..4u8)
+				.map(|transaction_index| {
+					ethereum::ReceiptV3::EIP1559(ethereum::EIP1559ReceiptData {
+						status_code: 1u8,
+						used_gas: U256::zero(),
+						logs_bloom: ethereum_types::Bloom::zero(),
+						logs: (0..2u8)
+							.map(|log_index| ethereum::Log {
+								address: H160::repeat_byte(block_number),
+								topics: vec![
+									H256::repeat_byte(transaction_index),
+									H256::repeat_byte(log_index),
+								],
+								data: vec![],
+							})
+							.collect(),
+					})
+				})
+				.collect::<Vec<_>>();
+			builder
+				.push_storage_change(
+					storage_prefix_build(PALLET_ETHEREUM, ETHEREUM_CURRENT_RECEIPTS),
+					Some(Encode::encode(&receipts)),
+				)
+				.unwrap();
+			let block = builder.build().unwrap().block;
+			let block_hash = block.header.hash();
+			executor::block_on(client.import(BlockOrigin::Own, block)).unwrap();
+			for indexer_backend in &indexer_backends {
+				indexer_backend
+					.insert_block_metadata(client.clone(), block_hash)
+					.await
+					.expect("insert block metadata");
+			}
+			block_hashes.push(block_hash);
+		}
+
+		let serial_commits = indexer_backends[0].indexing_commits();
+		for block_hash in &block_hashes {
+			indexer_backends[0]
+				.index_block_logs(*block_hash)
+				.await
+				.expect("index block logs");
+		}
+		let batched_commits = indexer_backends[1].indexing_commits();
+		indexer_backends[1]
+			.index_blocks_logs(&block_hashes)
+			.await
+			.expect("index blocks logs");
+		// The batch is written in a single transaction.
+		assert_eq!(indexer_backends[0].indexing_commits() - serial_commits, 200);
+		assert_eq!(indexer_backends[1].indexing_commits() - batched_commits, 1);
+
+		let mut indexed_logs = vec![];
+		for indexer_backend in &indexer_backends {
+			let logs = sqlx::query(
+				"SELECT b.block_number, transaction_index, log_index, address, topic_1, topic_2
+				FROM logs AS a INNER JOIN blocks AS b ON a.substrate_block_hash = b.substrate_block_hash
+				ORDER BY b.block_number ASC, transaction_index ASC, log_index ASC",
+			)
+			.fetch_all(indexer_backend.pool())
+			.await
+			.expect("test query result")
+			.iter()
+			.map(|row| {
+				(
+					row.get::<i32, _>(0),
+					row.get::<i64, _>(1),
+					row.get::<i64, _>(2),
+					row.get::<Vec<u8>, _>(3),
+					row.get::<Option<Vec<u8>>, _>(4),
+					row.get::<Option<Vec<u8>>, _>(5),
+				)
+			})
+			.collect::<Vec<_>>();
+			indexed_logs.push(logs);
+		}
+		assert_eq!(indexed_logs[0].len(), 200 * 4 * 2);
+		assert_eq!(indexed_logs[0], indexed_logs[1]);
+		assert_eq!(
+			indexer_backends[1].blocks_pending_logs().await.unwrap(),
+			vec![]
+		);
+	}
+
+	#[tokio::test]
+	async fn schema_v1_blocks_are_indexed_with_their_logs() {
+		let tmp = tempdir().expect("create a temporary directory");
+		let builder = TestClientBuilder::new().add_extra_storage(
+			PALLET_ETHEREUM_SCHEMA.to_vec(),
+			Encode::encode(&EthereumStorageSchema::V1),
+		);
+		let (client, _) =
+			builder.build_with_native_executor::<frontier_template_runtime::RuntimeApi, _>(None);
+		let mut client = Arc::new(client);
+		// Dispatches to the override of the schema found in the state of each block.
+		let storage_override = Arc::new(StorageOverrideHandler::<OpaqueBlock, _, _>::new(
+			client.clone(),
+		));
+		let indexer_backend = fc_db::sql::Backend::new(
+			fc_db::sql::BackendConfig::Sqlite(fc_db::sql::SqliteBackendConfig {
+				path: Path::new("sqlite:///")
+					.join(tmp.path())
+					.join("test.db3")
+					.to_str()
+					.unwrap(),
+				create_if_missing: true,
+				cache_size: 204800,
+				thread_count: 4,
+				journal_mode: None,
+				synchronous: None,
+				busy_timeout: None,
+				index_logs: true,
+			}),
+			100,
+			None,
+			storage_override,
+		)
+		.await
+		.expect("indexer pool to be created");
+
+		// A block storing its receipts in the V1 (ReceiptV0) layout.
+		let address = H160::repeat_byte(0x01);
+		let topic = H256::repeat_byte(0x02);
+		let chain = client.chain_info();
+		let mut builder = BlockBuilderBuilder::new(&*client)
+			.on_parent_block(chain.best_hash)
+			.with_parent_block_number(chain.best_number)
+			.build()
+			.unwrap();
+		builder
+			.push_deposit_log_digest_item(ethereum_digest())
+			.expect("deposit log");
+		let receipts = vec![ethereum::ReceiptV0 {
+			state_root: H256::from_low_u64_be(1),
+			used_gas: U256::from(21_000),
+			logs_bloom: ethereum_types::Bloom::zero(),
+			logs: vec![
+				ethereum::Log {
+					address,
+					topics: vec![topic],
+					data: vec![],
+				},
+				ethereum::Log {
+					address,
+					topics: vec![],
+					data: vec![0x01],
+				},
+			],
+		}];
+		builder
+			.push_storage_change(
+				storage_prefix_build(PALLET_ETHEREUM, ETHEREUM_CURRENT_RECEIPTS),
+				Some(Encode::encode(&receipts)),
+			)
+			.unwrap();
+		let block = builder.build().unwrap().block;
+		let block_hash = block.header.hash();
+		executor::block_on(client.import(BlockOrigin::Own, block)).unwrap();
+
+		indexer_backend
+			.insert_block_metadata(client.clone(), block_hash)
+			.await
+			.expect("insert block metadata");
+		indexer_backend
+			.index_block_logs(block_hash)
+			.await
+			.expect("index block logs");
+
+		let schema = sqlx::query(
+			"SELECT ethereum_storage_schema FROM blocks WHERE substrate_block_hash = ?",
+		)
+		.bind(block_hash.as_bytes())
+		.fetch_one(indexer_backend.pool())
+		.await
+		.expect("block indexed")
+		.get::<Vec<u8>, _>(0);
+		assert_eq!(schema, Encode::encode(&EthereumStorageSchema::V1));
+		let logs = sqlx::query(
+			"SELECT address, topic_1, log_index, transaction_index FROM logs
+			WHERE substrate_block_hash = ? ORDER BY log_index ASC",
+		)
+		.bind(block_hash.as_bytes())
+		.fetch_all(indexer_backend.pool())
+		.await
+		.expect("test query result")
+		.iter()
+		.map(|row| {
+			(
+				row.get::<Vec<u8>, _>(0),
+				row.get::<Option<Vec<u8>>, _>(1),
+				row.get::<i64, _>(2),
+				row.get::<i64, _>(3),
+			)
+		})
+		.collect::<Vec<_>>();
+		assert_eq!(
+			logs,
+			vec![
+				(
+					address.as_bytes().to_vec(),
+					Some(topic.as_bytes().to_vec()),
+					0,
+					0
+				),
+				(address.as_bytes().to_vec(), None, 1, 0),
+			]
+		);
+	}
+
+	#[tokio::test]
+	async fn blocks_without_a_schema_are_indexed_with_the_fallback() {
+		let tmp = tempdir().expect("create a temporary directory");
+		// No ethereum storage schema in the state.
+		let (client, _) = TestClientBuilder::new()
+			.build_with_native_executor::<frontier_template_runtime::RuntimeApi, _>(None);
+		let mut client = Arc::new(client);
+		let storage_override = Arc::new(StorageOverrideHandler::<OpaqueBlock, _, _>::new(
+			client.clone(),
+		));
+		let indexer_backend = fc_db::sql::Backend::new(
+			fc_db::sql::BackendConfig::Sqlite(fc_db::sql::SqliteBackendConfig {
+				path: Path::new("sqlite:///")
+					.join(tmp.path())
+					.join("test.db3")
+					.to_str()
+					.unwrap(),
+				create_if_missing: true,
+				cache_size: 204800,
+				thread_count: 4,
+				journal_mode: None,
+				synchronous: None,
+				busy_timeout: None,
+				index_logs: true,
+			}),
+			100,
+			None,
+			storage_override,
+		)
+		.await
+		.expect("indexer pool to be created");
+
+		let chain = client.chain_info();
+		let mut builder = BlockBuilderBuilder::new(&*client)
+			.on_parent_block(chain.best_hash)
+			.with_parent_block_number(chain.best_number)
+			.build()
+			.unwrap();
+		builder
+			.push_deposit_log_digest_item(ethereum_digest())
+			.expect("deposit log");
+		let block = builder.build().unwrap().block;
+		let block_hash = block.header.hash();
+		executor::block_on(client.import(BlockOrigin::Own, block)).unwrap();
+
+		assert_eq!(
+			indexer_backend
+				.insert_block_metadata(client.clone(), block_hash)
+				.await
+				.expect("insert block metadata"),
+			fc_db::sql::BlockIndexing::Indexed
+		);
+		let schema = sqlx::query(
+			"SELECT ethereum_storage_schema FROM blocks WHERE substrate_block_hash = ?",
+		)
+		.bind(block_hash.as_bytes())
+		.fetch_one(indexer_backend.pool())
+		.await
+		.expect("block indexed")
+		.get::<Vec<u8>, _>(0);
+		assert_eq!(schema, Encode::encode(&EthereumStorageSchema::V3));
+		assert!(indexer_backend.is_block_indexed(block_hash).await);
+
+		// Indexing it again writes nothing.
+		assert_eq!(
+			indexer_backend
+				.insert_block_metadata(client.clone(), block_hash)
+				.await
+				.expect("insert block metadata"),
+			fc_db::sql::BlockIndexing::AlreadyIndexed
+		);
+	}
+
+	#[tokio::test]
+	async fn sync_state_reports_the_index_reaching_the_best_block() {
+		let tmp = tempdir().expect("create a temporary directory");
+		let builder = TestClientBuilder::new().add_extra_storage(
+			PALLET_ETHEREUM_SCHEMA.to_vec(),
+			Encode::encode(&EthereumStorageSchema::V3),
+		);
+		let backend = builder.backend();
+		let (client, _) =
+			builder.build_with_native_executor::<frontier_template_runtime::RuntimeApi, _>(None);
+		let mut client = Arc::new(client);
+		let storage_override = Arc::new(SchemaV3StorageOverride::new(client.clone()));
+		let indexer_backend = fc_db::sql::Backend::new(
+			fc_db::sql::BackendConfig::Sqlite(fc_db::sql::SqliteBackendConfig {
+				path: Path::new("sqlite:///")
+					.join(tmp.path())
+					.join("test.db3")
+					.to_str()
+					.unwrap(),
+				create_if_missing: true,
+				cache_size: 204800,
+				thread_count: 4,
+				journal_mode: None,
+				synchronous: None,
+				busy_timeout: None,
+				index_logs: true,
+			}),
+			100,
+			None,
+			storage_override.clone(),
+		)
+		.await
+		.expect("indexer pool to be created");
+
+		// Import 10 blocks before the worker starts, leaving the index behind.
+		for _ in 0..10 {
+			let chain = client.chain_info();
+			let mut builder = BlockBuilderBuilder::new(&*client)
+				.on_parent_block(chain.best_hash)
+				.with_parent_block_number(chain.best_number)
+				.build()
+				.unwrap();
+			builder
+				.push_deposit_log_digest_item(ethereum_digest())
+				.expect("deposit log");
+			let block = builder.build().unwrap().block;
+			executor::block_on(client.import(BlockOrigin::Own, block)).unwrap();
+		}
+
+		let (sync_state_tx, mut sync_state) =
+			tokio::sync::watch::channel(crate::sql::SyncState::default());
+		let pubsub_notification_sinks: EthereumBlockNotificationSinks<
+			EthereumBlockNotification<OpaqueBlock>,
+		> = Default::default();
+		let notification_stream = client.clone().import_notification_stream();
+		let client_inner = client.clone();
+		tokio::task::spawn(async move {
+			crate::sql::SyncWorker::run(
+				client_inner,
+				backend.clone(),
+				Arc::new(indexer_backend),
+				notification_stream,
+				SyncWorkerConfig {
+					read_notification_timeout: Duration::from_millis(100),
+					..Default::default()
+				},
+				SyncStrategy::Parachain,
+				Arc::new(TestSyncOracleNotSyncing {}),
+				Arc::new(pubsub_notification_sinks),
+				None,
+				Some(sync_state_tx),
+				future::pending(),
+			)
+			.await
+		});
+
+		// The genesis block is indexed first, then the leaves catch up with the best block.
+		let state = tokio::time::timeout(
+			Duration::from_secs(10),
+			sync_state.wait_for(|state| state.is_synced),
+		)
+		.await
+		.expect("the index must reach the best block")
+		.map(|state| *state)
+		.expect("worker running");
+		assert_eq!(
+			state,
+			crate::sql::SyncState {
+				indexed_number: 10,
+				best_number: 10,
+				is_synced: true,
+			}
+		);
+	}
+
+	#[tokio::test]
+	async fn restarts_resume_forward_from_the_checkpoint() {
+		let tmp = tempdir().expect("create a temporary directory");
+		let builder = TestClientBuilder::new().add_extra_storage(
+			PALLET_ETHEREUM_SCHEMA.to_vec(),
+			Encode::encode(&EthereumStorageSchema::V3),
+		);
+		let backend = builder.backend();
+		let (client, _) =
+			builder.build_with_native_executor::<frontier_template_runtime::RuntimeApi, _>(None);
+		let mut client = Arc::new(client);
+		let storage_override = Arc::new(SchemaV3StorageOverride::new(client.clone()));
+		let new_indexer_backend = || async {
+			let indexer_backend = fc_db::sql::Backend::new(
+				fc_db::sql::BackendConfig::Sqlite(fc_db::sql::SqliteBackendConfig {
+					path: Path::new("sqlite:///")
+						.join(tmp.path())
+						.join("test.db3")
+						.to_str()
+						.unwrap(),
+					create_if_missing: true,
+					cache_size: 204800,
+					thread_count: 4,
+					journal_mode: None,
+					synchronous: None,
+					busy_timeout: None,
+					index_logs: true,
+				}),
+				100,
+				None,
+				storage_override.clone(),
+			)
+			.await
+			.expect("indexer pool to be created");
+			// Long enough for the rate to count every block committed by the test.
+			Arc::new(indexer_backend.with_throughput_window(Duration::from_secs(3600)))
+		};
+
+		// Create 12 blocks, the last 2 being imported while the node is down.
+		let mut block_hashes: Vec<H256> = vec![];
+		for _ in 1..=12 {
+			let chain = client.chain_info();
+			let mut builder = BlockBuilderBuilder::new(&*client)
+				.on_parent_block(chain.best_hash)
+				.with_parent_block_number(chain.best_number)
+				.build()
+				.unwrap();
+			builder
+				.push_deposit_log_digest_item(ethereum_digest())
+				.expect("deposit log");
+			let block = builder.build().unwrap().block;
+			block_hashes.push(block.header.hash());
+			executor::block_on(client.import(BlockOrigin::Own, block)).unwrap();
+		}
+
+		let mut indexer_backend = new_indexer_backend().await;
+		for (run, commands) in [
+			vec![
+				WorkerCommand::ResumeSync,
+				WorkerCommand::IndexBestBlock(block_hashes[9]),
+			],
+			vec![WorkerCommand::ResumeSync],
+		]
+		.into_iter()
+		.enumerate()
+		{
+			// Restart with the checkpoint left by the first run.
+			if run == 1 {
+				assert_eq!(
+					indexer_backend.checkpoint().await.unwrap(),
+					Some((10, block_hashes[9]))
+				);
+				indexer_backend = new_indexer_backend().await;
+			}
+			let pubsub_notification_sinks: crate::EthereumBlockNotificationSinks<
+				crate::EthereumBlockNotification<OpaqueBlock>,
+			> = Default::default();
+			let tx = crate::sql::SyncWorker::spawn_worker(
+				client.clone(),
+				backend.clone(),
+				indexer_backend.clone(),
+				Arc::new(pubsub_notification_sinks),
+				None,
+				None,
+				None,
+			)
+			.await;
+			for command in commands {
+				tx.send(command).await.expect("send command");
+			}
+			// The worker acknowledges the shutdown once the commands are executed.
+			let (done_tx, done_rx) = futures::channel::oneshot::channel();
+			tx.send(WorkerCommand::Shutdown(done_tx))
+				.await
+				.expect("send command");
+			done_rx.await.expect("worker stopped");
+		}
+
+		// Only the new blocks were committed by the restarted worker.
+		for block_hash in &block_hashes {
+			assert!(indexer_backend.is_block_indexed(*block_hash).await);
+		}
+		assert_eq!(
+			(indexer_backend.throughput().blocks_per_sec * 3600.0).round(),
+			2.0
+		);
+		assert_eq!(
+			indexer_backend.checkpoint().await.unwrap(),
+			Some((12, block_hashes[11]))
+		);
+	}
+
+	#[tokio::test]
+	async fn blocks_below_the_prune_depth_are_pruned() {
+		let tmp = tempdir().expect("create a temporary directory");
+		let builder = TestClientBuilder::new().add_extra_storage(
+			PALLET_ETHEREUM_SCHEMA.to_vec(),
+			Encode::encode(&EthereumStorageSchema::V3),
+		);
+		let backend = builder.backend();
+		let (client, _) =
+			builder.build_with_native_executor::<frontier_template_runtime::RuntimeApi, _>(None);
+		let mut client = Arc::new(client);
+		let storage_override = Arc::new(SchemaV3StorageOverride::new(client.clone()));
+		let indexer_backend = fc_db::sql::Backend::new(
+			fc_db::sql::BackendConfig::Sqlite(fc_db::sql::SqliteBackendConfig {
+				path: Path::new("sqlite:///")
+					.join(tmp.path())
+					.join("test.db3")
+					.to_str()
+					.unwrap(),
+				create_if_missing: true,
+				cache_size: 204800,
+				thread_count: 4,
+				journal_mode: None,
+				synchronous: None,
+				busy_timeout: None,
+				index_logs: true,
+			}),
+			100,
+			None,
+			storage_override.clone(),
+		)
+		.await
+		.expect("indexer pool to be created");
+		let indexer_backend = Arc::new(indexer_backend);
+
+		let pubsub_notification_sinks: EthereumBlockNotificationSinks<
+			EthereumBlockNotification<OpaqueBlock>,
+		> = Default::default();
+		let notification_stream = client.clone().import_notification_stream();
+		let client_inner = client.clone();
+		let indexer_backend_inner = indexer_backend.clone();
+		let substrate_backend = backend.clone();
+		tokio::task::spawn(async move {
+			crate::sql::SyncWorker::run(
+				client_inner,
+				backend.clone(),
+				indexer_backend_inner,
+				notification_stream,
+				SyncWorkerConfig {
+					read_notification_timeout: Duration::from_secs(10),
+					prune_depth: Some(5),
+					..Default::default()
+				},
+				SyncStrategy::Parachain,
+				Arc::new(TestSyncOracleNotSyncing {}),
+				Arc::new(pubsub_notification_sinks),
+				None,
+				None,
+				future::pending(),
+			)
+			.await
+		});
+
+		// Create 20 blocks, 1 log each.
+		let mut block_hashes: Vec<H256> = vec![];
+		for _ in 1..=20 {
+			let chain = client.chain_info();
+			let mut builder = BlockBuilderBuilder::new(&*client)
+				.on_parent_block(chain.best_hash)
+				.with_parent_block_number(chain.best_number)
+				.build()
+				.unwrap();
+			builder
+				.push_deposit_log_digest_item(ethereum_digest())
+				.expect("deposit log");
+			let receipts = Encode::encode(&vec![ethereum::ReceiptV3::EIP1559(
+				ethereum::EIP1559ReceiptData {
+					status_code: 0u8,
+					used_gas: U256::zero(),
+					logs_bloom: ethereum_types::Bloom::zero(),
+					logs: vec![ethereum::Log {
+						address: H160::repeat_byte(0x01),
+						topics: vec![H256::repeat_byte(0x02)],
+						data: vec![],
+					}],
+				},
+			)]);
+			builder
+				.push_storage_change(
+					storage_prefix_build(PALLET_ETHEREUM, ETHEREUM_CURRENT_RECEIPTS),
+					Some(receipts),
+				)
+				.unwrap();
+			let block = builder.build().unwrap().block;
+			block_hashes.push(block.header.hash());
+			executor::block_on(client.import(BlockOrigin::Own, block)).unwrap();
+		}
+
+		// Enough time for the notifications to be indexed
+		futures_timer::Delay::new(Duration::from_millis(1500)).await;
+
+		let log_block_numbers = sqlx::query(
+			"SELECT b.block_number FROM logs AS l
+			INNER JOIN blocks AS b ON b.substrate_block_hash = l.substrate_block_hash
+			ORDER BY b.block_number",
+		)
+		.fetch_all(indexer_backend.pool())
+		.await
+		.expect("test query result")
+		.iter()
+		.map(|row| row.get::<i32, _>(0))
+		.collect::<Vec<_>>();
+		assert_eq!(log_block_numbers, (16..=20).collect::<Vec<_>>());
+		for block_hash in &block_hashes[..15] {
+			assert!(!indexer_backend.is_block_indexed(*block_hash).await);
+		}
+		// Genesis is kept, and the pruned blocks are not backfilled.
+		let genesis_hash = client
+			.hash(sp_runtime::traits::Zero::zero())
+			.unwrap()
+			.expect("genesis hash");
+		assert_eq!(
+			indexer_backend.block_number(genesis_hash).await.unwrap(),
+			Some(0)
+		);
+		assert_eq!(indexer_backend.get_first_missing_canon_block().await, None);
+		assert!(indexer_backend.find_gaps().await.unwrap().is_empty());
+
+		// Nor indexed again by the ancestor descents.
+		index_block_and_ancestors(
+			client.clone(),
+			substrate_backend.clone(),
+			indexer_backend.clone(),
+			block_hashes[10],
+		)
+		.await;
+		index_canonical_block_and_ancestors(
+			client.clone(),
+			substrate_backend,
+			indexer_backend.clone(),
+			block_hashes[14],
+		)
+		.await;
+		for block_hash in &block_hashes[..15] {
+			assert!(!indexer_backend.is_block_indexed(*block_hash).await);
+		}
+	}
+
+	#[test]
+	fn notifications_are_read_ahead_up_to_the_lookahead() {
+		// 100 notifications queued at once, every tenth one being a new best block.
+		let mut notifications = stream::iter((0..100).map(|n| (n, n % 10 == 9)));
+		let mut followed = vec![];
+		while let Some(first) = notifications.next().now_or_never().flatten() {
+			let (best, read) =
+				read_ahead(first, &mut notifications, |(_, is_new_best)| *is_new_best);
+			assert!(read <= NOTIFICATION_LOOKAHEAD);
+			followed.extend(best.map(|(n, _)| n));
+		}
+		// The first read covers #0..=#64, the second one the rest.
+		assert_eq!(followed, vec![59, 99]);
+	}
+
+	#[tokio::test]
+	async fn rapid_notifications_are_read_ahead() {
+		let tmp = tempdir().expect("create a temporary directory");
+		// Initialize storage with schema V3
+		let builder = TestClientBuilder::new().add_extra_storage(
+			PALLET_ETHEREUM_SCHEMA.to_vec(),
+			Encode::encode(&EthereumStorageSchema::V3),
+		);
+		// Backend
+		let backend = builder.backend();
+		// Client
+		let (client, _) =
+			builder.build_with_native_executor::<frontier_template_runtime::RuntimeApi, _>(None);
+		let mut client = Arc::new(client);
+		// Overrides
+		let storage_override = Arc::new(SchemaV3StorageOverride::new(client.clone()));
+		// Indexer backend
+		let indexer_backend = fc_db::sql::Backend::new(
+			fc_db::sql::BackendConfig::Sqlite(fc_db::sql::SqliteBackendConfig {
+				path: Path::new("sqlite:///")
+					.join(tmp.path())
+					.join("test.db3")
+					.to_str()
+					.unwrap(),
+				create_if_missing: true,
+				cache_size: 204800,
+				thread_count: 4,
+				journal_mode: None,
+				synchronous: None,
+				busy_timeout: None,
+				index_logs: true,
+			}),
+			100,
+			None,
+			storage_override.clone(),
+		)
+		.await
+		.expect("indexer pool to be created");
+		// Pool
+		let pool = indexer_backend.pool().clone();
+
+		let test_sync_oracle = TestSyncOracleNotSyncing {};
+		let pubsub_notification_sinks: EthereumBlockNotificationSinks<
+			EthereumBlockNotification<OpaqueBlock>,
+		> = Default::default();
+		let pubsub_notification_sinks = Arc::new(pubsub_notification_sinks);
+
+		let pubsub_notification_sinks_inner = pubsub_notification_sinks.clone();
+
+		// The worker is spawned before the blocks are imported, so their notifications queue
+		// up while it handles the first ones and are read ahead.
+		let (sink, mut notified) =
+			sc_utils::mpsc::tracing_unbounded("test_pubsub_notifications", 1_000);
+		pubsub_notification_sinks.lock().push(sink);
+		let notification_stream = client.clone().import_notification_stream();
+		let client_inner = client.clone();
+		tokio::task::spawn(async move {
+			crate::sql::SyncWorker::run(
+				client_inner,
+				backend.clone(),
+				Arc::new(indexer_backend),
+				notification_stream,
+				SyncWorkerConfig {
+					read_notification_timeout: Duration::from_secs(10),
+					..Default::default()
+				},
+				SyncStrategy::Parachain,
+				Arc::new(test_sync_oracle),
+				pubsub_notification_sinks_inner,
+				None,
+				None,
+				future::pending(),
+			)
+			.await
+		});
+
+		// Import 100 blocks without letting the worker catch up in between.
+		let mut hashes = vec![];
+		for _ in 0..100 {
+			let chain = client.chain_info();
+			let mut builder = BlockBuilderBuilder::new(&*client)
+				.on_parent_block(chain.best_hash)
+				.with_parent_block_number(chain.best_number)
+				.build()
+				.unwrap();
+			builder
+				.push_deposit_log_digest_item(ethereum_digest())
+				.expect("deposit log");
+			let block = builder.build().unwrap().block;
+			hashes.push(block.header.hash());
+			executor::block_on(client.import(BlockOrigin::Own, block)).unwrap();
+		}
+
+		// Every imported block ends up indexed as canon.
+		let canon_hashes = || async {
+			sqlx::query(
+				"SELECT substrate_block_hash FROM blocks
+				WHERE is_canon = 1 AND block_number > 0 ORDER BY block_number",
+			)
+			.fetch_all(&pool)
+			.await
+			.expect("test query result")
+			.iter()
+			.map(|row| H256::from_slice(&row.get::<Vec<u8>, _>(0)[..]))
+			.collect::<Vec<H256>>()
+		};
+		for _ in 0..100 {
+			if canon_hashes().await.len() == hashes.len() {
+				break;
+			}
+			futures_timer::Delay::new(Duration::from_millis(100)).await;
+		}
+		assert_eq!(canon_hashes().await, hashes);
+
+		// Each imported block is notified, in order, though the indexing was collapsed.
+		let mut notified_hashes = vec![];
+		while notified_hashes.len() < hashes.len() {
+			let notification = tokio::time::timeout(Duration::from_secs(10), notified.next())
+				.await
+				.expect("notification in time")
+				.expect("notification");
+			assert!(notification.is_new_best);
+			notified_hashes.push(notification.hash);
+		}
+		assert_eq!(notified_hashes, hashes);
+	}
+
+	/// Records the name of each created span along with the name of its parent.
+	#[derive(Clone, Default)]
+	struct SpanRecorder(Arc<parking_lot::Mutex<Vec<(&'static str, Option<&'static str>)>>>);
+
+	impl<S> tracing_subscriber::Layer<S> for SpanRecorder
+	where
+		S: tracing::Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
+	{
+		fn on_new_span(
+			&self,
+			_attrs: &tracing::span::Attributes<'_>,
+			id: &tracing::span::Id,
+			ctx: tracing_subscriber::layer::Context<'_, S>,
+		) {
+			let span = ctx.span(id).expect("new span is registered");
+			let parent = span.parent().map(|parent| parent.name());
+			self.0.lock().push((span.name(), parent));
+		}
+	}
+
+	#[tokio::test]
+	async fn indexing_spans_nest_the_block_phases() {
+		use tracing_subscriber::layer::SubscriberExt;
+
+		let tmp = tempdir().expect("create a temporary directory");
+		let builder = TestClientBuilder::new().add_extra_storage(
+			PALLET_ETHEREUM_SCHEMA.to_vec(),
+			Encode::encode(&EthereumStorageSchema::V3),
+		);
+		let backend = builder.backend();
+		let (client, _) =
+			builder.build_with_native_executor::<frontier_template_runtime::RuntimeApi, _>(None);
+		let mut client = Arc::new(client);
+		let storage_override = Arc::new(SchemaV3StorageOverride::new(client.clone()));
+		let indexer_backend = fc_db::sql::Backend::new(
+			fc_db::sql::BackendConfig::Sqlite(fc_db::sql::SqliteBackendConfig {
+				path: Path::new("sqlite:///")
+					.join(tmp.path())
+					.join("test.db3")
+					.to_str()
+					.unwrap(),
+				create_if_missing: true,
+				cache_size: 204800,
+				thread_count: 4,
+				journal_mode: None,
+				synchronous: None,
+				busy_timeout: None,
+				index_logs: true,
+			}),
+			100,
+			None,
+			storage_override.clone(),
+		)
+		.await
+		.expect("indexer pool to be created");
+		let indexer_backend = Arc::new(indexer_backend);
+		let genesis_hash = indexer_backend
+			.insert_genesis_block_metadata(client.clone())
+			.await
+			.expect("insert genesis block metadata")
+			.expect("genesis block indexed");
+
+		let mut hashes = vec![];
+		for _ in 0..2 {
+			let chain = client.chain_info();
+			let mut builder = BlockBuilderBuilder::new(&*client)
+				.on_parent_block(chain.best_hash)
+				.with_parent_block_number(chain.best_number)
+				.build()
+				.unwrap();
+			builder
+				.push_deposit_log_digest_item(ethereum_digest())
+				.expect("deposit log");
+			let block = builder.build().unwrap().block;
+			hashes.push(block.header.hash());
+			executor::block_on(client.import(BlockOrigin::Own, block)).unwrap();
+		}
+
+		let recorder = SpanRecorder::default();
+		let _guard =
+			tracing::subscriber::set_default(tracing_subscriber::registry().with(recorder.clone()));
+		index_canonical_block_and_ancestors(
+			client.clone(),
+			backend.clone(),
+			indexer_backend.clone(),
+			hashes[1],
+		)
+		.await;
+		canonicalize_blocks::<_, sc_client_db::Backend<OpaqueBlock>, _>(
+			client.clone(),
+			indexer_backend.clone(),
+			genesis_hash,
+			hashes.clone(),
+			vec![],
+			DEFAULT_MAX_REORG_DEPTH,
+		)
+		.await;
+
+		// Both blocks are inserted within the descent, their logs indexed in a single batch.
+		assert_eq!(
+			*recorder.0.lock(),
+			vec![
+				("index_blocks", None),
+				("insert_block_metadata", Some("index_blocks")),
+				("insert_block_metadata", Some("index_blocks")),
+				("index_logs", Some("index_blocks")),
+				("canonicalize", None),
+			]
+		);
+	}
+
+	#[tokio::test]
+	async fn deep_reorgs_are_canonicalized_in_chunks() {
+		let tmp = tempdir().expect("create a temporary directory");
+		let builder = TestClientBuilder::new().add_extra_storage(
+			PALLET_ETHEREUM_SCHEMA.to_vec(),
+			Encode::encode(&EthereumStorageSchema::V3),
+		);
+		let (client, _) =
+			builder.build_with_native_executor::<frontier_template_runtime::RuntimeApi, _>(None);
+		let mut client = Arc::new(client);
+		let storage_override = Arc::new(SchemaV3StorageOverride::new(client.clone()));
+		let indexer_backend = fc_db::sql::Backend::new(
+			fc_db::sql::BackendConfig::Sqlite(fc_db::sql::SqliteBackendConfig {
+				path: Path::new("sqlite:///")
+					.join(tmp.path())
+					.join("test.db3")
+					.to_str()
+					.unwrap(),
+				create_if_missing: true,
+				cache_size: 204800,
+				thread_count: 4,
+				journal_mode: None,
+				synchronous: None,
+				busy_timeout: None,
+				index_logs: true,
+			}),
+			100,
+			None,
+			storage_override.clone(),
+		)
+		.await
+		.expect("indexer pool to be created");
+		let indexer_backend = Arc::new(indexer_backend);
+		let genesis_hash = indexer_backend
+			.insert_genesis_block_metadata(client.clone())
+			.await
+			.expect("insert genesis block metadata")
+			.expect("genesis block indexed");
+
+		// Two forks of 5 blocks from genesis, the first one being the best chain.
+		let mut forks: Vec<Vec<H256>> = vec![];
+		for _ in 0..2 {
+			let mut parent = (genesis_hash, 0);
+			let mut fork = vec![];
+			for _ in 0..5 {
+				let mut builder = BlockBuilderBuilder::new(&*client)
+					.on_parent_block(parent.0)
+					.with_parent_block_number(parent.1)
+					.build()
+					.unwrap();
+				builder
+					.push_deposit_log_digest_item(ethereum_digest())
+					.expect("deposit log");
+				let block = builder.build().unwrap().block;
+				let block_hash = block.header.hash();
+				executor::block_on(client.import(BlockOrigin::Own, block)).unwrap();
+				indexer_backend
+					.insert_block_metadata(client.clone(), block_hash)
+					.await
+					.expect("insert block metadata");
+				fork.push(block_hash);
+				parent = (block_hash, parent.1 + 1);
+			}
+			forks.push(fork);
+		}
+		let (retracted, enacted) = (forks[0].clone(), forks[1].clone());
+
+		// Every step retracts or enacts at most 2 blocks, all retractions coming first.
+		let steps = canonicalization_steps(&retracted, &enacted, 2);
+		assert_eq!(
+			steps
+				.iter()
+				.map(|(retracted, enacted)| (retracted.len(), enacted.len()))
+				.collect::<Vec<_>>(),
+			vec![(2, 0), (2, 0), (1, 0), (0, 2), (0, 2), (0, 1)]
+		);
+		assert_eq!(canonicalization_steps(&retracted, &enacted, 5).len(), 1);
+
+		// Each step is committed in its own transaction.
+		let commits = indexer_backend.indexing_commits();
+		canonicalize_blocks::<_, sc_client_db::Backend<OpaqueBlock>, _>(
+			client.clone(),
+			indexer_backend.clone(),
+			genesis_hash,
+			enacted.clone(),
+			retracted.clone(),
+			2,
+		)
+		.await;
+		assert_eq!(
+			indexer_backend.indexing_commits() - commits,
+			steps.len() as u64
+		);
+		for hash in &retracted {
+			assert_eq!(indexer_backend.is_canon(*hash).await.unwrap(), Some(false));
+		}
+		for hash in &enacted {
+			assert_eq!(indexer_backend.is_canon(*hash).await.unwrap(), Some(true));
+		}
+	}
+
+	#[tokio::test]
+	async fn grouped_commits_produce_the_same_index() {
+		let tmp = tempdir().expect("create a temporary directory");
+		let builder = TestClientBuilder::new().add_extra_storage(
+			PALLET_ETHEREUM_SCHEMA.to_vec(),
+			Encode::encode(&EthereumStorageSchema::V3),
+		);
+		let backend = builder.backend();
+		let (client, _) =
+			builder.build_with_native_executor::<frontier_template_runtime::RuntimeApi, _>(None);
+		let mut client = Arc::new(client);
+		let storage_override = Arc::new(SchemaV3StorageOverride::new(client.clone()));
+		let mut indexer_backends = vec![];
+		for name in ["single.db3", "grouped.db3"] {
+			let indexer_backend = fc_db::sql::Backend::new(
+				fc_db::sql::BackendConfig::Sqlite(fc_db::sql::SqliteBackendConfig {
+					path: Path::new("sqlite:///")
+						.join(tmp.path())
+						.join(name)
+						.to_str()
+						.unwrap(),
+					create_if_missing: true,
+					cache_size: 204800,
+					thread_count: 4,
+					journal_mode: None,
+					synchronous: None,
+					busy_timeout: None,
+					index_logs: true,
+				}),
+				100,
+				None,
+				storage_override.clone(),
+			)
+			.await
+			.expect("indexer pool to be created");
+			indexer_backends.push(indexer_backend);
+		}
+		indexer_backends[1] = indexer_backends[1].clone().with_commit_interval(
+			Duration::from_secs(60),
+			std::num::NonZeroU32::new(1000).unwrap(),
+		);
+
+		// Create 50 blocks with a log each.
+		let mut block_hashes: Vec<H256> = vec![];
+		for block_number in 1..=50u8 {
+			let chain = client.chain_info();
+			let mut builder = BlockBuilderBuilder::new(&*client)
+				.on_parent_block(chain.best_hash)
+				.with_parent_block_number(chain.best_number)
+				.build()
+				.unwrap();
+			builder
+				.push_deposit_log_digest_item(ethereum_digest())
+				.expect("deposit log");
+			let receipts = vec![ethereum::ReceiptV3::EIP1559(ethereum::EIP1559ReceiptData {
+				status_code: 1u8,
+				used_gas: U256::zero(),
+				logs_bloom: ethereum_types::Bloom::zero(),
+				logs: vec![ethereum::Log {
+					address: H160::repeat_byte(block_number),
+					topics: vec![H256::repeat_byte(block_number)],
+					data: vec![],
+				}],
+			})];
+			builder
+				.push_storage_change(
+					storage_prefix_build(PALLET_ETHEREUM, ETHEREUM_CURRENT_RECEIPTS),
+					Some(Encode::encode(&receipts)),
+				)
+				.unwrap();
+			let block = builder.build().unwrap().block;
+			block_hashes.push(block.header.hash());
+			executor::block_on(client.import(BlockOrigin::Own, block)).unwrap();
+		}
+
+		// Genesis, then each block notified as the new best one in turn.
+		for indexer_backend in &indexer_backends {
+			let pubsub_notification_sinks: crate::EthereumBlockNotificationSinks<
+				crate::EthereumBlockNotification<OpaqueBlock>,
+			> = Default::default();
+			let tx = crate::sql::SyncWorker::spawn_worker(
+				client.clone(),
+				backend.clone(),
+				Arc::new(indexer_backend.clone()),
+				Arc::new(pubsub_notification_sinks),
+				None,
+				None,
+				None,
+				DEFAULT_MAX_REORG_DEPTH,
+			)
+			.await;
+			tx.send(WorkerCommand::ResumeSync)
+				.await
+				.expect("send command");
+			for block_hash in &block_hashes {
+				tx.send(WorkerCommand::IndexBestBlock(*block_hash))
+					.await
+					.expect("send command");
+			}
+			let (done_tx, done_rx) = futures::channel::oneshot::channel();
+			tx.send(WorkerCommand::Shutdown(done_tx))
+				.await
+				.expect("send command");
+			done_rx.await.expect("worker stopped");
+		}
+
+		let single = indexer_backends[0].indexing_commits();
+		let grouped = indexer_backends[1].indexing_commits();
+		assert!(single >= 50);
+		assert!(grouped * 10 <= single);
+
+		let mut contents = vec![];
+		for indexer_backend in &indexer_backends {
+			let blocks = sqlx::query(
+				"SELECT b.substrate_block_hash, b.block_number, b.is_canon, s.metadata_done,
+					s.logs_done
+				FROM blocks AS b INNER JOIN sync_status AS s
+				ON s.substrate_block_hash = b.substrate_block_hash
+				ORDER BY b.block_number ASC",
+			)
+			.fetch_all(indexer_backend.pool())
+			.await
+			.expect("test query result")
+			.iter()
+			.map(|row| {
+				(
+					row.get::<Vec<u8>, _>(0),
+					row.get::<i32, _>(1),
+					row.get::<i32, _>(2),
+					row.get::<i32, _>(3),
+					row.get::<i32, _>(4),
+				)
+			})
+			.collect::<Vec<_>>();
+			let logs = sqlx::query(
+				"SELECT b.block_number, address, topic_1
+				FROM logs AS a INNER JOIN blocks AS b ON a.substrate_block_hash = b.substrate_block_hash
+				ORDER BY b.block_number ASC, log_index ASC",
+			)
+			.fetch_all(indexer_backend.pool())
+			.await
+			.expect("test query result")
+			.iter()
+			.map(|row| {
+				(
+					row.get::<i32, _>(0),
+					row.get::<Vec<u8>, _>(1),
+					row.get::<Option<Vec<u8>>, _>(2),
+				)
+			})
+			.collect::<Vec<_>>();
+			let checkpoint = indexer_backend.checkpoint().await.expect("checkpoint");
+			contents.push((blocks, logs, checkpoint));
+		}
+		// The 50 blocks and genesis.
+		assert_eq!(contents[0].0.len(), 51);
+		assert_eq!(contents[0].1.len(), 50);
+		assert_eq!(contents[0].2, Some((50, block_hashes[49])));
+		assert_eq!(contents[0], contents[1]);
+	}
+
+	#[tokio::test]
+	async fn failed_logs_are_indexed_again() {
+		let tmp = tempdir().expect("create a temporary directory");
+		let builder = TestClientBuilder::new().add_extra_storage(
+			PALLET_ETHEREUM_SCHEMA.to_vec(),
+			Encode::encode(&EthereumStorageSchema::V3),
+		);
+		let backend = builder.backend();
+		let (client, _) =
+			builder.build_with_native_executor::<frontier_template_runtime::RuntimeApi, _>(None);
+		let mut client = Arc::new(client);
+		let storage_override = Arc::new(SchemaV3StorageOverride::new(client.clone()));
+		let indexer_backend = fc_db::sql::Backend::new(
+			fc_db::sql::BackendConfig::Sqlite(fc_db::sql::SqliteBackendConfig {
+				path: Path::new("sqlite:///")
+					.join(tmp.path())
+					.join("test.db3")
+					.to_str()
+					.unwrap(),
+				create_if_missing: true,
+				cache_size: 204800,
+				thread_count: 4,
+				journal_mode: None,
+				synchronous: None,
+				busy_timeout: None,
+				index_logs: true,
+			}),
+			100,
+			None,
+			storage_override.clone(),
+		)
+		.await
+		.expect("indexer pool to be created");
+		let indexer_backend = Arc::new(indexer_backend);
+		let pool = indexer_backend.pool().clone();
+
+		// Create 2 blocks, the second one with a log.
+		let mut block_hashes: Vec<H256> = vec![];
+		for number in 1..=2 {
+			let chain = client.chain_info();
+			let mut builder = BlockBuilderBuilder::new(&*client)
+				.on_parent_block(chain.best_hash)
+				.with_parent_block_number(chain.best_number)
+				.build()
+				.unwrap();
+			builder
+				.push_deposit_log_digest_item(ethereum_digest())
+				.expect("deposit log");
+			if number == 2 {
+				let receipts = Encode::encode(&vec![ethereum::ReceiptV3::EIP1559(
+					ethereum::EIP1559ReceiptData {
+						status_code: 0u8,
+						used_gas: U256::zero(),
+						logs_bloom: ethereum_types::Bloom::zero(),
+						logs: vec![ethereum::Log {
+							address: H160::repeat_byte(0x01),
+							topics: vec![],
+							data: vec![],
+						}],
+					},
+				)]);
+				builder
+					.push_storage_change(
+						storage_prefix_build(PALLET_ETHEREUM, ETHEREUM_CURRENT_RECEIPTS),
+						Some(receipts),
+					)
+					.unwrap();
+			}
+			let block = builder.build().unwrap().block;
+			block_hashes.push(block.header.hash());
+			executor::block_on(client.import(BlockOrigin::Own, block)).unwrap();
+		}
+
+		let pubsub_notification_sinks: crate::EthereumBlockNotificationSinks<
+			crate::EthereumBlockNotification<OpaqueBlock>,
+		> = Default::default();
+		let tx = crate::sql::SyncWorker::spawn_worker(
+			client.clone(),
+			backend.clone(),
+			indexer_backend.clone(),
+			Arc::new(pubsub_notification_sinks),
+			None,
+			None,
+			None,
+			DEFAULT_MAX_REORG_DEPTH,
+		)
+		.await;
+		tx.send(WorkerCommand::IndexBestBlock(block_hashes[0]))
+			.await
+			.expect("send command");
+		futures_timer::Delay::new(Duration::from_millis(500)).await;
+		let checkpoint = Some((1, block_hashes[0]));
+		assert_eq!(indexer_backend.checkpoint().await.unwrap(), checkpoint);
+
+		// The logs of the next block fail to be inserted.
+		sqlx::query(
+			"CREATE TRIGGER reject_logs BEFORE INSERT ON logs
+			BEGIN SELECT RAISE(ABORT, 'injected failure'); END;",
+		)
+		.execute(&pool)
+		.await
+		.expect("create trigger");
+		tx.send(WorkerCommand::IndexBestBlock(block_hashes[1]))
+			.await
+			.expect("send command");
+		futures_timer::Delay::new(Duration::from_millis(500)).await;
+
+		// The block is indexed without being marked done, nor moving the checkpoint.
+		assert!(indexer_backend.is_block_indexed(block_hashes[1]).await);
+		assert_eq!(
+			indexer_backend.blocks_pending_logs().await.unwrap(),
+			vec![block_hashes[1]]
+		);
+		assert_eq!(indexer_backend.checkpoint().await.unwrap(), checkpoint);
+
+		// The next check indexes its logs again.
+		sqlx::query("DROP TRIGGER reject_logs")
+			.execute(&pool)
+			.await
+			.expect("drop trigger");
+		tx.send(WorkerCommand::CheckIndexedBlocks)
+			.await
+			.expect("send command");
+		futures_timer::Delay::new(Duration::from_millis(500)).await;
+
+		assert!(indexer_backend
+			.blocks_pending_logs()
+			.await
+			.unwrap()
+			.is_empty());
+		let logs = sqlx::query("SELECT substrate_block_hash FROM logs")
+			.fetch_all(&pool)
+			.await
+			.expect("test query result")
+			.iter()
+			.map(|row| H256::from_slice(&row.get::<Vec<u8>, _>(0)[..]))
+			.collect::<Vec<H256>>();
+		assert_eq!(logs, vec![block_hashes[1]]);
+	}
+
+	#[tokio::test]
+	async fn initial_delay_postpones_indexing() {
+		let tmp = tempdir().expect("create a temporary directory");
+		let builder = TestClientBuilder::new().add_extra_storage(
+			PALLET_ETHEREUM_SCHEMA.to_vec(),
+			Encode::encode(&EthereumStorageSchema::V3),
+		);
+		let backend = builder.backend();
+		let (client, _) =
+			builder.build_with_native_executor::<frontier_template_runtime::RuntimeApi, _>(None);
+		let mut client = Arc::new(client);
+		let storage_override = Arc::new(SchemaV3StorageOverride::new(client.clone()));
+		let indexer_backend = fc_db::sql::Backend::new(
+			fc_db::sql::BackendConfig::Sqlite(fc_db::sql::SqliteBackendConfig {
+				path: Path::new("sqlite:///")
+					.join(tmp.path())
+					.join("test.db3")
+					.to_str()
+					.unwrap(),
+				create_if_missing: true,
+				cache_size: 204800,
+				thread_count: 4,
+				journal_mode: None,
+				synchronous: None,
+				busy_timeout: None,
+				index_logs: true,
+			}),
+			100,
+			None,
+			storage_override.clone(),
+		)
+		.await
+		.expect("indexer pool to be created");
+		let pool = indexer_backend.pool().clone();
+
+		// Create 3 blocks.
+		for _ in 1..=3 {
+			let chain = client.chain_info();
+			let mut builder = BlockBuilderBuilder::new(&*client)
+				.on_parent_block(chain.best_hash)
+				.with_parent_block_number(chain.best_number)
+				.build()
+				.unwrap();
+			builder
+				.push_deposit_log_digest_item(ethereum_digest())
+				.expect("deposit log");
+			let block = builder.build().unwrap().block;
+			executor::block_on(client.import(BlockOrigin::Own, block)).unwrap();
+		}
+
+		let pubsub_notification_sinks: EthereumBlockNotificationSinks<
+			EthereumBlockNotification<OpaqueBlock>,
+		> = Default::default();
+		tokio::task::spawn(async move {
+			crate::sql::SyncWorker::run(
+				client.clone(),
+				backend.clone(),
+				Arc::new(indexer_backend),
+				client.clone().import_notification_stream(),
+				SyncWorkerConfig {
+					read_notification_timeout: Duration::from_millis(100),
+					initial_delay: Duration::from_millis(500),
+					..Default::default()
+				},
+				SyncStrategy::Parachain,
+				Arc::new(TestSyncOracleNotSyncing {}),
+				Arc::new(pubsub_notification_sinks),
+				None,
+				None,
+				future::pending(),
+			)
+			.await
+		});
+
+		let indexed_blocks = || {
+			let pool = &pool;
+			async move {
+				sqlx::query("SELECT COUNT(*) FROM blocks")
+					.fetch_one(pool)
+					.await
+					.expect("test query result")
+					.get::<i64, _>(0)
+			}
+		};
+		// Nothing is indexed before the delay elapses, not even the genesis block.
+		futures_timer::Delay::new(Duration::from_millis(300)).await;
+		assert_eq!(indexed_blocks().await, 0);
+
+		// The 3 blocks and genesis.
+		futures_timer::Delay::new(Duration::from_millis(1200)).await;
+		assert_eq!(indexed_blocks().await, 4);
+	}
 }
@@ -0,0 +1,401 @@
+// This file is part of Frontier.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! WebSocket feed of the blocks indexed by the SQL worker, e.g. for live dashboards.
+//!
+//! The feed subscribes to the worker's block notifications and pushes a JSON message
+//! per new best block to every connected client. Blocks previously pushed that are no
+//! longer canon are pushed again with `is_reorg` set.
+
+use std::{collections::VecDeque, sync::Arc, time::Duration};
+
+use futures::{SinkExt, StreamExt};
+use serde::Serialize;
+use tokio::{net::TcpListener, sync::broadcast};
+use tokio_tungstenite::tungstenite::Message;
+// Substrate
+use sc_utils::mpsc::TracingUnboundedReceiver;
+use sp_core::H256;
+use sp_runtime::traits::Block as BlockT;
+
+use crate::{EthereumBlockNotification, EthereumBlockNotificationSinks};
+
+/// Number of recently pushed blocks checked for retraction on every new best block.
+const MAX_TRACKED_BLOCKS: usize = 32;
+
+/// Number of messages buffered per client before it starts lagging.
+const FEED_CHANNEL_CAPACITY: usize = 1024;
+
+/// Initial and maximum delay before accepting clients again after a failed `accept`.
+const MIN_ACCEPT_BACKOFF: Duration = Duration::from_millis(10);
+const MAX_ACCEPT_BACKOFF: Duration = Duration::from_secs(1);
+
+/// A message pushed to the feed clients.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct FeedMessage {
+	pub block_number: u32,
+	pub block_hash: H256,
+	pub log_count: u32,
+	/// Whether the block was retracted by a re-org.
+	pub is_reorg: bool,
+}
+
+/// Spawns the feed, serving WebSocket clients on `listener`.
+pub fn spawn_ws_feed<Block>(
+	listener: TcpListener,
+	indexer_backend: Arc<fc_db::sql::Backend<Block>>,
+	pubsub_notification_sinks: Arc<
+		EthereumBlockNotificationSinks<EthereumBlockNotification<Block>>,
+	>,
+) where
+	Block: BlockT<Hash = H256>,
+{
+	let (feed_tx, _) = broadcast::channel(FEED_CHANNEL_CAPACITY);
+	// Subscribe before spawning so no notification is missed.
+	let notifications = subscribe(&pubsub_notification_sinks);
+	tokio::task::spawn(serve(listener, feed_tx.clone()));
+	tokio::task::spawn(announce(
+		indexer_backend,
+		pubsub_notification_sinks,
+		notifications,
+		feed_tx,
+	));
+}
+
+fn subscribe<Block: BlockT>(
+	sinks: &EthereumBlockNotificationSinks<EthereumBlockNotification<Block>>,
+) -> TracingUnboundedReceiver<EthereumBlockNotification<Block>> {
+	let (sink, stream) = sc_utils::mpsc::tracing_unbounded("ws_feed_notification_stream", 100_000);
+	sinks.lock().push(sink);
+	stream
+}
+
+/// Accepts the WebSocket clients and forwards them the feed messages.
+async fn serve(listener: TcpListener, feed_tx: broadcast::Sender<FeedMessage>) {
+	let mut backoff = MIN_ACCEPT_BACKOFF;
+	loop {
+		let (stream, peer) = match listener.accept().await {
+			Ok(connection) => {
+				backoff = MIN_ACCEPT_BACKOFF;
+				connection
+			}
+			Err(err) => {
+				// Errors such as running out of file descriptors persist for a while, back
+				// off instead of spinning on them.
+				log::warn!(
+					target: "frontier-sql",
+					"WebSocket feed failed accepting a client, retrying in {backoff:?}: {err}",
+				);
+				tokio::time::sleep(backoff).await;
+				backoff = (backoff * 2).min(MAX_ACCEPT_BACKOFF);
+				continue;
+			}
+		};
+		let mut feed_rx = feed_tx.subscribe();
+		tokio::task::spawn(async move {
+			let mut ws = match tokio_tungstenite::accept_async(stream).await {
+				Ok(ws) => ws,
+				Err(err) => {
+					log::debug!(target: "frontier-sql", "WebSocket handshake with {peer} failed: {err}");
+					return;
+				}
+			};
+			log::debug!(target: "frontier-sql", "📡 WebSocket feed client connected {peer}");
+			loop {
+				match feed_rx.recv().await {
+					Ok(message) => {
+						let Ok(text) = serde_json::to_string(&message) else {
+							continue;
+						};
+						if ws.send(Message::Text(text)).await.is_err() {
+							break;
+						}
+					}
+					Err(broadcast::error::RecvError::Lagged(skipped)) => {
+						log::debug!(
+							target: "frontier-sql",
+							"WebSocket feed client {peer} lagging, skipped {skipped} messages",
+						);
+					}
+					Err(broadcast::error::RecvError::Closed) => break,
+				}
+			}
+			log::debug!(target: "frontier-sql", "📡 WebSocket feed client disconnected {peer}");
+		});
+	}
+}
+
+/// Turns the worker notifications into feed messages.
+async fn announce<Block>(
+	indexer_backend: Arc<fc_db::sql::Backend<Block>>,
+	pubsub_notification_sinks: Arc<
+		EthereumBlockNotificationSinks<EthereumBlockNotification<Block>>,
+	>,
+	mut notifications: TracingUnboundedReceiver<EthereumBlockNotification<Block>>,
+	feed_tx: broadcast::Sender<FeedMessage>,
+) where
+	Block: BlockT<Hash = H256>,
+{
+	let mut tracked: VecDeque<FeedMessage> = VecDeque::new();
+	loop {
+		let Some(notification) = notifications.next().await else {
+			// The worker drops the sinks while major syncing, subscribe again.
+			futures_timer::Delay::new(Duration::from_secs(1)).await;
+			notifications = subscribe(&pubsub_notification_sinks);
+			continue;
+		};
+		if !notification.is_new_best {
+			continue;
+		}
+
+		// The worker canonicalizes before notifying, so any tracked block that is
+		// no longer canon was retracted.
+		let mut retained = VecDeque::with_capacity(tracked.len());
+		for message in tracked.drain(..) {
+			let status = indexer_backend
+				.block_indexed_and_canon_status(message.block_hash)
+				.await;
+			if status.canon {
+				retained.push_back(message);
+			} else {
+				let _ = feed_tx.send(FeedMessage {
+					is_reorg: true,
+					..message
+				});
+			}
+		}
+		tracked = retained;
+
+		let hash = notification.hash;
+		let block_number = match indexer_backend.block_number(hash).await {
+			Ok(Some(block_number)) => block_number,
+			_ => {
+				log::debug!(target: "frontier-sql", "WebSocket feed skipping unindexed block {hash:?}");
+				continue;
+			}
+		};
+		let log_count = indexer_backend.log_count(hash).await.unwrap_or_default();
+		let message = FeedMessage {
+			block_number,
+			block_hash: hash,
+			log_count,
+			is_reorg: false,
+		};
+		let _ = feed_tx.send(message.clone());
+		tracked.push_back(message);
+		if tracked.len() > MAX_TRACKED_BLOCKS {
+			tracked.pop_front();
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	use std::path::Path;
+
+	use futures::executor;
+	use scale_codec::Encode;
+	use tempfile::tempdir;
+	// Substrate
+	use sc_block_builder::BlockBuilderBuilder;
+	use sc_client_api::HeaderBackend;
+	use sp_consensus::BlockOrigin;
+	use sp_core::{H160, U256};
+	use sp_runtime::{
+		generic::{DigestItem, Header},
+		traits::{BlakeTwo256, Header as HeaderT},
+	};
+	use substrate_test_runtime_client::{
+		prelude::*, DefaultTestClientBuilderExt, TestClientBuilder, TestClientBuilderExt,
+	};
+	// Frontier
+	use fc_storage::SchemaV3StorageOverride;
+	use fp_storage::{EthereumStorageSchema, PALLET_ETHEREUM_SCHEMA};
+
+	type OpaqueBlock = sp_runtime::generic::Block<
+		Header<u64, BlakeTwo256>,
+		substrate_test_runtime_client::runtime::Extrinsic,
+	>;
+
+	fn ethereum_digest() -> DigestItem {
+		let partial_header = ethereum::PartialHeader {
+			parent_hash: H256::random(),
+			beneficiary: H160::default(),
+			state_root: H256::default(),
+			receipts_root: H256::default(),
+			logs_bloom: ethereum_types::Bloom::default(),
+			difficulty: U256::zero(),
+			number: U256::zero(),
+			gas_limit: U256::zero(),
+			gas_used: U256::zero(),
+			timestamp: 0u64,
+			extra_data: Vec::new(),
+			mix_hash: H256::default(),
+			nonce: ethereum_types::H64::default(),
+		};
+		let ethereum_transactions: Vec<ethereum::TransactionV2> = vec![];
+		let ethereum_block = ethereum::Block::new(partial_header, ethereum_transactions, vec![]);
+		DigestItem::Consensus(
+			fp_consensus::FRONTIER_ENGINE_ID,
+			fp_consensus::PostLog::Hashes(fp_consensus::Hashes::from_block(ethereum_block))
+				.encode(),
+		)
+	}
+
+	async fn next_message(
+		ws: &mut tokio_tungstenite::WebSocketStream<
+			tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>,
+		>,
+	) -> serde_json::Value {
+		let message = tokio::time::timeout(Duration::from_secs(5), ws.next())
+			.await
+			.expect("message before timeout")
+			.expect("open stream")
+			.expect("valid message");
+		serde_json::from_str(message.to_text().expect("text message")).expect("json message")
+	}
+
+	#[tokio::test]
+	async fn feed_pushes_indexed_and_retracted_blocks() {
+		let tmp = tempdir().expect("create a temporary directory");
+		let builder = TestClientBuilder::new().add_extra_storage(
+			PALLET_ETHEREUM_SCHEMA.to_vec(),
+			Encode::encode(&EthereumStorageSchema::V3),
+		);
+		let (client, _) =
+			builder.build_with_native_executor::<frontier_template_runtime::RuntimeApi, _>(None);
+		let mut client = Arc::new(client);
+		let storage_override = Arc::new(SchemaV3StorageOverride::new(client.clone()));
+		let indexer_backend = fc_db::sql::Backend::new(
+			fc_db::sql::BackendConfig::Sqlite(fc_db::sql::SqliteBackendConfig {
+				path: Path::new("sqlite:///")
+					.join(tmp.path())
+					.join("test.db3")
+					.to_str()
+					.unwrap(),
+				create_if_missing: true,
+				cache_size: 204800,
+				thread_count: 4,
+				journal_mode: None,
+				synchronous: None,
+				busy_timeout: None,
+				index_logs: true,
+			}),
+			100,
+			None,
+			storage_override.clone(),
+		)
+		.await
+		.expect("indexer pool to be created");
+		let indexer_backend = Arc::new(indexer_backend);
+
+		// Import and index two sibling blocks on top of genesis.
+		let genesis_hash = client
+			.hash(sp_runtime::traits::Zero::zero())
+			.unwrap()
+			.expect("genesis hash");
+		let mut sibling_hashes: Vec<H256> = vec![];
+		for _ in 0..2 {
+			let mut builder = BlockBuilderBuilder::new(&*client)
+				.on_parent_block(genesis_hash)
+				.fetch_parent_block_number(&*client)
+				.unwrap()
+				.build()
+				.unwrap();
+			builder
+				.push_deposit_log_digest_item(ethereum_digest())
+				.expect("deposit log");
+			let block = builder.build().unwrap().block;
+			let hash = block.header.hash();
+			executor::block_on(client.import(BlockOrigin::Own, block)).unwrap();
+			indexer_backend
+				.insert_block_metadata(client.clone(), hash)
+				.await
+				.expect("insert block metadata");
+			indexer_backend
+				.index_block_logs(hash)
+				.await
+				.expect("index block logs");
+			sibling_hashes.push(hash);
+		}
+		let (retracted_hash, enacted_hash) = (sibling_hashes[0], sibling_hashes[1]);
+
+		let pubsub_notification_sinks: Arc<
+			EthereumBlockNotificationSinks<EthereumBlockNotification<OpaqueBlock>>,
+		> = Default::default();
+		let listener = TcpListener::bind("127.0.0.1:0")
+			.await
+			.expect("bind listener");
+		let addr = listener.local_addr().expect("local address");
+		spawn_ws_feed(
+			listener,
+			indexer_backend.clone(),
+			pubsub_notification_sinks.clone(),
+		);
+		let (mut ws, _) = tokio_tungstenite::connect_async(format!("ws://{addr}"))
+			.await
+			.expect("connect to feed");
+		let notify = |hash: H256| {
+			for sink in pubsub_notification_sinks.lock().iter() {
+				let _ = sink.unbounded_send(EthereumBlockNotification {
+					is_new_best: true,
+					hash,
+				});
+			}
+		};
+
+		// A new best block is pushed.
+		notify(retracted_hash);
+		assert_eq!(
+			next_message(&mut ws).await,
+			serde_json::json!({
+				"block_number": 1,
+				"block_hash": retracted_hash,
+				"log_count": 0,
+				"is_reorg": false,
+			})
+		);
+
+		// A re-org pushes the retraction before the new best block.
+		indexer_backend
+			.canonicalize(&[retracted_hash], &[enacted_hash])
+			.await
+			.expect("canonicalize");
+		notify(enacted_hash);
+		assert_eq!(
+			next_message(&mut ws).await,
+			serde_json::json!({
+				"block_number": 1,
+				"block_hash": retracted_hash,
+				"log_count": 0,
+				"is_reorg": true,
+			})
+		);
+		assert_eq!(
+			next_message(&mut ws).await,
+			serde_json::json!({
+				"block_number": 1,
+				"block_hash": enacted_hash,
+				"log_count": 0,
+				"is_reorg": false,
+			})
+		);
+	}
+}
@@ -24,6 +24,7 @@ pub mod types;
 mod debug;
 mod eth;
 mod eth_pubsub;
+mod frontier;
 mod net;
 #[cfg(feature = "txpool")]
 mod txpool;
@@ -35,6 +36,7 @@ pub use self::{
 	debug::DebugApiServer,
 	eth::{EthApiServer, EthFilterApiServer},
 	eth_pubsub::EthPubSubApiServer,
+	frontier::FrontierApiServer,
 	net::NetApiServer,
 	web3::Web3ApiServer,
 };
@@ -57,8 +57,8 @@ pub use self::{
 	log::Log,
 	receipt::Receipt,
 	sync::{
-		ChainStatus, EthProtocolInfo, PeerCount, PeerInfo, PeerNetworkInfo, PeerProtocolsInfo,
-		Peers, PipProtocolInfo, SyncInfo, SyncStatus, TransactionStats,
+		ChainStatus, EthProtocolInfo, FrontierSyncStatus, PeerCount, PeerInfo, PeerNetworkInfo,
+		PeerProtocolsInfo, Peers, PipProtocolInfo, SyncInfo, SyncStatus, TransactionStats,
 	},
 	transaction::{LocalTransactionStatus, RichRawTransaction, Transaction},
 	transaction_request::{TransactionMessage, TransactionRequest},
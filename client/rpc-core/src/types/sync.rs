@@ -134,6 +134,23 @@ impl Serialize for SyncStatus {
 	}
 }
 
+/// Sync status of the Frontier mapping backend, tagged with the backend type.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[serde(tag = "backend", rename_all = "snake_case")]
+pub enum FrontierSyncStatus {
+	/// The key-value backend, which does not report its progress.
+	KeyValue,
+	/// The SQL backend.
+	Sql {
+		/// Number of the highest indexed canonical block.
+		indexed_block: u32,
+		/// Number of the best block of the client.
+		best_block: u32,
+		/// Whether the best block is indexed.
+		is_synced: bool,
+	},
+}
+
 /// Propagation statistics for pending transaction.
 #[derive(Debug, Default, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -151,3 +168,25 @@ pub struct ChainStatus {
 	/// Describes the gap in the blockchain, if there is one: (first, last)
 	pub block_gap: Option<(U256, U256)>,
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn frontier_sync_status_serialization() {
+		let sql = FrontierSyncStatus::Sql {
+			indexed_block: 9,
+			best_block: 10,
+			is_synced: false,
+		};
+		assert_eq!(
+			serde_json::to_string(&sql).unwrap(),
+			r#"{"backend":"sql","indexed_block":9,"best_block":10,"is_synced":false}"#
+		);
+		assert_eq!(
+			serde_json::to_string(&FrontierSyncStatus::KeyValue).unwrap(),
+			r#"{"backend":"key_value"}"#
+		);
+	}
+}
@@ -213,7 +213,7 @@ where
 
 					error_on_execution_failure(&info.exit_reason, &info.value)?;
 					Ok(Bytes(info.value))
-				} else if api_version == 4 || api_version == 5 {
+				} else if api_version >= 4 {
 					// Post-london + access list support
 					let encoded_params = Encode::encode(&(
 						&from.unwrap_or_default(),
@@ -267,7 +267,7 @@ where
 
 						error_on_execution_failure(&info.exit_reason, &info.value)?;
 						info.value
-					} else if api_version == 5 {
+					} else {
 						let info = self
 							.client
 							.call_api_at(params)
@@ -286,8 +286,6 @@ where
 
 						error_on_execution_failure(&info.exit_reason, &info.value)?;
 						info.value
-					} else {
-						unreachable!("invalid version");
 					};
 
 					Ok(Bytes(value))
@@ -371,7 +369,7 @@ where
 						.account_code_at(substrate_hash, info.value)
 						.map_err(|err| internal_err(format!("runtime error: {err}")))?;
 					Ok(Bytes(code))
-				} else if api_version == 5 {
+				} else if api_version >= 5 {
 					// Post-london + access list support
 					let access_list = access_list.unwrap_or_default();
 					let info = api
@@ -43,6 +43,9 @@ use fp_rpc::{EthereumRuntimeRPCApi, TransactionStatus};
 
 use crate::{cache::EthBlockDataCacheTask, frontier_backend_client, internal_err};
 
+/// The EIP-1474 code of a request exceeding a limit of the node.
+const LIMIT_EXCEEDED_CODE: i32 = -32005;
+
 pub struct EthFilter<B: BlockT, C, BE, A: ChainApi> {
 	client: Arc<C>,
 	backend: Arc<dyn fc_api::Backend<B>>,
@@ -585,89 +588,87 @@ where
 
 	let time_prepare = timer_prepare.elapsed().as_millis();
 	let timer_fetch = Instant::now();
-	if let Ok(logs) = backend
+	let logs = backend
 		.filter_logs(
 			UniqueSaturatedInto::<u64>::unique_saturated_into(from),
 			UniqueSaturatedInto::<u64>::unique_saturated_into(to),
 			addresses,
 			topics,
+			max_past_logs.saturating_add(1),
 		)
 		.await
-	{
-		let time_fetch = timer_fetch.elapsed().as_millis();
-		let timer_post = Instant::now();
+		.map_err(internal_err)?;
+	let time_fetch = timer_fetch.elapsed().as_millis();
+	// Rejected before reading the block data of any log.
+	if logs.len() as u32 > max_past_logs {
+		return Err(too_many_logs_err(max_past_logs));
+	}
+	let timer_post = Instant::now();
 
-		let mut statuses_cache: BTreeMap<B::Hash, Option<Vec<TransactionStatus>>> = BTreeMap::new();
+	let mut statuses_cache: BTreeMap<B::Hash, Option<Vec<TransactionStatus>>> = BTreeMap::new();
 
-		for log in logs.iter() {
-			let substrate_hash = log.substrate_block_hash;
+	for log in logs.iter() {
+		let substrate_hash = log.substrate_block_hash;
 
-			let ethereum_block_hash = log.ethereum_block_hash;
-			let block_number = log.block_number;
-			let db_transaction_index = log.transaction_index;
-			let db_log_index = log.log_index;
+		let ethereum_block_hash = log.ethereum_block_hash;
+		let block_number = log.block_number;
+		let db_transaction_index = log.transaction_index;
+		let db_log_index = log.log_index;
 
-			let statuses = if let Some(statuses) = statuses_cache.get(&log.substrate_block_hash) {
-				statuses.clone()
-			} else {
-				let statuses = block_data_cache
-					.current_transaction_statuses(substrate_hash)
-					.await;
-				statuses_cache.insert(log.substrate_block_hash, statuses.clone());
-				statuses
-			};
-			if let Some(statuses) = statuses {
-				let mut block_log_index: u32 = 0;
-				for status in statuses.iter() {
-					let mut transaction_log_index: u32 = 0;
-					let transaction_hash = status.transaction_hash;
-					let transaction_index = status.transaction_index;
-					for ethereum_log in &status.logs {
-						if transaction_index == db_transaction_index
-							&& transaction_log_index == db_log_index
-						{
-							ret.push(Log {
-								address: ethereum_log.address,
-								topics: ethereum_log.topics.clone(),
-								data: Bytes(ethereum_log.data.clone()),
-								block_hash: Some(ethereum_block_hash),
-								block_number: Some(U256::from(block_number)),
-								transaction_hash: Some(transaction_hash),
-								transaction_index: Some(U256::from(transaction_index)),
-								log_index: Some(U256::from(block_log_index)),
-								transaction_log_index: Some(U256::from(transaction_log_index)),
-								removed: false,
-							});
-						}
-						transaction_log_index += 1;
-						block_log_index += 1;
+		let statuses = if let Some(statuses) = statuses_cache.get(&log.substrate_block_hash) {
+			statuses.clone()
+		} else {
+			let statuses = block_data_cache
+				.current_transaction_statuses(substrate_hash)
+				.await;
+			statuses_cache.insert(log.substrate_block_hash, statuses.clone());
+			statuses
+		};
+		if let Some(statuses) = statuses {
+			let mut block_log_index: u32 = 0;
+			for status in statuses.iter() {
+				let mut transaction_log_index: u32 = 0;
+				let transaction_hash = status.transaction_hash;
+				let transaction_index = status.transaction_index;
+				for ethereum_log in &status.logs {
+					if transaction_index == db_transaction_index
+						&& transaction_log_index == db_log_index
+					{
+						ret.push(Log {
+							address: ethereum_log.address,
+							topics: ethereum_log.topics.clone(),
+							data: Bytes(ethereum_log.data.clone()),
+							block_hash: Some(ethereum_block_hash),
+							block_number: Some(U256::from(block_number)),
+							transaction_hash: Some(transaction_hash),
+							transaction_index: Some(U256::from(transaction_index)),
+							log_index: Some(U256::from(block_log_index)),
+							transaction_log_index: Some(U256::from(transaction_log_index)),
+							removed: false,
+						});
 					}
+					transaction_log_index += 1;
+					block_log_index += 1;
 				}
 			}
-			// Check for restrictions
-			if ret.len() as u32 > max_past_logs {
-				return Err(internal_err(format!(
-					"query returned more than {} results",
-					max_past_logs
-				)));
-			}
-			if begin_request.elapsed() > max_duration {
-				return Err(internal_err(format!(
-					"query timeout of {} seconds exceeded",
-					max_duration.as_secs()
-				)));
-			}
 		}
+		// Check for restrictions
+		if begin_request.elapsed() > max_duration {
+			return Err(internal_err(format!(
+				"query timeout of {} seconds exceeded",
+				max_duration.as_secs()
+			)));
+		}
+	}
 
-		let time_post = timer_post.elapsed().as_millis();
+	let time_post = timer_post.elapsed().as_millis();
 
-		log::info!(
-			target: "frontier-sql",
-			"OUTER-TIMER fetch={}, post={}",
-			time_fetch,
-			time_post,
-		);
-	}
+	log::info!(
+		target: "frontier-sql",
+		"OUTER-TIMER fetch={}, post={}",
+		time_fetch,
+		time_post,
+	);
 
 	log::info!(
 		target: "frontier-sql",
@@ -733,10 +734,7 @@ where
 		}
 		// Check for restrictions
 		if ret.len() as u32 > max_past_logs {
-			return Err(internal_err(format!(
-				"query returned more than {} results",
-				max_past_logs
-			)));
+			return Err(too_many_logs_err(max_past_logs));
 		}
 		if begin_request.elapsed() > max_duration {
 			return Err(internal_err(format!(
@@ -753,6 +751,16 @@ where
 	Ok(())
 }
 
+fn too_many_logs_err(max_past_logs: u32) -> jsonrpsee::types::ErrorObjectOwned {
+	crate::err(
+		LIMIT_EXCEEDED_CODE,
+		format!(
+			"query returned more than {max_past_logs} results, narrow the block range or the filter"
+		),
+		None,
+	)
+}
+
 fn filter_block_logs<'a>(
 	ret: &'a mut Vec<Log>,
 	filter: &'a Filter,
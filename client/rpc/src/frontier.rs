@@ -0,0 +1,47 @@
+// This file is part of Frontier.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use jsonrpsee::core::RpcResult;
+// Frontier
+use fc_rpc_core::{types::FrontierSyncStatus, FrontierApiServer};
+
+/// Frontier API implementation, reporting the progress of the mapping backend.
+pub enum Frontier {
+	/// The key-value backend.
+	KeyValue,
+	/// The SQL backend, reading the state reported by its sync worker.
+	#[cfg(feature = "sql")]
+	Sql(tokio::sync::watch::Receiver<fc_mapping_sync::sql::SyncState>),
+}
+
+impl FrontierApiServer for Frontier {
+	fn sync_status(&self) -> RpcResult<FrontierSyncStatus> {
+		Ok(match self {
+			Self::KeyValue => FrontierSyncStatus::KeyValue,
+			#[cfg(feature = "sql")]
+			Self::Sql(sync_state) => {
+				let state = *sync_state.borrow();
+				FrontierSyncStatus::Sql {
+					indexed_block: state.indexed_number,
+					best_block: state.best_number,
+					is_synced: state.is_synced,
+				}
+			}
+		})
+	}
+}
@@ -30,6 +30,7 @@ mod cache;
 mod debug;
 mod eth;
 mod eth_pubsub;
+mod frontier;
 mod net;
 mod signer;
 #[cfg(feature = "txpool")]
@@ -43,6 +44,7 @@ pub use self::{
 	debug::Debug,
 	eth::{format, pending, EstimateGasAdapter, Eth, EthConfig, EthFilter},
 	eth_pubsub::{EthPubSub, EthereumSubIdProvider},
+	frontier::Frontier,
 	net::Net,
 	signer::{EthDevSigner, EthSigner},
 	web3::Web3,
@@ -51,8 +53,8 @@ pub use ethereum::TransactionV2 as EthereumTransaction;
 #[cfg(feature = "txpool")]
 pub use fc_rpc_core::TxPoolApiServer;
 pub use fc_rpc_core::{
-	DebugApiServer, EthApiServer, EthFilterApiServer, EthPubSubApiServer, NetApiServer,
-	Web3ApiServer,
+	DebugApiServer, EthApiServer, EthFilterApiServer, EthPubSubApiServer, FrontierApiServer,
+	NetApiServer, Web3ApiServer,
 };
 pub use fc_storage::{overrides::*, StorageOverrideHandler};
 
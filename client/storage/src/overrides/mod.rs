@@ -122,6 +122,35 @@ where
 		self.query::<Vec<u8>>(at, &StorageKey(key))
 	}
 
+	/// Returns the address and code of every account with code at the given block.
+	pub fn account_codes(&self, at: B::Hash) -> Vec<(Address, Vec<u8>)> {
+		let prefix = storage_prefix_build(PALLET_EVM, EVM_ACCOUNT_CODES);
+		let Ok(pairs) = self
+			.client
+			.storage_pairs(at, Some(&StorageKey(prefix.clone())), None)
+		else {
+			return vec![];
+		};
+		pairs
+			.filter_map(|(key, data)| {
+				// The map key is the blake2_128 concat hashed address.
+				let address = key.0.get(prefix.len() + 16..).filter(|a| a.len() == 20)?;
+				let code = Vec::<u8>::decode(&mut &data.0[..]).ok()?;
+				Some((Address::from_slice(address), code))
+			})
+			.collect()
+	}
+
+	/// Returns the number of storage slots set for the given account.
+	pub fn account_storage_count(&self, at: B::Hash, address: Address) -> usize {
+		let mut key: Vec<u8> = storage_prefix_build(PALLET_EVM, EVM_ACCOUNT_STORAGES);
+		key.extend(blake2_128_extend(address.as_bytes()));
+		self.client
+			.storage_keys(at, Some(&StorageKey(key)), None)
+			.map(|keys| keys.count())
+			.unwrap_or_default()
+	}
+
 	pub fn account_storage(&self, at: B::Hash, address: Address, index: U256) -> Option<H256> {
 		let tmp: &mut [u8; 32] = &mut [0; 32];
 		index.to_big_endian(tmp);
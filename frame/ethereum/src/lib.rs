@@ -177,6 +177,19 @@ pub enum PostLogContent {
 	OnlyBlockHash,
 }
 
+/// Overrides the chain id expected by the transactions of a block, by block number, so that
+/// a chain can move to a new chain id at a fork height.
+pub trait ChainIdAt<BlockNumber> {
+	/// The chain id expected at `number`, `None` for the `pallet_evm::Config::ChainId` one.
+	fn chain_id_at(number: BlockNumber) -> Option<u64>;
+}
+
+impl<BlockNumber> ChainIdAt<BlockNumber> for () {
+	fn chain_id_at(_: BlockNumber) -> Option<u64> {
+		None
+	}
+}
+
 pub use self::pallet::*;
 
 #[frame_support::pallet]
@@ -202,6 +215,11 @@ pub mod pallet {
 		type PostLogContent: Get<PostLogContent>;
 		/// The maximum length of the extra data in the Executed event.
 		type ExtraDataLength: Get<u32>;
+		/// The maximum distance between a pooled transaction nonce and the account nonce.
+		/// `None` allows any future nonce in the pool.
+		type MaxFutureNonceGap: Get<Option<u64>>;
+		/// The chain id the transactions are validated against at each block number.
+		type ChainIdAt: ChainIdAt<BlockNumberFor<Self>>;
 	}
 
 	#[pallet::hooks]
@@ -502,7 +520,7 @@ impl<T: Config> Pallet<T> {
 				evm_config: T::config(),
 				block_gas_limit: T::BlockGasLimit::get(),
 				base_fee,
-				chain_id: T::ChainId::get(),
+				chain_id: Self::chain_id(),
 				is_transactional: true,
 			},
 			transaction_data.clone().into(),
@@ -552,6 +570,17 @@ impl<T: Config> Pallet<T> {
 			.and_provides((origin, transaction_nonce))
 			.priority(priority);
 
+		// Bound how far ahead of the account nonce a pooled transaction can be, so
+		// future transactions cannot exhaust the pool memory.
+		if let Some(max_gap) = T::MaxFutureNonceGap::get() {
+			if transaction_nonce.saturating_sub(who.nonce) > U256::from(max_gap) {
+				return Err(InvalidTransaction::Custom(
+					TransactionValidationError::TxNonceTooHigh as u8,
+				)
+				.into());
+			}
+		}
+
 		// In the context of the pool, a transaction with
 		// too high a nonce is still considered valid
 		if transaction_nonce > who.nonce {
@@ -716,6 +745,38 @@ impl<T: Config> Pallet<T> {
 		<CurrentBlock<T>>::get().map(|block| block.header.hash())
 	}
 
+	/// The receipts of the block being built, or else of the last stored block.
+	fn current_block_receipts() -> Vec<Receipt> {
+		let pending = Pending::<T>::get();
+		if pending.is_empty() {
+			<CurrentReceipts<T>>::get().unwrap_or_default()
+		} else {
+			pending.into_iter().map(|(_, _, receipt)| receipt).collect()
+		}
+	}
+
+	/// Get the gas used by the ethereum transactions of the current block, the cumulative
+	/// gas of its last receipt.
+	pub fn current_block_gas_used() -> U256 {
+		Self::current_block_receipts()
+			.last()
+			.map(receipt_used_gas)
+			.unwrap_or_default()
+	}
+
+	/// Get the gas used by each ethereum transaction of the current block, in order.
+	pub fn current_transactions_gas_used() -> Vec<U256> {
+		let mut cumulative_gas_used = U256::zero();
+		Self::current_block_receipts()
+			.iter()
+			.map(|receipt| {
+				let used_gas = receipt_used_gas(receipt).saturating_sub(cumulative_gas_used);
+				cumulative_gas_used = receipt_used_gas(receipt);
+				used_gas
+			})
+			.collect()
+	}
+
 	/// Execute an Ethereum transaction.
 	pub fn execute(
 		from: H160,
@@ -852,6 +913,13 @@ impl<T: Config> Pallet<T> {
 		}
 	}
 
+	/// The chain id expected by the transactions of the current block. The pool validates
+	/// a transaction in the block it would be included in, so both use the same id.
+	fn chain_id() -> u64 {
+		T::ChainIdAt::chain_id_at(frame_system::Pallet::<T>::block_number())
+			.unwrap_or_else(T::ChainId::get)
+	}
+
 	/// Validate an Ethereum transaction already in block
 	///
 	/// This function must be called during the pre-dispatch phase
@@ -870,7 +938,7 @@ impl<T: Config> Pallet<T> {
 				evm_config: T::config(),
 				block_gas_limit: T::BlockGasLimit::get(),
 				base_fee,
-				chain_id: T::ChainId::get(),
+				chain_id: Self::chain_id(),
 				is_transactional: true,
 			},
 			transaction_data.into(),
@@ -886,6 +954,18 @@ impl<T: Config> Pallet<T> {
 		Ok(())
 	}
 
+	/// Validate an Ethereum transaction as the pool would, without executing it.
+	///
+	/// Nonce, balance and base fee are checked against the current state and nothing is
+	/// written, so senders can pre-flight a transaction. As in the pool, a nonce ahead of
+	/// the account nonce is accepted.
+	pub fn validate_transaction_in_pool_like(
+		origin: H160,
+		transaction: &Transaction,
+	) -> Result<(), TransactionValidityError> {
+		Self::validate_transaction_in_pool(origin, transaction).map(|_| ())
+	}
+
 	pub fn migrate_block_v0_to_v2() -> Weight {
 		let db_weights = T::DbWeight::get();
 		let mut weight: Weight = db_weights.reads(1);
@@ -967,6 +1047,13 @@ pub enum ReturnValue {
 	Hash(H160),
 }
 
+/// The cumulative gas used by the transactions of a block up to the one of the receipt.
+fn receipt_used_gas(receipt: &Receipt) -> U256 {
+	match receipt {
+		Receipt::Legacy(d) | Receipt::EIP2930(d) | Receipt::EIP1559(d) => d.used_gas,
+	}
+}
+
 pub struct IntermediateStateRoot<T>(PhantomData<T>);
 impl<T: Config> Get<H256> for IntermediateStateRoot<T> {
 	fn get() -> H256 {
@@ -184,6 +184,19 @@ impl pallet_evm::Config for Test {
 
 parameter_types! {
 	pub const PostBlockAndTxnHashes: PostLogContent = PostLogContent::BlockAndTxnHashes;
+	pub static MaxFutureNonceGap: Option<u64> = None;
+	/// The block number from which the chain id changes, along with the new chain id.
+	pub static ChainIdFork: Option<(u64, u64)> = None;
+}
+
+/// The chain id of [`ChainIdFork`] from its block number on.
+pub struct ForkChainId;
+impl crate::ChainIdAt<u64> for ForkChainId {
+	fn chain_id_at(number: u64) -> Option<u64> {
+		ChainIdFork::get()
+			.filter(|(fork_number, _)| number >= *fork_number)
+			.map(|(_, chain_id)| chain_id)
+	}
 }
 
 impl Config for Test {
@@ -191,6 +204,8 @@ impl Config for Test {
 	type StateRoot = IntermediateStateRoot<Self>;
 	type PostLogContent = PostBlockAndTxnHashes;
 	type ExtraDataLength = ConstU32<30>;
+	type MaxFutureNonceGap = MaxFutureNonceGap;
+	type ChainIdAt = ForkChainId;
 }
 
 impl fp_self_contained::SelfContainedCall for RuntimeCall {
@@ -344,7 +359,7 @@ pub struct LegacyUnsignedTransaction {
 }
 
 impl LegacyUnsignedTransaction {
-	fn signing_rlp_append(&self, s: &mut RlpStream) {
+	fn signing_rlp_append(&self, s: &mut RlpStream, chain_id: u64) {
 		s.begin_list(9);
 		s.append(&self.nonce);
 		s.append(&self.gas_price);
@@ -352,14 +367,14 @@ impl LegacyUnsignedTransaction {
 		s.append(&self.action);
 		s.append(&self.value);
 		s.append(&self.input);
-		s.append(&ChainId::get());
+		s.append(&chain_id);
 		s.append(&0u8);
 		s.append(&0u8);
 	}
 
-	fn signing_hash(&self) -> H256 {
+	fn signing_hash(&self, chain_id: u64) -> H256 {
 		let mut stream = RlpStream::new();
-		self.signing_rlp_append(&mut stream);
+		self.signing_rlp_append(&mut stream, chain_id);
 		H256::from(keccak_256(&stream.out()))
 	}
 
@@ -368,7 +383,7 @@ impl LegacyUnsignedTransaction {
 	}
 
 	pub fn sign_with_chain_id(&self, key: &H256, chain_id: u64) -> Transaction {
-		let hash = self.signing_hash();
+		let hash = self.signing_hash(chain_id);
 		let msg = libsecp256k1::Message::parse(hash.as_fixed_bytes());
 		let s = libsecp256k1::sign(
 			&msg,
@@ -210,6 +210,46 @@ fn transaction_with_to_low_nonce_should_not_work() {
 	});
 }
 
+#[test]
+fn transaction_can_be_validated_without_being_applied() {
+	let (pairs, mut ext) = new_test_ext(1);
+	let alice = &pairs[0];
+
+	ext.execute_with(|| {
+		let state_root = sp_io::storage::root(sp_runtime::StateVersion::V1);
+		let t = eip1559_erc20_creation_transaction(alice);
+		assert_ok!(Ethereum::validate_transaction_in_pool_like(
+			alice.address,
+			&t
+		));
+
+		let mut transaction = eip1559_erc20_creation_unsigned_transaction();
+		transaction.max_fee_per_gas = U256::from(11_000_000);
+		assert_err!(
+			Ethereum::validate_transaction_in_pool_like(
+				alice.address,
+				&transaction.sign(&alice.private_key, None)
+			),
+			InvalidTransaction::Payment
+		);
+
+		// Validation leaves the state untouched.
+		assert!(crate::Pending::<Test>::get().is_empty());
+		assert_eq!(EVM::account_basic(&alice.address).0.nonce, U256::zero());
+		assert_eq!(
+			sp_io::storage::root(sp_runtime::StateVersion::V1),
+			state_root
+		);
+
+		// The transaction is stale once applied.
+		assert_ok!(Ethereum::execute(alice.address, &t, None,));
+		assert_err!(
+			Ethereum::validate_transaction_in_pool_like(alice.address, &t),
+			InvalidTransaction::Stale
+		);
+	});
+}
+
 #[test]
 fn transaction_with_to_hight_nonce_should_fail_in_block() {
 	let (pairs, mut ext) = new_test_ext(1);
@@ -341,6 +381,47 @@ fn transaction_should_generate_correct_gas_used() {
 	});
 }
 
+#[test]
+fn current_block_gas_used_should_sum_the_transactions_gas_used() {
+	let (pairs, mut ext) = new_test_ext(1);
+	let alice = &pairs[0];
+
+	ext.execute_with(|| {
+		System::set_block_number(1);
+
+		let t = eip1559_erc20_creation_transaction(alice);
+		let (_, create_info) = Ethereum::apply_validated_transaction(alice.address, t).unwrap();
+		let t2 = EIP1559UnsignedTransaction {
+			nonce: U256::from(1),
+			..eip1559_erc20_creation_unsigned_transaction()
+		}
+		.sign(&alice.private_key, None);
+		let (_, create_info2) = Ethereum::apply_validated_transaction(alice.address, t2).unwrap();
+
+		let used_gas = [create_info, create_info2]
+			.into_iter()
+			.map(|info| match info {
+				CallOrCreateInfo::Create(info) => info.used_gas.effective,
+				CallOrCreateInfo::Call(_) => panic!("expected create info"),
+			})
+			.collect::<Vec<_>>();
+		assert_eq!(Ethereum::current_transactions_gas_used(), used_gas);
+		assert_eq!(
+			Ethereum::current_block_gas_used(),
+			used_gas[0] + used_gas[1]
+		);
+
+		// Read from the stored receipts once the block is finalized.
+		Ethereum::store_block(None, U256::one());
+		crate::Pending::<Test>::kill();
+		assert_eq!(Ethereum::current_transactions_gas_used(), used_gas);
+		assert_eq!(
+			Ethereum::current_block_gas_used(),
+			used_gas[0] + used_gas[1]
+		);
+	});
+}
+
 #[test]
 fn call_should_handle_errors() {
 	let (pairs, mut ext) = new_test_ext(1);
@@ -403,6 +484,70 @@ fn call_should_handle_errors() {
 	});
 }
 
+#[test]
+fn call_should_return_the_revert_reason() {
+	let (pairs, mut ext) = new_test_ext(1);
+	let alice = &pairs[0];
+
+	ext.execute_with(|| {
+		let t = EIP1559UnsignedTransaction {
+			nonce: U256::zero(),
+			max_priority_fee_per_gas: U256::from(1),
+			max_fee_per_gas: U256::from(1),
+			gas_limit: U256::from(0x100000),
+			action: ethereum::TransactionAction::Create,
+			value: U256::zero(),
+			input: hex::decode(TEST_CONTRACT_CODE).unwrap(),
+		}
+		.sign(&alice.private_key, None);
+		assert_ok!(Ethereum::execute(alice.address, &t, None,));
+
+		let contract_address = hex::decode("32dcab0ef3fb2de2fce1d2e0799d36239671f04a").unwrap();
+		let bar = hex::decode("febb0f7e").unwrap();
+		let t2 = EIP1559UnsignedTransaction {
+			nonce: U256::from(1),
+			max_priority_fee_per_gas: U256::from(1),
+			max_fee_per_gas: U256::from(1),
+			gas_limit: U256::from(0x100000),
+			action: TransactionAction::Call(H160::from_slice(&contract_address)),
+			value: U256::zero(),
+			input: bar,
+		}
+		.sign(&alice.private_key, None);
+
+		let (_, _, info) = Ethereum::execute(alice.address, &t2, None).unwrap();
+		let CallOrCreateInfo::Call(info) = info else {
+			panic!("expected call info");
+		};
+		let reason = info.revert_reason().expect("call reverted");
+		// `Error(string)` selector, followed by the string offset, length and bytes.
+		assert_eq!(reason[..4], [0x08, 0xc3, 0x79, 0xa0]);
+		let len = U256::from_big_endian(&reason[36..68]).as_usize();
+		assert_eq!(
+			&reason[68..68 + len],
+			b"very_long_error_msg_that_we_expect_to_be_trimmed_away"
+		);
+
+		// A successful call has no revert reason.
+		let foo = hex::decode("c2985578").unwrap();
+		let t3 = EIP1559UnsignedTransaction {
+			nonce: U256::from(2),
+			max_priority_fee_per_gas: U256::from(1),
+			max_fee_per_gas: U256::from(1),
+			gas_limit: U256::from(0x100000),
+			action: TransactionAction::Call(H160::from_slice(&contract_address)),
+			value: U256::zero(),
+			input: foo,
+		}
+		.sign(&alice.private_key, None);
+		let (_, _, info) = Ethereum::execute(alice.address, &t3, None).unwrap();
+		let CallOrCreateInfo::Call(info) = info else {
+			panic!("expected call info");
+		};
+		assert_eq!(info.revert_reason(), None);
+	});
+}
+
 #[test]
 fn event_extra_data_should_be_handle_properly() {
 	let (pairs, mut ext) = new_test_ext(1);
@@ -18,7 +18,7 @@
 //! Consensus extension module tests for BABE consensus.
 
 use super::*;
-use evm::{ExitReason, ExitRevert, ExitSucceed};
+use evm::{ExitError, ExitReason, ExitRevert, ExitSucceed};
 use fp_ethereum::{TransactionData, ValidatedTransaction};
 use frame_support::{
 	dispatch::{DispatchClass, GetDispatchInfo},
@@ -142,6 +142,40 @@ fn transaction_with_to_low_nonce_should_not_work() {
 	});
 }
 
+#[test]
+fn transaction_with_nonce_beyond_max_future_gap_should_not_work() {
+	let (pairs, mut ext) = new_test_ext(1);
+	let alice = &pairs[0];
+
+	ext.execute_with(|| {
+		let validate = |nonce: u64| {
+			let mut transaction = legacy_erc20_creation_unsigned_transaction();
+			transaction.nonce = U256::from(nonce);
+			let call = crate::Call::<Test>::transact {
+				transaction: transaction.sign(&alice.private_key),
+			};
+			let source = call.check_self_contained().unwrap().unwrap();
+			let extrinsic = CheckedExtrinsic::<u64, _, SignedExtra, H160> {
+				signed: fp_self_contained::CheckedSignature::SelfContained(source),
+				function: RuntimeCall::Ethereum(call.clone()),
+			};
+			call.validate_self_contained(&source, &extrinsic.get_dispatch_info(), 0)
+				.unwrap()
+		};
+
+		// Unbounded by default.
+		assert_ok!(validate(1_000));
+
+		MaxFutureNonceGap::set(Some(10));
+		assert_ok!(validate(10));
+		assert_err!(
+			validate(1_000),
+			InvalidTransaction::Custom(fp_evm::TransactionValidationError::TxNonceTooHigh as u8)
+		);
+		MaxFutureNonceGap::set(None);
+	});
+}
+
 #[test]
 fn transaction_with_to_hight_nonce_should_fail_in_block() {
 	let (pairs, mut ext) = new_test_ext(1);
@@ -193,6 +227,49 @@ fn transaction_with_invalid_chain_id_should_fail_in_block() {
 	});
 }
 
+#[test]
+fn transaction_chain_id_should_follow_the_chain_id_fork() {
+	let (pairs, mut ext) = new_test_ext(1);
+	let alice = &pairs[0];
+
+	ext.execute_with(|| {
+		ChainIdFork::set(Some((10, 43)));
+		let extrinsic = |nonce: u64, chain_id: u64| {
+			let mut transaction = legacy_erc20_creation_unsigned_transaction();
+			transaction.nonce = U256::from(nonce);
+			let call = crate::Call::<Test>::transact {
+				transaction: transaction.sign_with_chain_id(&alice.private_key, chain_id),
+			};
+			let source = call.check_self_contained().unwrap().unwrap();
+			CheckedExtrinsic::<u64, _, SignedExtra, H160> {
+				signed: fp_self_contained::CheckedSignature::SelfContained(source),
+				function: RuntimeCall::Ethereum(call),
+			}
+		};
+		let invalid_chain_id = TransactionValidityError::Invalid(InvalidTransaction::Custom(
+			fp_evm::TransactionValidationError::InvalidChainId as u8,
+		));
+
+		// Before the fork, the old chain id only.
+		System::set_block_number(9);
+		let dispatch_info = extrinsic(0, 42).get_dispatch_info();
+		assert_err!(
+			extrinsic(0, 43).apply::<Test>(&dispatch_info, 0),
+			invalid_chain_id
+		);
+		assert_ok!(extrinsic(0, 42).apply::<Test>(&dispatch_info, 0).unwrap());
+
+		// From the fork on, the new chain id only.
+		System::set_block_number(10);
+		assert_err!(
+			extrinsic(1, 42).apply::<Test>(&dispatch_info, 0),
+			invalid_chain_id
+		);
+		assert_ok!(extrinsic(1, 43).apply::<Test>(&dispatch_info, 0).unwrap());
+		ChainIdFork::set(None);
+	});
+}
+
 #[test]
 fn contract_constructor_should_get_executed() {
 	let (pairs, mut ext) = new_test_ext(1);
@@ -253,6 +330,126 @@ fn contract_should_be_created_at_given_address() {
 	});
 }
 
+#[test]
+fn contract_creation_at_existing_address_reports_collision() {
+	let (pairs, mut ext) = new_test_ext(1);
+	let alice = &pairs[0];
+
+	let erc20_address = contract_address(alice.address, 0);
+
+	ext.execute_with(|| {
+		let t = legacy_erc20_creation_transaction(alice);
+		let (_, _, info) = Ethereum::execute(alice.address, &t, None).unwrap();
+		assert!(!info.is_create_collision());
+
+		// Rewind alice's nonce, so the next create targets the same address.
+		let substrate_alice =
+			<Test as pallet_evm::Config>::AddressMapping::into_account_id(alice.address);
+		frame_system::Account::<Test>::mutate(&substrate_alice, |account| account.nonce = 0);
+
+		let (_, _, info) = Ethereum::execute(alice.address, &t, None).unwrap();
+		assert!(info.is_create_collision());
+		assert_eq!(
+			info.exit_reason(),
+			&ExitReason::Error(ExitError::CreateCollision)
+		);
+		match info {
+			CallOrCreateInfo::Create(info) => assert_eq!(info.value, erc20_address),
+			CallOrCreateInfo::Call(_) => panic!("expected create info"),
+		}
+	});
+}
+
+#[test]
+fn logs_of_self_destructed_contract_are_kept() {
+	let (pairs, mut ext) = new_test_ext(1);
+	let alice = &pairs[0];
+
+	// The constructor emits `LOG1(0xaa)` and deploys a runtime code self-destructing
+	// to the caller.
+	let contract_address = contract_address(alice.address, 0);
+	let creation = LegacyUnsignedTransaction {
+		nonce: U256::zero(),
+		gas_price: U256::from(1),
+		gas_limit: U256::from(0x100000),
+		action: ethereum::TransactionAction::Create,
+		value: U256::zero(),
+		input: hex::decode("60aa60006000a16002601360003960026000f333ff").unwrap(),
+	}
+	.sign(&alice.private_key);
+	let destruction = LegacyUnsignedTransaction {
+		nonce: U256::one(),
+		gas_price: U256::from(1),
+		gas_limit: U256::from(0x100000),
+		action: ethereum::TransactionAction::Call(contract_address),
+		value: U256::zero(),
+		input: vec![],
+	}
+	.sign(&alice.private_key);
+
+	ext.execute_with(|| {
+		for t in [creation, destruction] {
+			assert_ok!(Ethereum::transact(
+				RawOrigin::EthereumTransaction(alice.address).into(),
+				t,
+			));
+		}
+		assert!(pallet_evm::AccountCodes::<Test>::get(contract_address).is_empty());
+
+		// The log of the creation is still part of the block.
+		let pending = crate::Pending::<Test>::get();
+		assert_eq!(pending.len(), 2);
+		let (_, status, _) = &pending[0];
+		assert_eq!(status.contract_address, Some(contract_address));
+		assert_eq!(status.logs.len(), 1);
+		assert_eq!(status.logs[0].address, contract_address);
+		assert_eq!(status.logs[0].topics, vec![H256::from_low_u64_be(0xaa)]);
+		assert!(pending[1].1.logs.is_empty());
+	});
+}
+
+#[test]
+fn is_contract_distinguishes_contracts_from_eoas() {
+	let (pairs, mut ext) = new_test_ext(1);
+	let alice = &pairs[0];
+
+	let erc20_address = contract_address(alice.address, 0);
+
+	ext.execute_with(|| {
+		assert!(!pallet_evm::Pallet::<Test>::is_contract(&erc20_address));
+
+		let t = legacy_erc20_creation_transaction(alice);
+		assert_ok!(Ethereum::execute(alice.address, &t, None,));
+
+		assert!(pallet_evm::Pallet::<Test>::is_contract(&erc20_address));
+		assert!(!pallet_evm::Pallet::<Test>::is_contract(&alice.address));
+	});
+}
+
+#[test]
+fn evm_storage_root_changes_after_state_mutation() {
+	let (pairs, mut ext) = new_test_ext(1);
+	let alice = &pairs[0];
+
+	ext.execute_with(|| {
+		let root_before = pallet_evm::Pallet::<Test>::evm_storage_root(u32::MAX);
+		// Stable while the state is untouched.
+		assert_eq!(
+			pallet_evm::Pallet::<Test>::evm_storage_root(u32::MAX),
+			root_before
+		);
+
+		let t = legacy_erc20_creation_transaction(alice);
+		assert_ok!(Ethereum::execute(alice.address, &t, None,));
+
+		let root_after = pallet_evm::Pallet::<Test>::evm_storage_root(u32::MAX);
+		assert!(root_after.is_some());
+		assert_ne!(root_after, root_before);
+		// The created contract's code does not fit in an empty bound.
+		assert_eq!(pallet_evm::Pallet::<Test>::evm_storage_root(0), None);
+	});
+}
+
 #[test]
 fn transaction_should_generate_correct_gas_used() {
 	let (pairs, mut ext) = new_test_ext(1);
@@ -133,6 +133,47 @@ benchmarks! {
 		assert!(call_runner_results.is_ok(), "call() failed");
 	}
 
+	create {
+		// The init code is analysed for its jump destinations and charged per word before being
+		// executed, so its length drives the cost of a create beyond the gas it consumes.
+		let n in 1..49152;
+
+		use sp_core::{H160, U256};
+		use sp_runtime::traits::UniqueSaturatedInto;
+
+		let caller = H160::from_low_u64_be(1);
+		// `n` `JUMPDEST` followed by an implicit `STOP`, deploying an empty contract.
+		let init = alloc::vec![0x5b; n as usize];
+		let gas_limit: u64 = 10_000_000;
+		// The fee is paid at the lowest accepted price, the caller being funded for it.
+		let (max_fee_per_gas, _) = T::FeeCalculator::min_gas_price();
+		let fee = U256::from(gas_limit).saturating_mul(max_fee_per_gas);
+		let caller_account_id = T::AddressMapping::into_account_id(caller);
+		CurrencyOf::<T>::make_free_balance_be(
+			&caller_account_id,
+			fee.low_u128().unique_saturated_into(),
+		);
+	}: {
+		let is_transactional = true;
+		let validate = true;
+		let create_runner_results = T::Runner::create(
+			caller,
+			init,
+			U256::default(),
+			gas_limit,
+			Some(max_fee_per_gas),
+			None,
+			None,
+			Vec::new(),
+			is_transactional,
+			validate,
+			None,
+			None,
+			T::config(),
+		);
+		assert!(create_runner_results.is_ok(), "create() failed");
+	}
+
 	withdraw {
 		let caller = frame_benchmarking::whitelisted_caller::<T::AccountId>();
 		let from = H160::from_low_u64_le(0);
@@ -802,6 +802,11 @@ impl<T: Config> Pallet<T> {
 
 		account.nonce == U256::zero() && account.balance == U256::zero() && code_len == 0
 	}
+	/// Check whether an account has code deployed, i.e. is a contract.
+	pub fn is_contract(address: &H160) -> bool {
+		<AccountCodes<T>>::decode_len(address).unwrap_or(0) > 0
+	}
+
 	/// Check whether an account is a suicided contract
 	pub fn is_account_suicided(address: &H160) -> bool {
 		<Suicided<T>>::contains_key(address)
@@ -811,6 +816,34 @@ impl<T: Config> Pallet<T> {
 		<AccountStorages<T>>::iter_key_prefix(address)
 	}
 
+	/// Compute the blake2-256 trie root of the account codes and storages, keyed by their
+	/// storage keys. This is neither the Ethereum state root nor an account storage root.
+	///
+	/// The entries are collected in memory, `None` is returned if there are more than
+	/// `max_entries` of them. Meant for off-chain use only.
+	pub fn evm_storage_root(max_entries: u32) -> Option<H256> {
+		let max_entries = max_entries as usize;
+		let codes = <AccountCodes<T>>::iter()
+			.map(|(address, code)| (<AccountCodes<T>>::hashed_key_for(address), code.encode()));
+		let storages = <AccountStorages<T>>::iter().map(|(address, index, value)| {
+			(
+				<AccountStorages<T>>::hashed_key_for(address, index),
+				value.encode(),
+			)
+		});
+		let entries: Vec<(Vec<u8>, Vec<u8>)> = codes
+			.chain(storages)
+			.take(max_entries.saturating_add(1))
+			.collect();
+		if entries.len() > max_entries {
+			return None;
+		}
+		Some(sp_io::trie::blake2_256_root(
+			entries,
+			sp_runtime::StateVersion::V1,
+		))
+	}
+
 	/// Remove an account if its empty.
 	pub fn remove_account_if_empty(address: &H160) {
 		if Self::is_account_empty(address) {
@@ -22,6 +22,10 @@
 //! WORST CASE MAP SIZE: `1000000`
 //! HOSTNAME: `archlinux`, CPU: `AMD Ryzen 9 5900X 12-Core Processor`
 //! EXECUTION: Some(Wasm), WASM-EXECUTION: Compiled, CHAIN: Some("dev"), DB CACHE: 1024
+//!
+//! NOTE: the `create` weights are placeholders, not the output of this command, and no call
+//! is charged with them. Regenerate them on reference hardware with `--extrinsic=create`
+//! before relying on them.
 
 // Executed Command:
 // ./target/release/frontier-template-node
@@ -43,17 +47,32 @@
 #![allow(unused_imports)]
 #![allow(missing_docs)]
 
-use frame_support::{traits::Get, weights::{Weight, constants::RocksDbWeight}};
+use frame_support::{traits::Get, weights::{Weight, RuntimeDbWeight, constants::RocksDbWeight}};
 use core::marker::PhantomData;
 
 /// Weight functions needed for pallet_evm.
 pub trait WeightInfo {
+	fn create(n: u32, ) -> Weight;
 	fn withdraw() -> Weight;
 }
 
 /// Weights for pallet_evm using the Substrate node and recommended hardware.
 pub struct SubstrateWeight<T>(PhantomData<T>);
 impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
+	/// Storage: `EVM::AccountCodes` (r:2 w:1)
+	/// Proof: `EVM::AccountCodes` (`max_values`: None, `max_size`: None, mode: `Measured`)
+	/// Storage: `System::Account` (r:1 w:1)
+	/// Proof: `System::Account` (`max_values`: None, `max_size`: Some(128), added: 2603, mode: `MaxEncodedLen`)
+	/// Storage: `EVM::AccountCodesMetadata` (r:0 w:1)
+	/// Proof: `EVM::AccountCodesMetadata` (`max_values`: None, `max_size`: None, mode: `Measured`)
+	/// The range of component `n` is `[1, 49152]`.
+	fn create(n: u32, ) -> Weight {
+		// Placeholder, not measured: regenerate with the `create` benchmark.
+		Weight::from_parts(44_125_000, 3593)
+			.saturating_add(Weight::from_parts(2_604, 0).saturating_mul(n.into()))
+			.saturating_add(T::DbWeight::get().reads(3_u64))
+			.saturating_add(T::DbWeight::get().writes(3_u64))
+	}
 	fn withdraw() -> Weight {
 		// Proof Size summary in bytes:
 		//  Measured:  `0`
@@ -63,8 +82,24 @@ impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
 	}
 }
 
-// For backwards compatibility and tests
-impl WeightInfo for () {
+/// Weights for pallet_evm using the given database weights, such as `RocksDbWeight` or
+/// `ParityDbWeight`, regardless of the runtime's `DbWeight`.
+pub struct DbWeightInfo<W>(PhantomData<W>);
+impl<W: Get<RuntimeDbWeight>> WeightInfo for DbWeightInfo<W> {
+	/// Storage: `EVM::AccountCodes` (r:2 w:1)
+	/// Proof: `EVM::AccountCodes` (`max_values`: None, `max_size`: None, mode: `Measured`)
+	/// Storage: `System::Account` (r:1 w:1)
+	/// Proof: `System::Account` (`max_values`: None, `max_size`: Some(128), added: 2603, mode: `MaxEncodedLen`)
+	/// Storage: `EVM::AccountCodesMetadata` (r:0 w:1)
+	/// Proof: `EVM::AccountCodesMetadata` (`max_values`: None, `max_size`: None, mode: `Measured`)
+	/// The range of component `n` is `[1, 49152]`.
+	fn create(n: u32, ) -> Weight {
+		// Placeholder, not measured: regenerate with the `create` benchmark.
+		Weight::from_parts(44_125_000, 3593)
+			.saturating_add(Weight::from_parts(2_604, 0).saturating_mul(n.into()))
+			.saturating_add(W::get().reads(3_u64))
+			.saturating_add(W::get().writes(3_u64))
+	}
 	fn withdraw() -> Weight {
 		// Proof Size summary in bytes:
 		//  Measured:  `0`
@@ -73,3 +108,13 @@ impl WeightInfo for () {
 		Weight::from_parts(2_000_000, 0)
 	}
 }
+
+// For backwards compatibility and tests
+impl WeightInfo for () {
+	fn create(n: u32, ) -> Weight {
+		DbWeightInfo::<RocksDbWeight>::create(n)
+	}
+	fn withdraw() -> Weight {
+		DbWeightInfo::<RocksDbWeight>::withdraw()
+	}
+}
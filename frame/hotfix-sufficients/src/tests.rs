@@ -154,3 +154,20 @@ fn test_hotfix_inc_account_sufficients_does_not_increment_if_both_nonce_and_refs
 		assert_eq!(account.consumers, 1);
 	});
 }
+
+#[test]
+fn test_hotfix_inc_account_sufficients_weight_follows_the_db_weight() {
+	use crate::weights::{DbWeightInfo, WeightInfo};
+	use frame_support::weights::constants::{ParityDbWeight, RocksDbWeight};
+
+	let rocksdb = DbWeightInfo::<RocksDbWeight>::hotfix_inc_account_sufficients(10);
+	let paritydb = DbWeightInfo::<ParityDbWeight>::hotfix_inc_account_sufficients(10);
+
+	assert_eq!(
+		<() as WeightInfo>::hotfix_inc_account_sufficients(10),
+		rocksdb
+	);
+	// ParityDb reads and writes are cheaper, the proof size does not depend on the database.
+	assert!(paritydb.ref_time() < rocksdb.ref_time());
+	assert_eq!(paritydb.proof_size(), rocksdb.proof_size());
+}
@@ -43,7 +43,7 @@
 #![allow(unused_imports)]
 #![allow(missing_docs)]
 
-use frame_support::{traits::Get, weights::{Weight, constants::RocksDbWeight}};
+use frame_support::{traits::Get, weights::{Weight, RuntimeDbWeight, constants::RocksDbWeight}};
 use core::marker::PhantomData;
 
 /// Weight functions needed for pallet_hotfix_sufficients.
@@ -81,8 +81,10 @@ impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
 	}
 }
 
-// For backwards compatibility and tests
-impl WeightInfo for () {
+/// Weights for pallet_hotfix_sufficients using the given database weights, such as `RocksDbWeight` or
+/// `ParityDbWeight`, regardless of the runtime's `DbWeight`.
+pub struct DbWeightInfo<W>(PhantomData<W>);
+impl<W: Get<RuntimeDbWeight>> WeightInfo for DbWeightInfo<W> {
 	/// Storage: System Account (r:1000 w:1000)
 	/// Proof: System Account (max_values: None, max_size: Some(116), added: 2591, mode: MaxEncodedLen)
 	/// Storage: System Number (r:1 w:0)
@@ -102,10 +104,17 @@ impl WeightInfo for () {
 		Weight::from_parts(2_000_000, 6572)
 			// Standard Error: 12_231
 			.saturating_add(Weight::from_parts(15_224_397, 0).saturating_mul(n.into()))
-			.saturating_add(RocksDbWeight::get().reads(4_u64))
-			.saturating_add(RocksDbWeight::get().reads((1_u64).saturating_mul(n.into())))
-			.saturating_add(RocksDbWeight::get().writes(2_u64))
-			.saturating_add(RocksDbWeight::get().writes((1_u64).saturating_mul(n.into())))
+			.saturating_add(W::get().reads(4_u64))
+			.saturating_add(W::get().reads((1_u64).saturating_mul(n.into())))
+			.saturating_add(W::get().writes(2_u64))
+			.saturating_add(W::get().writes((1_u64).saturating_mul(n.into())))
 			.saturating_add(Weight::from_parts(0, 2715).saturating_mul(n.into()))
 	}
 }
+
+// For backwards compatibility and tests
+impl WeightInfo for () {
+	fn hotfix_inc_account_sufficients(n: u32, ) -> Weight {
+		DbWeightInfo::<RocksDbWeight>::hotfix_inc_account_sufficients(n)
+	}
+}
@@ -200,6 +200,14 @@ pub struct ExecutionInfoV2<T> {
 pub type CallInfo = ExecutionInfoV2<Vec<u8>>;
 pub type CreateInfo = ExecutionInfoV2<H160>;
 
+impl CallInfo {
+	/// The output of a reverted call, holding the ABI encoded `Error(string)` reason of a
+	/// failed `require` or `revert`.
+	pub fn revert_reason(&self) -> Option<&[u8]> {
+		matches!(self.exit_reason, ExitReason::Revert(_)).then_some(&self.value[..])
+	}
+}
+
 #[derive(Clone, Eq, PartialEq, Debug, Encode, Decode, TypeInfo)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum CallOrCreateInfo {
@@ -207,6 +215,28 @@ pub enum CallOrCreateInfo {
 	Create(CreateInfo),
 }
 
+impl CallOrCreateInfo {
+	/// The exit reason of the call or create.
+	pub fn exit_reason(&self) -> &ExitReason {
+		match self {
+			Self::Call(info) => &info.exit_reason,
+			Self::Create(info) => &info.exit_reason,
+		}
+	}
+
+	/// Whether this is a create rejected by EIP-684, because the target address already
+	/// has code or a non-zero nonce.
+	pub fn is_create_collision(&self) -> bool {
+		matches!(
+			self,
+			Self::Create(ExecutionInfoV2 {
+				exit_reason: ExitReason::Error(ExitError::CreateCollision),
+				..
+			})
+		)
+	}
+}
+
 #[derive(Clone, Eq, PartialEq, Debug, Encode, Decode)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct ExecutionInfo<T> {
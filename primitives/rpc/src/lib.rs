@@ -84,7 +84,7 @@ impl<B: BlockT, C> RuntimeStorageOverride<B, C> for () {
 
 sp_api::decl_runtime_apis! {
 	/// API necessary for Ethereum-compatibility layer.
-	#[api_version(5)]
+	#[api_version(9)]
 	pub trait EthereumRuntimeRPCApi {
 		/// Returns runtime defined pallet_evm::ChainId.
 		fn chain_id() -> u64;
@@ -98,6 +98,9 @@ sp_api::decl_runtime_apis! {
 		/// For a given account address, returns pallet_evm::AccountCodes.
 		fn account_code_at(address: Address) -> Vec<u8>;
 
+		/// For a given account address, returns whether it has code deployed (is a contract).
+		fn is_contract(address: Address) -> bool;
+
 		/// Returns the converted FindAuthor::find_author authority id.
 		fn author() -> Address;
 
@@ -256,6 +259,20 @@ sp_api::decl_runtime_apis! {
 		fn pending_block(
 			xts: Vec<<Block as BlockT>::Extrinsic>,
 		) -> (Option<ethereum::BlockV2>, Option<Vec<TransactionStatus>>);
+
+		/// Returns the blake2-256 trie root of pallet_evm::AccountCodes and
+		/// pallet_evm::AccountStorages, `None` if they hold more than `max_entries` entries.
+		/// This is not an Ethereum state root.
+		fn evm_storage_root(max_entries: u32) -> Option<H256>;
+
+		/// Returns the addresses of the precompiles configured in the runtime.
+		fn active_precompiles() -> Vec<Address>;
+
+		/// Returns the cumulative gas used by the ethereum transactions of the current block.
+		fn current_block_gas_used() -> U256;
+
+		/// Returns the gas used by each ethereum transaction of the current block, in order.
+		fn current_transactions_gas_used() -> Vec<U256>;
 	}
 
 	#[api_version(2)]
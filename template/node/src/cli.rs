@@ -8,6 +8,10 @@ pub enum Sealing {
 	Manual,
 	/// Seal when transaction is executed.
 	Instant,
+	/// Seal and finalize when transaction is executed.
+	InstantFinalize,
+	/// Seal at a fixed interval, and on rpc method.
+	Interval,
 }
 
 #[derive(Debug, clap::Parser)]
@@ -23,6 +27,10 @@ pub struct Cli {
 	#[arg(long, value_enum, ignore_case = true)]
 	pub sealing: Option<Sealing>,
 
+	/// Milliseconds between two blocks sealed by the interval sealing method.
+	#[arg(long, default_value_t = 6000)]
+	pub sealing_interval_ms: u64,
+
 	#[command(flatten)]
 	pub eth: EthConfiguration,
 }
@@ -65,4 +73,8 @@ pub enum Subcommand {
 
 	/// Db meta columns information.
 	FrontierDb(fc_cli::FrontierDbCmd),
+
+	/// Sql backend maintenance.
+	#[cfg(feature = "sql")]
+	FrontierSql(fc_cli::FrontierSqlCmd),
 }
@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use futures::TryFutureExt;
 // Substrate
 use sc_cli::{ChainSpec, SubstrateCli};
@@ -224,10 +226,42 @@ pub fn run() -> sc_cli::Result<()> {
 				cmd.run(client, frontier_backend)
 			})
 		}
+		#[cfg(feature = "sql")]
+		Some(Subcommand::FrontierSql(cmd)) => {
+			let runner = cli.create_runner(cmd)?;
+			runner.sync_run(|mut config| {
+				let migrate = matches!(cmd.operation, fc_cli::SqlOperation::MigrateKvToSql);
+				// The migration creates the SQL database next to the key-value one.
+				let mut eth_config = cli.eth.clone();
+				eth_config.frontier_backend_force |= migrate;
+				let (client, _, _, _, frontier_backend) =
+					service::new_chain_ops(&mut config, &eth_config)?;
+				let frontier_backend = match frontier_backend {
+					fc_db::Backend::Sql(sql) => sql,
+					_ => panic!("Only fc_db::Backend::Sql supported"),
+				};
+				if migrate {
+					let kv_backend = std::sync::Arc::new(fc_db::kv::Backend::open(
+						client.clone(),
+						&config.database,
+						&db_config_dir(&config),
+					)?);
+					return config.tokio_handle.block_on(cmd.migrate_kv_to_sql(
+						client,
+						kv_backend,
+						frontier_backend,
+					));
+				}
+				config
+					.tokio_handle
+					.block_on(cmd.run(client, frontier_backend))
+			})
+		}
 		None => {
 			let runner = cli.create_runner(&cli.run)?;
 			runner.run_node_until_exit(|config| async move {
-				service::build_full(config, cli.eth, cli.sealing)
+				let sealing_interval = Duration::from_millis(cli.sealing_interval_ms);
+				service::build_full(config, cli.eth, cli.sealing, sealing_interval)
 					.map_err(Into::into)
 					.await
 			})
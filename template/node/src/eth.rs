@@ -1,6 +1,6 @@
 use std::{
 	collections::BTreeMap,
-	path::PathBuf,
+	path::{Path, PathBuf},
 	sync::{Arc, Mutex},
 	time::Duration,
 };
@@ -11,14 +11,15 @@ use sc_client_api::BlockchainEvents;
 use sc_executor::HostFunctions;
 use sc_network_sync::SyncingService;
 use sc_service::{error::Error as ServiceError, Configuration, TaskManager};
-use sp_api::ConstructRuntimeApi;
-use sp_core::H256;
+use sp_api::{ConstructRuntimeApi, ProvideRuntimeApi};
+use sp_core::{H160, H256, U256};
 use sp_runtime::traits::Block as BlockT;
 // Frontier
 pub use fc_consensus::FrontierBlockImport;
 use fc_rpc::EthTask;
 pub use fc_rpc_core::types::{FeeHistoryCache, FeeHistoryCacheLimit, FilterPool};
 pub use fc_storage::{StorageOverride, StorageOverrideHandler};
+use fp_rpc::EthereumRuntimeRPCApi;
 
 use crate::client::{FullBackend, FullClient};
 
@@ -29,6 +30,48 @@ pub fn db_config_dir(config: &Configuration) -> PathBuf {
 	config.base_path.config_dir(config.chain_spec.id())
 }
 
+/// The database file of the SQL backend, relative paths being resolved against the working
+/// directory.
+pub fn sql_db_path(
+	config: &Configuration,
+	eth_config: &EthConfiguration,
+) -> std::io::Result<PathBuf> {
+	match &eth_config.frontier_sql_backend_path {
+		Some(path) => Ok(std::env::current_dir()?.join(path)),
+		None => Ok(db_config_dir(config).join("sql").join("frontier.db3")),
+	}
+}
+
+/// Refuses to start a frontier backend with no database yet next to an existing database of
+/// the other backend type, as it would silently be indexed from scratch.
+pub fn check_backend_type(
+	backend_type: BackendType,
+	db_config_dir: &Path,
+	sql_db_path: &Path,
+) -> Result<(), String> {
+	let key_value_exists = ["db", "paritydb"]
+		.iter()
+		.any(|db_path| fc_db::kv::frontier_database_dir(db_config_dir, db_path).exists());
+	let sql_exists = sql_db_path.exists();
+	match backend_type {
+		BackendType::Sql if key_value_exists && !sql_exists => Err(format!(
+			"A key-value frontier database exists in {}, but none for the SQL backend at {}. \
+			Start with `--frontier-backend-type key-value` to keep using it, migrate it with \
+			`frontier-sql migrate-kv-to-sql`, or pass `--frontier-backend-force` to build the \
+			SQL index from scratch.",
+			fc_db::kv::frontier_database_dir(db_config_dir, "").display(),
+			sql_db_path.display(),
+		)),
+		BackendType::KeyValue if sql_exists && !key_value_exists => Err(format!(
+			"A SQL frontier database exists at {}, but none for the key-value backend. \
+			Start with `--frontier-backend-type sql` to keep using it, or pass \
+			`--frontier-backend-force` to build the key-value mapping from scratch.",
+			sql_db_path.display(),
+		)),
+		_ => Ok(()),
+	}
+}
+
 /// Available frontier backend types.
 #[derive(Debug, Copy, Clone, Default, clap::ValueEnum)]
 pub enum BackendType {
@@ -39,11 +82,99 @@ pub enum BackendType {
 	Sql,
 }
 
+/// Where the block author takes the dynamic-fee pallet target gas price from.
+#[derive(Debug, Copy, Clone, Default, clap::ValueEnum)]
+pub enum GasPriceSource {
+	/// The `--target-gas-price` value.
+	#[default]
+	Fixed,
+	/// An exponential moving average of the base fees of the best blocks, starting from the
+	/// `--target-gas-price` value.
+	Ema,
+}
+
+/// The highest accepted `--max-past-logs`, bounding the logs a single query holds in memory.
+const MAX_PAST_LOGS_LIMIT: i64 = 100_000;
+
+/// The number of blocks over which the `ema` target gas price averages the base fees.
+const TARGET_GAS_PRICE_EMA_PERIOD: u32 = 20;
+
+/// Provides the target gas price of the dynamic-fee inherent.
+pub trait GasPriceProvider: Send + Sync {
+	fn target_gas_price(&self) -> U256;
+}
+
+/// A constant target gas price.
+pub struct FixedGasPrice(pub U256);
+
+impl GasPriceProvider for FixedGasPrice {
+	fn target_gas_price(&self) -> U256 {
+		self.0
+	}
+}
+
+/// An exponential moving average of the observed base fees.
+pub struct EmaGasPrice {
+	period: u32,
+	average: Mutex<U256>,
+}
+
+impl EmaGasPrice {
+	pub fn new(initial: U256, period: u32) -> Self {
+		Self {
+			period: period.max(1),
+			average: Mutex::new(initial),
+		}
+	}
+
+	/// Folds a base fee into the average, weighted by `2 / (period + 1)`.
+	pub fn observe(&self, base_fee: U256) {
+		let mut average = self.average.lock().expect("gas price lock poisoned");
+		let period = U256::from(self.period);
+		*average = base_fee
+			.saturating_mul(U256::from(2))
+			.saturating_add(average.saturating_mul(period - 1))
+			/ (period + 1);
+	}
+}
+
+impl GasPriceProvider for EmaGasPrice {
+	fn target_gas_price(&self) -> U256 {
+		*self.average.lock().expect("gas price lock poisoned")
+	}
+}
+
+/// How the SQL backend indexes a block with an unrecoverable transaction signature.
+#[derive(Debug, Copy, Clone, Default, clap::ValueEnum)]
+pub enum MalformedSignaturePolicy {
+	/// Index the transaction without a sender and log a warning.
+	#[default]
+	Skip,
+	/// Leave the block unindexed, recorded as a dead letter.
+	DeadLetter,
+	/// Stop indexing on a fatal error.
+	Halt,
+}
+
+impl From<MalformedSignaturePolicy> for fc_db::sql::MalformedSignaturePolicy {
+	fn from(policy: MalformedSignaturePolicy) -> Self {
+		match policy {
+			MalformedSignaturePolicy::Skip => Self::Skip,
+			MalformedSignaturePolicy::DeadLetter => Self::DeadLetter,
+			MalformedSignaturePolicy::Halt => Self::Halt,
+		}
+	}
+}
+
 /// The ethereum-compatibility configuration used to run a node.
 #[derive(Clone, Debug, clap::Parser)]
 pub struct EthConfiguration {
-	/// Maximum number of logs in a query.
-	#[arg(long, default_value = "10000")]
+	/// Maximum number of logs in a query, at most 100000.
+	#[arg(
+		long,
+		default_value = "10000",
+		value_parser = clap::value_parser!(u32).range(1..=MAX_PAST_LOGS_LIMIT),
+	)]
 	pub max_past_logs: u32,
 
 	/// Maximum fee history cache size.
@@ -57,6 +188,11 @@ pub struct EthConfiguration {
 	#[arg(long, default_value = "1")]
 	pub target_gas_price: u64,
 
+	/// Where the block author takes the target gas price from, `ema` averaging the base fees
+	/// of the recent best blocks.
+	#[arg(long, value_enum, ignore_case = true, default_value_t = GasPriceSource::default())]
+	pub target_gas_price_source: GasPriceSource,
+
 	/// Maximum allowed gas limit will be `block.gas_limit * execute_gas_limit_multiplier`
 	/// when using eth_call/eth_estimateGas.
 	#[arg(long, default_value = "10")]
@@ -74,8 +210,18 @@ pub struct EthConfiguration {
 	#[arg(long, value_enum, ignore_case = true, default_value_t = BackendType::default())]
 	pub frontier_backend_type: BackendType,
 
-	// Sets the SQL backend's pool size.
-	#[arg(long, default_value = "100")]
+	/// Starts the selected frontier backend even though only a database of the other
+	/// backend type exists.
+	#[arg(long)]
+	pub frontier_backend_force: bool,
+
+	/// Sets the SQL backend's database file.
+	/// Defaults to `frontier.db3` in the chain's `sql` directory.
+	#[arg(long)]
+	pub frontier_sql_backend_path: Option<PathBuf>,
+
+	/// Sets the SQL backend's pool size.
+	#[arg(long, default_value = "100", value_parser = clap::value_parser!(u32).range(1..))]
 	pub frontier_sql_backend_pool_size: u32,
 
 	/// Sets the SQL backend's query timeout in number of VM ops.
@@ -90,6 +236,141 @@ pub struct EthConfiguration {
 	/// Default value is 200MB.
 	#[arg(long, default_value = "209715200")]
 	pub frontier_sql_backend_cache_size: u64,
+
+	/// Sets the SQL backend's number of rows fetched per chunk when filtering logs.
+	#[arg(long, default_value = "1000")]
+	pub frontier_sql_backend_log_fetch_chunk_size: std::num::NonZeroU32,
+
+	/// Sets the SQL backend's maximum number of addresses in a single logs filter.
+	#[arg(long, default_value = "1000")]
+	pub frontier_sql_backend_max_filter_addresses: std::num::NonZeroU32,
+
+	/// Sets the SQL backend's maximum number of alternatives per topic position in a single
+	/// logs filter.
+	#[arg(long, default_value = "1000")]
+	pub frontier_sql_backend_max_topic_alternatives: std::num::NonZeroU32,
+
+	/// Limits the SQL backend's committed write transactions per second.
+	/// Unlimited by default.
+	#[arg(long = "frontier-sql-max-write-ops-per-sec")]
+	pub frontier_sql_backend_max_write_ops_per_sec: Option<std::num::NonZeroU32>,
+
+	/// Limits the SQL indexer's state reads per second, keeping RPC state reads responsive
+	/// during a backfill. Unlimited by default.
+	#[arg(long = "frontier-sql-max-state-reads-per-sec")]
+	pub frontier_sql_backend_max_state_reads_per_sec: Option<std::num::NonZeroU32>,
+
+	/// Adapts the number of blocks whose logs the SQL indexer commits together, between 1
+	/// and 1024, to commit each batch within about this many milliseconds. Batches are of
+	/// 64 blocks by default.
+	#[arg(long = "frontier-sql-logs-batch-target-ms")]
+	pub frontier_sql_backend_logs_batch_target_ms: Option<u64>,
+
+	/// Groups the SQL indexer writes of a burst of blocks into a single transaction,
+	/// committed by the first write past this many milliseconds, or as soon as the indexer
+	/// has no more blocks queued. Each block is committed on its own by default.
+	#[arg(long = "frontier-sql-commit-interval-ms")]
+	pub frontier_sql_backend_commit_interval_ms: Option<u64>,
+
+	/// Commits the grouped SQL indexer writes once this many blocks are grouped.
+	#[arg(long = "frontier-sql-commit-max-blocks", default_value = "256")]
+	pub frontier_sql_backend_commit_max_blocks: std::num::NonZeroU32,
+
+	/// Indexes blocks and transactions only, skipping the SQL backend's logs table.
+	/// `eth_getLogs` is unavailable in this mode.
+	#[arg(long = "frontier-sql-no-logs")]
+	pub frontier_sql_backend_no_logs: bool,
+
+	/// Indexes blocks in the SQL backend only once they are canonical, and deletes the
+	/// retracted ones on re-orgs instead of keeping them as non-canonical rows.
+	#[arg(long = "frontier-sql-canonical-only")]
+	pub frontier_sql_backend_canonical_only: bool,
+
+	/// Deduplicates the SQL backend's logs by a 16 bytes hash of their position instead of
+	/// the position itself, shrinking the unique index. An existing logs table is rebuilt,
+	/// and keeps its hashed key on later starts even without this flag.
+	#[arg(long = "frontier-sql-hashed-log-key")]
+	pub frontier_sql_backend_hashed_log_key: bool,
+
+	/// Indexes the contracts seeded in the genesis state along with the genesis block of the
+	/// SQL backend.
+	#[arg(long = "frontier-sql-genesis-contracts")]
+	pub frontier_sql_backend_genesis_contracts: bool,
+
+	/// Stores the encoded receipts of the blocks indexed by the SQL backend, needed to prove
+	/// the inclusion of their logs.
+	#[arg(long = "frontier-sql-store-receipts")]
+	pub frontier_sql_backend_store_receipts: bool,
+
+	/// Prunes the SQL backend's retracted blocks once finalized this many blocks past them.
+	/// Retracted blocks are kept by default.
+	#[arg(long = "frontier-sql-retracted-block-retention")]
+	pub frontier_sql_backend_retracted_block_retention: Option<u32>,
+
+	/// Prunes the logs of the SQL backend once finalized this many blocks past their block.
+	/// Logs are kept by default.
+	#[arg(long = "frontier-sql-log-retention")]
+	pub frontier_sql_backend_log_retention: Option<u32>,
+
+	/// Contracts whose logs are never pruned by `--frontier-sql-log-retention`.
+	#[arg(long = "frontier-sql-retain-logs-of", value_delimiter = ',')]
+	pub frontier_sql_backend_retained_log_addresses: Vec<H160>,
+
+	/// How the SQL backend indexes a block with a transaction whose sender cannot be
+	/// recovered. `halt` only stops the indexer along with `--frontier-sql-exit-on-fatal`.
+	#[arg(
+		long = "frontier-sql-malformed-signature-policy",
+		value_enum,
+		ignore_case = true,
+		default_value_t = MalformedSignaturePolicy::default()
+	)]
+	pub frontier_sql_backend_malformed_signature_policy: MalformedSignaturePolicy,
+
+	/// Stops the SQL indexer on unrecoverable errors, such as constraint violations, instead
+	/// of indexing on. The node then shuts down with an error, to be restarted by its
+	/// supervisor.
+	#[arg(long = "frontier-sql-exit-on-fatal")]
+	pub frontier_sql_exit_on_fatal: bool,
+
+	/// Coalesces the re-orgs reported by the SQL indexer within this many milliseconds of a
+	/// first one into a single canonicalization. Disabled by default.
+	#[arg(long = "frontier-sql-reorg-debounce-ms", default_value = "0")]
+	pub frontier_sql_reorg_debounce_ms: u64,
+
+	/// Makes the SQL indexer poll the client's best and finalized blocks every this many
+	/// milliseconds instead of following the import and finality notifications.
+	#[arg(long = "frontier-sql-poll-interval-ms")]
+	pub frontier_sql_poll_interval_ms: Option<u64>,
+
+	/// Makes the SQL indexer backfill every gap of its indexed block range on each check,
+	/// instead of the lowest one only.
+	#[arg(long = "frontier-sql-index-all-gaps")]
+	pub frontier_sql_index_all_gaps: bool,
+
+	/// Makes the SQL indexer keep the blocks and logs of this many best blocks only, pruning
+	/// the older ones. Everything is kept by default.
+	#[arg(long = "frontier-sql-prune-depth")]
+	pub frontier_sql_prune_depth: Option<u32>,
+
+	/// Makes the SQL indexer canonicalize the re-orgs retracting more blocks than this in
+	/// bounded chunks, instead of in a single transaction.
+	#[arg(
+		long = "frontier-sql-max-reorg-depth",
+		default_value_t = fc_mapping_sync::sql::DEFAULT_MAX_REORG_DEPTH,
+		value_parser = clap::value_parser!(u32).range(1..),
+	)]
+	pub frontier_sql_max_reorg_depth: u32,
+
+	/// Makes the SQL indexer wait this many milliseconds after the node starts before
+	/// indexing. Starts at once by default.
+	#[arg(long = "frontier-sql-initial-delay-ms", default_value = "0")]
+	pub frontier_sql_initial_delay_ms: u64,
+
+	/// Serves a WebSocket feed of the blocks indexed by the SQL backend on this address.
+	/// Disabled by default.
+	#[cfg(feature = "ws-feed")]
+	#[arg(long = "frontier-sql-ws-feed-addr")]
+	pub frontier_sql_ws_feed_addr: Option<std::net::SocketAddr>,
 }
 
 pub struct FrontierPartialComponents {
@@ -125,6 +406,52 @@ where
 {
 }
 
+/// Builds the target gas price provider of the dynamic-fee inherent. The `ema` source is fed
+/// the base fees of the new best blocks by a background task.
+pub fn target_gas_price_provider<B, RA, HF>(
+	task_manager: &TaskManager,
+	client: Arc<FullClient<B, RA, HF>>,
+	eth_config: &EthConfiguration,
+) -> Arc<dyn GasPriceProvider>
+where
+	B: BlockT<Hash = H256>,
+	RA: ConstructRuntimeApi<B, FullClient<B, RA, HF>>,
+	RA: Send + Sync + 'static,
+	RA::RuntimeApi: EthCompatRuntimeApiCollection<B>,
+	HF: HostFunctions + 'static,
+{
+	let target_gas_price = U256::from(eth_config.target_gas_price);
+	match eth_config.target_gas_price_source {
+		GasPriceSource::Fixed => Arc::new(FixedGasPrice(target_gas_price)),
+		GasPriceSource::Ema => {
+			let ema = Arc::new(EmaGasPrice::new(
+				target_gas_price,
+				TARGET_GAS_PRICE_EMA_PERIOD,
+			));
+			let observer = ema.clone();
+			let notifications = client.import_notification_stream();
+			task_manager.spawn_handle().spawn(
+				"frontier-target-gas-price",
+				Some("frontier"),
+				notifications
+					.filter(|notification| future::ready(notification.is_new_best))
+					.for_each(move |notification| {
+						match client.runtime_api().gas_price(notification.hash) {
+							Ok(base_fee) => observer.observe(base_fee),
+							Err(err) => log::debug!(
+								target: "frontier",
+								"Failed to read the base fee of {}: {err}",
+								notification.hash,
+							),
+						}
+						future::ready(())
+					}),
+			);
+			ema
+		}
+	}
+}
+
 pub async fn spawn_frontier_tasks<B, RA, HF>(
 	task_manager: &TaskManager,
 	client: Arc<FullClient<B, RA, HF>>,
@@ -140,6 +467,9 @@ pub async fn spawn_frontier_tasks<B, RA, HF>(
 			fc_mapping_sync::EthereumBlockNotification<B>,
 		>,
 	>,
+	eth_config: &EthConfiguration,
+	prometheus_registry: Option<prometheus_endpoint::Registry>,
+	sync_state: tokio::sync::watch::Sender<fc_mapping_sync::sql::SyncState>,
 ) where
 	B: BlockT<Hash = H256>,
 	RA: ConstructRuntimeApi<B, FullClient<B, RA, HF>>,
@@ -170,6 +500,17 @@ pub async fn spawn_frontier_tasks<B, RA, HF>(
 			);
 		}
 		fc_db::Backend::Sql(b) => {
+			#[cfg(feature = "ws-feed")]
+			if let Some(addr) = eth_config.frontier_sql_ws_feed_addr {
+				let listener = tokio::net::TcpListener::bind(addr)
+					.await
+					.unwrap_or_else(|err| panic!("failed binding sql ws feed to {addr}: {err:?}"));
+				fc_mapping_sync::sql::ws_feed::spawn_ws_feed(
+					listener,
+					b.clone(),
+					pubsub_notification_sinks.clone(),
+				);
+			}
 			task_manager.spawn_essential_handle().spawn_blocking(
 				"frontier-mapping-sync-worker",
 				Some("frontier"),
@@ -181,10 +522,27 @@ pub async fn spawn_frontier_tasks<B, RA, HF>(
 					fc_mapping_sync::sql::SyncWorkerConfig {
 						read_notification_timeout: Duration::from_secs(30),
 						check_indexed_blocks_interval: Duration::from_secs(60),
+						exit_on_fatal: eth_config.frontier_sql_exit_on_fatal,
+						reorg_debounce: Duration::from_millis(
+							eth_config.frontier_sql_reorg_debounce_ms,
+						),
+						poll_interval: eth_config
+							.frontier_sql_poll_interval_ms
+							.map(Duration::from_millis),
+						index_all_gaps: eth_config.frontier_sql_index_all_gaps,
+						prune_depth: eth_config.frontier_sql_prune_depth,
+						max_reorg_depth: eth_config.frontier_sql_max_reorg_depth,
+						initial_delay: Duration::from_millis(
+							eth_config.frontier_sql_initial_delay_ms,
+						),
 					},
 					fc_mapping_sync::SyncStrategy::Parachain,
 					sync,
 					pubsub_notification_sinks,
+					prometheus_registry,
+					Some(sync_state),
+					// The task manager drops the worker on shutdown.
+					future::pending(),
 				),
 			);
 		}
@@ -213,3 +571,85 @@ pub async fn spawn_frontier_tasks<B, RA, HF>(
 		),
 	);
 }
+
+#[cfg(test)]
+mod tests {
+	use clap::Parser;
+
+	use super::*;
+
+	#[test]
+	fn sql_backend_path_and_pool_size_are_parsed() {
+		let eth_config = EthConfiguration::try_parse_from([
+			"frontier-template-node",
+			"--frontier-sql-backend-path",
+			"/tmp/frontier/index.db3",
+			"--frontier-sql-backend-pool-size",
+			"8",
+		])
+		.unwrap();
+		assert_eq!(
+			eth_config.frontier_sql_backend_path,
+			Some(PathBuf::from("/tmp/frontier/index.db3"))
+		);
+		assert_eq!(eth_config.frontier_sql_backend_pool_size, 8);
+
+		let eth_config = EthConfiguration::try_parse_from(["frontier-template-node"]).unwrap();
+		assert_eq!(eth_config.frontier_sql_backend_path, None);
+		assert_eq!(eth_config.frontier_sql_backend_pool_size, 100);
+
+		assert!(EthConfiguration::try_parse_from([
+			"frontier-template-node",
+			"--frontier-sql-backend-pool-size",
+			"0",
+		])
+		.is_err());
+	}
+
+	#[test]
+	fn switching_backend_type_is_refused() {
+		let tmp = tempfile::tempdir().expect("create a temporary directory");
+		let db_config_dir = tmp.path();
+		let sql_db_path = db_config_dir.join("sql").join("frontier.db3");
+
+		// A fresh node may start with either backend.
+		assert!(check_backend_type(BackendType::KeyValue, db_config_dir, &sql_db_path).is_ok());
+		assert!(check_backend_type(BackendType::Sql, db_config_dir, &sql_db_path).is_ok());
+
+		// Started with the key-value backend, then switched to the SQL one.
+		std::fs::create_dir_all(fc_db::kv::frontier_database_dir(db_config_dir, "db")).unwrap();
+		assert!(check_backend_type(BackendType::KeyValue, db_config_dir, &sql_db_path).is_ok());
+		assert!(check_backend_type(BackendType::Sql, db_config_dir, &sql_db_path).is_err());
+
+		// Once both exist, the backends were knowingly run side by side.
+		std::fs::create_dir_all(sql_db_path.parent().unwrap()).unwrap();
+		std::fs::write(&sql_db_path, []).unwrap();
+		assert!(check_backend_type(BackendType::Sql, db_config_dir, &sql_db_path).is_ok());
+
+		// Started with the SQL backend, then switched to the key-value one.
+		std::fs::remove_dir_all(fc_db::kv::frontier_database_dir(db_config_dir, "")).unwrap();
+		assert!(check_backend_type(BackendType::Sql, db_config_dir, &sql_db_path).is_ok());
+		assert!(check_backend_type(BackendType::KeyValue, db_config_dir, &sql_db_path).is_err());
+	}
+
+	#[test]
+	fn ema_gas_price_follows_the_base_fees() {
+		let ema = EmaGasPrice::new(U256::from(1_000), 9);
+		assert_eq!(ema.target_gas_price(), U256::from(1_000));
+
+		// Each base fee weighs 2 / (9 + 1) in the average.
+		ema.observe(U256::from(2_000));
+		assert_eq!(ema.target_gas_price(), U256::from(1_200));
+		ema.observe(U256::from(2_000));
+		assert_eq!(ema.target_gas_price(), U256::from(1_360));
+
+		// A steady base fee is converged to, up to the integer rounding.
+		for _ in 0..100 {
+			ema.observe(U256::from(2_000));
+		}
+		assert_eq!(ema.target_gas_price(), U256::from(1_996));
+
+		ema.observe(U256::zero());
+		assert_eq!(ema.target_gas_price(), U256::from(1_596));
+	}
+}
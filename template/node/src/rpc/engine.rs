@@ -0,0 +1,143 @@
+use futures::{
+	channel::{mpsc, oneshot},
+	SinkExt,
+};
+use jsonrpsee::{core::RpcResult, proc_macros::rpc};
+// Substrate
+use sc_consensus_manual_seal::rpc::EngineCommand;
+// Frontier
+use fc_rpc::{err, internal_err};
+// Runtime
+use frontier_template_runtime::Hash;
+
+/// The most blocks sealed by a single `engine_createBlocks` call.
+const MAX_CREATED_BLOCKS: u32 = 1000;
+
+/// Manual seal extensions for test harnesses.
+#[rpc(server)]
+pub trait EngineApi {
+	/// Seals `count` blocks in sequence, each one on top of the previous one, and returns
+	/// their hashes in order. At most [`MAX_CREATED_BLOCKS`] blocks are sealed per call.
+	#[method(name = "engine_createBlocks")]
+	async fn create_blocks(
+		&self,
+		count: u32,
+		create_empty: bool,
+		finalize: bool,
+	) -> RpcResult<Vec<Hash>>;
+}
+
+/// Sends the seal commands of [`EngineApi`] to the manual seal authorship task.
+pub struct Engine {
+	command_sink: mpsc::Sender<EngineCommand<Hash>>,
+}
+
+impl Engine {
+	pub fn new(command_sink: mpsc::Sender<EngineCommand<Hash>>) -> Self {
+		Self { command_sink }
+	}
+}
+
+#[jsonrpsee::core::async_trait]
+impl EngineApiServer for Engine {
+	async fn create_blocks(
+		&self,
+		count: u32,
+		create_empty: bool,
+		finalize: bool,
+	) -> RpcResult<Vec<Hash>> {
+		if count > MAX_CREATED_BLOCKS {
+			return Err(err(
+				jsonrpsee::types::error::INVALID_PARAMS_CODE,
+				format!("cannot create more than {MAX_CREATED_BLOCKS} blocks at once, got {count}"),
+				None,
+			));
+		}
+		let mut sink = self.command_sink.clone();
+		let mut hashes = vec![];
+		for _ in 0..count {
+			let (sender, receiver) = oneshot::channel();
+			sink.send(EngineCommand::SealNewBlock {
+				create_empty,
+				finalize,
+				parent_hash: None,
+				sender: Some(sender),
+			})
+			.await
+			.map_err(|err| internal_err(format!("Failed sending the seal command: {err}")))?;
+			// The blocks sealed so far stay on chain if a later one fails.
+			let created = receiver
+				.await
+				.map_err(|_| internal_err("The seal command was dropped"))??;
+			hashes.push(created.hash);
+		}
+		Ok(hashes)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use futures::{executor, StreamExt};
+	use sc_consensus::ImportedAux;
+	use sc_consensus_manual_seal::CreatedBlock;
+
+	#[test]
+	fn create_blocks_seals_the_requested_number_of_blocks() {
+		let (command_sink, mut commands) = mpsc::channel(1);
+		let module = Engine::new(command_sink).into_rpc();
+
+		// Seals a block per command, numbering their hashes.
+		let author = async move {
+			let mut sealed = vec![];
+			for number in 1..=5u64 {
+				let Some(EngineCommand::SealNewBlock {
+					create_empty,
+					finalize,
+					sender: Some(sender),
+					..
+				}) = commands.next().await
+				else {
+					panic!("expected a seal command");
+				};
+				sealed.push((create_empty, finalize));
+				let hash = Hash::from_low_u64_be(number);
+				let _ = sender.send(Ok(CreatedBlock {
+					hash,
+					aux: ImportedAux::default(),
+					proof_size: 0,
+				}));
+			}
+			sealed
+		};
+		let (hashes, sealed) = executor::block_on(futures::future::join(
+			module.call::<_, Vec<Hash>>("engine_createBlocks", (5, true, false)),
+			author,
+		));
+
+		assert_eq!(
+			hashes.expect("blocks created"),
+			(1..=5u64).map(Hash::from_low_u64_be).collect::<Vec<_>>()
+		);
+		assert_eq!(sealed, vec![(true, false); 5]);
+	}
+
+	#[test]
+	fn create_blocks_rejects_counts_above_the_cap() {
+		let (command_sink, mut commands) = mpsc::channel(1);
+		let module = Engine::new(command_sink).into_rpc();
+
+		let result = executor::block_on(
+			module
+				.call::<_, Vec<Hash>>("engine_createBlocks", (MAX_CREATED_BLOCKS + 1, true, false)),
+		);
+
+		let Err(jsonrpsee::core::server::MethodsError::JsonRpc(error)) = result else {
+			panic!("expected a call error, got {result:?}");
+		};
+		assert_eq!(error.code(), jsonrpsee::types::error::INVALID_PARAMS_CODE);
+		// Nothing is sealed.
+		assert!(commands.try_next().is_err());
+	}
+}
@@ -23,6 +23,7 @@ use sp_runtime::traits::Block as BlockT;
 // Runtime
 use frontier_template_runtime::{AccountId, Balance, Hash, Nonce};
 
+mod engine;
 mod eth;
 pub use self::eth::{create_eth, EthDeps};
 
@@ -36,6 +37,10 @@ pub struct FullDeps<B: BlockT, C, P, A: ChainApi, CT, CIDP> {
 	pub deny_unsafe: DenyUnsafe,
 	/// Manual seal command sink
 	pub command_sink: Option<mpsc::Sender<EngineCommand<Hash>>>,
+	/// Whether blocks are only sealed on request, enabling `engine_createBlocks`.
+	pub manual_sealing: bool,
+	/// Sync status of the Frontier mapping backend.
+	pub frontier: fc_rpc::Frontier,
 	/// Ethereum-compatibility specific dependencies.
 	pub eth: EthDeps<B, C, P, A, CT, CIDP>,
 }
@@ -80,23 +85,32 @@ where
 	CIDP: CreateInherentDataProviders<B, ()> + Send + 'static,
 	CT: fp_rpc::ConvertTransaction<<B as BlockT>::Extrinsic> + Send + Sync + 'static,
 {
+	use fc_rpc::FrontierApiServer;
 	use pallet_transaction_payment_rpc::{TransactionPayment, TransactionPaymentApiServer};
 	use sc_consensus_manual_seal::rpc::{ManualSeal, ManualSealApiServer};
 	use substrate_frame_rpc_system::{System, SystemApiServer};
 
+	use self::engine::{Engine, EngineApiServer};
+
 	let mut io = RpcModule::new(());
 	let FullDeps {
 		client,
 		pool,
 		deny_unsafe,
 		command_sink,
+		manual_sealing,
+		frontier,
 		eth,
 	} = deps;
 
 	io.merge(System::new(client.clone(), pool, deny_unsafe).into_rpc())?;
 	io.merge(TransactionPayment::new(client).into_rpc())?;
+	io.merge(frontier.into_rpc())?;
 
 	if let Some(command_sink) = command_sink {
+		if manual_sealing {
+			io.merge(Engine::new(command_sink.clone()).into_rpc())?;
+		}
 		io.merge(
 			// We provide the rpc handler with the sending end of the channel to allow the rpc
 			// send EngineCommands to the background block authorship task.
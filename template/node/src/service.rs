@@ -28,8 +28,9 @@ use crate::{
 	cli::Sealing,
 	client::{BaseRuntimeApiCollection, FullBackend, FullClient, RuntimeApiCollection},
 	eth::{
-		new_frontier_partial, spawn_frontier_tasks, BackendType, EthCompatRuntimeApiCollection,
-		FrontierBackend, FrontierBlockImport, FrontierPartialComponents, StorageOverride,
+		check_backend_type, new_frontier_partial, spawn_frontier_tasks, sql_db_path,
+		target_gas_price_provider, BackendType, EthCompatRuntimeApiCollection, FrontierBackend,
+		FrontierBlockImport, FrontierPartialComponents, GasPriceProvider, StorageOverride,
 		StorageOverrideHandler,
 	},
 };
@@ -129,6 +130,15 @@ where
 	)?;
 
 	let storage_override = Arc::new(StorageOverrideHandler::<B, _, _>::new(client.clone()));
+	let sql_db_path = sql_db_path(config, eth_config)?;
+	if !eth_config.frontier_backend_force {
+		check_backend_type(
+			eth_config.frontier_backend_type,
+			&db_config_dir(config),
+			&sql_db_path,
+		)
+		.map_err(ServiceError::Other)?;
+	}
 	let frontier_backend = match eth_config.frontier_backend_type {
 		BackendType::KeyValue => FrontierBackend::KeyValue(Arc::new(fc_db::kv::Backend::open(
 			Arc::clone(&client),
@@ -136,24 +146,97 @@ where
 			&db_config_dir(config),
 		)?)),
 		BackendType::Sql => {
-			let db_path = db_config_dir(config).join("sql");
-			std::fs::create_dir_all(&db_path).expect("failed creating sql db directory");
+			if let Some(db_dir) = sql_db_path.parent() {
+				std::fs::create_dir_all(db_dir).expect("failed creating sql db directory");
+			}
 			let backend = futures::executor::block_on(fc_db::sql::Backend::new(
 				fc_db::sql::BackendConfig::Sqlite(fc_db::sql::SqliteBackendConfig {
-					path: Path::new("sqlite:///")
-						.join(db_path)
-						.join("frontier.db3")
-						.to_str()
-						.unwrap(),
+					path: Path::new("sqlite:///").join(&sql_db_path).to_str().unwrap(),
 					create_if_missing: true,
 					thread_count: eth_config.frontier_sql_backend_thread_count,
 					cache_size: eth_config.frontier_sql_backend_cache_size,
+					journal_mode: None,
+					synchronous: None,
+					busy_timeout: None,
+					index_logs: !eth_config.frontier_sql_backend_no_logs,
 				}),
 				eth_config.frontier_sql_backend_pool_size,
 				std::num::NonZeroU32::new(eth_config.frontier_sql_backend_num_ops_timeout),
 				storage_override.clone(),
 			))
 			.unwrap_or_else(|err| panic!("failed creating sql backend: {:?}", err));
+			let backend = backend
+				.with_log_fetch_chunk_size(eth_config.frontier_sql_backend_log_fetch_chunk_size);
+			let backend = backend
+				.with_max_filter_addresses(eth_config.frontier_sql_backend_max_filter_addresses);
+			let backend = backend.with_max_topic_alternatives(
+				eth_config.frontier_sql_backend_max_topic_alternatives,
+			);
+			let backend = match eth_config.frontier_sql_backend_max_write_ops_per_sec {
+				Some(ops_per_sec) => backend.with_max_write_ops_per_sec(ops_per_sec),
+				None => backend,
+			};
+			let backend = match eth_config.frontier_sql_backend_max_state_reads_per_sec {
+				Some(reads_per_sec) => backend.with_max_state_reads_per_sec(reads_per_sec),
+				None => backend,
+			};
+			let backend = match eth_config.frontier_sql_backend_logs_batch_target_ms {
+				Some(target_ms) => {
+					backend.with_logs_batch_size(fc_db::sql::LogsBatchSize::Adaptive {
+						min: 1,
+						max: 1024,
+						target: Duration::from_millis(target_ms),
+					})
+				}
+				None => backend,
+			};
+			let backend = match eth_config.frontier_sql_backend_commit_interval_ms {
+				Some(interval_ms) => backend.with_commit_interval(
+					Duration::from_millis(interval_ms),
+					eth_config.frontier_sql_backend_commit_max_blocks,
+				),
+				None => backend,
+			};
+			let backend = if eth_config.frontier_sql_backend_canonical_only {
+				backend.canonical_only()
+			} else {
+				backend
+			};
+			let backend = if eth_config.frontier_sql_backend_genesis_contracts {
+				backend.with_genesis_contracts()
+			} else {
+				backend
+			};
+			let backend = if eth_config.frontier_sql_backend_store_receipts {
+				backend.with_receipt_storage()
+			} else {
+				backend
+			};
+			let backend = match eth_config.frontier_sql_backend_retracted_block_retention {
+				Some(blocks) => backend.with_retracted_block_retention(blocks),
+				None => backend,
+			};
+			let backend = match eth_config.frontier_sql_backend_log_retention {
+				Some(blocks) => backend.with_log_retention(blocks),
+				None => backend,
+			};
+			let backend = eth_config
+				.frontier_sql_backend_retained_log_addresses
+				.iter()
+				.fold(backend, |backend, address| {
+					backend.with_contract_log_retention(*address, None)
+				});
+			let backend = backend.with_malformed_signature_policy(
+				eth_config
+					.frontier_sql_backend_malformed_signature_policy
+					.into(),
+			);
+			let backend = if eth_config.frontier_sql_backend_hashed_log_key {
+				futures::executor::block_on(backend.with_log_key(fc_db::sql::LogKey::Hashed))
+					.unwrap_or_else(|err| panic!("failed rebuilding the sql logs table: {:?}", err))
+			} else {
+				backend
+			};
 			FrontierBackend::Sql(Arc::new(backend))
 		}
 	};
@@ -276,6 +359,7 @@ pub async fn new_full<B, RA, HF, NB>(
 	mut config: Configuration,
 	eth_config: EthConfiguration,
 	sealing: Option<Sealing>,
+	sealing_interval: Duration,
 ) -> Result<TaskManager, ServiceError>
 where
 	B: BlockT<Hash = H256>,
@@ -309,6 +393,7 @@ where
 		fee_history_cache,
 		fee_history_cache_limit,
 	} = new_frontier_partial(&eth_config)?;
+	let target_gas_price = target_gas_price_provider(&task_manager, client.clone(), &eth_config);
 
 	let mut net_config =
 		sc_network::config::FullNetworkConfiguration::<_, _, NB>::new(&config.network);
@@ -398,6 +483,9 @@ where
 	> = Default::default();
 	let pubsub_notification_sinks = Arc::new(pubsub_notification_sinks);
 
+	// Progress of the SQL indexer, reported by its worker and served over RPC.
+	let (sync_state_tx, sync_state_rx) = tokio::sync::watch::channel(Default::default());
+
 	// for ethereum-compatibility rpc.
 	config.rpc_id_provider = Some(Box::new(fc_rpc::EthereumSubIdProvider));
 
@@ -425,17 +513,20 @@ where
 		));
 
 		let slot_duration = sc_consensus_aura::slot_duration(&*client)?;
-		let target_gas_price = eth_config.target_gas_price;
-		let pending_create_inherent_data_providers = move |_, ()| async move {
-			let current = sp_timestamp::InherentDataProvider::from_system_time();
-			let next_slot = current.timestamp().as_millis() + slot_duration.as_millis();
-			let timestamp = sp_timestamp::InherentDataProvider::new(next_slot.into());
-			let slot = sp_consensus_aura::inherents::InherentDataProvider::from_timestamp_and_slot_duration(
-				*timestamp,
-				slot_duration,
-			);
-			let dynamic_fee = fp_dynamic_fee::InherentDataProvider(U256::from(target_gas_price));
-			Ok((slot, timestamp, dynamic_fee))
+		let target_gas_price = target_gas_price.clone();
+		let pending_create_inherent_data_providers = move |_, ()| {
+			let dynamic_fee =
+				fp_dynamic_fee::InherentDataProvider(target_gas_price.target_gas_price());
+			async move {
+				let current = sp_timestamp::InherentDataProvider::from_system_time();
+				let next_slot = current.timestamp().as_millis() + slot_duration.as_millis();
+				let timestamp = sp_timestamp::InherentDataProvider::new(next_slot.into());
+				let slot = sp_consensus_aura::inherents::InherentDataProvider::from_timestamp_and_slot_duration(
+					*timestamp,
+					slot_duration,
+				);
+				Ok((slot, timestamp, dynamic_fee))
+			}
 		};
 
 		Box::new(move |deny_unsafe, subscription_task_executor| {
@@ -471,6 +562,11 @@ where
 				} else {
 					None
 				},
+				manual_sealing: matches!(sealing, Some(Sealing::Manual)),
+				frontier: match &*frontier_backend {
+					fc_db::Backend::KeyValue(_) => fc_rpc::Frontier::KeyValue,
+					fc_db::Backend::Sql(_) => fc_rpc::Frontier::Sql(sync_state_rx.clone()),
+				},
 				eth: eth_deps,
 			};
 			crate::rpc::create_full(
@@ -508,6 +604,9 @@ where
 		fee_history_cache_limit,
 		sync_service.clone(),
 		pubsub_notification_sinks,
+		&eth_config,
+		prometheus_registry.clone(),
+		sync_state_tx,
 	)
 	.await;
 
@@ -515,8 +614,9 @@ where
 		// manual-seal authorship
 		if let Some(sealing) = sealing {
 			run_manual_seal_authorship(
-				&eth_config,
+				target_gas_price,
 				sealing,
+				sealing_interval,
 				client,
 				transaction_pool,
 				select_chain,
@@ -541,15 +641,17 @@ where
 		);
 
 		let slot_duration = sc_consensus_aura::slot_duration(&*client)?;
-		let target_gas_price = eth_config.target_gas_price;
-		let create_inherent_data_providers = move |_, ()| async move {
-			let timestamp = sp_timestamp::InherentDataProvider::from_system_time();
-			let slot = sp_consensus_aura::inherents::InherentDataProvider::from_timestamp_and_slot_duration(
-				*timestamp,
-				slot_duration,
-			);
-			let dynamic_fee = fp_dynamic_fee::InherentDataProvider(U256::from(target_gas_price));
-			Ok((slot, timestamp, dynamic_fee))
+		let create_inherent_data_providers = move |_, ()| {
+			let dynamic_fee =
+				fp_dynamic_fee::InherentDataProvider(target_gas_price.target_gas_price());
+			async move {
+				let timestamp = sp_timestamp::InherentDataProvider::from_system_time();
+				let slot = sp_consensus_aura::inherents::InherentDataProvider::from_timestamp_and_slot_duration(
+					*timestamp,
+					slot_duration,
+				);
+				Ok((slot, timestamp, dynamic_fee))
+			}
 		};
 
 		let aura = sc_consensus_aura::start_aura::<AuraPair, _, _, _, _, _, _, _, _, _, _>(
@@ -631,8 +733,9 @@ where
 }
 
 fn run_manual_seal_authorship<B, RA, HF>(
-	eth_config: &EthConfiguration,
+	target_gas_price: Arc<dyn GasPriceProvider>,
 	sealing: Sealing,
+	sealing_interval: Duration,
 	client: Arc<FullClient<B, RA, HF>>,
 	transaction_pool: Arc<FullPool<B, FullClient<B, RA, HF>>>,
 	select_chain: FullSelectChain<B>,
@@ -687,16 +790,22 @@ where
 		}
 	}
 
-	let target_gas_price = eth_config.target_gas_price;
-	let create_inherent_data_providers = move |_, ()| async move {
-		let timestamp = MockTimestampInherentDataProvider;
-		let dynamic_fee = fp_dynamic_fee::InherentDataProvider(U256::from(target_gas_price));
-		Ok((timestamp, dynamic_fee))
+	let create_inherent_data_providers = move |_, ()| {
+		let dynamic_fee = fp_dynamic_fee::InherentDataProvider(target_gas_price.target_gas_price());
+		async move {
+			let timestamp = MockTimestampInherentDataProvider;
+			Ok((timestamp, dynamic_fee))
+		}
+	};
+
+	let commands_stream = match sealing {
+		Sealing::Interval => interval_sealing_commands(commands_stream, sealing_interval).boxed(),
+		_ => commands_stream.boxed(),
 	};
 
 	let manual_seal = match sealing {
-		Sealing::Manual => future::Either::Left(sc_consensus_manual_seal::run_manual_seal(
-			sc_consensus_manual_seal::ManualSealParams {
+		Sealing::Manual | Sealing::Interval => future::Either::Left(
+			sc_consensus_manual_seal::run_manual_seal(sc_consensus_manual_seal::ManualSealParams {
 				block_import,
 				env: proposer_factory,
 				client,
@@ -705,18 +814,33 @@ where
 				select_chain,
 				consensus_data_provider: None,
 				create_inherent_data_providers,
-			},
+			}),
+		),
+		Sealing::Instant => future::Either::Right(future::Either::Left(
+			sc_consensus_manual_seal::run_instant_seal(
+				sc_consensus_manual_seal::InstantSealParams {
+					block_import,
+					env: proposer_factory,
+					client,
+					pool: transaction_pool,
+					select_chain,
+					consensus_data_provider: None,
+					create_inherent_data_providers,
+				},
+			),
 		)),
-		Sealing::Instant => future::Either::Right(sc_consensus_manual_seal::run_instant_seal(
-			sc_consensus_manual_seal::InstantSealParams {
-				block_import,
-				env: proposer_factory,
-				client,
-				pool: transaction_pool,
-				select_chain,
-				consensus_data_provider: None,
-				create_inherent_data_providers,
-			},
+		Sealing::InstantFinalize => future::Either::Right(future::Either::Right(
+			sc_consensus_manual_seal::run_instant_seal_and_finalize(
+				sc_consensus_manual_seal::InstantSealParams {
+					block_import,
+					env: proposer_factory,
+					client,
+					pool: transaction_pool,
+					select_chain,
+					consensus_data_provider: None,
+					create_inherent_data_providers,
+				},
+			),
 		)),
 	};
 
@@ -727,13 +851,57 @@ where
 	Ok(())
 }
 
+/// Adds a block seal request to the manual seal commands every `interval`. Sealing a block
+/// through the rpc method postpones the next periodic seal by a full interval.
+fn interval_sealing_commands<Hash: Send + 'static>(
+	commands_stream: mpsc::Receiver<sc_consensus_manual_seal::rpc::EngineCommand<Hash>>,
+	interval: Duration,
+) -> impl Stream<Item = sc_consensus_manual_seal::rpc::EngineCommand<Hash>> {
+	use sc_consensus_manual_seal::rpc::EngineCommand;
+	use tokio::time::{sleep_until, Instant};
+
+	let deadline = Instant::now() + interval;
+	stream::unfold(
+		(commands_stream, deadline),
+		move |(mut commands_stream, deadline)| async move {
+			let command = {
+				let sleep = sleep_until(deadline);
+				futures::pin_mut!(sleep);
+				match future::select(commands_stream.next(), sleep).await {
+					future::Either::Left((command, _)) => Some(command?),
+					future::Either::Right(_) => None,
+				}
+			};
+			match command {
+				Some(command @ EngineCommand::SealNewBlock { .. }) => {
+					Some((command, (commands_stream, Instant::now() + interval)))
+				}
+				Some(command) => Some((command, (commands_stream, deadline))),
+				None => {
+					let command = EngineCommand::SealNewBlock {
+						create_empty: true,
+						finalize: false,
+						parent_hash: None,
+						sender: None,
+					};
+					Some((command, (commands_stream, Instant::now() + interval)))
+				}
+			}
+		},
+	)
+}
+
 pub async fn build_full(
 	config: Configuration,
 	eth_config: EthConfiguration,
 	sealing: Option<Sealing>,
+	sealing_interval: Duration,
 ) -> Result<TaskManager, ServiceError> {
 	new_full::<Block, RuntimeApi, HostFunctions, sc_network::NetworkWorker<_, _>>(
-		config, eth_config, sealing,
+		config,
+		eth_config,
+		sealing,
+		sealing_interval,
 	)
 	.await
 }
@@ -380,6 +380,8 @@ impl pallet_ethereum::Config for Runtime {
 	type StateRoot = pallet_ethereum::IntermediateStateRoot<Self>;
 	type PostLogContent = PostBlockAndTxnHashes;
 	type ExtraDataLength = ConstU32<30>;
+	type MaxFutureNonceGap = ();
+	type ChainIdAt = ();
 }
 
 parameter_types! {
@@ -773,6 +775,10 @@ impl_runtime_apis! {
 			pallet_evm::AccountCodes::<Runtime>::get(address)
 		}
 
+		fn is_contract(address: H160) -> bool {
+			<pallet_evm::Pallet<Runtime>>::is_contract(&address)
+		}
+
 		fn author() -> H160 {
 			<pallet_evm::Pallet<Runtime>>::find_author()
 		}
@@ -994,6 +1000,22 @@ impl_runtime_apis! {
 				pallet_ethereum::CurrentTransactionStatuses::<Runtime>::get()
 			)
 		}
+
+		fn evm_storage_root(max_entries: u32) -> Option<H256> {
+			<pallet_evm::Pallet<Runtime>>::evm_storage_root(max_entries)
+		}
+
+		fn active_precompiles() -> Vec<H160> {
+			FrontierPrecompiles::<Runtime>::used_addresses().to_vec()
+		}
+
+		fn current_block_gas_used() -> U256 {
+			Ethereum::current_block_gas_used()
+		}
+
+		fn current_transactions_gas_used() -> Vec<U256> {
+			Ethereum::current_transactions_gas_used()
+		}
 	}
 
 	impl fp_rpc::ConvertTransactionRuntimeApi<Block> for Runtime {
@@ -1047,7 +1069,15 @@ impl_runtime_apis! {
 
 #[cfg(test)]
 mod tests {
-	use super::{Runtime, WeightPerGas};
+	use super::{Block, Runtime, WeightPerGas, H160};
+	use fp_rpc::runtime_decl_for_ethereum_runtime_rpc_api::EthereumRuntimeRPCApiV9;
+
+	#[test]
+	fn active_precompiles_include_ecrecover() {
+		let precompiles = <Runtime as EthereumRuntimeRPCApiV9<Block>>::active_precompiles();
+		assert!(precompiles.contains(&H160::from_low_u64_be(1)));
+	}
+
 	#[test]
 	fn configured_base_extrinsic_weight_is_evm_compatible() {
 		let min_ethereum_transaction_weight = WeightPerGas::get() * 21_000;
@@ -1,6 +1,6 @@
 import { assert, expect } from "chai";
 import { step } from "mocha-steps";
-import { ETH_BLOCK_GAS_LIMIT, GENESIS_ACCOUNT, GENESIS_ACCOUNT_PRIVATE_KEY } from "./config";
+import { ETH_BLOCK_GAS_LIMIT, FIRST_CONTRACT_ADDRESS, GENESIS_ACCOUNT, GENESIS_ACCOUNT_PRIVATE_KEY } from "./config";
 
 import { describeWithFrontier, customRequest, createAndFinalizeBlock } from "./util";
 import { AbiItem } from "web3-utils";
@@ -11,6 +11,7 @@ import ForceGasLimit from "../build/contracts/ForceGasLimit.json";
 
 const TEST_CONTRACT_BYTECODE = Test.bytecode;
 const TEST_CONTRACT_DEPLOYED_BYTECODE = Test.deployedBytecode;
+const TEST_CONTRACT_ABI = Test.abi as AbiItem[];
 
 const FORCE_GAS_CONTRACT_BYTECODE = ForceGasLimit.bytecode;
 const FORCE_GAS_CONTRACT_ABI = ForceGasLimit.abi as AbiItem[];
@@ -264,3 +265,51 @@ describeWithFrontier("Frontier RPC (RPC execution)", (context) => {
 		expect(result.error.data).to.match(/^Ambiguous value for `data` and `input`/);
 	});
 });
+
+describeWithFrontier("Frontier RPC (RPC execution against a contract)", (context) => {
+	before("create the contract", async function () {
+		this.timeout(15000);
+		const tx = await context.web3.eth.accounts.signTransaction(
+			{
+				from: GENESIS_ACCOUNT,
+				data: TEST_CONTRACT_BYTECODE,
+				value: "0x00",
+				gasPrice: "0x3B9ACA00",
+				gas: "0x100000",
+			},
+			GENESIS_ACCOUNT_PRIVATE_KEY
+		);
+		await customRequest(context.web3, "eth_sendRawTransaction", [tx.rawTransaction]);
+		await createAndFinalizeBlock(context.web3);
+	});
+
+	// Every runtime API version from 5 on is dispatched to the latest `call`.
+	step("should call a contract method on the current runtime API", async function () {
+		const contract = new context.web3.eth.Contract(TEST_CONTRACT_ABI, FIRST_CONTRACT_ADDRESS);
+		const result = await customRequest(context.web3, "eth_call", [
+			{
+				from: GENESIS_ACCOUNT,
+				to: FIRST_CONTRACT_ADDRESS,
+				data: contract.methods.multiply(3).encodeABI(),
+			},
+		]);
+
+		expect(result.error).to.be.undefined;
+		expect(context.web3.utils.hexToNumber(result.result)).to.be.equal(21);
+	});
+
+	step("should call a contract method with an access list", async function () {
+		const contract = new context.web3.eth.Contract(TEST_CONTRACT_ABI, FIRST_CONTRACT_ADDRESS);
+		const result = await customRequest(context.web3, "eth_call", [
+			{
+				from: GENESIS_ACCOUNT,
+				to: FIRST_CONTRACT_ADDRESS,
+				data: contract.methods.multiply(3).encodeABI(),
+				accessList: [{ address: FIRST_CONTRACT_ADDRESS, storageKeys: [] }],
+			},
+		]);
+
+		expect(result.error).to.be.undefined;
+		expect(context.web3.utils.hexToNumber(result.result)).to.be.equal(21);
+	});
+});
@@ -0,0 +1,19 @@
+import { expect } from "chai";
+import { step } from "mocha-steps";
+
+import { createAndFinalizeBlock, customRequest, describeWithFrontier, FRONTIER_BACKEND_TYPE } from "./util";
+
+describeWithFrontier("Frontier RPC (SyncStatus)", (context) => {
+	step("should report the mapping backend", async function () {
+		await createAndFinalizeBlock(context.web3);
+		const status = (await customRequest(context.web3, "frontier_syncStatus", [])).result;
+		if (FRONTIER_BACKEND_TYPE === "sql") {
+			expect(status.backend).to.equal("sql");
+			expect(status.best_block).to.be.a("number");
+			expect(status.indexed_block).to.be.at.most(status.best_block);
+			expect(status.is_synced).to.be.a("boolean");
+		} else {
+			expect(status).to.deep.equal({ backend: "key_value" });
+		}
+	});
+});
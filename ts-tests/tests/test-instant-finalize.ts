@@ -0,0 +1,37 @@
+import { expect } from "chai";
+import { step } from "mocha-steps";
+
+import { GENESIS_ACCOUNT, GENESIS_ACCOUNT_PRIVATE_KEY } from "./config";
+import { describeWithFrontier, customRequest } from "./util";
+
+describeWithFrontier(
+	"Frontier RPC (InstantFinalize sealing)",
+	(context) => {
+		const TEST_ACCOUNT = "0xdd33Af49c851553841E94066B54Fd28612522901";
+
+		step("a submitted transaction is sealed and finalized", async function () {
+			this.timeout(15000);
+			expect((await context.web3.eth.getBlock("finalized")).number).to.equal(0);
+
+			const tx = await context.web3.eth.accounts.signTransaction(
+				{
+					from: GENESIS_ACCOUNT,
+					to: TEST_ACCOUNT,
+					value: "0x200",
+					gasPrice: "0x3B9ACA00",
+					gas: "0x100000",
+					nonce: 0,
+				},
+				GENESIS_ACCOUNT_PRIVATE_KEY
+			);
+			await customRequest(context.web3, "eth_sendRawTransaction", [tx.rawTransaction]);
+			await new Promise<void>((resolve) => setTimeout(() => resolve(), 2000));
+
+			const finalized = await context.web3.eth.getBlock("finalized");
+			expect(finalized.number).to.equal(1);
+			expect(finalized.transactions).to.deep.equal([tx.transactionHash]);
+		});
+	},
+	undefined,
+	"instant-finalize"
+);
@@ -0,0 +1,24 @@
+import { expect } from "chai";
+import { step } from "mocha-steps";
+
+import { describeWithFrontier, createAndFinalizeBlock } from "./util";
+
+describeWithFrontier(
+	"Frontier RPC (Interval sealing)",
+	(context) => {
+		step("blocks are sealed at the configured interval", async function () {
+			const start = Number(await context.web3.eth.getBlockNumber());
+			await new Promise<void>((resolve) => setTimeout(() => resolve(), 1200));
+			expect(Number(await context.web3.eth.getBlockNumber()) - start).to.be.at.least(2);
+		});
+
+		step("blocks can still be sealed through the rpc", async function () {
+			const start = Number(await context.web3.eth.getBlockNumber());
+			await createAndFinalizeBlock(context.web3);
+			expect(Number(await context.web3.eth.getBlockNumber())).to.be.above(start);
+		});
+	},
+	undefined,
+	"interval",
+	["--sealing-interval-ms=500"]
+);
@@ -58,7 +58,11 @@ export async function createAndFinalizeBlockNowait(web3: Web3) {
 	}
 }
 
-export async function startFrontierNode(provider?: string): Promise<{
+export async function startFrontierNode(
+	provider?: string,
+	sealing: string = "Manual",
+	extraArgs: string[] = []
+): Promise<{
 	web3: Web3;
 	binary: ChildProcess;
 	ethersjs: ethers.JsonRpcProvider;
@@ -75,7 +79,7 @@ export async function startFrontierNode(provider?: string): Promise<{
 		`--execution=Native`, // Faster execution using native
 		`--no-telemetry`,
 		`--no-prometheus`,
-		`--sealing=Manual`,
+		`--sealing=${sealing}`,
 		`--no-grandpa`,
 		`--force-authoring`,
 		`-l${FRONTIER_LOG}`,
@@ -84,6 +88,7 @@ export async function startFrontierNode(provider?: string): Promise<{
 		`--frontier-backend-type=${FRONTIER_BACKEND_TYPE}`,
 		`--tmp`,
 		`--unsafe-force-node-key-generation`,
+		...extraArgs,
 	];
 	const binary = spawn(cmd, args);
 
@@ -144,7 +149,13 @@ export async function startFrontierNode(provider?: string): Promise<{
 	return { web3, binary, ethersjs };
 }
 
-export function describeWithFrontier(title: string, cb: (context: { web3: Web3 }) => void, provider?: string) {
+export function describeWithFrontier(
+	title: string,
+	cb: (context: { web3: Web3 }) => void,
+	provider?: string,
+	sealing?: string,
+	extraArgs?: string[]
+) {
 	describe(title, () => {
 		let context: {
 			web3: Web3;
@@ -154,7 +165,7 @@ export function describeWithFrontier(title: string, cb: (context: { web3: Web3 }
 		// Making sure the Frontier node has started
 		before("Starting Frontier Test Node", async function () {
 			this.timeout(SPAWNING_TIME);
-			const init = await startFrontierNode(provider);
+			const init = await startFrontierNode(provider, sealing, extraArgs);
 			context.web3 = init.web3;
 			context.ethersjs = init.ethersjs;
 			binary = init.binary;